@@ -1,19 +1,66 @@
 use crate::{errors::VectorizeError, types::VectorizeJob};
 use sqlx::{FromRow, PgPool};
 
+/// the vectorize.job columns a VectorizeJob is read from
+pub const JOB_COLUMNS: &str = "job_name, src_table, src_schema, src_columns, primary_key, update_time_col, model, index_dist_type, target_schema, conflict_strategy, modality, fts_language, schedule, normalization, deleted_filter, text_join, normalize_embeddings, watch_columns, vector_type, table_prefixes";
+
 pub async fn get_vectorize_job(
     pool: &PgPool,
     job_name: &str,
 ) -> Result<VectorizeJob, VectorizeError> {
     // Changed return type
-    let row = sqlx::query(
-        "SELECT job_name, src_table, src_schema, src_columns, primary_key, update_time_col, model 
-         FROM vectorize.job 
-         WHERE job_name = $1",
-    )
+    let row = sqlx::query(&format!(
+        "SELECT {JOB_COLUMNS}
+         FROM vectorize.job
+         WHERE job_name = $1"
+    ))
     .bind(job_name)
     .fetch_one(pool)
     .await?;
 
     Ok(VectorizeJob::from_row(&row)?) // Handle the Result from from_row
 }
+
+/// the job's current row, None when it does not exist
+pub async fn find_vectorize_job(
+    pool: &PgPool,
+    job_name: &str,
+) -> Result<Option<VectorizeJob>, VectorizeError> {
+    let job = sqlx::query_as(&format!(
+        "SELECT {JOB_COLUMNS} FROM vectorize.job WHERE job_name = $1"
+    ))
+    .bind(job_name)
+    .fetch_optional(pool)
+    .await?;
+    Ok(job)
+}
+
+/// whether the job's processing is paused, false when it does not exist
+pub async fn is_job_paused(pool: &PgPool, job_name: &str) -> Result<bool, VectorizeError> {
+    let paused: Option<bool> =
+        sqlx::query_scalar("SELECT paused FROM vectorize.job WHERE job_name = $1")
+            .bind(job_name)
+            .fetch_optional(pool)
+            .await?;
+    Ok(paused.unwrap_or(false))
+}
+
+/// every job, as loaded into the server and proxy job caches
+pub async fn get_all_jobs(pool: &PgPool) -> Result<Vec<VectorizeJob>, VectorizeError> {
+    let jobs = sqlx::query_as(&format!("SELECT {JOB_COLUMNS} FROM vectorize.job"))
+        .fetch_all(pool)
+        .await?;
+    Ok(jobs)
+}
+
+/// jobs whose rows are embedded on a cron schedule instead of by triggers
+pub async fn get_scheduled_jobs(pool: &PgPool) -> Result<Vec<VectorizeJob>, VectorizeError> {
+    let jobs = sqlx::query_as(&format!(
+        "SELECT {JOB_COLUMNS}
+         FROM vectorize.job
+         WHERE schedule <> 'realtime'"
+    ))
+    .fetch_all(pool)
+    .await?;
+    Ok(jobs)
+}
@@ -22,7 +22,10 @@ pub enum VectorizeError {
     #[error("SQL error: {0}")]
     SqlError(#[from] DbError),
     #[error("HTTP error: {0}")]
-    Reqwest(#[from] reqwest::Error),
+    Reqwest(reqwest::Error),
+    // the provider did not respond in time, the worker retries the message
+    #[error("model provider timed out: {0}")]
+    ProviderTimeout(String),
     #[error("Serde error: {0}")]
     SerdeError(#[from] serde_json::Error),
     #[error("An internal error occurred: {0}")]
@@ -42,4 +45,16 @@ pub enum VectorizeError {
     EmbeddingGenerationFailed(String),
     #[error("pgmq error: {0}")]
     PgmqError(#[from] PgmqError),
+    #[error("invalid input: {0}")]
+    InvalidInput(String),
+}
+
+impl From<reqwest::Error> for VectorizeError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            VectorizeError::ProviderTimeout(e.to_string())
+        } else {
+            VectorizeError::Reqwest(e)
+        }
+    }
 }
@@ -18,6 +18,8 @@ pub enum VectorizeGuc {
     PortkeyServiceUrl,
     VoyageApiKey,
     VoyageServiceUrl,
+    AzureOpenAIKey,
+    AzureOpenAIServiceUrl,
     TextIndexType,
 }
 
@@ -43,6 +45,8 @@ pub async fn get_guc(guc: VectorizeGuc, pool: &PgPool) -> Option<String> {
         VectorizeGuc::PortkeyServiceUrl => "portkey_service_url",
         VectorizeGuc::VoyageApiKey => "voyage_api_key",
         VectorizeGuc::VoyageServiceUrl => "voyage_service_url",
+        VectorizeGuc::AzureOpenAIKey => "azure_openai_key",
+        VectorizeGuc::AzureOpenAIServiceUrl => "azure_openai_service_url",
         VectorizeGuc::TextIndexType => "experimental_fts_index_type",
     };
     let query = format!("SHOW vectorize.{guc_name}");
@@ -86,5 +90,28 @@ pub async fn get_guc_configs(model_source: &ModelSource, pool: &PgPool) -> Model
             service_url: get_guc(VectorizeGuc::VoyageServiceUrl, pool).await,
             virtual_key: None,
         },
+        ModelSource::AzureOpenAI => ModelGucConfig {
+            api_key: get_guc(VectorizeGuc::AzureOpenAIKey, pool).await,
+            service_url: get_guc(VectorizeGuc::AzureOpenAIServiceUrl, pool).await,
+            virtual_key: None,
+        },
+        // signed with AWS credentials from the environment
+        ModelSource::Bedrock => ModelGucConfig {
+            api_key: None,
+            service_url: None,
+            virtual_key: None,
+        },
+        // authenticated with a Google Cloud service account or the metadata server
+        ModelSource::Vertex => ModelGucConfig {
+            api_key: None,
+            service_url: None,
+            virtual_key: None,
+        },
+        // base url and api key come from OPENAI_COMPAT_BASE_URL and OPENAI_COMPAT_API_KEY
+        ModelSource::OpenAICompatible => ModelGucConfig {
+            api_key: None,
+            service_url: None,
+            virtual_key: None,
+        },
     }
 }
@@ -4,5 +4,7 @@ pub mod errors;
 pub mod guc;
 pub mod init;
 pub mod query;
+pub mod rerank;
+pub mod schedule;
 pub mod transformers;
 pub mod types;
@@ -1,12 +1,12 @@
+use crate::transformers::tokenizer::Tokenizer;
 use crate::transformers::types::Inputs;
 use crate::types::{self, JobParams};
 use anyhow::{Result, anyhow};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use sqlx::error::Error;
 use sqlx::postgres::PgRow;
 use sqlx::{Postgres, Row};
 use std::collections::BTreeMap;
-use tiktoken_rs::cl100k_base;
 pub const VECTORIZE_SCHEMA: &str = "vectorize";
 static TRIGGER_FN_PREFIX: &str = "vectorize.handle_update_";
 
@@ -23,6 +23,10 @@ pub enum FilterOperator {
     LessThan,
     /// Less than or equal (<=)
     LessThanOrEqual,
+    /// Case-sensitive pattern match (LIKE)
+    Like,
+    /// Case-insensitive pattern match (ILIKE)
+    ILike,
 }
 
 impl FilterOperator {
@@ -34,15 +38,82 @@ impl FilterOperator {
             FilterOperator::GreaterThanOrEqual => ">=",
             FilterOperator::LessThan => "<",
             FilterOperator::LessThanOrEqual => "<=",
+            FilterOperator::Like => "LIKE",
+            FilterOperator::ILike => "ILIKE",
         }
     }
 }
 
+/// which rankings a search combines
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    /// semantic and full-text rankings, combined with reciprocal rank fusion
+    #[default]
+    Hybrid,
+    /// only the similarity of the query embedding
+    Semantic,
+    /// only the full-text rank, the query is not embedded
+    Fts,
+}
+
 /// A filter value with an operator
 #[derive(Debug, Clone, Serialize)]
 pub struct FilterValue {
     pub operator: FilterOperator,
     pub value: FilterValueType,
+    /// the value as written, compared as is with text columns, e.g. keeping leading zeros
+    #[serde(skip)]
+    pub text: String,
+    /// the column's type, the text value is cast to it, e.g. for date and timestamp columns
+    #[serde(skip)]
+    pub cast: Option<String>,
+}
+
+impl FilterValue {
+    /// converts the value to the type of the source column it is compared with, data_type as named
+    /// by information_schema.columns, errors when it cannot be compared with such a column
+    pub fn coerce_to_column(&mut self, data_type: &str) -> Result<()> {
+        let pattern = matches!(self.operator, FilterOperator::Like | FilterOperator::ILike);
+        match data_type {
+            // text columns compare text, comparison operators order it as the column's collation does
+            "text" | "character varying" | "character" => {
+                self.value = FilterValueType::String(self.text.clone());
+            }
+            _ if pattern => {
+                return Err(anyhow!(
+                    "like and ilike require a text column, not {data_type}"
+                ));
+            }
+            "smallint" | "integer" | "bigint" | "numeric" | "real" | "double precision"
+                if !matches!(
+                    self.value,
+                    FilterValueType::Integer(_) | FilterValueType::Float(_)
+                ) =>
+            {
+                return Err(anyhow!(
+                    "{data_type} column requires a numeric value, got '{}'",
+                    self.text
+                ));
+            }
+            "boolean" if !matches!(self.value, FilterValueType::Boolean(_)) => {
+                return Err(anyhow!(
+                    "boolean column requires true or false, got '{}'",
+                    self.text
+                ));
+            }
+            "smallint" | "integer" | "bigint" | "numeric" | "real" | "double precision"
+            | "boolean" => {}
+            // enums and arrays are not named by their data_type, their values bind as parsed
+            "USER-DEFINED" | "ARRAY" => {}
+            // other types, e.g. dates, timestamps and uuids, cast the value as written
+            _ => {
+                self.value = FilterValueType::String(self.text.clone());
+                self.cast = Some(data_type.to_string());
+            }
+        }
+        Ok(())
+    }
 }
 
 /// The actual value stored in a filter
@@ -78,6 +149,58 @@ impl FilterValueType {
     }
 }
 
+/// The column a filter applies to, or a path into a jsonb column, e.g. metadata->>category
+/// Nested keys are separated by dots, e.g. metadata->>author.name
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterColumn {
+    pub column: String,
+    pub path: Vec<String>,
+}
+
+impl FilterColumn {
+    pub fn parse(key: &str) -> Result<Self> {
+        let (column, path) = match key.split_once("->>") {
+            Some((column, path)) => (column, path.split('.').map(str::to_string).collect()),
+            None => (key, Vec::new()),
+        };
+        for name in std::iter::once(column).chain(path.iter().map(String::as_str)) {
+            if name.is_empty() {
+                return Err(anyhow!("Invalid Input: {}", key));
+            }
+            check_input(name)?;
+        }
+        Ok(FilterColumn {
+            column: column.to_string(),
+            path,
+        })
+    }
+
+    /// SQL comparing the column of source table t0 with the bind parameter ${bind}
+    pub fn condition(&self, filter: &FilterValue, bind: i16) -> String {
+        let operator = filter.operator.to_sql();
+        let column = format!("t0.\"{}\"", self.column);
+        let pattern = matches!(
+            filter.operator,
+            FilterOperator::Like | FilterOperator::ILike
+        );
+        match (self.path.as_slice(), pattern) {
+            ([], _) => match &filter.cast {
+                Some(data_type) => format!("{column} {operator} ${bind}::{data_type}"),
+                None => format!("{column} {operator} ${bind}"),
+            },
+            // pattern operators match the value as text
+            ([key], true) => format!("{column}->>'{key}' {operator} ${bind}"),
+            (path, true) => format!("{column}#>>'{{{}}}' {operator} ${bind}", path.join(",")),
+            // other operators compare jsonb, so numbers compare numerically and no cast can fail
+            ([key], false) => format!("{column}->'{key}' {operator} to_jsonb(${bind})"),
+            (path, false) => format!(
+                "{column}#>'{{{}}}' {operator} to_jsonb(${bind})",
+                path.join(",")
+            ),
+        }
+    }
+}
+
 /// Custom deserializer for FilterValue that parses operator.value format
 impl<'de> serde::Deserialize<'de> for FilterValue {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -93,7 +216,56 @@ impl<'de> serde::Deserialize<'de> for FilterValue {
             type Value = FilterValue;
 
             fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                formatter.write_str("a string in format 'operator.value' or just 'value'.")
+                formatter.write_str(
+                    "a string in format 'operator.value' or just 'value', or a number or boolean.",
+                )
+            }
+
+            // native JSON scalars, e.g. {"price": 25}, are equality filters
+            fn visit_bool<E>(self, value: bool) -> Result<FilterValue, E>
+            where
+                E: de::Error,
+            {
+                Ok(FilterValue {
+                    operator: FilterOperator::Equal,
+                    value: FilterValueType::Boolean(value),
+                    text: value.to_string(),
+                    cast: None,
+                })
+            }
+
+            fn visit_i64<E>(self, value: i64) -> Result<FilterValue, E>
+            where
+                E: de::Error,
+            {
+                Ok(FilterValue {
+                    operator: FilterOperator::Equal,
+                    value: FilterValueType::Integer(value),
+                    text: value.to_string(),
+                    cast: None,
+                })
+            }
+
+            fn visit_u64<E>(self, value: u64) -> Result<FilterValue, E>
+            where
+                E: de::Error,
+            {
+                let value = i64::try_from(value).map_err(|_| {
+                    de::Error::custom(format!("Integer filter value out of range: {}", value))
+                })?;
+                self.visit_i64(value)
+            }
+
+            fn visit_f64<E>(self, value: f64) -> Result<FilterValue, E>
+            where
+                E: de::Error,
+            {
+                Ok(FilterValue {
+                    operator: FilterOperator::Equal,
+                    value: FilterValueType::Float(value),
+                    text: value.to_string(),
+                    cast: None,
+                })
             }
 
             fn visit_str<E>(self, value: &str) -> Result<FilterValue, E>
@@ -110,6 +282,8 @@ impl<'de> serde::Deserialize<'de> for FilterValue {
                         "gte" => FilterOperator::GreaterThanOrEqual,
                         "lt" => FilterOperator::LessThan,
                         "lte" => FilterOperator::LessThanOrEqual,
+                        "like" => FilterOperator::Like,
+                        "ilike" => FilterOperator::ILike,
                         _ => {
                             return Err(de::Error::custom(format!(
                                 "Unknown operator: {}",
@@ -133,20 +307,22 @@ impl<'de> serde::Deserialize<'de> for FilterValue {
                                 FilterValueType::String(val.to_string())
                             }
                         }
+                        FilterOperator::Like | FilterOperator::ILike => {
+                            // Pattern operators always take the raw string, including % and _ wildcards
+                            FilterValueType::String(val.to_string())
+                        }
                         FilterOperator::GreaterThan
                         | FilterOperator::GreaterThanOrEqual
                         | FilterOperator::LessThan
                         | FilterOperator::LessThanOrEqual => {
-                            // For comparison operators, require numeric values
+                            // numbers compare numerically, other values, e.g. text or dates,
+                            // are checked against the column's type by coerce_to_column
                             if let Ok(int_val) = val.parse::<i64>() {
                                 FilterValueType::Integer(int_val)
                             } else if let Ok(float_val) = val.parse::<f64>() {
                                 FilterValueType::Float(float_val)
                             } else {
-                                return Err(de::Error::custom(format!(
-                                    "Comparison operators (gt, gte, lt, lte) require numeric values, got: '{}'",
-                                    val
-                                )));
+                                FilterValueType::String(val.to_string())
                             }
                         }
                     };
@@ -154,6 +330,8 @@ impl<'de> serde::Deserialize<'de> for FilterValue {
                     Ok(FilterValue {
                         operator,
                         value: parsed_value,
+                        text: val.to_string(),
+                        cast: None,
                     })
                 } else {
                     // Default to equality if no operator specified
@@ -172,23 +350,121 @@ impl<'de> serde::Deserialize<'de> for FilterValue {
                     Ok(FilterValue {
                         operator: FilterOperator::Equal,
                         value: parsed_value,
+                        text: value.to_string(),
+                        cast: None,
                     })
                 }
             }
         }
 
-        deserializer.deserialize_str(FilterValueVisitor)
+        // deserialize_any so JSON bodies may use numbers and booleans, query strings still arrive as str
+        deserializer.deserialize_any(FilterValueVisitor)
     }
 }
 
-fn generate_column_concat(src_columns: &[String], prefix: &str) -> String {
+// concatenates the source columns and any joined text columns into a single text input
+// NULL columns, and source rows without a joined row, become empty strings
+pub fn generate_column_concat(
+    src_columns: &[String],
+    text_join: Option<&types::TextJoin>,
+    src_schema: &str,
+    prefix: &str,
+) -> String {
+    let joined = text_join
+        .map(|join| joined_text_columns(join, src_schema, prefix))
+        .unwrap_or_default();
     src_columns
         .iter()
         .map(|col| format!("COALESCE({prefix}.{col}, '')"))
+        .chain(
+            joined
+                .iter()
+                .map(|(_, expr)| format!("COALESCE({expr}, '')")),
+        )
         .collect::<Vec<String>>()
         .join(" || ' ' || ")
 }
 
+// the text columns of a joined table for the source row aliased by prefix, as (column, expression)
+// each is a scalar subquery, initialize_job requires a unique join_column so it matches at most one joined row
+pub fn joined_text_columns(
+    text_join: &types::TextJoin,
+    src_schema: &str,
+    prefix: &str,
+) -> Vec<(String, String)> {
+    let schema = text_join.schema.as_deref().unwrap_or(src_schema);
+    text_join
+        .columns
+        .iter()
+        .map(|col| {
+            (
+                col.clone(),
+                format!(
+                    "(SELECT j.{col} FROM {schema}.{table} j WHERE j.{join_column} = {prefix}.{src_column})",
+                    table = text_join.table,
+                    join_column = text_join.join_column,
+                    src_column = text_join.src_column,
+                ),
+            )
+        })
+        .collect()
+}
+
+// the key columns as a column list, e.g. "t0.order_id, t0.line_no"
+pub fn key_columns(keys: &[String], prefix: &str) -> String {
+    keys.iter()
+        .map(|k| format!("{prefix}.{k}"))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+// joins two relations on every key column
+pub fn key_join_condition(keys: &[String], left: &str, right: &str) -> String {
+    keys.iter()
+        .map(|k| format!("{left}.{k} = {right}.{k}"))
+        .collect::<Vec<String>>()
+        .join(" AND ")
+}
+
+// a row's primary key as text, this is the record_id carried in queue messages
+// a single key is cast to text, a composite key becomes a JSON array of its values
+pub fn record_id_expr(keys: &[String], prefix: &str) -> String {
+    match keys {
+        [key] => format!("{prefix}.{key}::text"),
+        _ => format!("jsonb_build_array({})::text", key_columns(keys, prefix)),
+    }
+}
+
+// casts a record_id back to the typed key values, in key column order
+fn record_id_values(key_types: &[String], record_id: &str) -> String {
+    match key_types {
+        [key_type] => format!("{record_id}::{key_type}"),
+        _ => key_types
+            .iter()
+            .enumerate()
+            .map(|(i, t)| format!("({record_id}::jsonb->>{i})::{t}"))
+            .collect::<Vec<String>>()
+            .join(", "),
+    }
+}
+
+// matches the rows whose record_id is in the text[] bound at ${param}
+pub fn record_ids_filter(keys: &[String], key_types: &[String], param: usize) -> String {
+    match (keys, key_types) {
+        ([key], [key_type]) => format!("{key} = ANY (${param}::{key_type}[])"),
+        _ => format!(
+            "({}) IN (SELECT {} FROM unnest(${param}::text[]) AS record_id)",
+            keys.join(", "),
+            record_id_values(key_types, "record_id")
+        ),
+    }
+}
+
+// a VALUES entry for the key columns, decoded from the record_id bound at ${param}
+pub fn record_id_params(key_types: &[String], param: usize) -> String {
+    record_id_values(key_types, &format!("${param}"))
+}
+
 // errors if input contains non-alphanumeric characters or underscore
 // in other worse - valid column names only
 pub fn check_input(input: &str) -> Result<()> {
@@ -203,20 +479,86 @@ pub fn check_input(input: &str) -> Result<()> {
 }
 
 pub fn create_vectorize_table() -> String {
-    "CREATE TABLE IF NOT EXISTS vectorize.job
+    format!(
+        "CREATE TABLE IF NOT EXISTS vectorize.job
         (
             id UUID DEFAULT gen_random_uuid() PRIMARY KEY,
             job_name TEXT NOT NULL UNIQUE,
             src_schema TEXT NOT NULL,
             src_table TEXT NOT NULL,
             src_columns TEXT[] NOT NULL,
-            primary_key TEXT NOT NULL,
-            update_time_col TEXT NOT NULL,
+            primary_key TEXT[] NOT NULL,
+            update_time_col TEXT,
             model TEXT NOT NULL,
-            params JSONB
+            params JSONB,
+            index_dist_type TEXT NOT NULL DEFAULT 'pgv_hnsw_cosine',
+            target_schema TEXT NOT NULL DEFAULT 'vectorize',
+            conflict_strategy TEXT NOT NULL DEFAULT 'overwrite',
+            last_completion TIMESTAMPTZ,
+            modality TEXT NOT NULL DEFAULT 'text',
+            fts_language TEXT NOT NULL DEFAULT 'english',
+            schedule TEXT NOT NULL DEFAULT 'realtime',
+            normalization JSONB NOT NULL DEFAULT '{{}}',
+            deleted_filter TEXT,
+            text_join JSONB,
+            normalize_embeddings BOOLEAN NOT NULL DEFAULT false,
+            watch_columns TEXT[],
+            paused BOOLEAN NOT NULL DEFAULT false,
+            vector_type TEXT NOT NULL DEFAULT 'vector',
+            table_prefixes JSONB NOT NULL DEFAULT '{TABLE_PREFIXES_DEFAULT}'
         );
         "
-    .to_string()
+    )
+}
+
+// jobs created before the table name prefixes were stored used the default ones
+const TABLE_PREFIXES_DEFAULT: &str =
+    r#"{"embeddings": "_embeddings_", "search_tokens": "_search_tokens_"}"#;
+
+// brings a vectorize.job table created by an older version up to date
+pub fn migrate_vectorize_table() -> Vec<String> {
+    vec![
+        "ALTER TABLE vectorize.job ADD COLUMN IF NOT EXISTS index_dist_type TEXT NOT NULL DEFAULT 'pgv_hnsw_cosine';"
+            .to_string(),
+        "ALTER TABLE vectorize.job ADD COLUMN IF NOT EXISTS target_schema TEXT NOT NULL DEFAULT 'vectorize';"
+            .to_string(),
+        "ALTER TABLE vectorize.job ADD COLUMN IF NOT EXISTS conflict_strategy TEXT NOT NULL DEFAULT 'overwrite';"
+            .to_string(),
+        "ALTER TABLE vectorize.job ADD COLUMN IF NOT EXISTS last_completion TIMESTAMPTZ;".to_string(),
+        "ALTER TABLE vectorize.job ADD COLUMN IF NOT EXISTS modality TEXT NOT NULL DEFAULT 'text';"
+            .to_string(),
+        "ALTER TABLE vectorize.job ADD COLUMN IF NOT EXISTS fts_language TEXT NOT NULL DEFAULT 'english';"
+            .to_string(),
+        "ALTER TABLE vectorize.job ADD COLUMN IF NOT EXISTS schedule TEXT NOT NULL DEFAULT 'realtime';"
+            .to_string(),
+        "ALTER TABLE vectorize.job ADD COLUMN IF NOT EXISTS normalization JSONB NOT NULL DEFAULT '{}';"
+            .to_string(),
+        "ALTER TABLE vectorize.job ADD COLUMN IF NOT EXISTS deleted_filter TEXT;".to_string(),
+        "ALTER TABLE vectorize.job ADD COLUMN IF NOT EXISTS text_join JSONB;".to_string(),
+        "ALTER TABLE vectorize.job ADD COLUMN IF NOT EXISTS normalize_embeddings BOOLEAN NOT NULL DEFAULT false;"
+            .to_string(),
+        "ALTER TABLE vectorize.job ADD COLUMN IF NOT EXISTS watch_columns TEXT[];".to_string(),
+        "ALTER TABLE vectorize.job ADD COLUMN IF NOT EXISTS paused BOOLEAN NOT NULL DEFAULT false;"
+            .to_string(),
+        "ALTER TABLE vectorize.job ADD COLUMN IF NOT EXISTS vector_type TEXT NOT NULL DEFAULT 'vector';"
+            .to_string(),
+        "ALTER TABLE vectorize.job ALTER COLUMN update_time_col DROP NOT NULL;".to_string(),
+        format!(
+            "ALTER TABLE vectorize.job ADD COLUMN IF NOT EXISTS table_prefixes JSONB NOT NULL DEFAULT '{TABLE_PREFIXES_DEFAULT}';"
+        ),
+        add_content_hash_columns(),
+        // primary_key holds every column of a composite key
+        "DO $$
+        BEGIN
+            IF (
+                SELECT data_type FROM information_schema.columns
+                WHERE table_schema = 'vectorize' AND table_name = 'job' AND column_name = 'primary_key'
+            ) = 'text' THEN
+                ALTER TABLE vectorize.job ALTER COLUMN primary_key TYPE TEXT[] USING ARRAY[primary_key];
+            END IF;
+        END $$;"
+            .to_string(),
+    ]
 }
 
 pub fn init_index_query(job_name: &str, idx_type: &str, job_params: &JobParams) -> String {
@@ -239,29 +581,43 @@ pub fn init_index_query(job_name: &str, idx_type: &str, job_params: &JobParams)
     )
 }
 
-pub fn create_fts_index_query(job_name: &str, idx_type: &str) -> String {
+pub fn create_fts_index_query(
+    job_name: &str,
+    prefixes: &types::TablePrefixes,
+    idx_type: &str,
+    target_schema: &str,
+) -> String {
+    let search_tokens_table = prefixes.search_tokens_table(job_name);
     check_input(job_name).expect("invalid job name");
     match idx_type.to_uppercase().as_str() {
         "GIN" | "GIST" => {} // Do nothing, it's valid
         _ => panic!("Expected 'GIN' or 'GIST', got '{idx_type}' index type"),
     }
     format!(
-        "CREATE INDEX IF NOT EXISTS {job_name}_{idx_type}_idx ON vectorize._search_tokens_{job_name}
+        "CREATE INDEX IF NOT EXISTS {job_name}_{idx_type}_idx ON {target_schema}.{search_tokens_table}
         USING {idx_type} (search_tokens);"
     )
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn update_search_tokens_trigger_queries(
     job_name: &str,
-    join_key: &str,
+    prefixes: &types::TablePrefixes,
+    join_key: &[String],
     src_schema: &str,
     src_table: &str,
     src_columns: &[String],
+    text_join: Option<&types::TextJoin>,
+    target_schema: &str,
+    fts_language: &str,
 ) -> Vec<String> {
+    let search_tokens_table = prefixes.search_tokens_table(job_name);
     let trigger_fn_name = format!("update_{job_name}_search_tokens");
 
-    let new_cols = generate_column_concat(src_columns, "NEW");
-    let old_cols = generate_column_concat(src_columns, "OLD");
+    let new_cols = generate_column_concat(src_columns, text_join, src_schema, "NEW");
+    let old_cols = generate_column_concat(src_columns, text_join, src_schema, "OLD");
+    let new_keys = key_columns(join_key, "NEW");
+    let join_key = join_key.join(", ");
 
     let trigger_dev = format!(
         "
@@ -270,13 +626,13 @@ RETURNS TRIGGER AS $$
 BEGIN
 -- Handle INSERT and UPDATE operations
 IF TG_OP = 'INSERT' THEN
-    INSERT INTO vectorize._search_tokens_{job_name} ({join_key}, search_tokens)
+    INSERT INTO {target_schema}.{search_tokens_table} ({join_key}, search_tokens)
     VALUES (
-        NEW.{join_key},
-        to_tsvector('english', {new_cols})
+        {new_keys},
+        to_tsvector('{fts_language}', {new_cols})
     )
     ON CONFLICT ({join_key}) DO UPDATE SET
-        search_tokens = to_tsvector('english', {new_cols}),
+        search_tokens = to_tsvector('{fts_language}', {new_cols}),
         updated_at = CLOCK_TIMESTAMP()
     ;
     RETURN NEW;
@@ -284,10 +640,10 @@ END IF;
 
 IF TG_OP = 'UPDATE' THEN
     IF {old_cols} IS DISTINCT FROM {new_cols} THEN
-        INSERT INTO vectorize._search_tokens_{job_name} ({join_key}, search_tokens)
-        VALUES (NEW.{join_key}, to_tsvector('english', {new_cols}))
+        INSERT INTO {target_schema}.{search_tokens_table} ({join_key}, search_tokens)
+        VALUES ({new_keys}, to_tsvector('{fts_language}', {new_cols}))
         ON CONFLICT ({join_key}) DO UPDATE SET
-            search_tokens = to_tsvector('english', {new_cols}),
+            search_tokens = to_tsvector('{fts_language}', {new_cols}),
             updated_at = CLOCK_TIMESTAMP();
     END IF;
     RETURN NEW;
@@ -307,49 +663,113 @@ $$ LANGUAGE plpgsql;",
     vec![trigger_dev, apply_trigger]
 }
 
+/// fills a job's search tokens table from the rows already in the source table
+#[allow(clippy::too_many_arguments)]
+pub fn populate_search_tokens_query(
+    job_name: &str,
+    prefixes: &types::TablePrefixes,
+    join_key: &[String],
+    src_schema: &str,
+    src_table: &str,
+    src_columns: &[String],
+    text_join: Option<&types::TextJoin>,
+    target_schema: &str,
+    fts_language: &str,
+) -> String {
+    let search_tokens_table = prefixes.search_tokens_table(job_name);
+    let search_cols = generate_column_concat(src_columns, text_join, src_schema, "t0");
+    let key_cols = key_columns(join_key, "t0");
+    let join_key = join_key.join(", ");
+    format!(
+        "
+        INSERT INTO {target_schema}.{search_tokens_table} ({join_key}, search_tokens)
+        SELECT 
+            {key_cols}, 
+            to_tsvector('{fts_language}', {search_cols})
+        FROM {src_schema}.{src_table} t0
+        ON CONFLICT ({join_key}) DO UPDATE SET
+            search_tokens = EXCLUDED.search_tokens,
+            updated_at = NOW();
+    "
+    )
+}
+
 /// creates a project view over a source table and the embeddings table
-pub fn create_project_view(job_name: &str, schema: &str, relation: &str, pkey: &str) -> String {
+pub fn create_project_view(
+    job_name: &str,
+    prefixes: &types::TablePrefixes,
+    schema: &str,
+    relation: &str,
+    pkey: &[String],
+    target_schema: &str,
+) -> String {
+    let embeddings_table = prefixes.embeddings_table(job_name);
+    let join_condition = key_join_condition(pkey, "t0", "t1");
     format!(
-        "CREATE OR REPLACE VIEW vectorize.{job_name}_view as 
+        "CREATE OR REPLACE VIEW {target_schema}.{job_name}_view as 
         SELECT t0.*, t1.embeddings, t1.updated_at as embeddings_updated_at
         FROM {schema}.{relation} t0
-        INNER JOIN vectorize._embeddings_{job_name} t1
-            ON t0.{pkey} = t1.{pkey};
+        INNER JOIN {target_schema}.{embeddings_table} t1
+            ON {join_condition};
         "
     )
 }
 
 pub fn create_search_tokens_table(
     job_name: &str,
-    join_key: &str,
-    join_key_type: &str,
+    prefixes: &types::TablePrefixes,
+    join_key: &[String],
+    join_key_type: &[String],
     src_schema: &str,
     src_table: &str,
+    target_schema: &str,
 ) -> String {
+    let search_tokens_table = prefixes.search_tokens_table(job_name);
+    let key_cols = key_column_definitions(join_key, join_key_type);
+    let join_key = join_key.join(", ");
     format!(
-        "CREATE TABLE IF NOT EXISTS vectorize._search_tokens_{job_name} (
-            {join_key} {join_key_type} UNIQUE NOT NULL,
+        "CREATE TABLE IF NOT EXISTS {target_schema}.{search_tokens_table} (
+            {key_cols},
             search_tokens TSVECTOR NOT NULL,
             updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW() NOT NULL,
+            UNIQUE ({join_key}),
             FOREIGN KEY ({join_key}) REFERENCES {src_schema}.{src_table} ({join_key}) ON DELETE CASCADE
         );
         ",
     )
 }
 
+// column definitions for the key columns of a job's embeddings and search tokens tables
+fn key_column_definitions(join_key: &[String], join_key_type: &[String]) -> String {
+    join_key
+        .iter()
+        .zip(join_key_type)
+        .map(|(k, t)| format!("{k} {t} NOT NULL"))
+        .collect::<Vec<String>>()
+        .join(",\n            ")
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn create_embedding_table(
     job_name: &str,
-    join_key: &str,
-    join_key_type: &str,
+    prefixes: &types::TablePrefixes,
+    join_key: &[String],
+    join_key_type: &[String],
     col_type: &str,
     src_schema: &str,
     src_table: &str,
+    target_schema: &str,
 ) -> String {
+    let embeddings_table = prefixes.embeddings_table(job_name);
+    let key_cols = key_column_definitions(join_key, join_key_type);
+    let join_key = join_key.join(", ");
     format!(
-        "CREATE TABLE IF NOT EXISTS vectorize._embeddings_{job_name} (
-            {join_key} {join_key_type} UNIQUE NOT NULL,
+        "CREATE TABLE IF NOT EXISTS {target_schema}.{embeddings_table} (
+            {key_cols},
             embeddings {col_type} NOT NULL,
+            content_hash TEXT,
             updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW() NOT NULL,
+            UNIQUE ({join_key}),
             FOREIGN KEY ({join_key}) REFERENCES {src_schema}.{src_table} ({join_key}) ON DELETE CASCADE
         );
         ",
@@ -361,10 +781,11 @@ pub fn create_hnsw_l2_index(
     schema: &str,
     table: &str,
     embedding_col: &str,
+    vector_type: &types::VectorType,
 ) -> String {
     format!(
         "CREATE INDEX IF NOT EXISTS {job_name}_hnsw_l2_idx ON {schema}.{table}
-        USING hnsw ({embedding_col} vector_l2_ops);
+        USING hnsw ({embedding_col} {vector_type}_l2_ops);
         ",
     )
 }
@@ -374,10 +795,11 @@ pub fn create_hnsw_ip_index(
     schema: &str,
     table: &str,
     embedding_col: &str,
+    vector_type: &types::VectorType,
 ) -> String {
     format!(
         "CREATE INDEX IF NOT EXISTS {job_name}_hnsw_ip_idx ON {schema}.{table}
-        USING hnsw ({embedding_col} vector_ip_ops);
+        USING hnsw ({embedding_col} {vector_type}_ip_ops);
         ",
     )
 }
@@ -387,14 +809,223 @@ pub fn create_hnsw_cosine_index(
     schema: &str,
     table: &str,
     embedding_col: &str,
+    vector_type: &types::VectorType,
 ) -> String {
     format!(
         "CREATE INDEX IF NOT EXISTS {job_name}_hnsw_cos_idx ON {schema}.{table}
-        USING hnsw ({embedding_col} vector_cosine_ops);
+        USING hnsw ({embedding_col} {vector_type}_cosine_ops);
         ",
     )
 }
 
+/// for binary quantized embeddings, stored as bit(n)
+pub fn create_hnsw_hamming_index(
+    job_name: &str,
+    schema: &str,
+    table: &str,
+    embedding_col: &str,
+) -> String {
+    format!(
+        "CREATE INDEX IF NOT EXISTS {job_name}_hnsw_hamming_idx ON {schema}.{table}
+        USING hnsw ({embedding_col} bit_hamming_ops);
+        ",
+    )
+}
+
+/// requires the pgvectorscale extension, installed as `vectorscale`
+pub fn create_diskann_cosine_index(
+    job_name: &str,
+    schema: &str,
+    table: &str,
+    embedding_col: &str,
+) -> String {
+    format!(
+        "CREATE INDEX IF NOT EXISTS {job_name}_diskann_cos_idx ON {schema}.{table}
+        USING diskann ({embedding_col} vector_cosine_ops);
+        ",
+    )
+}
+
+/// creates the vector index matching the job's distance type and the embeddings column's type
+/// diskann indexes only support vector columns and hamming indexes only bit columns, see validate_job
+pub fn create_embedding_index(
+    job_name: &str,
+    schema: &str,
+    table: &str,
+    embedding_col: &str,
+    index_dist: &types::IndexDist,
+    vector_type: &types::VectorType,
+) -> String {
+    match index_dist {
+        types::IndexDist::pgv_hnsw_l2 => {
+            create_hnsw_l2_index(job_name, schema, table, embedding_col, vector_type)
+        }
+        types::IndexDist::pgv_hnsw_ip => {
+            create_hnsw_ip_index(job_name, schema, table, embedding_col, vector_type)
+        }
+        types::IndexDist::pgv_hnsw_cosine => {
+            create_hnsw_cosine_index(job_name, schema, table, embedding_col, vector_type)
+        }
+        types::IndexDist::vsc_diskann_cosine => {
+            create_diskann_cosine_index(job_name, schema, table, embedding_col)
+        }
+        types::IndexDist::pgv_hnsw_hamming => {
+            create_hnsw_hamming_index(job_name, schema, table, embedding_col)
+        }
+    }
+}
+
+// name of the vector index built by create_embedding_index
+pub fn embedding_index_name(job_name: &str, index_dist: &types::IndexDist) -> String {
+    let suffix = match index_dist {
+        types::IndexDist::pgv_hnsw_l2 => "hnsw_l2_idx",
+        types::IndexDist::pgv_hnsw_ip => "hnsw_ip_idx",
+        types::IndexDist::pgv_hnsw_cosine => "hnsw_cos_idx",
+        types::IndexDist::vsc_diskann_cosine => "diskann_cos_idx",
+        types::IndexDist::pgv_hnsw_hamming => "hnsw_hamming_idx",
+    };
+    format!("{job_name}_{suffix}")
+}
+
+pub fn drop_embedding_index(job_name: &str, schema: &str, index_dist: &types::IndexDist) -> String {
+    let name = embedding_index_name(job_name, index_dist);
+    match index_dist {
+        // jobs created before diskann indexes were built have a cosine hnsw index instead
+        types::IndexDist::vsc_diskann_cosine => {
+            let old_name = embedding_index_name(job_name, &types::IndexDist::pgv_hnsw_cosine);
+            format!("DROP INDEX IF EXISTS {schema}.{name}, {schema}.{old_name};")
+        }
+        _ => format!("DROP INDEX IF EXISTS {schema}.{name};"),
+    }
+}
+
+// changes the embeddings column to a new vector type, e.g. vector(768) after a model change
+// the project view depends on the column and must be dropped first
+pub fn alter_embeddings_type(
+    job_name: &str,
+    prefixes: &types::TablePrefixes,
+    target_schema: &str,
+    col_type: &str,
+) -> String {
+    let embeddings_table = prefixes.embeddings_table(job_name);
+    format!(
+        "ALTER TABLE {target_schema}.{embeddings_table} ALTER COLUMN embeddings TYPE {col_type};"
+    )
+}
+
+// dimension of the job's embeddings column, from the vector(n), halfvec(n) or bit(n) type modifier
+pub fn embeddings_dim_query(
+    job_name: &str,
+    prefixes: &types::TablePrefixes,
+    target_schema: &str,
+) -> String {
+    let embeddings_table = prefixes.embeddings_table(job_name);
+    format!(
+        "SELECT atttypmod FROM pg_attribute
+        WHERE attrelid = '{target_schema}.{embeddings_table}'::regclass
+            AND attname = 'embeddings';"
+    )
+}
+
+// ON CONFLICT clause of the embeddings upsert, a conflict is a row that already has an embedding
+// value_columns are the inserted columns besides the key, overwrite replaces each of them
+pub fn embeddings_conflict_clause(
+    pkey: &[String],
+    value_columns: &[&str],
+    strategy: &types::ConflictStrategy,
+) -> String {
+    let pkey = pkey.join(", ");
+    match strategy {
+        types::ConflictStrategy::overwrite => {
+            let set_values: String = value_columns
+                .iter()
+                .map(|col| format!("{col} = EXCLUDED.{col}, "))
+                .collect();
+            format!(
+                " ON CONFLICT ({pkey})
+        DO UPDATE SET {set_values}updated_at = NOW();"
+            )
+        }
+        types::ConflictStrategy::skip => format!(" ON CONFLICT ({pkey}) DO NOTHING;"),
+    }
+}
+
+// stored content hashes of the rows whose record ids are bound to $1
+pub fn stored_content_hashes(
+    job_name: &str,
+    prefixes: &types::TablePrefixes,
+    target_schema: &str,
+    pkey: &[String],
+    pkey_type: &[String],
+) -> String {
+    let embeddings_table = prefixes.embeddings_table(job_name);
+    format!(
+        "SELECT {} AS record_id, e.content_hash
+        FROM {target_schema}.{embeddings_table} e
+        WHERE {}",
+        record_id_expr(pkey, "e"),
+        record_ids_filter(pkey, pkey_type, 1)
+    )
+}
+
+// marks the embeddings of the record ids bound to $1 as current without replacing them
+pub fn touch_embeddings(
+    job_name: &str,
+    prefixes: &types::TablePrefixes,
+    target_schema: &str,
+    pkey: &[String],
+    pkey_type: &[String],
+) -> String {
+    let embeddings_table = prefixes.embeddings_table(job_name);
+    format!(
+        "UPDATE {target_schema}.{embeddings_table} SET updated_at = NOW() WHERE {}",
+        record_ids_filter(pkey, pkey_type, 1)
+    )
+}
+
+// whether the job has any embeddings, false until the first batch is written
+pub fn has_embeddings(
+    job_name: &str,
+    prefixes: &types::TablePrefixes,
+    target_schema: &str,
+) -> String {
+    let embeddings_table = prefixes.embeddings_table(job_name);
+    format!("SELECT EXISTS (SELECT 1 FROM {target_schema}.{embeddings_table})")
+}
+
+// embeddings tables created before content hashes were stored
+pub fn add_content_hash_columns() -> String {
+    "DO $$
+    DECLARE
+        j RECORD;
+    BEGIN
+        FOR j IN SELECT target_schema, (table_prefixes->>'embeddings') || job_name AS embeddings_table FROM vectorize.job LOOP
+            IF to_regclass(format('%I.%I', j.target_schema, j.embeddings_table)) IS NOT NULL THEN
+                EXECUTE format(
+                    'ALTER TABLE %I.%I ADD COLUMN IF NOT EXISTS content_hash TEXT',
+                    j.target_schema, j.embeddings_table
+                );
+            END IF;
+        END LOOP;
+    END $$;"
+        .to_string()
+}
+
+// rows of the source table aliased `prefix` that have no embedding yet
+pub fn missing_embedding_filter(
+    job_name: &str,
+    prefixes: &types::TablePrefixes,
+    target_schema: &str,
+    pkey: &[String],
+    prefix: &str,
+) -> String {
+    let embeddings_table = prefixes.embeddings_table(job_name);
+    format!(
+        "NOT EXISTS (SELECT 1 FROM {target_schema}.{embeddings_table} e WHERE {})",
+        key_join_condition(pkey, "e", prefix)
+    )
+}
+
 pub fn init_job_query() -> String {
     format!(
         "
@@ -408,16 +1039,110 @@ pub fn init_job_query() -> String {
     )
 }
 
-pub fn drop_project_view(job_name: &str) -> String {
-    format!("DROP VIEW IF EXISTS vectorize.{job_name}_view;")
+pub fn drop_project_view(job_name: &str, target_schema: &str) -> String {
+    format!("DROP VIEW IF EXISTS {target_schema}.{job_name}_view;")
 }
 
-pub fn drop_embeddings_table(job_name: &str) -> String {
-    format!("DROP TABLE IF EXISTS vectorize._embeddings_{job_name} CASCADE;")
+pub fn drop_embeddings_table(
+    job_name: &str,
+    prefixes: &types::TablePrefixes,
+    target_schema: &str,
+) -> String {
+    let embeddings_table = prefixes.embeddings_table(job_name);
+    format!("DROP TABLE IF EXISTS {target_schema}.{embeddings_table} CASCADE;")
 }
 
-pub fn drop_search_tokens_table(job_name: &str) -> String {
-    format!("DROP TABLE IF EXISTS vectorize._search_tokens_{job_name} CASCADE;")
+pub fn truncate_embeddings_table(
+    job_name: &str,
+    prefixes: &types::TablePrefixes,
+    target_schema: &str,
+) -> String {
+    let embeddings_table = prefixes.embeddings_table(job_name);
+    format!("TRUNCATE TABLE {target_schema}.{embeddings_table};")
+}
+
+// deletes the embeddings of the record ids bound to $1
+pub fn delete_embeddings(
+    job_name: &str,
+    prefixes: &types::TablePrefixes,
+    target_schema: &str,
+    pkey: &[String],
+    pkey_type: &[String],
+) -> String {
+    let embeddings_table = prefixes.embeddings_table(job_name);
+    format!(
+        "DELETE FROM {target_schema}.{embeddings_table} WHERE {}",
+        record_ids_filter(pkey, pkey_type, 1)
+    )
+}
+
+// deletes the search tokens of the record ids bound to $1
+pub fn delete_search_tokens(
+    job_name: &str,
+    prefixes: &types::TablePrefixes,
+    target_schema: &str,
+    pkey: &[String],
+    pkey_type: &[String],
+) -> String {
+    let search_tokens_table = prefixes.search_tokens_table(job_name);
+    format!(
+        "DELETE FROM {target_schema}.{search_tokens_table} WHERE {}",
+        record_ids_filter(pkey, pkey_type, 1)
+    )
+}
+
+// the raw predicate search results are filtered on: the request's where_sql,
+// and a job's deleted_filter negated, so soft-deleted rows never match
+pub fn search_predicate(where_sql: Option<&str>, deleted_filter: Option<&str>) -> Option<String> {
+    let not_deleted = deleted_filter.map(|deleted| format!("NOT COALESCE(({deleted}), false)"));
+    match (where_sql, not_deleted) {
+        (Some(where_sql), Some(not_deleted)) => Some(format!("({where_sql}) AND {not_deleted}")),
+        (Some(where_sql), None) => Some(where_sql.to_string()),
+        (None, not_deleted) => not_deleted,
+    }
+}
+
+// progress of a job: source rows, embedded rows, last embedding time, messages still queued for it
+// and whether it is paused
+// expects the job name bound as $1
+pub fn job_status_query(
+    job_name: &str,
+    prefixes: &types::TablePrefixes,
+    src_schema: &str,
+    src_table: &str,
+    queue_name: &str,
+    target_schema: &str,
+) -> String {
+    let embeddings_table = prefixes.embeddings_table(job_name);
+    format!(
+        "SELECT
+            (SELECT COUNT(*) FROM {src_schema}.{src_table}) AS total_rows,
+            (SELECT COUNT(*) FROM {target_schema}.{embeddings_table}) AS embedded_rows,
+            (SELECT to_json(MAX(updated_at)) #>> '{{}}' FROM {target_schema}.{embeddings_table}) AS last_updated_at,
+            (SELECT COUNT(*) FROM pgmq.q_{queue_name} WHERE message->>'job_name' = $1) AS pending_messages,
+            (SELECT to_json(last_completion) #>> '{{}}' FROM vectorize.job WHERE job_name = $1) AS last_completion,
+            COALESCE((SELECT paused FROM vectorize.job WHERE job_name = $1), false) AS paused;"
+    )
+}
+
+// messages still queued for a job, a cheaper check than job_status_query
+// expects the job name bound as $1
+pub fn job_pending_messages_query(queue_name: &str) -> String {
+    format!("SELECT COUNT(*) FROM pgmq.q_{queue_name} WHERE message->>'job_name' = $1;")
+}
+
+// records that the worker finished a message of the job
+pub fn update_last_completion() -> String {
+    "UPDATE vectorize.job SET last_completion = now() WHERE job_name = $1".to_string()
+}
+
+pub fn drop_search_tokens_table(
+    job_name: &str,
+    prefixes: &types::TablePrefixes,
+    target_schema: &str,
+) -> String {
+    let search_tokens_table = prefixes.search_tokens_table(job_name);
+    format!("DROP TABLE IF EXISTS {target_schema}.{search_tokens_table} CASCADE;")
 }
 
 pub fn drop_trigger_handler(job_name: &str) -> String {
@@ -440,33 +1165,122 @@ pub fn drop_search_tokens_trigger(job_name: &str, src_schema: &str, src_table: &
     format!("DROP TRIGGER IF EXISTS {job_name}_search_tokens_trigger ON {src_schema}.{src_table};")
 }
 
+// whether each database object of a job exists, one boolean column per object
+// unquoted names are folded to lowercase when they are created, and so are the names looked up here
+pub fn job_objects_query(
+    job_name: &str,
+    prefixes: &types::TablePrefixes,
+    src_schema: &str,
+    src_table: &str,
+    target_schema: &str,
+    index_dist: &types::IndexDist,
+) -> String {
+    let embeddings_table = prefixes.embeddings_table(job_name);
+    let search_tokens_table = prefixes.search_tokens_table(job_name);
+    let embedding_index = embedding_index_name(job_name, index_dist);
+    let trigger_exists = |name: String| {
+        format!(
+            "EXISTS (SELECT 1 FROM pg_trigger WHERE tgrelid = to_regclass('{src_schema}.{src_table}') AND tgname = '{}')",
+            name.to_lowercase()
+        )
+    };
+    format!(
+        "SELECT
+            to_regclass('{target_schema}.{embeddings_table}') IS NOT NULL AS embeddings_table,
+            to_regclass('{target_schema}.{search_tokens_table}') IS NOT NULL AS search_tokens_table,
+            to_regclass('{target_schema}.{job_name}_view') IS NOT NULL AS view,
+            to_regclass('{target_schema}.{embedding_index}') IS NOT NULL AS embedding_index,
+            to_regclass('{target_schema}.{job_name}_gin_idx') IS NOT NULL AS fts_index,
+            to_regprocedure('{TRIGGER_FN_PREFIX}{job_name}()') IS NOT NULL AS trigger_handler,
+            {} AS insert_trigger,
+            {} AS update_trigger,
+            {} AS search_tokens_trigger;",
+        trigger_exists(format!("vectorize_insert_trigger_{job_name}")),
+        trigger_exists(format!("vectorize_update_trigger_{job_name}")),
+        trigger_exists(format!("{job_name}_search_tokens_trigger")),
+    )
+}
+
 pub fn delete_job_record(job_name: &str) -> String {
     format!("DELETE FROM vectorize.job WHERE job_name = '{job_name}';")
 }
 
 /// creates a function that can be called by trigger
-pub fn create_trigger_handler(job_name: &str, pkey: &str) -> String {
-    format!(
-        "
+/// with watch_columns, updated rows are only enqueued when one of those columns changed
+/// with queue_name, rows are enqueued onto that queue instead of vectorize_jobs
+pub fn create_trigger_handler(
+    job_name: &str,
+    pkey: &[String],
+    watch_columns: Option<&[String]>,
+    queue_name: Option<&str>,
+) -> String {
+    let record_id = record_id_expr(pkey, "new_table");
+    let queue_arg = queue_name
+        .map(|q| format!(", '{q}'::text"))
+        .unwrap_or_default();
+    let Some(watch_columns) = watch_columns else {
+        return format!(
+            "
 CREATE OR REPLACE FUNCTION {TRIGGER_FN_PREFIX}{job_name}()
 RETURNS TRIGGER AS $$
 DECLARE
 BEGIN
     PERFORM vectorize._handle_table_update(
         '{job_name}'::text,
-       (SELECT array_agg({pkey}::text) FROM new_table)::TEXT[]
+       (SELECT array_agg({record_id}) FROM new_table)::TEXT[]{queue_arg}
     );
     RETURN NULL;
 END;
 $$ LANGUAGE plpgsql;    
+"
+        );
+    };
+    // old_table only exists for updates, plpgsql plans the statement when it first runs
+    let changed = watch_condition(watch_columns, "new_table", "old_table");
+    let join = key_join_condition(pkey, "new_table", "old_table");
+    format!(
+        "
+CREATE OR REPLACE FUNCTION {TRIGGER_FN_PREFIX}{job_name}()
+RETURNS TRIGGER AS $$
+DECLARE
+BEGIN
+    IF TG_OP = 'UPDATE' THEN
+        PERFORM vectorize._handle_table_update(
+            '{job_name}'::text,
+           (SELECT array_agg({record_id}) FROM new_table JOIN old_table ON {join} WHERE {changed})::TEXT[]{queue_arg}
+        );
+    ELSE
+        PERFORM vectorize._handle_table_update(
+            '{job_name}'::text,
+           (SELECT array_agg({record_id}) FROM new_table)::TEXT[]{queue_arg}
+        );
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
 "
     )
 }
 
+// true for rows where any of the watched columns differs between the new and old row,
+// compared as text since types such as json, xml and point have no equality operator
+fn watch_condition(watch_columns: &[String], new: &str, old: &str) -> String {
+    let as_text = |prefix: &str| {
+        watch_columns
+            .iter()
+            .map(|c| format!("{prefix}.{c}::text"))
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+    format!("({}) IS DISTINCT FROM ({})", as_text(new), as_text(old))
+}
+
+// enqueues record_ids of a job onto the deployment's queue, called by the job's trigger handler
 pub fn handle_table_update() -> String {
     "CREATE OR REPLACE FUNCTION vectorize._handle_table_update(
     job_name text,
-    record_ids text[]
+    record_ids text[],
+    queue_name text
 ) RETURNS void AS $$
 DECLARE
     batch_size integer;
@@ -492,7 +1306,7 @@ BEGIN
     END LOOP;
 
     PERFORM pgmq.send_batch(
-        queue_name=>'vectorize_jobs'::text,
+        queue_name=>queue_name,
         msgs=>job_messages::jsonb[])
     ;
 
@@ -501,6 +1315,19 @@ $$ LANGUAGE plpgsql;"
         .to_string()
 }
 
+// the handler without a queue_name, for trigger handlers created before the queue was configurable
+pub fn handle_table_update_default_queue() -> String {
+    "CREATE OR REPLACE FUNCTION vectorize._handle_table_update(
+    job_name text,
+    record_ids text[]
+) RETURNS void AS $$
+BEGIN
+    PERFORM vectorize._handle_table_update(job_name, record_ids, 'vectorize_jobs'::text);
+END;
+$$ LANGUAGE plpgsql;"
+        .to_string()
+}
+
 pub fn create_batch_texts_fn() -> String {
     "CREATE OR REPLACE FUNCTION vectorize.batch_texts(
     record_ids text[],
@@ -540,12 +1367,17 @@ $$ LANGUAGE plpgsql;"
 // these triggers use transition tables
 // transition tables cannot be specified for triggers with more than one event
 // so we create two triggers instead
+// updates also reference the old rows, so the handler can skip rows whose watched columns did not change
 pub fn create_event_trigger(job_name: &str, schema: &str, table_name: &str, event: &str) -> String {
+    let transition_tables = match event {
+        "UPDATE" => "OLD TABLE AS old_table NEW TABLE AS new_table",
+        _ => "NEW TABLE AS new_table",
+    };
     format!(
         "
 CREATE OR REPLACE TRIGGER vectorize_{event_name}_trigger_{job_name}
 AFTER {event} ON {schema}.{table_name}
-REFERENCING NEW TABLE AS new_table
+REFERENCING {transition_tables}
 FOR EACH STATEMENT
 EXECUTE FUNCTION vectorize.handle_update_{job_name}();",
         event_name = event.to_lowercase()
@@ -553,26 +1385,41 @@ EXECUTE FUNCTION vectorize.handle_update_{job_name}();",
 }
 
 // generates query to fetch new rows have had data changed since last embedding generation
+#[allow(clippy::too_many_arguments)]
 pub fn new_rows_query_join(
     job_name: &str,
+    prefixes: &types::TablePrefixes,
     columns: &[String],
+    text_join: Option<&types::TextJoin>,
     schema: &str,
     table: &str,
-    pkey: &str,
+    pkey: &[String],
     update_time_col: Option<String>,
+    all_rows: bool,
+    target_schema: &str,
 ) -> String {
-    let cols = columns
-        .iter()
-        .map(|s| format!("t0.{s}"))
-        .collect::<Vec<_>>()
-        .join(",");
+    let embeddings_table = prefixes.embeddings_table(job_name);
+    let cols = generate_column_concat(columns, text_join, schema, "t0");
+    let record_id = record_id_expr(pkey, "t0");
+
+    if all_rows {
+        // every row in the source table, regardless of existing embeddings or update time
+        return format!(
+            "
+    SELECT {record_id} as record_id, {cols} as input_text
+    FROM {schema}.{table} t0"
+        );
+    }
 
+    let join_condition = key_join_condition(pkey, "t0", "t1");
+    // key columns are NOT NULL in the embeddings table, so any of them marks a missing row
+    let missing_key = &pkey[0];
     let base_query = format!(
         "
-    SELECT t0.{pkey}::text as record_id, {cols} as input_text
+    SELECT {record_id} as record_id, {cols} as input_text
     FROM {schema}.{table} t0
-    LEFT JOIN vectorize._embeddings_{job_name} t1 ON t0.{pkey} = t1.{pkey}
-    WHERE t1.{pkey} IS NULL"
+    LEFT JOIN {target_schema}.{embeddings_table} t1 ON {join_condition}
+    WHERE t1.{missing_key} IS NULL"
     );
     if let Some(updated_at_col) = update_time_col {
         // updated_at_column is not required when `schedule` is realtime
@@ -595,19 +1442,20 @@ pub fn new_rows_query_join(
     }
 }
 
+// token estimates are counted with the tokenizer of the job's model
 pub async fn get_new_updates<'c, E: sqlx::Executor<'c, Database = Postgres>>(
     pool: E,
     query: &str,
+    tokenizer: &Tokenizer,
 ) -> Result<Option<Vec<Inputs>>, Error> {
     let rows: Result<Vec<PgRow>, Error> = sqlx::query(query).fetch_all(pool).await;
     match rows {
         Ok(rows) => {
             if !rows.is_empty() {
-                let bpe = cl100k_base().unwrap();
                 let mut new_inputs: Vec<Inputs> = Vec::new();
                 for r in rows {
                     let ipt: String = r.get("input_text");
-                    let token_estimate = bpe.encode_with_special_tokens(&ipt).len() as i32;
+                    let token_estimate = tokenizer.count(&ipt) as i32;
                     new_inputs.push(Inputs {
                         record_id: r.get("record_id"),
                         inputs: ipt.trim().to_owned(),
@@ -633,7 +1481,7 @@ pub fn create_batches(data: Vec<Inputs>, batch_size: i32) -> Vec<Vec<Inputs>> {
     let mut current_token_count = 0;
 
     for input in data {
-        if current_token_count + input.token_estimate > batch_size {
+        if current_token_count + input.token_estimate > batch_size && !current_group.is_empty() {
             // Create a new group
             groups.push(current_group);
             current_group = Vec::new();
@@ -650,36 +1498,48 @@ pub fn create_batches(data: Vec<Inputs>, batch_size: i32) -> Vec<Vec<Inputs>> {
     groups
 }
 
+// WHERE clause of the filters, their values are bound from $first_bind on in key order
+fn filter_conditions(filters: &BTreeMap<String, FilterValue>, first_bind: i16) -> String {
+    let mut where_filter = "WHERE 1=1".to_string();
+    for (bind_value_counter, (key, filter_value)) in (first_bind..).zip(filters.iter()) {
+        let column = FilterColumn::parse(key).expect("invalid filter column");
+        let filt = format!(
+            " AND {}",
+            column.condition(filter_value, bind_value_counter)
+        );
+        where_filter.push_str(&filt);
+    }
+    where_filter
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn join_table_cosine_similarity(
     project: &str,
+    prefixes: &types::TablePrefixes,
     schema: &str,
     table: &str,
     join_key: &str,
     return_columns: &[String],
     num_results: i32,
     filters: &BTreeMap<String, FilterValue>,
+    target_schema: &str,
 ) -> String {
+    let embeddings_table = prefixes.embeddings_table(project);
     let cols = &return_columns
         .iter()
         .map(|s| format!("t0.{s}"))
         .collect::<Vec<_>>()
         .join(",");
 
-    let mut bind_value_counter: i16 = 2; // Start at $2 since $1 is the vector
-    let mut where_filter = "WHERE 1=1".to_string();
-    for (column, filter_value) in filters.iter() {
-        let operator = filter_value.operator.to_sql();
-        let filt = format!(" AND t0.\"{column}\" {operator} ${bind_value_counter}");
-        where_filter.push_str(&filt);
-        bind_value_counter += 1;
-    }
+    // Start at $2 since $1 is the vector
+    let where_filter = filter_conditions(filters, 2);
 
     let inner_query = format!(
         "
     SELECT
         {join_key},
         1 - (embeddings <=> $1::vector) AS similarity_score
-    FROM vectorize._embeddings_{project}
+    FROM {target_schema}.{embeddings_table}
     ORDER BY similarity_score DESC
     "
     );
@@ -701,12 +1561,26 @@ pub fn join_table_cosine_similarity(
     )
 }
 
+// the full-text query of a search: plain words match rows with any of them, like the semantic side
+// does, while queries using web search syntax, a "phrase", or, or -word, are parsed with
+// websearch_to_tsquery. Neither parser fails on punctuation or unbalanced quotes
+fn fts_query(fts_language: &str, param: &str) -> String {
+    format!(
+        "(CASE WHEN {param} ~* '\"|(^|\\s)-\\S|\\mor\\M'
+                         THEN websearch_to_tsquery('{fts_language}', {param})
+                         ELSE to_tsquery('{fts_language}',
+                             NULLIF(replace(plainto_tsquery('{fts_language}', {param})::text, ' & ', ' | '), ''))
+                     END)"
+    )
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn hybrid_search_query(
     job_name: &str,
+    prefixes: &types::TablePrefixes,
     src_schema: &str,
     src_table: &str,
-    join_key: &str,
+    join_key: &[String],
     return_columns: &[String],
     window_size: i32,
     limit: i32,
@@ -714,71 +1588,168 @@ pub fn hybrid_search_query(
     semantic_weight: f32,
     fts_weight: f32,
     filters: &BTreeMap<String, FilterValue>,
+    index_dist: &types::IndexDist,
+    vector_type: &types::VectorType,
+    target_schema: &str,
+    with_embeddings: bool,
+    min_score: Option<f32>,
+    where_sql: Option<&str>,
+    fts_language: &str,
+    mode: SearchMode,
 ) -> String {
+    let embeddings_table = prefixes.embeddings_table(job_name);
+    let search_tokens_table = prefixes.search_tokens_table(job_name);
     let cols = &return_columns
         .iter()
         .map(|s| format!("t0.{s}"))
         .collect::<Vec<_>>()
         .join(",");
 
-    let mut bind_value_counter: i16 = 3;
-    let mut where_filter = "WHERE 1=1".to_string();
-    for (column, filter_value) in filters.iter() {
-        let operator = filter_value.operator.to_sql();
-        let filt = format!(" AND t0.\"{column}\" {operator} ${bind_value_counter}");
-        where_filter.push_str(&filt);
-        bind_value_counter += 1;
-    }
+    // Start at $3 since $1 is the vector and $2 is the query text
+    let where_filter = filter_conditions(filters, 3);
+
+    let distance_operator = index_dist.distance_operator();
+    let similarity_score = index_dist.similarity_score();
+    // the query embedding gets the column's type, so the index is used
+    let query_embedding = vector_type.cast_param("$1");
+
+    // semantic candidates below the threshold are dropped before they are ranked and combined,
+    // and full-text matches without a semantic candidate have no score to clear it
+    let (semantic_filter, scored_only) = match min_score {
+        Some(min_score) => (
+            format!("WHERE {similarity_score} >= {min_score}"),
+            "\n            WHERE s.similarity_score IS NOT NULL",
+        ),
+        None => (String::new(), ""),
+    };
+
+    let keys = join_key.join(", ");
+    let merged_keys = |expr: &dyn Fn(&String) -> String| {
+        join_key
+            .iter()
+            .map(expr)
+            .collect::<Vec<String>>()
+            .join(",\n                ")
+    };
+    let src_join = key_join_condition(join_key, "t0", "t");
+
+    // raw predicates are applied to the source table alone, so its columns need no qualifier
+    let src = match where_sql {
+        Some(where_sql) => format!("(SELECT * FROM {src_schema}.{src_table} WHERE {where_sql})"),
+        None => format!("{src_schema}.{src_table}"),
+    };
+
+    // each result's embedding as a float array, e.g. for re-ranking by the caller
+    let (embedding_col, embedding_join) = if with_embeddings {
+        (
+            format!(
+                ", {} as embedding",
+                vector_type.to_real_array("e.embeddings")
+            ),
+            format!(
+                "LEFT JOIN {target_schema}.{embeddings_table} e ON {}",
+                key_join_condition(join_key, "e", "t")
+            ),
+        )
+    } else {
+        (String::new(), String::new())
+    };
 
-    format!(
-        "
-    SELECT to_jsonb(t) as results
-    FROM (
-        SELECT {cols}, t.rrf_score, t.semantic_rank, t.fts_rank, t.similarity_score
-        FROM (
-            SELECT
-                COALESCE(s.{join_key}, f.{join_key}) as {join_key},
-                s.semantic_rank,
-                s.similarity_score,
-                f.fts_rank,
-                (
-                    COALESCE({semantic_weight}::float / ({rrf_k} + s.semantic_rank), 0) +
-                    COALESCE({fts_weight}::float / ({rrf_k} + f.fts_rank), 0)
-                ) as rrf_score
-            FROM (
+    let semantic_candidates = format!(
+        "(
                 SELECT
-                    {join_key},
+                    {keys},
                     distance,
-                    ROW_NUMBER() OVER (ORDER BY distance) as semantic_rank,
-                    1 - distance as similarity_score
+                    ROW_NUMBER() OVER (ORDER BY distance, {keys}) as semantic_rank,
+                    {similarity_score} as similarity_score
                 FROM (
                     SELECT
-                        {join_key},
-                        embeddings <=> $1::vector as distance
-                    FROM vectorize._embeddings_{job_name}
+                        {keys},
+                        embeddings {distance_operator} {query_embedding} as distance
+                    FROM {target_schema}.{embeddings_table}
                 ) sub
-                ORDER BY distance
+                {semantic_filter}
+                ORDER BY distance, {keys}
                 LIMIT {window_size}
-            ) s
-            FULL OUTER JOIN (
+            ) s"
+    );
+    let fts_query = fts_query(fts_language, "$2");
+    let fts_candidates = format!(
+        "(
                 SELECT
-                    {join_key},
-                    ROW_NUMBER() OVER (ORDER BY ts_rank_cd(search_tokens, query) DESC) as fts_rank
-                FROM vectorize._search_tokens_{job_name},
-                     to_tsquery('english',
-                         NULLIF(
-                             replace(plainto_tsquery('english', $2)::text, ' & ', ' | '),
-                             ''
-                         )
-                     ) as query
+                    {keys},
+                    ROW_NUMBER() OVER (ORDER BY ts_rank_cd(search_tokens, query) DESC, {keys}) as fts_rank
+                FROM {target_schema}.{search_tokens_table},
+                     (SELECT {fts_query} as query) fts_query
                 WHERE search_tokens @@ query
-                ORDER BY ts_rank_cd(search_tokens, query) DESC
+                ORDER BY ts_rank_cd(search_tokens, query) DESC, {keys}
                 LIMIT {window_size}
-            ) f ON s.{join_key} = f.{join_key}
+            ) f"
+    );
+    let semantic_score =
+        format!("COALESCE({semantic_weight}::float / ({rrf_k} + s.semantic_rank), 0)");
+    let fts_score = format!("COALESCE({fts_weight}::float / ({rrf_k} + f.fts_rank), 0)");
+
+    // a single-ranking mode keeps the result columns of hybrid search, the other ranking is null
+    // $1 or $2 is then unused, their types are still given by the bound values
+    // equal scores are ordered by the primary key, so repeated searches and pages agree
+    let tie_break = join_key
+        .iter()
+        .map(|k| format!("t.{k}"))
+        .collect::<Vec<String>>()
+        .join(", ");
+    let (keys_select, ranks, rrf_score, candidates, order_by) = match mode {
+        SearchMode::Hybrid => (
+            merged_keys(&|k| format!("COALESCE(s.{k}, f.{k}) as {k}")),
+            "s.semantic_rank,
+                s.similarity_score,
+                f.fts_rank",
+            format!("{semantic_score} +\n                    {fts_score}"),
+            format!(
+                "{semantic_candidates}
+            FULL OUTER JOIN {fts_candidates} ON {}",
+                key_join_condition(join_key, "s", "f")
+            ),
+            "t.rrf_score DESC",
+        ),
+        SearchMode::Semantic => (
+            merged_keys(&|k| format!("s.{k}")),
+            "s.semantic_rank,
+                s.similarity_score,
+                NULL::bigint as fts_rank",
+            semantic_score,
+            semantic_candidates,
+            "t.semantic_rank",
+        ),
+        SearchMode::Fts => (
+            merged_keys(&|k| format!("f.{k}")),
+            "NULL::bigint as semantic_rank,
+                NULL::float as similarity_score,
+                f.fts_rank",
+            fts_score,
+            fts_candidates,
+            "t.fts_rank",
+        ),
+    };
+
+    format!(
+        "
+    SELECT to_jsonb(t) as results
+    FROM (
+        SELECT {cols}, t.rrf_score, t.semantic_rank, t.fts_rank, t.similarity_score{embedding_col}
+        FROM (
+            SELECT
+                {keys_select},
+                {ranks},
+                (
+                    {rrf_score}
+                ) as rrf_score
+            FROM {candidates}{scored_only}
         ) t
-        INNER JOIN {src_schema}.{src_table} t0 ON t0.{join_key} = t.{join_key}
+        INNER JOIN {src} t0 ON {src_join}
+        {embedding_join}
         {where_filter}
-        ORDER BY t.rrf_score DESC
+        ORDER BY {order_by}, {tie_break}
         LIMIT {limit}
     ) t"
     )
@@ -796,7 +1767,7 @@ mod tests {
         let expected = "
 CREATE OR REPLACE TRIGGER vectorize_update_trigger_another_job
 AFTER UPDATE ON myschema.another_table
-REFERENCING NEW TABLE AS new_table
+REFERENCING OLD TABLE AS old_table NEW TABLE AS new_table
 FOR EACH STATEMENT
 EXECUTE FUNCTION vectorize.handle_update_another_job();"
             .to_string();
@@ -804,6 +1775,72 @@ EXECUTE FUNCTION vectorize.handle_update_another_job();"
         assert_eq!(expected, result);
     }
 
+    #[test]
+    fn test_job_objects_query() {
+        let q = job_objects_query(
+            "My_Job",
+            &types::TablePrefixes::default(),
+            "public",
+            "products",
+            "vectorize",
+            &types::IndexDist::pgv_hnsw_cosine,
+        );
+        assert!(q.contains(
+            "to_regclass('vectorize._embeddings_My_Job') IS NOT NULL AS embeddings_table"
+        ));
+        assert!(q.contains("to_regclass('vectorize.My_Job_view') IS NOT NULL AS view"));
+        assert!(q.contains(
+            "to_regclass('vectorize.My_Job_hnsw_cos_idx') IS NOT NULL AS embedding_index"
+        ));
+        // trigger names are compared in the lowercase form they were created with
+        assert!(q.contains(
+            "tgrelid = to_regclass('public.products') AND tgname = 'vectorize_update_trigger_my_job'"
+        ));
+        assert!(q.contains("tgname = 'my_job_search_tokens_trigger') AS search_tokens_trigger"));
+    }
+
+    #[test]
+    fn test_create_trigger_handler_watch_columns() {
+        let keys = vec!["id".to_string()];
+        // a derived text column, re-embedded whenever its version changes
+        let watch_columns = vec!["content_version".to_string()];
+        let handler = create_trigger_handler("my_job", &keys, Some(&watch_columns), None);
+        assert!(handler.contains("IF TG_OP = 'UPDATE' THEN"));
+        assert!(handler.contains(
+            "(SELECT array_agg(new_table.id::text) FROM new_table JOIN old_table ON new_table.id = old_table.id WHERE (new_table.content_version::text) IS DISTINCT FROM (old_table.content_version::text))::TEXT[]"
+        ));
+        // inserts are always enqueued
+        assert!(handler.contains("(SELECT array_agg(new_table.id::text) FROM new_table)::TEXT[]"));
+
+        let watch_columns = vec!["title".to_string(), "content_version".to_string()];
+        let handler = create_trigger_handler("my_job", &keys, Some(&watch_columns), None);
+        assert!(handler.contains(
+            "WHERE (new_table.title::text, new_table.content_version::text) IS DISTINCT FROM (old_table.title::text, old_table.content_version::text)"
+        ));
+
+        // without watch_columns every updated row is enqueued
+        let handler = create_trigger_handler("my_job", &keys, None, None);
+        assert!(!handler.contains("old_table"));
+    }
+
+    #[test]
+    fn test_create_trigger_handler_queue_name() {
+        let keys = vec!["id".to_string()];
+        let watch_columns = vec!["content".to_string()];
+        let handler =
+            create_trigger_handler("my_job", &keys, Some(&watch_columns), Some("tenant_a_jobs"));
+        assert_eq!(
+            handler.matches("::TEXT[], 'tenant_a_jobs'::text\n").count(),
+            2
+        );
+
+        // the two argument handler enqueues onto vectorize_jobs
+        let handler = create_trigger_handler("my_job", &keys, None, None);
+        assert!(handler.contains("FROM new_table)::TEXT[]\n"));
+        assert!(handle_table_update_default_queue().contains("'vectorize_jobs'::text"));
+        assert!(handle_table_update().contains("queue_name=>queue_name"));
+    }
+
     #[test]
     fn test_create_insert_trigger_single() {
         let job_name = "another_job";
@@ -820,6 +1857,95 @@ EXECUTE FUNCTION vectorize.handle_update_another_job();"
         assert_eq!(expected, result);
     }
 
+    #[test]
+    fn test_new_rows_query_join_multiple_columns() {
+        let columns = vec!["title".to_string(), "body".to_string()];
+        let result = new_rows_query_join(
+            "my_job",
+            &types::TablePrefixes::default(),
+            &columns,
+            None,
+            "public",
+            "docs",
+            &["id".to_string()],
+            Some("updated_at".to_string()),
+            false,
+            "vectorize",
+        );
+        assert!(result.contains(
+            "SELECT t0.id::text as record_id, COALESCE(t0.title, '') || ' ' || COALESCE(t0.body, '') as input_text"
+        ));
+        assert!(result.contains("LEFT JOIN vectorize._embeddings_my_job t1 ON t0.id = t1.id"));
+        assert!(result.contains("OR t0.updated_at >"));
+    }
+
+    #[test]
+    fn test_new_rows_query_join_all_rows() {
+        let columns = vec!["content".to_string()];
+        let result = new_rows_query_join(
+            "my_job",
+            &types::TablePrefixes::default(),
+            &columns,
+            None,
+            "public",
+            "docs",
+            &["id".to_string()],
+            Some("updated_at".to_string()),
+            true,
+            "vectorize",
+        );
+        assert!(result.contains("FROM public.docs t0"));
+        assert!(!result.contains("WHERE"));
+        assert!(!result.contains("_embeddings_my_job"));
+        assert!(!result.contains("updated_at"));
+    }
+
+    #[test]
+    fn test_new_rows_query_join_text_join() {
+        let text_join = types::TextJoin {
+            schema: None,
+            table: "doc_bodies".to_string(),
+            src_column: "id".to_string(),
+            join_column: "doc_id".to_string(),
+            columns: vec!["body".to_string()],
+        };
+        let result = new_rows_query_join(
+            "my_job",
+            &types::TablePrefixes::default(),
+            &["title".to_string()],
+            Some(&text_join),
+            "public",
+            "docs",
+            &["id".to_string()],
+            Some("updated_at".to_string()),
+            false,
+            "vectorize",
+        );
+        assert!(result.contains(
+            "COALESCE(t0.title, '') || ' ' || COALESCE((SELECT j.body FROM public.doc_bodies j WHERE j.doc_id = t0.id), '') as input_text"
+        ));
+
+        // the joined table may live in another schema, and the triggers read it for the changed row
+        let text_join = types::TextJoin {
+            schema: Some("content".to_string()),
+            ..text_join
+        };
+        let triggers = update_search_tokens_trigger_queries(
+            "my_job",
+            &types::TablePrefixes::default(),
+            &["id".to_string()],
+            "public",
+            "docs",
+            &[],
+            Some(&text_join),
+            "vectorize",
+            "english",
+        );
+        assert!(triggers[0].contains(
+            "to_tsvector('english', COALESCE((SELECT j.body FROM content.doc_bodies j WHERE j.doc_id = NEW.id), ''))"
+        ));
+    }
+
     // ===== FilterValue Deserialization Tests =====
 
     #[test]
@@ -894,6 +2020,58 @@ EXECUTE FUNCTION vectorize.handle_update_another_job();"
         assert_eq!(filter.value.as_sql_value(), "42");
     }
 
+    #[test]
+    fn test_filter_value_coerce_to_column() {
+        let filter = |json: &str| serde_json::from_str::<FilterValue>(json).unwrap();
+
+        // numbers stay numbers for numeric columns, so gt.100 excludes 99
+        let mut price = filter("\"gt.100\"");
+        price.coerce_to_column("numeric").unwrap();
+        assert_eq!(price.value, FilterValueType::Integer(100));
+        assert!(filter("\"eq.abc\"").coerce_to_column("integer").is_err());
+        assert!(
+            filter("\"eq.true\"")
+                .coerce_to_column("double precision")
+                .is_err()
+        );
+
+        // text columns compare the value as written
+        let mut sku = filter("\"eq.00123\"");
+        sku.coerce_to_column("text").unwrap();
+        assert_eq!(sku.value, FilterValueType::String("00123".to_string()));
+        let mut flag = filter("true");
+        flag.coerce_to_column("character varying").unwrap();
+        assert_eq!(flag.value, FilterValueType::String("true".to_string()));
+        // text is ordered as text
+        let mut name = filter("\"gte.m\"");
+        name.coerce_to_column("text").unwrap();
+        assert_eq!(name.value, FilterValueType::String("m".to_string()));
+        filter("\"ilike.desk%\"").coerce_to_column("text").unwrap();
+        assert!(filter("\"like.1%\"").coerce_to_column("numeric").is_err());
+
+        let mut in_stock = filter("\"eq.false\"");
+        in_stock.coerce_to_column("boolean").unwrap();
+        assert_eq!(in_stock.value, FilterValueType::Boolean(false));
+        assert!(filter("\"eq.1\"").coerce_to_column("boolean").is_err());
+
+        // other types cast the value as written to the column's type
+        let mut day = filter("\"gt.2024-01-01\"");
+        day.coerce_to_column("date").unwrap();
+        assert_eq!(day.value, FilterValueType::String("2024-01-01".to_string()));
+        assert_eq!(day.cast.as_deref(), Some("date"));
+        let mut year = filter("\"gte.2024\"");
+        year.coerce_to_column("timestamp with time zone").unwrap();
+        assert_eq!(year.value, FilterValueType::String("2024".to_string()));
+        let column = FilterColumn::parse("created_at").unwrap();
+        assert_eq!(
+            column.condition(&year, 3),
+            "t0.\"created_at\" >= $3::timestamp with time zone"
+        );
+        let mut price = filter("\"gt.100\"");
+        price.coerce_to_column("numeric").unwrap();
+        assert!(price.cast.is_none());
+    }
+
     // ===== Edge Case Tests =====
 
     #[test]
@@ -991,7 +2169,7 @@ EXECUTE FUNCTION vectorize.handle_update_another_job();"
 
     #[test]
     fn test_filter_value_deserialize_comparison_with_string() {
-        // Test that comparison operators fail with non-numeric values
+        // comparison operators take text values, which only numeric columns reject
         let test_cases = vec![
             ("gt", "hello"),
             ("gte", "world"),
@@ -1001,14 +2179,11 @@ EXECUTE FUNCTION vectorize.handle_update_another_job();"
 
         for (op, value) in test_cases {
             let json = format!("\"{}.{}\"", op, value);
-            let result: Result<FilterValue, _> = serde_json::from_str(&json);
-            assert!(
-                result.is_err(),
-                "Should fail for non-numeric value with {} operator",
-                op
-            );
-            let error = result.unwrap_err();
-            assert!(error.to_string().contains("require numeric values"));
+            let mut filter: FilterValue = serde_json::from_str(&json).unwrap();
+            assert_eq!(filter.value, FilterValueType::String(value.to_string()));
+            let error = filter.clone().coerce_to_column("numeric").unwrap_err();
+            assert!(error.to_string().contains("requires a numeric value"));
+            filter.coerce_to_column("text").unwrap();
         }
     }
 
@@ -1039,6 +2214,47 @@ EXECUTE FUNCTION vectorize.handle_update_another_job();"
         }
     }
 
+    #[test]
+    fn test_filter_value_deserialize_json_scalars() {
+        let filter: FilterValue = serde_json::from_str("25").unwrap();
+        assert_eq!(filter.operator, FilterOperator::Equal);
+        assert_eq!(filter.value, FilterValueType::Integer(25));
+
+        let filter: FilterValue = serde_json::from_str("-3").unwrap();
+        assert_eq!(filter.value, FilterValueType::Integer(-3));
+
+        let filter: FilterValue = serde_json::from_str("19.99").unwrap();
+        assert_eq!(filter.operator, FilterOperator::Equal);
+        assert_eq!(filter.value, FilterValueType::Float(19.99));
+
+        let filter: FilterValue = serde_json::from_str("true").unwrap();
+        assert_eq!(filter.operator, FilterOperator::Equal);
+        assert_eq!(filter.value, FilterValueType::Boolean(true));
+
+        assert!(serde_json::from_str::<FilterValue>("18446744073709551615").is_err());
+        assert!(serde_json::from_str::<FilterValue>("null").is_err());
+        assert!(serde_json::from_str::<FilterValue>("[1, 2]").is_err());
+    }
+
+    #[test]
+    fn test_filter_value_deserialize_nested_json_object() {
+        let json =
+            r#"{"price": "gte.25", "stock": 3, "in_stock": true, "category": "electronics"}"#;
+        let filters: BTreeMap<String, FilterValue> = serde_json::from_str(json).unwrap();
+        assert_eq!(filters.len(), 4);
+        assert_eq!(
+            filters["price"].operator,
+            FilterOperator::GreaterThanOrEqual
+        );
+        assert_eq!(filters["price"].value, FilterValueType::Integer(25));
+        assert_eq!(filters["stock"].value, FilterValueType::Integer(3));
+        assert_eq!(filters["in_stock"].value, FilterValueType::Boolean(true));
+        assert_eq!(
+            filters["category"].value,
+            FilterValueType::String("electronics".to_string())
+        );
+    }
+
     #[test]
     fn test_filter_value_deserialize_empty_input() {
         let json = "\"\"";
@@ -1398,19 +2614,14 @@ EXECUTE FUNCTION vectorize.handle_update_another_job();"
 
     #[test]
     fn test_filter_value_deserialize_boolean_comparison_operators() {
-        // Test that comparison operators with boolean values fail (as they should require numeric values)
+        // booleans are not ordered, comparisons with them fail for boolean and numeric columns
         let test_cases = vec!["gt.true", "gte.false", "lt.true", "lte.false"];
 
         for input in test_cases {
             let json = format!("\"{}\"", input);
-            let result: Result<FilterValue, _> = serde_json::from_str(&json);
-            assert!(
-                result.is_err(),
-                "Should fail for boolean value with comparison operator: {}",
-                input
-            );
-            let error = result.unwrap_err();
-            assert!(error.to_string().contains("require numeric values"));
+            let filter: FilterValue = serde_json::from_str(&json).unwrap();
+            assert!(filter.clone().coerce_to_column("boolean").is_err());
+            assert!(filter.clone().coerce_to_column("integer").is_err());
         }
     }
 
@@ -1431,20 +2642,840 @@ EXECUTE FUNCTION vectorize.handle_update_another_job();"
         }
     }
 
+    #[test]
+    fn test_filter_value_deserialize_like_operators() {
+        let test_cases = vec![
+            ("\"like.Desk%\"", FilterOperator::Like, "Desk%"),
+            ("\"ilike.desk%\"", FilterOperator::ILike, "desk%"),
+            ("\"like.%lamp_\"", FilterOperator::Like, "%lamp_"),
+            // numeric and boolean looking patterns must stay strings
+            ("\"ilike.10\"", FilterOperator::ILike, "10"),
+            ("\"like.true\"", FilterOperator::Like, "true"),
+            // only the first dot separates the operator
+            ("\"ilike.%.com\"", FilterOperator::ILike, "%.com"),
+        ];
+
+        for (input, expected_op, expected_value) in test_cases {
+            let filter: FilterValue = serde_json::from_str(input).unwrap();
+            assert_eq!(filter.operator, expected_op);
+            assert_eq!(
+                filter.value,
+                FilterValueType::String(expected_value.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn test_filter_like_operator_to_sql() {
+        assert_eq!(FilterOperator::Like.to_sql(), "LIKE");
+        assert_eq!(FilterOperator::ILike.to_sql(), "ILIKE");
+    }
+
+    #[test]
+    fn test_hybrid_search_query_ilike_filter() {
+        let mut filters = BTreeMap::new();
+        filters.insert(
+            "product_name".to_string(),
+            serde_json::from_str::<FilterValue>("\"ilike.desk%\"").unwrap(),
+        );
+        let q = hybrid_search_query(
+            "test_job",
+            &types::TablePrefixes::default(),
+            "public",
+            "products",
+            &["product_id".to_string()],
+            &["product_name".to_string()],
+            20,
+            5,
+            60.0,
+            1.0,
+            1.0,
+            &filters,
+            &types::IndexDist::pgv_hnsw_cosine,
+            &types::VectorType::vector,
+            "vectorize",
+            false,
+            None,
+            None,
+            "english",
+            SearchMode::Hybrid,
+        );
+        assert!(q.contains("AND t0.\"product_name\" ILIKE $3"));
+        assert!(!q.contains("desk%"));
+    }
+
+    #[test]
+    fn test_fts_language() {
+        let triggers = update_search_tokens_trigger_queries(
+            "my_job",
+            &types::TablePrefixes::default(),
+            &["id".to_string()],
+            "public",
+            "docs",
+            &["content".to_string()],
+            None,
+            "vectorize",
+            "spanish",
+        );
+        assert_eq!(triggers[0].matches("to_tsvector('spanish', ").count(), 4);
+        assert!(!triggers[0].contains("english"));
+
+        let q = hybrid_search_query(
+            "my_job",
+            &types::TablePrefixes::default(),
+            "public",
+            "docs",
+            &["id".to_string()],
+            &["content".to_string()],
+            20,
+            5,
+            60.0,
+            1.0,
+            1.0,
+            &BTreeMap::new(),
+            &types::IndexDist::pgv_hnsw_cosine,
+            &types::VectorType::vector,
+            "vectorize",
+            false,
+            None,
+            None,
+            "spanish",
+            SearchMode::Hybrid,
+        );
+        assert!(q.contains("websearch_to_tsquery('spanish', $2)"));
+        assert!(q.contains("plainto_tsquery('spanish', $2)"));
+        assert!(!q.contains("english"));
+    }
+
+    #[test]
+    fn test_filter_column_parse() {
+        assert_eq!(
+            FilterColumn::parse("price").unwrap(),
+            FilterColumn {
+                column: "price".to_string(),
+                path: vec![],
+            }
+        );
+        assert_eq!(
+            FilterColumn::parse("metadata->>author.name").unwrap(),
+            FilterColumn {
+                column: "metadata".to_string(),
+                path: vec!["author".to_string(), "name".to_string()],
+            }
+        );
+        for key in [
+            "metadata->>",
+            "metadata->>a..b",
+            "->>category",
+            "metadata->>cat'egory",
+            "metadata->category",
+        ] {
+            assert!(
+                FilterColumn::parse(key).is_err(),
+                "{key} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn test_hybrid_search_query_jsonb_filters() {
+        let mut filters = BTreeMap::new();
+        for (key, value) in [
+            ("metadata->>category", "\"news\""),
+            ("metadata->>author.name", "\"ilike.ann%\""),
+            ("metadata->>stats.views", "\"gte.100\""),
+            ("price", "\"lt.10\""),
+        ] {
+            filters.insert(
+                key.to_string(),
+                serde_json::from_str::<FilterValue>(value).unwrap(),
+            );
+        }
+        let q = hybrid_search_query(
+            "test_job",
+            &types::TablePrefixes::default(),
+            "public",
+            "articles",
+            &["id".to_string()],
+            &["title".to_string()],
+            20,
+            5,
+            60.0,
+            1.0,
+            1.0,
+            &filters,
+            &types::IndexDist::pgv_hnsw_cosine,
+            &types::VectorType::vector,
+            "vectorize",
+            false,
+            None,
+            None,
+            "english",
+            SearchMode::Hybrid,
+        );
+        // filters are bound in key order
+        assert!(q.contains("AND t0.\"metadata\"#>>'{author,name}' ILIKE $3"));
+        assert!(q.contains("AND t0.\"metadata\"->'category' = to_jsonb($4)"));
+        assert!(q.contains("AND t0.\"metadata\"#>'{stats,views}' >= to_jsonb($5)"));
+        assert!(q.contains("AND t0.\"price\" < $6"));
+    }
+
+    #[test]
+    fn test_hybrid_search_query_modes() {
+        let build = |mode| {
+            hybrid_search_query(
+                "my_job",
+                &types::TablePrefixes::default(),
+                "public",
+                "docs",
+                &["id".to_string()],
+                &["content".to_string()],
+                20,
+                5,
+                60.0,
+                1.0,
+                1.0,
+                &BTreeMap::new(),
+                &types::IndexDist::pgv_hnsw_cosine,
+                &types::VectorType::vector,
+                "vectorize",
+                false,
+                None,
+                None,
+                "english",
+                mode,
+            )
+        };
+        let hybrid = build(SearchMode::Hybrid);
+        assert!(hybrid.contains("FULL OUTER JOIN"));
+        assert!(hybrid.contains("COALESCE(s.id, f.id) as id"));
+        assert!(hybrid.contains("ORDER BY t.rrf_score DESC, t.id"));
+
+        let semantic = build(SearchMode::Semantic);
+        assert!(semantic.contains("vectorize._embeddings_my_job"));
+        assert!(!semantic.contains("_search_tokens_"));
+        assert!(!semantic.contains("$2"));
+        assert!(semantic.contains("NULL::bigint as fts_rank"));
+        assert!(semantic.contains("ORDER BY t.semantic_rank"));
+
+        let fts = build(SearchMode::Fts);
+        assert!(fts.contains("vectorize._search_tokens_my_job"));
+        assert!(!fts.contains("_embeddings_"));
+        assert!(!fts.contains("$1"));
+        assert!(fts.contains("NULL::bigint as semantic_rank"));
+        assert!(fts.contains("ORDER BY t.fts_rank"));
+
+        // every mode returns the same columns
+        for q in [&hybrid, &semantic, &fts] {
+            assert!(q.contains("t.rrf_score, t.semantic_rank, t.fts_rank, t.similarity_score"));
+        }
+        assert_eq!(
+            serde_json::from_str::<SearchMode>("\"fts\"").unwrap(),
+            SearchMode::Fts
+        );
+    }
+
+    #[test]
+    fn test_search_predicate() {
+        assert_eq!(search_predicate(None, None), None);
+        assert_eq!(
+            search_predicate(Some("a = 1 OR b = 2"), None).as_deref(),
+            Some("a = 1 OR b = 2")
+        );
+        // null predicates, e.g. on a NULL deleted flag, do not exclude the row
+        assert_eq!(
+            search_predicate(None, Some("deleted_at IS NOT NULL")).as_deref(),
+            Some("NOT COALESCE((deleted_at IS NOT NULL), false)")
+        );
+        assert_eq!(
+            search_predicate(Some("a = 1 OR b = 2"), Some("is_deleted")).as_deref(),
+            Some("(a = 1 OR b = 2) AND NOT COALESCE((is_deleted), false)")
+        );
+    }
+
+    #[test]
+    fn test_hybrid_search_query_where_sql() {
+        let build = |where_sql| {
+            hybrid_search_query(
+                "test_job",
+                &types::TablePrefixes::default(),
+                "public",
+                "products",
+                &["product_id".to_string()],
+                &["*".to_string()],
+                50,
+                10,
+                60.0,
+                1.0,
+                1.0,
+                &BTreeMap::new(),
+                &types::IndexDist::pgv_hnsw_cosine,
+                &types::VectorType::vector,
+                "vectorize",
+                false,
+                None,
+                where_sql,
+                "english",
+                SearchMode::Hybrid,
+            )
+        };
+        let q = build(Some(
+            "price < 10 OR (product_id > 2 AND lower(product_name) LIKE 'desk%')",
+        ));
+        assert!(q.contains(
+            "INNER JOIN (SELECT * FROM public.products WHERE price < 10 OR (product_id > 2 AND lower(product_name) LIKE 'desk%')) t0 ON t0.product_id = t.product_id"
+        ));
+        assert!(
+            build(None).contains("INNER JOIN public.products t0 ON t0.product_id = t.product_id")
+        );
+    }
+
+    #[test]
+    fn test_hybrid_search_query_with_embeddings() {
+        let build = |with_embeddings| {
+            hybrid_search_query(
+                "test_job",
+                &types::TablePrefixes::default(),
+                "public",
+                "products",
+                &["product_id".to_string()],
+                &["*".to_string()],
+                50,
+                50,
+                60.0,
+                1.0,
+                1.0,
+                &BTreeMap::new(),
+                &types::IndexDist::pgv_hnsw_cosine,
+                &types::VectorType::vector,
+                "vectorize",
+                with_embeddings,
+                None,
+                None,
+                "english",
+                SearchMode::Hybrid,
+            )
+        };
+        let q = build(true);
+        assert!(q.contains("t.similarity_score, e.embeddings::real[] as embedding"));
+        assert!(
+            q.contains("LEFT JOIN vectorize._embeddings_test_job e ON e.product_id = t.product_id")
+        );
+        let q = build(false);
+        assert!(!q.contains("as embedding"));
+        assert!(!q.contains("LEFT JOIN"));
+    }
+
+    #[test]
+    fn test_hybrid_search_query_returns_scores() {
+        let q = hybrid_search_query(
+            "test_job",
+            &types::TablePrefixes::default(),
+            "public",
+            "products",
+            &["product_id".to_string()],
+            &["*".to_string()],
+            50,
+            10,
+            60.0,
+            1.0,
+            1.0,
+            &BTreeMap::new(),
+            &types::IndexDist::pgv_hnsw_cosine,
+            &types::VectorType::vector,
+            "vectorize",
+            false,
+            None,
+            None,
+            "english",
+            SearchMode::Hybrid,
+        );
+        // every result row carries its scores, ordered by the combined score
+        assert!(q.contains("t.rrf_score, t.semantic_rank, t.fts_rank, t.similarity_score"));
+        // plain words are or-ed, web search syntax is left to websearch_to_tsquery
+        // the CASE is not a function, so it is only valid in FROM as a subselect
+        assert!(q.contains("(SELECT (CASE WHEN $2 ~* "));
+        assert!(q.contains("END) as query) fts_query"));
+        assert!(q.contains("THEN websearch_to_tsquery('english', $2)"));
+        assert!(
+            q.contains("NULLIF(replace(plainto_tsquery('english', $2)::text, ' & ', ' | '), ''))")
+        );
+        assert!(q.contains("ORDER BY t.rrf_score DESC, t.product_id"));
+    }
+
+    #[test]
+    fn test_hybrid_search_query_tie_break() {
+        let q = hybrid_search_query(
+            "test_job",
+            &types::TablePrefixes::default(),
+            "public",
+            "products",
+            &["tenant_id".to_string(), "product_id".to_string()],
+            &["description".to_string()],
+            50,
+            10,
+            60.0,
+            1.0,
+            1.0,
+            &BTreeMap::new(),
+            &types::IndexDist::pgv_hnsw_cosine,
+            &types::VectorType::vector,
+            "vectorize",
+            false,
+            None,
+            None,
+            "english",
+            SearchMode::Hybrid,
+        );
+        // equal distances and text ranks get their ranks, and equal scores their order, by key
+        assert!(q.contains("ROW_NUMBER() OVER (ORDER BY distance, tenant_id, product_id)"));
+        assert!(q.contains("ORDER BY distance, tenant_id, product_id\n"));
+        assert!(q.contains(
+            "ROW_NUMBER() OVER (ORDER BY ts_rank_cd(search_tokens, query) DESC, tenant_id, product_id)"
+        ));
+        assert!(q.contains("ORDER BY t.rrf_score DESC, t.tenant_id, t.product_id\n"));
+        // the keys are ordered on even when they are not returned
+        assert!(q.contains("SELECT t0.description, t.rrf_score"));
+    }
+
+    #[test]
+    fn test_hybrid_search_query_min_score() {
+        let build = |index_dist, min_score| {
+            hybrid_search_query(
+                "test_job",
+                &types::TablePrefixes::default(),
+                "public",
+                "products",
+                &["product_id".to_string()],
+                &["*".to_string()],
+                50,
+                10,
+                60.0,
+                1.0,
+                1.0,
+                &BTreeMap::new(),
+                &index_dist,
+                &types::VectorType::vector,
+                "vectorize",
+                false,
+                min_score,
+                None,
+                "english",
+                SearchMode::Hybrid,
+            )
+        };
+        let q = build(types::IndexDist::pgv_hnsw_cosine, Some(0.8));
+        assert!(q.contains(") sub\n                WHERE 1 - distance >= 0.8\n"));
+        // full-text matches must be semantic candidates that cleared the threshold as well
+        assert!(q.contains(
+            ") f ON s.product_id = f.product_id\n            WHERE s.similarity_score IS NOT NULL\n"
+        ));
+        let q = build(types::IndexDist::pgv_hnsw_l2, Some(0.5));
+        assert!(q.contains("WHERE 1 / (1 + distance) >= 0.5"));
+        let q = build(types::IndexDist::pgv_hnsw_cosine, None);
+        assert!(!q.contains("distance >="));
+        assert!(!q.contains("similarity_score IS NOT NULL"));
+    }
+
+    #[test]
+    fn test_embeddings_conflict_clause() {
+        let pkey = vec!["order_id".to_string(), "line_no".to_string()];
+        let overwrite =
+            embeddings_conflict_clause(&pkey, &["embeddings"], &types::ConflictStrategy::overwrite);
+        assert!(overwrite.starts_with(" ON CONFLICT (order_id, line_no)"));
+        assert!(overwrite.contains("DO UPDATE SET embeddings = EXCLUDED.embeddings, updated_at"));
+
+        let overwrite = embeddings_conflict_clause(
+            &pkey,
+            &["embeddings", "content_hash"],
+            &types::ConflictStrategy::overwrite,
+        );
+        assert!(overwrite.contains(
+            "DO UPDATE SET embeddings = EXCLUDED.embeddings, content_hash = EXCLUDED.content_hash, updated_at = NOW();"
+        ));
+
+        let skip = embeddings_conflict_clause(
+            &pkey,
+            &["embeddings", "content_hash"],
+            &types::ConflictStrategy::skip,
+        );
+        assert_eq!(skip, " ON CONFLICT (order_id, line_no) DO NOTHING;");
+    }
+
+    #[test]
+    fn test_content_hash_queries() {
+        let pkey = vec!["id".to_string()];
+        let pkey_type = vec!["integer".to_string()];
+        let q = stored_content_hashes(
+            "my_job",
+            &types::TablePrefixes::default(),
+            "vectorize",
+            &pkey,
+            &pkey_type,
+        );
+        assert!(q.starts_with("SELECT e.id::text AS record_id, e.content_hash"));
+        assert!(q.contains("FROM vectorize._embeddings_my_job e"));
+        assert!(q.ends_with("WHERE id = ANY ($1::integer[])"));
+        assert_eq!(
+            touch_embeddings(
+                "my_job",
+                &types::TablePrefixes::default(),
+                "vectorize",
+                &pkey,
+                &pkey_type
+            ),
+            "UPDATE vectorize._embeddings_my_job SET updated_at = NOW() WHERE id = ANY ($1::integer[])"
+        );
+
+        // composite keys carry the same record ids as queue messages
+        let pkey = vec!["order_id".to_string(), "line_no".to_string()];
+        let pkey_type = vec!["integer".to_string(), "integer".to_string()];
+        let q = stored_content_hashes(
+            "my_job",
+            &types::TablePrefixes::default(),
+            "vectorize",
+            &pkey,
+            &pkey_type,
+        );
+        assert!(q.contains("jsonb_build_array(e.order_id, e.line_no)::text AS record_id"));
+        assert!(q.contains("(order_id, line_no) IN"));
+    }
+
+    #[test]
+    fn test_missing_embedding_filter() {
+        assert_eq!(
+            missing_embedding_filter(
+                "my_job",
+                &types::TablePrefixes::default(),
+                "vectorize",
+                &["id".to_string()],
+                "t0"
+            ),
+            "NOT EXISTS (SELECT 1 FROM vectorize._embeddings_my_job e WHERE e.id = t0.id)"
+        );
+        let pkey = vec!["order_id".to_string(), "line_no".to_string()];
+        assert_eq!(
+            missing_embedding_filter(
+                "my_job",
+                &types::TablePrefixes::default(),
+                "search",
+                &pkey,
+                "t0"
+            ),
+            "NOT EXISTS (SELECT 1 FROM search._embeddings_my_job e WHERE e.order_id = t0.order_id AND e.line_no = t0.line_no)"
+        );
+    }
+
+    #[test]
+    fn test_embedding_index_name_matches_create() {
+        for index_dist in [
+            types::IndexDist::pgv_hnsw_l2,
+            types::IndexDist::pgv_hnsw_ip,
+            types::IndexDist::pgv_hnsw_cosine,
+            types::IndexDist::vsc_diskann_cosine,
+        ] {
+            let name = embedding_index_name("my_job", &index_dist);
+            let create = create_embedding_index(
+                "my_job",
+                "vectorize",
+                "_embeddings_my_job",
+                "embeddings",
+                &index_dist,
+                &types::VectorType::vector,
+            );
+            assert!(create.contains(&format!("CREATE INDEX IF NOT EXISTS {name} ON")));
+            assert!(
+                drop_embedding_index("my_job", "vectorize", &index_dist)
+                    .starts_with(&format!("DROP INDEX IF EXISTS vectorize.{name}"))
+            );
+        }
+        assert_eq!(
+            drop_embedding_index("my_job", "vectorize", &types::IndexDist::pgv_hnsw_cosine),
+            "DROP INDEX IF EXISTS vectorize.my_job_hnsw_cos_idx;"
+        );
+        assert_eq!(
+            drop_embedding_index("my_job", "vectorize", &types::IndexDist::vsc_diskann_cosine),
+            "DROP INDEX IF EXISTS vectorize.my_job_diskann_cos_idx, vectorize.my_job_hnsw_cos_idx;"
+        );
+        assert_eq!(
+            alter_embeddings_type(
+                "my_job",
+                &types::TablePrefixes::default(),
+                "vectorize",
+                "vector(768)"
+            ),
+            "ALTER TABLE vectorize._embeddings_my_job ALTER COLUMN embeddings TYPE vector(768);"
+        );
+    }
+
+    #[test]
+    fn test_create_embedding_index() {
+        let cases = vec![
+            (
+                types::IndexDist::pgv_hnsw_l2,
+                "my_job_hnsw_l2_idx",
+                "vector_l2_ops",
+            ),
+            (
+                types::IndexDist::pgv_hnsw_ip,
+                "my_job_hnsw_ip_idx",
+                "vector_ip_ops",
+            ),
+            (
+                types::IndexDist::pgv_hnsw_cosine,
+                "my_job_hnsw_cos_idx",
+                "vector_cosine_ops",
+            ),
+        ];
+        for (index_dist, index_name, ops) in cases {
+            let q = create_embedding_index(
+                "my_job",
+                "vectorize",
+                "_embeddings_my_job",
+                "embeddings",
+                &index_dist,
+                &types::VectorType::vector,
+            );
+            assert!(q.contains(&format!(
+                "CREATE INDEX IF NOT EXISTS {index_name} ON vectorize._embeddings_my_job"
+            )));
+            assert!(q.contains(&format!("USING hnsw (embeddings {ops})")));
+        }
+    }
+
+    #[test]
+    fn test_halfvec_embeddings() {
+        let vector_type = types::VectorType::halfvec;
+        let q = create_embedding_table(
+            "my_job",
+            &types::TablePrefixes::default(),
+            &["id".to_string()],
+            &["integer".to_string()],
+            &vector_type.column_type(384),
+            "public",
+            "products",
+            "vectorize",
+        );
+        assert!(q.contains("embeddings halfvec(384) NOT NULL"));
+
+        let cases = vec![
+            (types::IndexDist::pgv_hnsw_l2, "halfvec_l2_ops"),
+            (types::IndexDist::pgv_hnsw_ip, "halfvec_ip_ops"),
+            (types::IndexDist::pgv_hnsw_cosine, "halfvec_cosine_ops"),
+        ];
+        for (index_dist, ops) in cases {
+            let q = create_embedding_index(
+                "my_job",
+                "vectorize",
+                "_embeddings_my_job",
+                "embeddings",
+                &index_dist,
+                &vector_type,
+            );
+            assert!(q.contains(&format!("USING hnsw (embeddings {ops})")));
+        }
+
+        // the query embedding is cast to the column's type, so the halfvec index is used
+        let q = hybrid_search_query(
+            "my_job",
+            &types::TablePrefixes::default(),
+            "public",
+            "products",
+            &["id".to_string()],
+            &["*".to_string()],
+            20,
+            5,
+            60.0,
+            1.0,
+            1.0,
+            &BTreeMap::new(),
+            &types::IndexDist::pgv_hnsw_cosine,
+            &vector_type,
+            "vectorize",
+            false,
+            None,
+            None,
+            "english",
+            SearchMode::Hybrid,
+        );
+        assert!(q.contains("embeddings <=> $1::halfvec as distance"));
+        assert!(!q.contains("::vector"));
+    }
+
+    #[test]
+    fn test_bit_embeddings() {
+        let vector_type = types::VectorType::bit;
+        let q = create_embedding_table(
+            "my_job",
+            &types::TablePrefixes::default(),
+            &["id".to_string()],
+            &["integer".to_string()],
+            &vector_type.column_type(384),
+            "public",
+            "products",
+            "vectorize",
+        );
+        assert!(q.contains("embeddings bit(384) NOT NULL"));
+
+        let q = create_embedding_index(
+            "my_job",
+            "vectorize",
+            "_embeddings_my_job",
+            "embeddings",
+            &types::IndexDist::pgv_hnsw_hamming,
+            &vector_type,
+        );
+        assert!(q.contains("my_job_hnsw_hamming_idx"));
+        assert!(q.contains("USING hnsw (embeddings bit_hamming_ops)"));
+
+        // the query embedding is quantized the same way as the stored embeddings
+        let q = hybrid_search_query(
+            "my_job",
+            &types::TablePrefixes::default(),
+            "public",
+            "products",
+            &["id".to_string()],
+            &["*".to_string()],
+            20,
+            5,
+            60.0,
+            1.0,
+            1.0,
+            &BTreeMap::new(),
+            &types::IndexDist::pgv_hnsw_hamming,
+            &vector_type,
+            "vectorize",
+            true,
+            None,
+            None,
+            "english",
+            SearchMode::Hybrid,
+        );
+        assert!(q.contains("embeddings <~> binary_quantize($1::vector) as distance"));
+        assert!(q.contains("1 / (1 + distance) as similarity_score"));
+        assert!(q.contains("regexp_split_to_array(e.embeddings::text, '')::real[] as embedding"));
+    }
+
+    #[test]
+    fn test_create_diskann_cosine_index() {
+        let q =
+            create_diskann_cosine_index("my_job", "vectorize", "_embeddings_my_job", "embeddings");
+        assert_eq!(
+            q,
+            "CREATE INDEX IF NOT EXISTS my_job_diskann_cos_idx ON vectorize._embeddings_my_job
+        USING diskann (embeddings vector_cosine_ops);
+        "
+        );
+        assert_eq!(
+            create_embedding_index(
+                "my_job",
+                "vectorize",
+                "_embeddings_my_job",
+                "embeddings",
+                &types::IndexDist::vsc_diskann_cosine,
+                &types::VectorType::vector,
+            ),
+            q
+        );
+    }
+
+    #[test]
+    fn test_hybrid_search_query_distance_operators() {
+        let cases = vec![
+            (types::IndexDist::pgv_hnsw_cosine, "<=>", "1 - distance"),
+            (types::IndexDist::pgv_hnsw_l2, "<->", "1 / (1 + distance)"),
+            (types::IndexDist::pgv_hnsw_ip, "<#>", "distance * -1"),
+            (types::IndexDist::vsc_diskann_cosine, "<=>", "1 - distance"),
+        ];
+        for (index_dist, operator, score) in cases {
+            let q = hybrid_search_query(
+                "test_job",
+                &types::TablePrefixes::default(),
+                "public",
+                "products",
+                &["product_id".to_string()],
+                &["*".to_string()],
+                20,
+                5,
+                60.0,
+                1.0,
+                1.0,
+                &BTreeMap::new(),
+                &index_dist,
+                &types::VectorType::vector,
+                "vectorize",
+                false,
+                None,
+                None,
+                "english",
+                SearchMode::Hybrid,
+            );
+            assert!(
+                q.contains(&format!("embeddings {operator} $1::vector as distance")),
+                "expected {operator} for {index_dist}"
+            );
+            assert!(q.contains(&format!("{score} as similarity_score")));
+            for other in ["<=>", "<->", "<#>"].iter().filter(|o| **o != operator) {
+                assert!(!q.contains(other), "unexpected {other} for {index_dist}");
+            }
+        }
+    }
+
     #[test]
     fn test_drop_embeddings_table() {
         let job_name = "test_job";
-        let result = drop_embeddings_table(job_name);
+        let result = drop_embeddings_table(job_name, &types::TablePrefixes::default(), "vectorize");
         assert_eq!(
             result,
             "DROP TABLE IF EXISTS vectorize._embeddings_test_job CASCADE;"
         );
     }
 
+    #[test]
+    fn test_truncate_embeddings_table() {
+        assert_eq!(
+            truncate_embeddings_table("test_job", &types::TablePrefixes::default(), "vectorize"),
+            "TRUNCATE TABLE vectorize._embeddings_test_job;"
+        );
+    }
+
+    #[test]
+    fn test_job_status_query() {
+        let q = job_status_query(
+            "my_job",
+            &types::TablePrefixes::default(),
+            "public",
+            "products",
+            "vectorize_jobs",
+            "vectorize",
+        );
+        assert!(q.contains("SELECT COUNT(*) FROM public.products) AS total_rows"));
+        assert!(q.contains("SELECT COUNT(*) FROM vectorize._embeddings_my_job) AS embedded_rows"));
+        assert!(q.contains(
+            "SELECT to_json(MAX(updated_at)) #>> '{}' FROM vectorize._embeddings_my_job"
+        ));
+        assert!(q.contains("FROM pgmq.q_vectorize_jobs WHERE message->>'job_name' = $1"));
+        assert!(q.contains(
+            "SELECT to_json(last_completion) #>> '{}' FROM vectorize.job WHERE job_name = $1"
+        ));
+        assert!(
+            q.contains("(SELECT paused FROM vectorize.job WHERE job_name = $1), false) AS paused")
+        );
+    }
+
+    #[test]
+    fn test_job_pending_messages_query() {
+        assert_eq!(
+            job_pending_messages_query("vectorize_jobs"),
+            "SELECT COUNT(*) FROM pgmq.q_vectorize_jobs WHERE message->>'job_name' = $1;"
+        );
+    }
+
     #[test]
     fn test_drop_search_tokens_table() {
         let job_name = "test_job";
-        let result = drop_search_tokens_table(job_name);
+        let result =
+            drop_search_tokens_table(job_name, &types::TablePrefixes::default(), "vectorize");
         assert_eq!(
             result,
             "DROP TABLE IF EXISTS vectorize._search_tokens_test_job CASCADE;"
@@ -1505,22 +3536,309 @@ EXECUTE FUNCTION vectorize.handle_update_another_job();"
     #[test]
     fn test_drop_project_view() {
         let job_name = "test_job";
-        let result = drop_project_view(job_name);
+        let result = drop_project_view(job_name, "vectorize");
         assert_eq!(result, "DROP VIEW IF EXISTS vectorize.test_job_view;");
     }
 
+    #[test]
+    fn test_ddl_uses_target_schema() {
+        let embeddings = create_embedding_table(
+            "my_job",
+            &types::TablePrefixes::default(),
+            &["id".to_string()],
+            &["int4".to_string()],
+            "vector(384)",
+            "public",
+            "docs",
+            "tenant_a",
+        );
+        assert!(embeddings.contains("CREATE TABLE IF NOT EXISTS tenant_a._embeddings_my_job"));
+        assert!(embeddings.contains("REFERENCES public.docs (id)"));
+
+        let view = create_project_view(
+            "my_job",
+            &types::TablePrefixes::default(),
+            "public",
+            "docs",
+            &["id".to_string()],
+            "tenant_a",
+        );
+        assert!(view.contains("CREATE OR REPLACE VIEW tenant_a.my_job_view"));
+        assert!(view.contains("INNER JOIN tenant_a._embeddings_my_job t1"));
+
+        let tokens = create_search_tokens_table(
+            "my_job",
+            &types::TablePrefixes::default(),
+            &["id".to_string()],
+            &["int4".to_string()],
+            "public",
+            "docs",
+            "tenant_a",
+        );
+        assert!(tokens.contains("CREATE TABLE IF NOT EXISTS tenant_a._search_tokens_my_job"));
+
+        let fts = create_fts_index_query(
+            "my_job",
+            &types::TablePrefixes::default(),
+            "GIN",
+            "tenant_a",
+        );
+        assert!(fts.contains("ON tenant_a._search_tokens_my_job"));
+
+        let triggers = update_search_tokens_trigger_queries(
+            "my_job",
+            &types::TablePrefixes::default(),
+            &["id".to_string()],
+            "public",
+            "docs",
+            &["content".to_string()],
+            None,
+            "tenant_a",
+            "english",
+        );
+        assert!(triggers[0].contains("INSERT INTO tenant_a._search_tokens_my_job"));
+
+        let index = create_hnsw_cosine_index(
+            "my_job",
+            "tenant_a",
+            "_embeddings_my_job",
+            "embeddings",
+            &types::VectorType::vector,
+        );
+        assert!(index.contains("ON tenant_a._embeddings_my_job"));
+
+        for q in [
+            embeddings,
+            view,
+            tokens,
+            fts,
+            drop_project_view("my_job", "tenant_a"),
+            drop_embeddings_table("my_job", &types::TablePrefixes::default(), "tenant_a"),
+            drop_search_tokens_table("my_job", &types::TablePrefixes::default(), "tenant_a"),
+        ] {
+            assert!(
+                !q.contains("vectorize."),
+                "unexpected vectorize schema in {q}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_composite_key_ddl() {
+        let keys = vec!["order_id".to_string(), "line_no".to_string()];
+        let key_types = vec!["integer".to_string(), "integer".to_string()];
+
+        let embeddings = create_embedding_table(
+            "my_job",
+            &types::TablePrefixes::default(),
+            &keys,
+            &key_types,
+            "vector(384)",
+            "public",
+            "order_lines",
+            "vectorize",
+        );
+        assert!(embeddings.contains("order_id integer NOT NULL,"));
+        assert!(embeddings.contains("line_no integer NOT NULL,"));
+        assert!(embeddings.contains("UNIQUE (order_id, line_no),"));
+        assert!(embeddings.contains(
+            "FOREIGN KEY (order_id, line_no) REFERENCES public.order_lines (order_id, line_no) ON DELETE CASCADE"
+        ));
+
+        let tokens = create_search_tokens_table(
+            "my_job",
+            &types::TablePrefixes::default(),
+            &keys,
+            &key_types,
+            "public",
+            "order_lines",
+            "vectorize",
+        );
+        assert!(tokens.contains("UNIQUE (order_id, line_no),"));
+
+        let view = create_project_view(
+            "my_job",
+            &types::TablePrefixes::default(),
+            "public",
+            "order_lines",
+            &keys,
+            "vectorize",
+        );
+        assert!(view.contains("ON t0.order_id = t1.order_id AND t0.line_no = t1.line_no;"));
+
+        let triggers = update_search_tokens_trigger_queries(
+            "my_job",
+            &types::TablePrefixes::default(),
+            &keys,
+            "public",
+            "order_lines",
+            &["content".to_string()],
+            None,
+            "vectorize",
+            "english",
+        );
+        assert!(triggers[0].contains("(order_id, line_no, search_tokens)"));
+        assert!(triggers[0].contains("NEW.order_id, NEW.line_no"));
+        assert!(triggers[0].contains("ON CONFLICT (order_id, line_no)"));
+
+        let populate = populate_search_tokens_query(
+            "my_job",
+            &types::TablePrefixes::default(),
+            &keys,
+            "public",
+            "order_lines",
+            &["content".to_string()],
+            None,
+            "vectorize",
+            "english",
+        );
+        assert!(populate.contains(
+            "INSERT INTO vectorize._search_tokens_my_job (order_id, line_no, search_tokens)"
+        ));
+        assert!(populate.contains("t0.order_id, t0.line_no"));
+        assert!(populate.contains("ON CONFLICT (order_id, line_no)"));
+
+        let handler = create_trigger_handler("my_job", &keys, None, None);
+        assert!(
+            handler.contains(
+                "array_agg(jsonb_build_array(new_table.order_id, new_table.line_no)::text)"
+            )
+        );
+    }
+
+    #[test]
+    fn test_composite_key_record_ids() {
+        let single = vec!["id".to_string()];
+        assert_eq!(record_id_expr(&single, "t0"), "t0.id::text");
+        assert_eq!(
+            record_ids_filter(&single, &["integer".to_string()], 1),
+            "id = ANY ($1::integer[])"
+        );
+        assert_eq!(record_id_params(&["integer".to_string()], 3), "$3::integer");
+
+        let keys = vec!["order_id".to_string(), "sku".to_string()];
+        let key_types = vec!["integer".to_string(), "text".to_string()];
+        assert_eq!(
+            record_id_expr(&keys, "t0"),
+            "jsonb_build_array(t0.order_id, t0.sku)::text"
+        );
+        assert_eq!(
+            record_ids_filter(&keys, &key_types, 1),
+            "(order_id, sku) IN (SELECT (record_id::jsonb->>0)::integer, (record_id::jsonb->>1)::text FROM unnest($1::text[]) AS record_id)"
+        );
+        assert_eq!(
+            record_id_params(&key_types, 3),
+            "($3::jsonb->>0)::integer, ($3::jsonb->>1)::text"
+        );
+
+        let new_rows = new_rows_query_join(
+            "my_job",
+            &types::TablePrefixes::default(),
+            &["content".to_string()],
+            None,
+            "public",
+            "order_lines",
+            &keys,
+            Some("updated_at".to_string()),
+            false,
+            "vectorize",
+        );
+        assert!(
+            new_rows.contains("SELECT jsonb_build_array(t0.order_id, t0.sku)::text as record_id")
+        );
+        assert!(new_rows.contains("ON t0.order_id = t1.order_id AND t0.sku = t1.sku"));
+        assert!(new_rows.contains("WHERE t1.order_id IS NULL"));
+    }
+
+    #[test]
+    fn test_hybrid_search_query_composite_key() {
+        let q = hybrid_search_query(
+            "my_job",
+            &types::TablePrefixes::default(),
+            "public",
+            "order_lines",
+            &["order_id".to_string(), "line_no".to_string()],
+            &["*".to_string()],
+            20,
+            5,
+            60.0,
+            1.0,
+            1.0,
+            &BTreeMap::new(),
+            &types::IndexDist::pgv_hnsw_cosine,
+            &types::VectorType::vector,
+            "vectorize",
+            true,
+            None,
+            None,
+            "english",
+            SearchMode::Hybrid,
+        );
+        assert!(q.contains("COALESCE(s.order_id, f.order_id) as order_id"));
+        assert!(q.contains("COALESCE(s.line_no, f.line_no) as line_no"));
+        assert!(q.contains("f ON s.order_id = f.order_id AND s.line_no = f.line_no"));
+        assert!(q.contains("t0 ON t0.order_id = t.order_id AND t0.line_no = t.line_no"));
+        assert!(q.contains("e ON e.order_id = t.order_id AND e.line_no = t.line_no"));
+    }
+
+    #[test]
+    fn test_search_queries_use_target_schema() {
+        let columns = vec!["content".to_string()];
+        let new_rows = new_rows_query_join(
+            "my_job",
+            &types::TablePrefixes::default(),
+            &columns,
+            None,
+            "public",
+            "docs",
+            &["id".to_string()],
+            None,
+            false,
+            "tenant_a",
+        );
+        assert!(new_rows.contains("LEFT JOIN tenant_a._embeddings_my_job t1"));
+
+        let q = hybrid_search_query(
+            "my_job",
+            &types::TablePrefixes::default(),
+            "public",
+            "docs",
+            &["id".to_string()],
+            &["content".to_string()],
+            20,
+            5,
+            60.0,
+            1.0,
+            1.0,
+            &BTreeMap::new(),
+            &types::IndexDist::pgv_hnsw_cosine,
+            &types::VectorType::vector,
+            "tenant_a",
+            false,
+            None,
+            None,
+            "english",
+            SearchMode::Hybrid,
+        );
+        assert!(q.contains("tenant_a._embeddings_my_job"));
+        assert!(q.contains("tenant_a._search_tokens_my_job"));
+        assert!(!q.contains("vectorize._"));
+    }
+
     #[test]
     fn test_cleanup_sql_with_special_chars() {
         // Test that job names with underscores work correctly
         let job_name = "my_test_job_123";
 
-        let embeddings = drop_embeddings_table(job_name);
+        let embeddings =
+            drop_embeddings_table(job_name, &types::TablePrefixes::default(), "vectorize");
         assert!(embeddings.contains("_embeddings_my_test_job_123"));
 
-        let tokens = drop_search_tokens_table(job_name);
+        let tokens =
+            drop_search_tokens_table(job_name, &types::TablePrefixes::default(), "vectorize");
         assert!(tokens.contains("_search_tokens_my_test_job_123"));
 
-        let view = drop_project_view(job_name);
+        let view = drop_project_view(job_name, "vectorize");
         assert!(view.contains("my_test_job_123_view"));
     }
 }
@@ -0,0 +1,175 @@
+use crate::errors::VectorizeError;
+use crate::transformers::providers::Reranker;
+
+/// cosine similarity of two vectors, 0 when either has no magnitude or the lengths differ
+pub fn cosine_similarity(a: &[f64], b: &[f64]) -> f64 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f64 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f64>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f64>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a * norm_b)
+}
+
+/// maximal marginal relevance, greedily picks up to `limit` candidates
+/// each pick maximizes lambda * sim(query, c) - (1 - lambda) * max sim(c, already picked)
+/// lambda = 1.0 ranks purely by relevance, lower values favor diverse results
+/// returns indices into `candidates`, in selection order
+pub fn mmr(query: &[f64], candidates: &[Vec<f64>], lambda: f64, limit: usize) -> Vec<usize> {
+    let relevance: Vec<f64> = candidates
+        .iter()
+        .map(|c| cosine_similarity(query, c))
+        .collect();
+
+    let mut selected: Vec<usize> = Vec::with_capacity(limit.min(candidates.len()));
+    let mut remaining: Vec<usize> = (0..candidates.len()).collect();
+    while selected.len() < limit && !remaining.is_empty() {
+        let (pos, _) = remaining
+            .iter()
+            .enumerate()
+            .map(|(pos, &idx)| {
+                let redundancy = selected
+                    .iter()
+                    .map(|&s| cosine_similarity(&candidates[idx], &candidates[s]))
+                    .fold(0.0, f64::max);
+                (pos, lambda * relevance[idx] - (1.0 - lambda) * redundancy)
+            })
+            // first candidate wins ties, keeping the incoming order stable
+            .fold((0, f64::NEG_INFINITY), |best, cur| {
+                if cur.1 > best.1 { cur } else { best }
+            });
+        selected.push(remaining.remove(pos));
+    }
+    selected
+}
+
+/// scores the documents with a reranker, returning at most `limit` indices, highest score first
+/// documents with equal scores keep their incoming order
+pub async fn rerank(
+    reranker: &dyn Reranker,
+    model_name: &str,
+    query: &str,
+    documents: &[String],
+    limit: usize,
+) -> Result<Vec<usize>, VectorizeError> {
+    if documents.is_empty() {
+        return Ok(Vec::new());
+    }
+    let scores = reranker.rerank(model_name, query, documents).await?;
+    if scores.len() != documents.len() {
+        return Err(VectorizeError::EmbeddingGenerationFailed(format!(
+            "expected {} rerank scores, got {}",
+            documents.len(),
+            scores.len()
+        )));
+    }
+    let mut order: Vec<usize> = (0..documents.len()).collect();
+    order.sort_by(|&a, &b| scores[b].total_cmp(&scores[a]));
+    order.truncate(limit);
+    Ok(order)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+
+    // scores a document by how many of the query's words it contains
+    struct WordOverlapReranker;
+
+    #[async_trait]
+    impl Reranker for WordOverlapReranker {
+        async fn rerank(
+            &self,
+            _model_name: &str,
+            query: &str,
+            documents: &[String],
+        ) -> Result<Vec<f64>, VectorizeError> {
+            Ok(documents
+                .iter()
+                .map(|d| {
+                    query
+                        .split_whitespace()
+                        .filter(|w| d.split_whitespace().any(|dw| dw == *w))
+                        .count() as f64
+                })
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-9);
+        assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]).abs() < 1e-9);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+        assert_eq!(cosine_similarity(&[1.0], &[1.0, 0.0]), 0.0);
+    }
+
+    #[test]
+    fn test_mmr_reduces_near_duplicates() {
+        let query = vec![1.0, 0.0, 0.0];
+        let candidates = vec![
+            vec![0.95, 0.3, 0.0],  // most relevant
+            vec![0.95, 0.31, 0.0], // near duplicate of the first
+            vec![0.94, 0.32, 0.0], // another near duplicate
+            vec![0.8, 0.0, 0.6],   // relevant, but a different direction
+        ];
+
+        // plain relevance ranking returns the near duplicates first
+        let plain = mmr(&query, &candidates, 1.0, 2);
+        assert_eq!(plain, vec![0, 1]);
+
+        let diverse = mmr(&query, &candidates, 0.5, 2);
+        assert_eq!(diverse, vec![0, 3]);
+    }
+
+    #[test]
+    fn test_mmr_limit() {
+        let query = vec![1.0, 0.0];
+        let candidates = vec![vec![1.0, 0.0], vec![0.0, 1.0]];
+        assert_eq!(mmr(&query, &candidates, 0.5, 5).len(), 2);
+        assert!(mmr(&query, &candidates, 0.5, 0).is_empty());
+        assert!(mmr(&query, &[], 0.5, 3).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rerank_orders_by_score() {
+        // candidates as they come out of hybrid search
+        let documents = vec![
+            "a tent for camping".to_string(),
+            "waterproof hiking boots".to_string(),
+            "hiking boots for camping trips".to_string(),
+            "a kitchen table".to_string(),
+        ];
+        let order = rerank(
+            &WordOverlapReranker,
+            "mock",
+            "hiking boots camping",
+            &documents,
+            10,
+        )
+        .await
+        .unwrap();
+        assert_eq!(order, vec![2, 1, 0, 3]);
+
+        let order = rerank(
+            &WordOverlapReranker,
+            "mock",
+            "hiking boots camping",
+            &documents,
+            2,
+        )
+        .await
+        .unwrap();
+        assert_eq!(order, vec![2, 1]);
+
+        let order = rerank(&WordOverlapReranker, "mock", "anything", &[], 5)
+            .await
+            .unwrap();
+        assert!(order.is_empty());
+    }
+}
@@ -0,0 +1,63 @@
+use chrono::{DateTime, Utc};
+use cron::Schedule;
+use std::str::FromStr;
+
+use crate::errors::VectorizeError;
+
+/// jobs with this schedule are updated by triggers on the source table
+pub const REALTIME: &str = "realtime";
+
+pub fn is_realtime(schedule: &str) -> bool {
+    schedule == REALTIME
+}
+
+/// parses a cron schedule, either 5 fields like pg_cron or 6 fields starting with seconds
+pub fn parse_schedule(schedule: &str) -> Result<Schedule, VectorizeError> {
+    // the cron crate expects a seconds field, run 5 field schedules at the top of the minute
+    let expr = match schedule.split_whitespace().count() {
+        5 => format!("0 {schedule}"),
+        _ => schedule.to_string(),
+    };
+    Schedule::from_str(&expr).map_err(|e| {
+        VectorizeError::InvalidInput(format!(
+            "schedule must be '{REALTIME}' or a cron expression, got '{schedule}': {e}"
+        ))
+    })
+}
+
+/// the first time the schedule comes due after `after`
+pub fn next_run(schedule: &Schedule, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    schedule.after(&after).next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_parse_schedule() {
+        let start = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 30).unwrap();
+
+        // 5 fields run at the top of the minute
+        let every_minute = parse_schedule("* * * * *").unwrap();
+        assert_eq!(
+            next_run(&every_minute, start),
+            Some(Utc.with_ymd_and_hms(2025, 1, 1, 12, 1, 0).unwrap())
+        );
+
+        let every_two_seconds = parse_schedule("*/2 * * * * *").unwrap();
+        assert_eq!(
+            next_run(&every_two_seconds, start),
+            Some(Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 32).unwrap())
+        );
+
+        assert!(matches!(
+            parse_schedule("every minute"),
+            Err(VectorizeError::InvalidInput(_))
+        ));
+        assert!(parse_schedule(REALTIME).is_err());
+        assert!(is_realtime(REALTIME));
+        assert!(!is_realtime("* * * * *"));
+    }
+}
@@ -1,5 +1,71 @@
+use crate::config::{provider_ca_cert, provider_proxy_url};
 use crate::errors::VectorizeError;
 use crate::transformers::types::{Inputs, PairedEmbeddings};
+use reqwest::{Certificate, Client, NoProxy, Proxy};
+use sha2::{Digest, Sha256};
+use std::sync::OnceLock;
+
+// outbound settings applied to every provider client
+#[derive(Clone, Default)]
+struct ClientOptions {
+    proxy: Option<Proxy>,
+    ca_cert: Option<Certificate>,
+}
+
+impl ClientOptions {
+    fn new(proxy_url: Option<&str>, ca_cert_path: Option<&str>) -> Result<Self, VectorizeError> {
+        let proxy = match proxy_url {
+            // hosts in NO_PROXY bypass the configured proxy like they bypass HTTPS_PROXY
+            Some(url) => Some(
+                Proxy::all(url)
+                    .map_err(|e| anyhow::anyhow!("invalid provider proxy url: {url}, error: {e}"))?
+                    .no_proxy(NoProxy::from_env()),
+            ),
+            None => None,
+        };
+        let ca_cert = match ca_cert_path {
+            Some(path) => {
+                let pem = std::fs::read(path).map_err(|e| {
+                    anyhow::anyhow!("failed to read provider CA certificate: {path}, error: {e}")
+                })?;
+                Some(Certificate::from_pem(&pem).map_err(|e| {
+                    anyhow::anyhow!("invalid provider CA certificate: {path}, error: {e}")
+                })?)
+            }
+            None => None,
+        };
+        Ok(ClientOptions { proxy, ca_cert })
+    }
+
+    // without a configured proxy, reqwest uses HTTPS_PROXY / HTTP_PROXY and honors NO_PROXY
+    fn build_client(&self) -> Result<Client, VectorizeError> {
+        let mut builder = Client::builder();
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(proxy.clone());
+        }
+        if let Some(cert) = &self.ca_cert {
+            builder = builder.add_root_certificate(cert.clone());
+        }
+        Ok(builder.build()?)
+    }
+}
+
+/// http client for calling model providers, going through PROVIDER_PROXY_URL and trusting PROVIDER_CA_CERT when set
+pub fn provider_client() -> Result<Client, VectorizeError> {
+    // read the settings once, a bad proxy url or certificate fails every request the same way
+    static OPTIONS: OnceLock<Result<ClientOptions, String>> = OnceLock::new();
+    let options = OPTIONS.get_or_init(|| {
+        ClientOptions::new(
+            provider_proxy_url().as_deref(),
+            provider_ca_cert().as_deref(),
+        )
+        .map_err(|e| e.to_string())
+    });
+    match options {
+        Ok(options) => options.build_client(),
+        Err(e) => Err(anyhow::anyhow!("{e}").into()),
+    }
+}
 
 pub async fn handle_response<T: for<'de> serde::Deserialize<'de>>(
     resp: reqwest::Response,
@@ -26,6 +92,78 @@ pub fn merge_input_output(inputs: Vec<Inputs>, values: Vec<Vec<f64>>) -> Vec<Pai
         .map(|(input, value)| PairedEmbeddings {
             primary_key: input.record_id,
             embeddings: value,
+            content_hash: None,
         })
         .collect()
 }
+
+// sha256 of the text sent to the embedding provider, unchanged text does not need a new embedding
+pub fn content_hash(input: &str) -> String {
+    hex::encode(Sha256::digest(input.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_client_uses_configured_proxy() {
+        // the proxy receives the request for the provider's url and answers it
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_url = format!("http://{}", listener.local_addr().unwrap());
+        let proxy = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0; 8192];
+            let n = socket.read(&mut buf).await.unwrap();
+            let body = r#"{"ok": true}"#;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&buf[..n]).to_string()
+        });
+
+        let client = ClientOptions::new(Some(&proxy_url), None)
+            .unwrap()
+            .build_client()
+            .unwrap();
+        let resp = client
+            .post("http://provider.invalid/v1/embeddings")
+            .send()
+            .await
+            .unwrap();
+        let value: serde_json::Value = handle_response(resp, "embeddings").await.unwrap();
+        assert_eq!(value, serde_json::json!({"ok": true}));
+
+        // a proxied request names the full target url
+        let received = proxy.await.unwrap();
+        assert!(received.starts_with("POST http://provider.invalid/v1/embeddings HTTP/1.1"));
+    }
+
+    #[test]
+    fn test_invalid_client_options() {
+        assert!(ClientOptions::new(Some("not a url"), None).is_err());
+        assert!(ClientOptions::new(None, Some("/nonexistent/ca.pem")).is_err());
+        assert!(ClientOptions::new(None, None).unwrap().proxy.is_none());
+    }
+
+    #[test]
+    fn test_content_hash() {
+        assert_eq!(
+            content_hash("hello"),
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        assert_eq!(
+            content_hash("content: pizza"),
+            content_hash("content: pizza")
+        );
+        assert_ne!(
+            content_hash("content: pizza"),
+            content_hash("content: pasta")
+        );
+    }
+}
@@ -1,4 +1,5 @@
 pub mod generic;
 pub mod http_handler;
 pub mod providers;
+pub mod tokenizer;
 pub mod types;
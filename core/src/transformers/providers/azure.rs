@@ -0,0 +1,177 @@
+use reqwest::{Client, RequestBuilder};
+
+use super::openai::{MODEL_DIMENSIONS, OpenAIEmbeddingBody, OpenAIEmbeddingResponse};
+use super::{EmbeddingProvider, GenericEmbeddingRequest, GenericEmbeddingResponse};
+use crate::config::provider_request_timeout;
+use crate::errors::VectorizeError;
+use crate::transformers::http_handler::{handle_response, provider_client};
+use crate::transformers::providers;
+use async_trait::async_trait;
+use std::env;
+use std::time::Duration;
+
+pub const AZURE_OPENAI_API_VERSION: &str = "2024-02-01";
+
+// Azure OpenAI serves each model from a named deployment on a resource,
+// e.g. https://{resource}.openai.azure.com/openai/deployments/{deployment}/embeddings?api-version=...
+pub struct AzureOpenAIProvider {
+    // resource endpoint, e.g. https://my-resource.openai.azure.com
+    pub url: String,
+    pub api_key: String,
+    pub api_version: String,
+    pub timeout: Duration,
+}
+
+impl AzureOpenAIProvider {
+    pub fn new(url: Option<String>, api_key: Option<String>) -> Result<Self, VectorizeError> {
+        let final_url = match url {
+            Some(url) => url,
+            None => match env::var("AZURE_OPENAI_ENDPOINT") {
+                Ok(url) => url,
+                Err(e) => {
+                    log::error!("AZURE_OPENAI_ENDPOINT environment variable is not set");
+                    Err(e)?
+                }
+            },
+        };
+        let final_api_key = match api_key {
+            Some(api_key) => api_key,
+            None => match env::var("AZURE_OPENAI_API_KEY") {
+                Ok(key) => key,
+                Err(e) => {
+                    log::error!("AZURE_OPENAI_API_KEY environment variable is not set");
+                    Err(e)?
+                }
+            },
+        };
+        let api_version = env::var("AZURE_OPENAI_API_VERSION")
+            .unwrap_or_else(|_| AZURE_OPENAI_API_VERSION.to_string());
+        Ok(AzureOpenAIProvider {
+            url: final_url.trim_end_matches('/').to_string(),
+            api_key: final_api_key,
+            api_version,
+            timeout: provider_request_timeout(),
+        })
+    }
+
+    pub fn embeddings_url(&self, deployment: &str) -> String {
+        format!(
+            "{}/openai/deployments/{deployment}/embeddings?api-version={}",
+            self.url, self.api_version
+        )
+    }
+
+    fn embedding_request(
+        &self,
+        client: &Client,
+        deployment: &str,
+        body: &OpenAIEmbeddingBody,
+    ) -> RequestBuilder {
+        client
+            .post(self.embeddings_url(deployment))
+            .timeout(self.timeout)
+            .header("Accept", "application/json")
+            .header("Content-Type", "application/json")
+            .header("api-key", &self.api_key)
+            .json(body)
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for AzureOpenAIProvider {
+    async fn generate_embedding<'a>(
+        &self,
+        request: &'a GenericEmbeddingRequest,
+    ) -> Result<GenericEmbeddingResponse, VectorizeError> {
+        let client = provider_client()?;
+        // the deployment name is the model name, e.g. azure/my-embedding-deployment
+        let deployment = request.model.clone();
+        let num_inputs = request.input.len();
+        let todo_requests: Vec<OpenAIEmbeddingBody> =
+            providers::split_vector(request.input.clone(), 2048)
+                .into_iter()
+                .map(|chunk| OpenAIEmbeddingBody {
+                    input: chunk,
+                    model: deployment.clone(),
+                })
+                .collect();
+
+        let mut all_embeddings: Vec<Vec<f64>> = Vec::with_capacity(num_inputs);
+        for request_payload in todo_requests.iter() {
+            let response = self
+                .embedding_request(&client, &deployment, request_payload)
+                .send()
+                .await?;
+            let embeddings =
+                handle_response::<OpenAIEmbeddingResponse>(response, "embeddings").await?;
+            all_embeddings.extend(embeddings.data.iter().map(|x| x.embedding.clone()));
+        }
+        Ok(GenericEmbeddingResponse {
+            embeddings: all_embeddings,
+        })
+    }
+
+    // deployments are sized by the OpenAI model they are named after, any other name is not
+    // guessed, resolve_model_dim probes it instead
+    async fn model_dim(&self, model_name: &str) -> Result<u32, VectorizeError> {
+        match MODEL_DIMENSIONS
+            .iter()
+            .find(|(name, _)| *name == model_name)
+        {
+            Some((_, dim)) => Ok(*dim as u32),
+            None => Err(VectorizeError::ModelNotFound(format!(
+                "{model_name}, name the azure deployment after the OpenAI model it serves"
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_azure_embedding_request_layout() {
+        let provider = AzureOpenAIProvider::new(
+            Some("https://my-resource.openai.azure.com/".to_string()),
+            Some("secret".to_string()),
+        )
+        .unwrap();
+        let body = OpenAIEmbeddingBody {
+            model: "text-embedding-3-small".to_string(),
+            input: vec!["hello world".to_string()],
+        };
+        let request = provider
+            .embedding_request(&Client::new(), "text-embedding-3-small", &body)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            request.url().as_str(),
+            format!(
+                "https://my-resource.openai.azure.com/openai/deployments/text-embedding-3-small/embeddings?api-version={}",
+                provider.api_version
+            )
+        );
+        assert_eq!(request.headers()["api-key"], "secret");
+        assert!(request.headers().get("Authorization").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_azure_model_dim() {
+        let provider = AzureOpenAIProvider::new(
+            Some("https://my-resource.openai.azure.com/".to_string()),
+            Some("secret".to_string()),
+        )
+        .unwrap();
+        assert_eq!(
+            provider.model_dim("text-embedding-3-large").await.unwrap(),
+            3072
+        );
+        // left to resolve_model_dim to probe
+        assert!(matches!(
+            provider.model_dim("my-embedding-deployment").await,
+            Err(VectorizeError::ModelNotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,424 @@
+use chrono::{DateTime, Utc};
+use futures::stream::{self, StreamExt, TryStreamExt};
+use hmac::{Hmac, Mac};
+use reqwest::{Client, RequestBuilder, Url};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::{EmbeddingProvider, GenericEmbeddingRequest, GenericEmbeddingResponse, InputType};
+use crate::config::provider_request_timeout;
+use crate::errors::VectorizeError;
+use crate::transformers::http_handler::{handle_response, provider_client};
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::env;
+use std::time::Duration;
+
+// Bedrock's InvokeModel embeds one input per call, this many calls run at once
+pub const BEDROCK_MAX_CONCURRENCY: usize = 8;
+
+lazy_static! {
+    // from https://docs.aws.amazon.com/bedrock/latest/userguide/titan-embedding-models.html
+    pub(crate) static ref MODEL_DIMENSIONS: HashMap<&'static str, u32> = {
+        let mut m = HashMap::new();
+        m.insert("amazon.titan-embed-text-v2:0", 1024);
+        m.insert("amazon.titan-embed-text-v1", 1536);
+        m.insert("amazon.titan-embed-g1-text-02", 1536);
+        m
+    };
+}
+
+#[derive(Clone, Debug)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+}
+
+impl AwsCredentials {
+    // the environment variables of the standard AWS credential chain
+    pub fn from_env() -> Result<Self, VectorizeError> {
+        let access_key_id = env::var("AWS_ACCESS_KEY_ID").inspect_err(|_| {
+            log::error!("AWS_ACCESS_KEY_ID environment variable is not set");
+        })?;
+        let secret_access_key = env::var("AWS_SECRET_ACCESS_KEY").inspect_err(|_| {
+            log::error!("AWS_SECRET_ACCESS_KEY environment variable is not set");
+        })?;
+        Ok(AwsCredentials {
+            access_key_id,
+            secret_access_key,
+            session_token: env::var("AWS_SESSION_TOKEN").ok(),
+        })
+    }
+}
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+// percent-encodes everything except the unreserved characters, as SigV4 requires
+fn uri_encode(input: &str) -> String {
+    let mut encoded = String::with_capacity(input.len());
+    for b in input.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{b:02X}"));
+        }
+    }
+    encoded
+}
+
+// AWS Signature Version 4, https://docs.aws.amazon.com/IAM/latest/UserGuide/reference_sigv.html
+#[derive(Clone, Debug)]
+pub struct SigV4Signer {
+    pub credentials: AwsCredentials,
+    pub region: String,
+    pub service: String,
+}
+
+impl SigV4Signer {
+    /// returns the headers to add to the request: x-amz-date, x-amz-security-token and authorization
+    /// `headers` are the request's other headers to sign, host is always signed
+    pub fn sign(
+        &self,
+        method: &str,
+        url: &Url,
+        headers: &[(&str, &str)],
+        body: &[u8],
+        now: DateTime<Utc>,
+    ) -> Vec<(&'static str, String)> {
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let host = match url.port() {
+            Some(port) => format!("{}:{port}", url.host_str().unwrap_or_default()),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+
+        let mut signed_headers: Vec<(String, String)> = headers
+            .iter()
+            .map(|(k, v)| (k.to_lowercase(), v.trim().to_string()))
+            .collect();
+        signed_headers.push(("host".to_string(), host));
+        signed_headers.push(("x-amz-date".to_string(), amz_date.clone()));
+        if let Some(token) = &self.credentials.session_token {
+            signed_headers.push(("x-amz-security-token".to_string(), token.clone()));
+        }
+        signed_headers.sort();
+
+        // non-S3 services encode each path segment again, on top of the request's own encoding
+        let canonical_uri = url
+            .path()
+            .split('/')
+            .map(uri_encode)
+            .collect::<Vec<String>>()
+            .join("/");
+        let mut query: Vec<String> = url
+            .query_pairs()
+            .map(|(k, v)| format!("{}={}", uri_encode(&k), uri_encode(&v)))
+            .collect();
+        query.sort();
+        let canonical_headers: String = signed_headers
+            .iter()
+            .map(|(k, v)| format!("{k}:{v}\n"))
+            .collect();
+        let signed_header_names = signed_headers
+            .iter()
+            .map(|(k, _)| k.as_str())
+            .collect::<Vec<&str>>()
+            .join(";");
+        let canonical_request = format!(
+            "{method}\n{canonical_uri}\n{}\n{canonical_headers}\n{signed_header_names}\n{}",
+            query.join("&"),
+            hex::encode(Sha256::digest(body))
+        );
+
+        let scope = format!("{date}/{}/{}/aws4_request", self.region, self.service);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+        let signature = hex::encode(hmac_sha256(&self.signing_key(&date), &string_to_sign));
+
+        let mut out = vec![("x-amz-date", amz_date)];
+        if let Some(token) = &self.credentials.session_token {
+            out.push(("x-amz-security-token", token.clone()));
+        }
+        out.push((
+            "authorization",
+            format!(
+                "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_header_names}, Signature={signature}",
+                self.credentials.access_key_id
+            ),
+        ));
+        out
+    }
+
+    fn signing_key(&self, date: &str) -> Vec<u8> {
+        let k_date = hmac_sha256(
+            format!("AWS4{}", self.credentials.secret_access_key).as_bytes(),
+            date,
+        );
+        let k_region = hmac_sha256(&k_date, &self.region);
+        let k_service = hmac_sha256(&k_region, &self.service);
+        hmac_sha256(&k_service, "aws4_request")
+    }
+}
+
+pub struct BedrockProvider {
+    // runtime endpoint, e.g. https://bedrock-runtime.us-east-1.amazonaws.com
+    pub url: String,
+    pub signer: SigV4Signer,
+    pub timeout: Duration,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TitanEmbeddingBody {
+    #[serde(rename = "inputText")]
+    pub input_text: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TitanEmbeddingResponse {
+    pub embedding: Vec<f64>,
+}
+
+impl BedrockProvider {
+    pub fn new(url: Option<String>) -> Result<Self, VectorizeError> {
+        let region = match env::var("AWS_REGION").or_else(|_| env::var("AWS_DEFAULT_REGION")) {
+            Ok(region) => region,
+            Err(e) => {
+                log::error!("AWS_REGION environment variable is not set");
+                Err(e)?
+            }
+        };
+        Ok(Self::with_credentials(
+            url,
+            region,
+            AwsCredentials::from_env()?,
+        ))
+    }
+
+    pub fn with_credentials(
+        url: Option<String>,
+        region: String,
+        credentials: AwsCredentials,
+    ) -> Self {
+        let url = url.unwrap_or_else(|| format!("https://bedrock-runtime.{region}.amazonaws.com"));
+        BedrockProvider {
+            url: url.trim_end_matches('/').to_string(),
+            signer: SigV4Signer {
+                credentials,
+                region,
+                service: "bedrock".to_string(),
+            },
+            timeout: provider_request_timeout(),
+        }
+    }
+
+    pub fn invoke_url(&self, model_id: &str) -> String {
+        // model ids contain a colon, e.g. amazon.titan-embed-text-v2:0
+        format!("{}/model/{}/invoke", self.url, uri_encode(model_id))
+    }
+
+    fn invoke_request(
+        &self,
+        client: &Client,
+        model_id: &str,
+        body: &TitanEmbeddingBody,
+        now: DateTime<Utc>,
+    ) -> Result<RequestBuilder, VectorizeError> {
+        let url = Url::parse(&self.invoke_url(model_id))
+            .map_err(|e| VectorizeError::InvalidInput(format!("invalid Bedrock url: {e}")))?;
+        let payload = serde_json::to_vec(body)?;
+        let signed = self.signer.sign(
+            "POST",
+            &url,
+            &[("content-type", "application/json")],
+            &payload,
+            now,
+        );
+        let mut request = client
+            .post(url)
+            .timeout(self.timeout)
+            .header("Accept", "application/json")
+            .header("Content-Type", "application/json")
+            .body(payload);
+        for (name, value) in signed {
+            request = request.header(name, value);
+        }
+        Ok(request)
+    }
+
+    async fn embed_one(
+        &self,
+        client: &Client,
+        model_id: &str,
+        input: &str,
+    ) -> Result<Vec<f64>, VectorizeError> {
+        let body = TitanEmbeddingBody {
+            input_text: input.to_string(),
+        };
+        let response = self
+            .invoke_request(client, model_id, &body, Utc::now())?
+            .send()
+            .await?;
+        let embedding = handle_response::<TitanEmbeddingResponse>(response, "embeddings").await?;
+        Ok(embedding.embedding)
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for BedrockProvider {
+    async fn generate_embedding<'a>(
+        &self,
+        request: &'a GenericEmbeddingRequest,
+    ) -> Result<GenericEmbeddingResponse, VectorizeError> {
+        let client = provider_client()?;
+        let calls: Vec<_> = request
+            .input
+            .iter()
+            .map(|input| self.embed_one(&client, &request.model, input))
+            .collect();
+        // buffered keeps the embeddings in input order
+        let embeddings: Vec<Vec<f64>> = stream::iter(calls)
+            .buffered(BEDROCK_MAX_CONCURRENCY)
+            .try_collect()
+            .await?;
+        Ok(GenericEmbeddingResponse { embeddings })
+    }
+
+    async fn model_dim(&self, model_name: &str) -> Result<u32, VectorizeError> {
+        if let Some(dim) = MODEL_DIMENSIONS.get(model_name) {
+            return Ok(*dim);
+        }
+        // unknown model, determine embedding dim by generating an embedding and getting length of array
+        let req = GenericEmbeddingRequest {
+            input: vec!["hello world".to_string()],
+            model: model_name.to_string(),
+            input_type: InputType::Document,
+        };
+        let embedding = self.generate_embedding(&req).await?;
+        Ok(embedding.embeddings[0].len() as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn credentials(session_token: Option<&str>) -> AwsCredentials {
+        AwsCredentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: session_token.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_sigv4_matches_aws_example() {
+        // the IAM ListUsers example from the AWS SigV4 documentation
+        let signer = SigV4Signer {
+            credentials: credentials(None),
+            region: "us-east-1".to_string(),
+            service: "iam".to_string(),
+        };
+        assert_eq!(
+            hex::encode(signer.signing_key("20150830")),
+            "c4afb1cc5771d871763a393e44b703571b55cc28424d1a5e86da6ed3c154a4b9"
+        );
+        let url =
+            Url::parse("https://iam.amazonaws.com/?Action=ListUsers&Version=2010-05-08").unwrap();
+        let headers = signer.sign(
+            "GET",
+            &url,
+            &[(
+                "Content-Type",
+                "application/x-www-form-urlencoded; charset=utf-8",
+            )],
+            b"",
+            Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap(),
+        );
+        assert_eq!(headers[0], ("x-amz-date", "20150830T123600Z".to_string()));
+        assert_eq!(
+            headers[1].1,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/iam/aws4_request, SignedHeaders=content-type;host;x-amz-date, Signature=5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7"
+        );
+    }
+
+    #[test]
+    fn test_bedrock_invoke_request_layout() {
+        let provider = BedrockProvider::with_credentials(
+            None,
+            "us-west-2".to_string(),
+            credentials(Some("session-token")),
+        );
+        let body = TitanEmbeddingBody {
+            input_text: "hello world".to_string(),
+        };
+        let now = Utc.with_ymd_and_hms(2024, 5, 1, 8, 30, 0).unwrap();
+        let request = provider
+            .invoke_request(&Client::new(), "amazon.titan-embed-text-v2:0", &body, now)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(request.method(), "POST");
+        assert_eq!(
+            request.url().as_str(),
+            "https://bedrock-runtime.us-west-2.amazonaws.com/model/amazon.titan-embed-text-v2%3A0/invoke"
+        );
+        let sent: serde_json::Value =
+            serde_json::from_slice(request.body().unwrap().as_bytes().unwrap()).unwrap();
+        assert_eq!(sent, serde_json::json!({"inputText": "hello world"}));
+
+        let headers = request.headers();
+        assert_eq!(headers["x-amz-date"], "20240501T083000Z");
+        assert_eq!(headers["x-amz-security-token"], "session-token");
+        let authorization = headers["authorization"].to_str().unwrap();
+        assert!(authorization.starts_with(
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20240501/us-west-2/bedrock/aws4_request, SignedHeaders=content-type;host;x-amz-date;x-amz-security-token, Signature="
+        ));
+
+        // the signature covers the body, so a different input signs differently
+        let other = provider
+            .invoke_request(
+                &Client::new(),
+                "amazon.titan-embed-text-v2:0",
+                &TitanEmbeddingBody {
+                    input_text: "goodbye".to_string(),
+                },
+                now,
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_ne!(other.headers()["authorization"], headers["authorization"]);
+    }
+
+    #[tokio::test]
+    async fn test_bedrock_model_dim() {
+        let provider =
+            BedrockProvider::with_credentials(None, "us-east-1".to_string(), credentials(None));
+        assert_eq!(
+            provider
+                .model_dim("amazon.titan-embed-text-v2:0")
+                .await
+                .unwrap(),
+            1024
+        );
+        assert_eq!(
+            provider
+                .model_dim("amazon.titan-embed-text-v1")
+                .await
+                .unwrap(),
+            1536
+        );
+    }
+}
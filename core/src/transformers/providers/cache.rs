@@ -0,0 +1,344 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::{EmbeddingProvider, InputType, generate_embeddings_batched};
+use crate::config::Config;
+use crate::errors::VectorizeError;
+use crate::transformers::types::Inputs;
+use crate::types::Model;
+
+// the same text embeds differently per model, and asymmetric models embed queries and documents differently
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct CacheKey {
+    model: String,
+    input_type: InputType,
+    input: String,
+}
+
+impl CacheKey {
+    fn new(model: &Model, input_type: InputType, input: &str) -> Self {
+        CacheKey {
+            model: model.to_string(),
+            input_type,
+            input: normalize_input(input),
+        }
+    }
+}
+
+// surrounding and repeated whitespace does not make two texts different inputs
+fn normalize_input(input: &str) -> String {
+    input.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+struct Entry {
+    embedding: Vec<f64>,
+    inserted: Instant,
+    // position in the recency order, higher was used more recently
+    tick: u64,
+}
+
+struct Lru {
+    entries: HashMap<CacheKey, Entry>,
+    // tick -> key, the first entry is the least recently used
+    order: BTreeMap<u64, CacheKey>,
+    next_tick: u64,
+}
+
+/// least recently used cache of embeddings keyed by model and normalized input text
+/// holds at most `capacity` embeddings, each for at most `ttl`
+/// clones share the same entries, so one cache serves the search routes and the worker
+#[derive(Clone)]
+pub struct EmbeddingCache {
+    capacity: usize,
+    // None keeps entries until they are evicted
+    ttl: Option<Duration>,
+    lru: Arc<Mutex<Lru>>,
+}
+
+impl EmbeddingCache {
+    /// a capacity of 0 disables the cache, a ttl of 0 never expires entries
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        EmbeddingCache {
+            capacity,
+            ttl: (!ttl.is_zero()).then_some(ttl),
+            lru: Arc::new(Mutex::new(Lru {
+                entries: HashMap::new(),
+                order: BTreeMap::new(),
+                next_tick: 0,
+            })),
+        }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        EmbeddingCache::new(
+            config.embedding_cache_size,
+            Duration::from_secs(config.embedding_cache_ttl),
+        )
+    }
+
+    pub fn len(&self) -> usize {
+        self.lru.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn get(&self, key: &CacheKey) -> Option<Vec<f64>> {
+        if self.capacity == 0 {
+            return None;
+        }
+        let mut lru = self.lru.lock().unwrap();
+        let expired = match lru.entries.get(key) {
+            Some(entry) => self.ttl.is_some_and(|ttl| entry.inserted.elapsed() >= ttl),
+            None => return None,
+        };
+        if expired {
+            if let Some(entry) = lru.entries.remove(key) {
+                lru.order.remove(&entry.tick);
+            }
+            return None;
+        }
+        let tick = lru.next_tick;
+        lru.next_tick += 1;
+        let entry = lru.entries.get_mut(key)?;
+        let previous = std::mem::replace(&mut entry.tick, tick);
+        let embedding = entry.embedding.clone();
+        lru.order.remove(&previous);
+        lru.order.insert(tick, key.clone());
+        Some(embedding)
+    }
+
+    fn insert(&self, key: CacheKey, embedding: Vec<f64>) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut lru = self.lru.lock().unwrap();
+        let tick = lru.next_tick;
+        lru.next_tick += 1;
+        lru.order.insert(tick, key.clone());
+        let entry = Entry {
+            embedding,
+            inserted: Instant::now(),
+            tick,
+        };
+        if let Some(previous) = lru.entries.insert(key, entry) {
+            lru.order.remove(&previous.tick);
+        }
+        while lru.entries.len() > self.capacity {
+            let Some((_, evicted)) = lru.order.pop_first() else {
+                break;
+            };
+            lru.entries.remove(&evicted);
+        }
+    }
+}
+
+/// like generate_embeddings_batched, but inputs already in the cache are not sent to the provider
+/// repeated inputs are embedded once, returns one embedding per input, in input order
+pub async fn generate_embeddings_cached(
+    cache: &EmbeddingCache,
+    provider: &(dyn EmbeddingProvider + Send + Sync),
+    model: &Model,
+    inputs: &[Inputs],
+    input_type: InputType,
+    max_tokens: i32,
+) -> Result<Vec<Vec<f64>>, VectorizeError> {
+    let keys: Vec<CacheKey> = inputs
+        .iter()
+        .map(|input| CacheKey::new(model, input_type, &input.inputs))
+        .collect();
+    let mut embeddings: Vec<Option<Vec<f64>>> = keys.iter().map(|key| cache.get(key)).collect();
+
+    // the first input of each missing key is embedded, the others reuse its embedding
+    let mut missing: HashMap<&CacheKey, Vec<usize>> = HashMap::new();
+    let mut to_embed: Vec<Inputs> = Vec::new();
+    for (idx, key) in keys.iter().enumerate() {
+        if embeddings[idx].is_some() {
+            continue;
+        }
+        let positions = missing.entry(key).or_default();
+        if positions.is_empty() {
+            to_embed.push(inputs[idx].clone());
+        }
+        positions.push(idx);
+    }
+    if to_embed.is_empty() {
+        return Ok(embeddings.into_iter().flatten().collect());
+    }
+    log::debug!(
+        "{} of {} inputs not in the embedding cache",
+        to_embed.len(),
+        inputs.len()
+    );
+
+    let generated =
+        generate_embeddings_batched(provider, model, &to_embed, input_type, max_tokens).await?;
+    for (input, embedding) in to_embed.iter().zip(generated) {
+        let key = CacheKey::new(model, input_type, &input.inputs);
+        for &idx in &missing[&key] {
+            embeddings[idx] = Some(embedding.clone());
+        }
+        cache.insert(key, embedding);
+    }
+    Ok(embeddings.into_iter().flatten().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transformers::providers::{GenericEmbeddingRequest, GenericEmbeddingResponse};
+    use async_trait::async_trait;
+
+    // embeds each input as its length and counts the requests it receives
+    struct CountingProvider {
+        calls: Mutex<usize>,
+    }
+
+    #[async_trait]
+    impl EmbeddingProvider for CountingProvider {
+        async fn generate_embedding<'a>(
+            &self,
+            request: &'a GenericEmbeddingRequest,
+        ) -> Result<GenericEmbeddingResponse, VectorizeError> {
+            *self.calls.lock().unwrap() += 1;
+            Ok(GenericEmbeddingResponse {
+                embeddings: request.input.iter().map(|i| vec![i.len() as f64]).collect(),
+            })
+        }
+
+        async fn model_dim(&self, _model_name: &str) -> Result<u32, VectorizeError> {
+            Ok(1)
+        }
+    }
+
+    fn inputs(texts: &[&str]) -> Vec<Inputs> {
+        texts
+            .iter()
+            .map(|t| Inputs {
+                record_id: t.to_string(),
+                inputs: t.to_string(),
+                token_estimate: 1,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_same_text_embedded_once() {
+        let provider = CountingProvider {
+            calls: Mutex::new(0),
+        };
+        let cache = EmbeddingCache::new(10, Duration::from_secs(60));
+        let model = Model::new("openai/text-embedding-3-small").unwrap();
+
+        let first = generate_embeddings_cached(
+            &cache,
+            &provider,
+            &model,
+            &inputs(&["hello world"]),
+            InputType::Query,
+            10000,
+        )
+        .await
+        .unwrap();
+        // whitespace differences normalize to the same key
+        let second = generate_embeddings_cached(
+            &cache,
+            &provider,
+            &model,
+            &inputs(&["  hello   world "]),
+            InputType::Query,
+            10000,
+        )
+        .await
+        .unwrap();
+        assert_eq!(first, vec![vec![11.0]]);
+        assert_eq!(second, first);
+        assert_eq!(*provider.calls.lock().unwrap(), 1);
+
+        // another model or input type is a different key
+        let other = Model::new("openai/text-embedding-3-large").unwrap();
+        generate_embeddings_cached(
+            &cache,
+            &provider,
+            &other,
+            &inputs(&["hello world"]),
+            InputType::Query,
+            10000,
+        )
+        .await
+        .unwrap();
+        generate_embeddings_cached(
+            &cache,
+            &provider,
+            &model,
+            &inputs(&["hello world"]),
+            InputType::Document,
+            10000,
+        )
+        .await
+        .unwrap();
+        assert_eq!(*provider.calls.lock().unwrap(), 3);
+        assert_eq!(cache.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_inputs_in_one_call() {
+        let provider = CountingProvider {
+            calls: Mutex::new(0),
+        };
+        let cache = EmbeddingCache::new(10, Duration::from_secs(60));
+        let model = Model::new("openai/text-embedding-3-small").unwrap();
+
+        let embeddings = generate_embeddings_cached(
+            &cache,
+            &provider,
+            &model,
+            &inputs(&["a", "bb", "a", "ccc"]),
+            InputType::Document,
+            10000,
+        )
+        .await
+        .unwrap();
+        assert_eq!(embeddings, vec![vec![1.0], vec![2.0], vec![1.0], vec![3.0]]);
+        assert_eq!(*provider.calls.lock().unwrap(), 1);
+        assert_eq!(cache.len(), 3);
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let model = Model::new("openai/text-embedding-3-small").unwrap();
+        let key = |text: &str| CacheKey::new(&model, InputType::Query, text);
+        let cache = EmbeddingCache::new(2, Duration::ZERO);
+
+        cache.insert(key("a"), vec![1.0]);
+        cache.insert(key("b"), vec![2.0]);
+        // reading a makes b the least recently used
+        assert_eq!(cache.get(&key("a")), Some(vec![1.0]));
+        cache.insert(key("c"), vec![3.0]);
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&key("b")), None);
+        assert_eq!(cache.get(&key("a")), Some(vec![1.0]));
+        assert_eq!(cache.get(&key("c")), Some(vec![3.0]));
+    }
+
+    #[test]
+    fn test_expired_and_disabled() {
+        let model = Model::new("openai/text-embedding-3-small").unwrap();
+        let key = CacheKey::new(&model, InputType::Query, "a");
+
+        let cache = EmbeddingCache::new(2, Duration::from_millis(10));
+        cache.insert(key.clone(), vec![1.0]);
+        assert_eq!(cache.get(&key), Some(vec![1.0]));
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(cache.get(&key), None);
+        assert!(cache.is_empty());
+
+        let disabled = EmbeddingCache::new(0, Duration::from_secs(60));
+        disabled.insert(key.clone(), vec![1.0]);
+        assert_eq!(disabled.get(&key), None);
+        assert!(disabled.is_empty());
+    }
+}
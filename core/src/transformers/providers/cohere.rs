@@ -1,18 +1,22 @@
-use reqwest::Client;
 use serde::{Deserialize, Serialize};
 
-use super::{EmbeddingProvider, GenericEmbeddingRequest, GenericEmbeddingResponse};
+use super::{
+    EmbeddingProvider, GenericEmbeddingRequest, GenericEmbeddingResponse, InputType, RerankResult,
+    Reranker, scores_in_document_order,
+};
+use crate::config::provider_request_timeout;
 use crate::errors::VectorizeError;
-use crate::transformers::http_handler::handle_response;
+use crate::transformers::http_handler::{handle_response, provider_client};
 use async_trait::async_trait;
 use lazy_static::lazy_static;
 use std::collections::HashMap;
 use std::env;
+use std::time::Duration;
 
 pub const COHERE_BASE_URL: &str = "https://api.cohere.com/v1";
 
 lazy_static! {
-    static ref MODEL_DIMENSIONS: HashMap<&'static str, u32> = {
+    pub(crate) static ref MODEL_DIMENSIONS: HashMap<&'static str, u32> = {
         let mut m = HashMap::new();
         m.insert("embed-english-v3.0", 1024);
         m.insert("embed-multilingual-v3.0", 1024);
@@ -28,6 +32,7 @@ lazy_static! {
 pub struct CohereProvider {
     pub url: String,
     pub api_key: String,
+    pub timeout: Duration,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -43,7 +48,10 @@ impl From<GenericEmbeddingRequest> for CohereEmbeddingBody {
         CohereEmbeddingBody {
             model: request.model,
             texts: request.input,
-            input_type: "search_document".to_string(),
+            input_type: match request.input_type {
+                InputType::Document => "search_document".to_string(),
+                InputType::Query => "search_query".to_string(),
+            },
             truncate: "END".to_string(),
         }
     }
@@ -68,6 +76,7 @@ impl CohereProvider {
         Ok(CohereProvider {
             url: final_url,
             api_key: final_api_key,
+            timeout: provider_request_timeout(),
         })
     }
 }
@@ -78,14 +87,14 @@ impl EmbeddingProvider for CohereProvider {
         &self,
         request: &'a GenericEmbeddingRequest,
     ) -> Result<GenericEmbeddingResponse, VectorizeError> {
-        let client = Client::new();
+        let client = provider_client()?;
 
         let payload = CohereEmbeddingBody::from(request.clone());
         let payload_val = serde_json::to_value(payload)?;
         let embeddings_url = format!("{}/embed", self.url);
         let response = client
             .post(&embeddings_url)
-            .timeout(std::time::Duration::from_secs(120_u64))
+            .timeout(self.timeout)
             .header("Accept", "application/json")
             .header("Content-Type", "application/json")
             .header("Authorization", format!("Bearer {}", self.api_key))
@@ -106,6 +115,66 @@ impl EmbeddingProvider for CohereProvider {
     }
 }
 
+#[derive(Clone, Debug, Serialize)]
+struct CohereRerankBody<'a> {
+    model: &'a str,
+    query: &'a str,
+    documents: &'a [String],
+}
+
+#[derive(Deserialize, Debug)]
+struct CohereRerankResponse {
+    results: Vec<RerankResult>,
+}
+
+#[async_trait]
+impl Reranker for CohereProvider {
+    async fn rerank(
+        &self,
+        model_name: &str,
+        query: &str,
+        documents: &[String],
+    ) -> Result<Vec<f64>, VectorizeError> {
+        let client = provider_client()?;
+        let body = CohereRerankBody {
+            model: model_name,
+            query,
+            documents,
+        };
+        let response = client
+            .post(format!("{}/rerank", self.url))
+            .timeout(self.timeout)
+            .header("Accept", "application/json")
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .json(&body)
+            .send()
+            .await?;
+        let reranked = handle_response::<CohereRerankResponse>(response, "rerank").await?;
+        scores_in_document_order(reranked.results, documents.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cohere_input_type() {
+        let mut request = GenericEmbeddingRequest {
+            model: "embed-english-v3.0".to_string(),
+            input: vec!["hello world".to_string()],
+            input_type: InputType::Document,
+        };
+        let body = CohereEmbeddingBody::from(request.clone());
+        assert_eq!(body.input_type, "search_document");
+
+        request.input_type = InputType::Query;
+        let body = CohereEmbeddingBody::from(request);
+        assert_eq!(body.input_type, "search_query");
+    }
+}
+
 #[cfg(test)]
 mod integration_tests {
     use super::*;
@@ -118,6 +187,7 @@ mod integration_tests {
         let request = GenericEmbeddingRequest {
             model: "embed-english-light-v3.0".to_string(),
             input: vec!["hello world".to_string()],
+            input_type: InputType::Document,
         };
 
         let embeddings = provider.generate_embedding(&request).await.unwrap();
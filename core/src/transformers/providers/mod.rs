@@ -1,16 +1,23 @@
+pub mod azure;
+pub mod bedrock;
+pub mod cache;
 pub mod cohere;
 pub mod ollama;
 pub mod openai;
+pub mod openai_compat;
 pub mod portkey;
 pub mod vector_serve;
+pub mod vertex;
 pub mod voyage;
 
 use anyhow::Result;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 use super::types::Inputs;
 use crate::errors::VectorizeError;
+use crate::query;
 use crate::transformers::providers;
 use crate::types::Model;
 use crate::types::ModelSource;
@@ -24,12 +31,80 @@ pub trait EmbeddingProvider: Send + Sync {
     ) -> Result<GenericEmbeddingResponse, VectorizeError>;
     #[allow(async_fn_in_trait)]
     async fn model_dim(&self, model_name: &str) -> Result<u32, VectorizeError>;
+    /// embeds images into the same space as the model's text embeddings, for text-to-image search
+    /// only multimodal models support it
+    #[allow(async_fn_in_trait)]
+    async fn generate_image_embedding<'a>(
+        &self,
+        request: &'a GenericImageEmbeddingRequest,
+    ) -> Result<GenericEmbeddingResponse, VectorizeError> {
+        Err(VectorizeError::InvalidInput(format!(
+            "model {} does not support image inputs",
+            request.model
+        )))
+    }
+}
+
+/// whether the inputs are documents being indexed or a search query
+/// providers with asymmetric models (Cohere, Voyage) embed the two differently
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputType {
+    #[default]
+    Document,
+    Query,
+}
+
+/// scores how relevant each document is to a query, e.g. with a cross-encoder model
+#[async_trait]
+pub trait Reranker: Send + Sync {
+    /// returns one relevance score per document, in document order, higher is more relevant
+    async fn rerank(
+        &self,
+        model_name: &str,
+        query: &str,
+        documents: &[String],
+    ) -> Result<Vec<f64>, VectorizeError>;
+}
+
+// response item shared by the Cohere and Voyage rerank endpoints
+#[derive(Deserialize, Debug)]
+struct RerankResult {
+    index: usize,
+    relevance_score: f64,
+}
+
+// places each score at its document's index, the endpoints return them sorted by score
+fn scores_in_document_order(
+    results: Vec<RerankResult>,
+    num_documents: usize,
+) -> Result<Vec<f64>, VectorizeError> {
+    let mut scores = vec![f64::NEG_INFINITY; num_documents];
+    for r in results {
+        let score = scores.get_mut(r.index).ok_or_else(|| {
+            VectorizeError::EmbeddingGenerationFailed(format!(
+                "rerank result index {} out of range for {num_documents} documents",
+                r.index
+            ))
+        })?;
+        *score = r.relevance_score;
+    }
+    Ok(scores)
 }
 
 #[derive(Clone, Deserialize, Debug, Serialize)]
 pub struct GenericEmbeddingRequest {
     pub input: Vec<String>,
     pub model: String,
+    #[serde(default)]
+    pub input_type: InputType,
+}
+
+/// images to embed, each an http(s) URL, a gs:// URI or a base64 data URI
+#[derive(Clone, Deserialize, Debug, Serialize)]
+pub struct GenericImageEmbeddingRequest {
+    pub images: Vec<String>,
+    pub model: String,
 }
 
 #[derive(Deserialize, Debug)]
@@ -40,13 +115,62 @@ pub struct GenericEmbeddingResponse {
 pub fn prepare_generic_embedding_request(
     model: &Model,
     inputs: &[Inputs],
+    input_type: InputType,
 ) -> GenericEmbeddingRequest {
     let text_inputs = providers::openai::trim_inputs(inputs);
 
     GenericEmbeddingRequest {
         input: text_inputs,
         model: model.api_name(),
+        input_type,
+    }
+}
+
+/// embeds the inputs in sequential requests of at most max_tokens estimated tokens each
+/// providers cap the tokens per request, so one large batch would be rejected
+/// returns one embedding per input, in input order
+pub async fn generate_embeddings_batched(
+    provider: &(dyn EmbeddingProvider + Send + Sync),
+    model: &Model,
+    inputs: &[Inputs],
+    input_type: InputType,
+    max_tokens: i32,
+) -> Result<Vec<Vec<f64>>, VectorizeError> {
+    let mut embeddings = Vec::with_capacity(inputs.len());
+    for batch in query::create_batches(inputs.to_vec(), max_tokens) {
+        let request = prepare_generic_embedding_request(model, &batch, input_type);
+        let response = provider.generate_embedding(&request).await?;
+        if response.embeddings.len() != batch.len() {
+            return Err(VectorizeError::EmbeddingGenerationFailed(format!(
+                "expected {} embeddings, got {}",
+                batch.len(),
+                response.embeddings.len()
+            )));
+        }
+        embeddings.extend(response.embeddings);
     }
+    Ok(embeddings)
+}
+
+/// embeds the images the inputs point to, returns one embedding per input, in input order
+pub async fn generate_image_embeddings(
+    provider: &(dyn EmbeddingProvider + Send + Sync),
+    model: &Model,
+    inputs: &[Inputs],
+) -> Result<Vec<Vec<f64>>, VectorizeError> {
+    let request = GenericImageEmbeddingRequest {
+        images: inputs.iter().map(|i| i.inputs.clone()).collect(),
+        model: model.api_name(),
+    };
+    let response = provider.generate_image_embedding(&request).await?;
+    if response.embeddings.len() != inputs.len() {
+        return Err(VectorizeError::EmbeddingGenerationFailed(format!(
+            "expected {} embeddings, got {}",
+            inputs.len(),
+            response.embeddings.len()
+        )));
+    }
+    Ok(response.embeddings)
 }
 
 pub fn get_provider(
@@ -73,7 +197,230 @@ pub fn get_provider(
         ModelSource::SentenceTransformers => Ok(Box::new(
             providers::vector_serve::VectorServeProvider::new(url, api_key),
         )),
-        ModelSource::Ollama => Ok(Box::new(providers::ollama::OllamaProvider::new(url))),
+        ModelSource::Ollama => Ok(Box::new(providers::ollama::OllamaProvider::new(url)?)),
+        ModelSource::AzureOpenAI => Ok(Box::new(providers::azure::AzureOpenAIProvider::new(
+            url, api_key,
+        )?)),
+        // credentials and region come from the AWS environment, not an api key
+        ModelSource::Bedrock => Ok(Box::new(providers::bedrock::BedrockProvider::new(url)?)),
+        // the api key, if any, is used as the OAuth2 access token
+        ModelSource::Vertex => Ok(Box::new(providers::vertex::VertexProvider::new(
+            url, api_key,
+        )?)),
+        ModelSource::OpenAICompatible => Ok(Box::new(
+            providers::openai_compat::OpenAICompatibleProvider::new(url, api_key)?,
+        )),
+    }
+}
+
+/// how a source's embedding dimension is determined when a job is created
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DimensionLookup {
+    /// from the source's table of models
+    Static,
+    /// from the source's table of models, other models are probed with a short embedding
+    StaticOrProbed,
+    /// asked from the model server, or probed with a short embedding
+    Probed,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct KnownModel {
+    /// model string to use in a job, e.g. openai/text-embedding-3-small
+    pub model: String,
+    /// embedding dimension, when it is known without calling the provider
+    pub dim: Option<u32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct ModelSourceInfo {
+    pub source: String,
+    pub dimension_lookup: DimensionLookup,
+    pub models: Vec<KnownModel>,
+}
+
+fn known_models<'a>(
+    source: &ModelSource,
+    dims: impl IntoIterator<Item = (&'a str, u32)>,
+) -> Vec<KnownModel> {
+    let mut models: Vec<KnownModel> = dims
+        .into_iter()
+        .map(|(name, dim)| KnownModel {
+            model: format!("{source}/{name}"),
+            dim: Some(dim),
+        })
+        .collect();
+    models.sort_by(|a, b| a.model.cmp(&b.model));
+    models
+}
+
+fn example_models(source: &ModelSource, names: &[&str]) -> Vec<KnownModel> {
+    names
+        .iter()
+        .map(|name| KnownModel {
+            model: format!("{source}/{name}"),
+            dim: None,
+        })
+        .collect()
+}
+
+/// the models each source served by `get_provider` is known to support
+pub fn model_catalog() -> Vec<ModelSourceInfo> {
+    let openai_dims = || {
+        providers::openai::MODEL_DIMENSIONS
+            .iter()
+            .map(|(name, dim)| (*name, *dim as u32))
+    };
+    ModelSource::ALL
+        .iter()
+        .map(|source| {
+            let (dimension_lookup, models) = match source {
+                ModelSource::OpenAI => {
+                    (DimensionLookup::Static, known_models(source, openai_dims()))
+                }
+                // deployments are named after the OpenAI model they serve
+                ModelSource::AzureOpenAI => {
+                    (DimensionLookup::Static, known_models(source, openai_dims()))
+                }
+                ModelSource::Cohere => (
+                    DimensionLookup::Static,
+                    known_models(
+                        source,
+                        providers::cohere::MODEL_DIMENSIONS
+                            .iter()
+                            .map(|(n, d)| (*n, *d)),
+                    ),
+                ),
+                ModelSource::Voyage => (
+                    DimensionLookup::StaticOrProbed,
+                    known_models(
+                        source,
+                        providers::voyage::MODEL_DIMENSIONS
+                            .iter()
+                            .map(|(n, d)| (*n, *d)),
+                    ),
+                ),
+                ModelSource::Bedrock => (
+                    DimensionLookup::StaticOrProbed,
+                    known_models(
+                        source,
+                        providers::bedrock::MODEL_DIMENSIONS
+                            .iter()
+                            .map(|(n, d)| (*n, *d)),
+                    ),
+                ),
+                ModelSource::Vertex => (
+                    DimensionLookup::StaticOrProbed,
+                    known_models(
+                        source,
+                        providers::vertex::MODEL_DIMENSIONS
+                            .iter()
+                            .map(|(n, d)| (*n, *d)),
+                    ),
+                ),
+                ModelSource::SentenceTransformers => (
+                    DimensionLookup::Probed,
+                    example_models(source, &["all-MiniLM-L6-v2", "all-mpnet-base-v2"]),
+                ),
+                ModelSource::Ollama => (
+                    DimensionLookup::Probed,
+                    example_models(source, &["nomic-embed-text", "mxbai-embed-large"]),
+                ),
+                ModelSource::Portkey => (
+                    DimensionLookup::Probed,
+                    example_models(source, &["text-embedding-3-small"]),
+                ),
+                ModelSource::OpenAICompatible => (
+                    DimensionLookup::Probed,
+                    example_models(source, &["BAAI/bge-small-en-v1.5"]),
+                ),
+            };
+            ModelSourceInfo {
+                source: source.to_string(),
+                dimension_lookup,
+                models,
+            }
+        })
+        .collect()
+}
+
+/// true for multimodal models, which embed images into the same space as text
+pub fn supports_images(model: &Model) -> bool {
+    model.source == ModelSource::Vertex && vertex::is_multimodal(&model.api_name())
+}
+
+/// the model's embedding dimension, checked before a job is created so an unknown model
+/// is reported as invalid input rather than failing part way through creating the job
+pub async fn resolve_model_dim(
+    provider: &(dyn EmbeddingProvider + Send + Sync),
+    model: &Model,
+) -> Result<u32, VectorizeError> {
+    // these sources look dimensions up in a static table, a model released since, or an azure
+    // deployment not named after an OpenAI model, is probed
+    let static_dims = matches!(
+        model.source,
+        ModelSource::OpenAI | ModelSource::Cohere | ModelSource::AzureOpenAI
+    );
+    let cataloged = model_catalog()
+        .into_iter()
+        .filter(|info| info.source == model.source.to_string())
+        .flat_map(|info| info.models)
+        .any(|known| known.model == model.fullname);
+    let dim = if static_dims && !cataloged {
+        probe_model_dim(provider, model).await
+    } else {
+        provider.model_dim(&model.api_name()).await
+    };
+    match dim {
+        Ok(dim) => Ok(dim),
+        // the provider was reached and rejected the model
+        Err(
+            e @ (VectorizeError::ModelNotFound(_)
+            | VectorizeError::EmbeddingGenerationFailed(_)
+            | VectorizeError::OllamaError(_)
+            | VectorizeError::InternalError(_)),
+        ) => Err(VectorizeError::InvalidInput(format!(
+            "model {model} is not available: {e}"
+        ))),
+        Err(e) => Err(e),
+    }
+}
+
+// the dimension of an embedding the provider returns for the model
+async fn probe_model_dim(
+    provider: &(dyn EmbeddingProvider + Send + Sync),
+    model: &Model,
+) -> Result<u32, VectorizeError> {
+    let request = GenericEmbeddingRequest {
+        input: vec!["hello world".to_string()],
+        model: model.api_name(),
+        input_type: InputType::Document,
+    };
+    let response = provider.generate_embedding(&request).await?;
+    match response.embeddings.first() {
+        Some(embedding) if !embedding.is_empty() => Ok(embedding.len() as u32),
+        _ => Err(VectorizeError::EmbeddingGenerationFailed(format!(
+            "model {model} returned no embedding"
+        ))),
+    }
+}
+
+pub fn get_reranker(
+    model_source: &ModelSource,
+    api_key: Option<String>,
+    url: Option<String>,
+) -> Result<Box<dyn Reranker>, VectorizeError> {
+    match model_source {
+        ModelSource::Cohere => Ok(Box::new(providers::cohere::CohereProvider::new(
+            url, api_key,
+        )?)),
+        ModelSource::Voyage => Ok(Box::new(providers::voyage::VoyageProvider::new(
+            url, api_key,
+        )?)),
+        _ => Err(VectorizeError::InvalidInput(format!(
+            "reranking is not supported for model source: {model_source}"
+        ))),
     }
 }
 
@@ -101,3 +448,230 @@ struct Choice {
 struct ResponseMessage {
     content: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    struct FailingProvider;
+
+    #[async_trait]
+    impl EmbeddingProvider for FailingProvider {
+        async fn generate_embedding<'a>(
+            &self,
+            _request: &'a GenericEmbeddingRequest,
+        ) -> Result<GenericEmbeddingResponse, VectorizeError> {
+            Err(anyhow::anyhow!("status code: 404, model not found").into())
+        }
+
+        async fn model_dim(&self, model_name: &str) -> Result<u32, VectorizeError> {
+            match model_name {
+                "text-embedding-3-small" => Ok(1536),
+                "env-missing" => Err(std::env::VarError::NotPresent.into()),
+                _ => Err(anyhow::anyhow!("status code: 404, model {model_name} not found").into()),
+            }
+        }
+    }
+
+    // serves any model, with a dimension its static table does not know
+    struct ProbedProvider;
+
+    #[async_trait]
+    impl EmbeddingProvider for ProbedProvider {
+        async fn generate_embedding<'a>(
+            &self,
+            request: &'a GenericEmbeddingRequest,
+        ) -> Result<GenericEmbeddingResponse, VectorizeError> {
+            Ok(GenericEmbeddingResponse {
+                embeddings: request.input.iter().map(|_| vec![0.5; 3]).collect(),
+            })
+        }
+
+        async fn model_dim(&self, model_name: &str) -> Result<u32, VectorizeError> {
+            Err(VectorizeError::ModelNotFound(model_name.to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_model_dim() {
+        let model = Model::new("openai/text-embedding-3-small").unwrap();
+        assert_eq!(
+            resolve_model_dim(&FailingProvider, &model).await.unwrap(),
+            1536
+        );
+
+        // models missing from the catalog are probed, a rejected probe is invalid input
+        let model = Model::new("openai/text-embedding-bogus").unwrap();
+        match resolve_model_dim(&FailingProvider, &model).await {
+            Err(VectorizeError::InvalidInput(msg)) => {
+                assert!(msg.contains("model openai/text-embedding-bogus is not available"));
+                assert!(msg.contains("404"));
+            }
+            other => panic!("expected InvalidInput, got {other:?}"),
+        }
+        let model = Model::new("openai/text-embedding-4-small").unwrap();
+        assert_eq!(resolve_model_dim(&ProbedProvider, &model).await.unwrap(), 3);
+        let model = Model::new("cohere/embed-v5.0").unwrap();
+        assert_eq!(resolve_model_dim(&ProbedProvider, &model).await.unwrap(), 3);
+        let model = Model::new("azure/my-embedding-deployment").unwrap();
+        assert_eq!(resolve_model_dim(&ProbedProvider, &model).await.unwrap(), 3);
+        let model = Model::new("azure/text-embedding-3-small").unwrap();
+        assert_eq!(
+            resolve_model_dim(&FailingProvider, &model).await.unwrap(),
+            1536
+        );
+
+        // other sources report the provider's rejection as invalid input
+        let model = Model::new("ollama/not-a-model").unwrap();
+        match resolve_model_dim(&FailingProvider, &model).await {
+            Err(VectorizeError::InvalidInput(msg)) => {
+                assert!(msg.contains("model ollama/not-a-model is not available"));
+                assert!(msg.contains("404"));
+            }
+            other => panic!("expected InvalidInput, got {other:?}"),
+        }
+
+        // configuration problems are not the model's fault
+        let model = Model::new("ollama/env-missing").unwrap();
+        assert!(matches!(
+            resolve_model_dim(&FailingProvider, &model).await,
+            Err(VectorizeError::EnvError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_image_embedding_unsupported_by_default() {
+        let model = Model::new("openai/text-embedding-3-small").unwrap();
+        let inputs = vec![Inputs {
+            record_id: "1".to_string(),
+            inputs: "https://example.com/cat.png".to_string(),
+            token_estimate: 0,
+        }];
+        match generate_image_embeddings(&FailingProvider, &model, &inputs).await {
+            Err(VectorizeError::InvalidInput(msg)) => {
+                assert_eq!(
+                    msg,
+                    "model text-embedding-3-small does not support image inputs"
+                );
+            }
+            other => panic!("expected InvalidInput, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_model_catalog() {
+        let catalog = model_catalog();
+        assert_eq!(catalog.len(), ModelSource::ALL.len());
+        for (info, source) in catalog.iter().zip(ModelSource::ALL.iter()) {
+            assert_eq!(info.source, source.to_string());
+            assert!(!info.models.is_empty(), "{} lists no models", info.source);
+            for known in &info.models {
+                // every listed model string resolves back to its source
+                let model = Model::new(&known.model).unwrap();
+                assert_eq!(&model.source, source, "{}", known.model);
+                assert_eq!(
+                    known.dim.is_some(),
+                    info.dimension_lookup != DimensionLookup::Probed
+                );
+            }
+        }
+
+        let openai = &catalog[0];
+        assert_eq!(openai.dimension_lookup, DimensionLookup::Static);
+        let large = openai
+            .models
+            .iter()
+            .find(|m| m.model == "openai/text-embedding-3-large")
+            .unwrap();
+        assert_eq!(large.dim, Some(3072));
+        let bedrock = catalog.iter().find(|i| i.source == "bedrock").unwrap();
+        assert!(
+            bedrock
+                .models
+                .iter()
+                .any(|m| m.model == "bedrock/amazon.titan-embed-text-v2:0" && m.dim == Some(1024))
+        );
+    }
+
+    // returns the input's position as its embedding and records the size of each request
+    struct RecordingProvider {
+        requests: Mutex<Vec<usize>>,
+    }
+
+    #[async_trait]
+    impl EmbeddingProvider for RecordingProvider {
+        async fn generate_embedding<'a>(
+            &self,
+            request: &'a GenericEmbeddingRequest,
+        ) -> Result<GenericEmbeddingResponse, VectorizeError> {
+            self.requests.lock().unwrap().push(request.input.len());
+            Ok(GenericEmbeddingResponse {
+                embeddings: request
+                    .input
+                    .iter()
+                    .map(|i| vec![i.trim_start_matches("doc ").parse().unwrap()])
+                    .collect(),
+            })
+        }
+
+        async fn model_dim(&self, _model_name: &str) -> Result<u32, VectorizeError> {
+            Ok(1)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generate_embeddings_batched() {
+        let provider = RecordingProvider {
+            requests: Mutex::new(Vec::new()),
+        };
+        let model = Model::new("openai/text-embedding-3-small").unwrap();
+        let inputs = (0..50)
+            .map(|i| Inputs {
+                record_id: i.to_string(),
+                inputs: format!("doc {i}"),
+                token_estimate: 4000,
+            })
+            .collect::<Vec<_>>();
+
+        let embeddings =
+            generate_embeddings_batched(&provider, &model, &inputs, InputType::Document, 10000)
+                .await
+                .unwrap();
+
+        // two 4000 token inputs fit under the 10000 token budget
+        assert_eq!(*provider.requests.lock().unwrap(), vec![2; 25]);
+        let expected = (0..50).map(|i| vec![i as f64]).collect::<Vec<_>>();
+        assert_eq!(embeddings, expected);
+    }
+
+    #[test]
+    fn test_scores_in_document_order() {
+        let results: Vec<RerankResult> = serde_json::from_value(serde_json::json!([
+            {"index": 2, "relevance_score": 0.9},
+            {"index": 0, "relevance_score": 0.5},
+            {"index": 1, "relevance_score": 0.1},
+        ]))
+        .unwrap();
+        assert_eq!(
+            scores_in_document_order(results, 3).unwrap(),
+            vec![0.5, 0.1, 0.9]
+        );
+
+        let out_of_range = vec![RerankResult {
+            index: 3,
+            relevance_score: 1.0,
+        }];
+        assert!(scores_in_document_order(out_of_range, 3).is_err());
+    }
+
+    #[test]
+    fn test_get_reranker() {
+        assert!(get_reranker(&ModelSource::Cohere, Some("key".to_string()), None).is_ok());
+        assert!(get_reranker(&ModelSource::Voyage, Some("key".to_string()), None).is_ok());
+        assert!(matches!(
+            get_reranker(&ModelSource::OpenAI, Some("key".to_string()), None),
+            Err(VectorizeError::InvalidInput(_))
+        ));
+    }
+}
@@ -1,34 +1,48 @@
 use super::{
     ChatMessageRequest, EmbeddingProvider, GenericEmbeddingRequest, GenericEmbeddingResponse,
+    InputType,
 };
+use crate::config::provider_request_timeout;
 use crate::errors::VectorizeError;
 use async_trait::async_trait;
+use futures::StreamExt;
 use ollama_rs::{
     Ollama,
-    generation::completion::request::GenerationRequest,
+    generation::completion::{GenerationResponseStream, request::GenerationRequest},
     generation::embeddings::request::{EmbeddingsInput, GenerateEmbeddingsRequest},
 };
+use std::time::Duration;
 use url::Url;
 
 pub const OLLAMA_BASE_URL: &str = "http://localhost:3001";
 
 pub struct OllamaProvider {
     pub instance: Ollama,
+    // abandon embedding requests after this long, ollama-rs does not expose its http client
+    pub timeout: Duration,
 }
 
 impl OllamaProvider {
-    pub fn new(url: Option<String>) -> Self {
+    pub fn new(url: Option<String>) -> Result<Self, VectorizeError> {
         let url_in = url.unwrap_or_else(|| OLLAMA_BASE_URL.to_string());
-        let parsed_url = Url::parse(&url_in).unwrap_or_else(|_| panic!("invalid url: {url_in}"));
-        let instance = Ollama::new(
-            format!(
-                "{}://{}",
-                parsed_url.scheme(),
-                parsed_url.host_str().expect("parsed url missing")
-            ),
-            parsed_url.port().expect("parsed port missing"),
-        );
-        OllamaProvider { instance }
+        let parsed_url = Url::parse(&url_in)
+            .map_err(|e| anyhow::anyhow!("invalid ollama url: {url_in}, error: {e}"))?;
+        let host = parsed_url
+            .host_str()
+            .ok_or_else(|| anyhow::anyhow!("ollama url missing host: {url_in}"))?;
+        let port = parsed_url
+            .port_or_known_default()
+            .ok_or_else(|| anyhow::anyhow!("ollama url missing port: {url_in}"))?;
+        // build the url ourselves, Ollama::new panics on urls that cannot have a port
+        let mut base_url = Url::parse(&format!("{}://{}", parsed_url.scheme(), host))
+            .map_err(|e| anyhow::anyhow!("invalid ollama url: {url_in}, error: {e}"))?;
+        base_url
+            .set_port(Some(port))
+            .map_err(|_| anyhow::anyhow!("ollama url cannot have a port: {url_in}"))?;
+        Ok(OllamaProvider {
+            instance: Ollama::from_url(base_url),
+            timeout: provider_request_timeout(),
+        })
     }
 }
 
@@ -43,7 +57,14 @@ impl EmbeddingProvider for OllamaProvider {
         let embedding_input = EmbeddingsInput::Multiple(request.input.clone());
         let req = GenerateEmbeddingsRequest::new(model_name.clone(), embedding_input);
 
-        let embed = self.instance.generate_embeddings(req).await?;
+        let embed = tokio::time::timeout(self.timeout, self.instance.generate_embeddings(req))
+            .await
+            .map_err(|_| {
+                VectorizeError::ProviderTimeout(format!(
+                    "no response from ollama within {}s",
+                    self.timeout.as_secs_f64()
+                ))
+            })??;
 
         let embed = embed
             .embeddings
@@ -59,10 +80,15 @@ impl EmbeddingProvider for OllamaProvider {
         let req = GenericEmbeddingRequest {
             input: vec!["hello world".to_string()],
             model: model_name.to_string(),
+            input_type: InputType::Document,
         };
         let embedding = self.generate_embedding(&req).await?;
-        let dim = embedding.embeddings[0].len();
-        Ok(dim as u32)
+        match embedding.embeddings.first() {
+            Some(e) if !e.is_empty() => Ok(e.len() as u32),
+            _ => Err(VectorizeError::EmbeddingGenerationFailed(format!(
+                "ollama returned no embedding for model: {model_name}"
+            ))),
+        }
     }
 }
 
@@ -81,27 +107,169 @@ impl OllamaProvider {
         let res = self.instance.generate(req).await?;
         Ok(res.response)
     }
+
+    /// same as generate_response, but yields the completion as it is generated
+    pub async fn generate_response_stream(
+        &self,
+        model_name: String,
+        prompt_text: &[ChatMessageRequest],
+    ) -> Result<ResponseStream, VectorizeError> {
+        let single_prompt: String = prompt_text
+            .iter()
+            .map(|x| x.content.clone())
+            .collect::<Vec<String>>()
+            .join("\n\n");
+        let req = GenerationRequest::new(model_name, single_prompt);
+        let inner = self.instance.generate_stream(req).await?;
+        Ok(ResponseStream { inner })
+    }
+}
+
+/// a streamed chat completion
+///
+/// chunks are returned in the order they are generated by the model, and concatenating
+/// every chunk yields the same text as the non-streaming response
+pub struct ResponseStream {
+    inner: GenerationResponseStream,
+}
+
+impl ResponseStream {
+    /// returns the next piece of the completion, or None once the completion is finished
+    pub async fn next_chunk(&mut self) -> Option<Result<String, VectorizeError>> {
+        loop {
+            match self.inner.next().await? {
+                Ok(responses) => {
+                    let chunk: String = responses.into_iter().map(|r| r.response).collect();
+                    // a network read can hold only metadata, skip until there is text
+                    if !chunk.is_empty() {
+                        return Some(Ok(chunk));
+                    }
+                }
+                Err(e) => return Some(Err(e.into())),
+            }
+        }
+    }
 }
 
-pub fn check_model_host(url: &str) -> Result<String, String> {
+pub fn check_model_host(url: &str) -> Result<String, VectorizeError> {
     let runtime = tokio::runtime::Builder::new_current_thread()
         .enable_io()
         .enable_time()
         .build()
-        .unwrap_or_else(|e| panic!("failed to initialize tokio runtime: {e}"));
+        .map_err(|e| anyhow::anyhow!("failed to initialize tokio runtime: {e}"))?;
 
     runtime.block_on(async {
-        let response = reqwest::get(url).await.unwrap();
+        let response = reqwest::get(url).await?;
         match response.status() {
             reqwest::StatusCode::OK => Ok(format!("Success! {response:?}")),
-            _ => Err(format!("Error! {response:?}")),
+            _ => Err(anyhow::anyhow!("Error! {response:?}").into()),
         }
     })
 }
 
-pub fn ollama_embedding_dim(model_name: &str) -> i32 {
-    match model_name {
-        "llama2" => 5192,
-        _ => 1536,
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    // serves a single canned /api/embed response, returning the server's url
+    async fn mock_ollama(body: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0; 4096];
+            let _ = socket.read(&mut buf).await.unwrap();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+        format!("http://{addr}")
+    }
+
+    #[test]
+    fn test_new_invalid_url() {
+        assert!(OllamaProvider::new(Some("not a url".to_string())).is_err());
+        assert!(OllamaProvider::new(Some("unix:/tmp/ollama.sock".to_string())).is_err());
+    }
+
+    #[test]
+    fn test_new_default_port() {
+        let provider = OllamaProvider::new(Some("http://ollama.internal".to_string())).unwrap();
+        let url = provider.instance.url();
+        assert_eq!(url.host_str(), Some("ollama.internal"));
+        assert_eq!(url.port_or_known_default(), Some(80));
+    }
+
+    #[test]
+    fn test_check_model_host_invalid_url() {
+        assert!(check_model_host("not a url").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_model_dim_probes_model() {
+        let url = mock_ollama(r#"{"embeddings": [[0.1, 0.2, 0.3, 0.4, 0.5]]}"#).await;
+        let provider = OllamaProvider::new(Some(url)).unwrap();
+        // dimension comes from the generated embedding, not a hardcoded table
+        let dim = provider.model_dim("llama2").await.unwrap();
+        assert_eq!(dim, 5);
+    }
+
+    #[tokio::test]
+    async fn test_generate_response_stream() {
+        let url = mock_ollama(concat!(
+            r#"{"model":"llama3","created_at":"2024-01-01T00:00:00Z","response":"Hello","done":false}"#,
+            "\n",
+            r#"{"model":"llama3","created_at":"2024-01-01T00:00:00Z","response":" world","done":false}"#,
+            "\n",
+            r#"{"model":"llama3","created_at":"2024-01-01T00:00:00Z","response":"","done":true}"#,
+            "\n",
+        ))
+        .await;
+        let provider = OllamaProvider::new(Some(url)).unwrap();
+        let messages = vec![ChatMessageRequest {
+            role: "user".to_string(),
+            content: "say hello".to_string(),
+        }];
+        let mut stream = provider
+            .generate_response_stream("llama3".to_string(), &messages)
+            .await
+            .unwrap();
+        let mut response = String::new();
+        while let Some(chunk) = stream.next_chunk().await {
+            response.push_str(&chunk.unwrap());
+        }
+        assert_eq!(response, "Hello world");
+    }
+
+    #[tokio::test]
+    async fn test_generate_embedding_timeout() {
+        // accepts the request but never responds
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0; 4096];
+            let _ = socket.read(&mut buf).await;
+            tokio::time::sleep(Duration::from_secs(30)).await;
+        });
+        let mut provider = OllamaProvider::new(Some(format!("http://{addr}"))).unwrap();
+        provider.timeout = Duration::from_millis(200);
+
+        let started = std::time::Instant::now();
+        let result = provider.model_dim("llama2").await;
+        assert!(matches!(result, Err(VectorizeError::ProviderTimeout(_))));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_model_dim_empty_embedding() {
+        let url = mock_ollama(r#"{"embeddings": []}"#).await;
+        let provider = OllamaProvider::new(Some(url)).unwrap();
+        assert!(provider.model_dim("llama2").await.is_err());
     }
 }
@@ -1,16 +1,17 @@
-use reqwest::Client;
 use serde::{Deserialize, Serialize};
 
 use super::{
     ChatMessageRequest, ChatResponse, EmbeddingProvider, GenericEmbeddingRequest,
     GenericEmbeddingResponse,
 };
+use crate::config::{openai_organization, openai_project, provider_request_timeout};
 use crate::errors::VectorizeError;
-use crate::transformers::http_handler::handle_response;
+use crate::transformers::http_handler::{handle_response, provider_client};
 use crate::transformers::providers;
 use crate::transformers::types::Inputs;
 use async_trait::async_trait;
 use std::env;
+use std::time::Duration;
 
 pub const OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
 pub const MAX_TOKEN_LEN: usize = 8192;
@@ -18,6 +19,10 @@ pub const MAX_TOKEN_LEN: usize = 8192;
 pub struct OpenAIProvider {
     pub url: String,
     pub api_key: String,
+    pub timeout: Duration,
+    // sent as OpenAI-Organization and OpenAI-Project, enterprise accounts bill usage to them
+    pub organization: Option<String>,
+    pub project: Option<String>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -74,8 +79,22 @@ impl OpenAIProvider {
         Ok(OpenAIProvider {
             url: final_url,
             api_key: final_api_key,
+            timeout: provider_request_timeout(),
+            organization: openai_organization(),
+            project: openai_project(),
         })
     }
+
+    // adds the organization and project headers, when they are configured
+    fn account_headers(&self, mut request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if let Some(organization) = &self.organization {
+            request = request.header("OpenAI-Organization", organization);
+        }
+        if let Some(project) = &self.project {
+            request = request.header("OpenAI-Project", project);
+        }
+        request
+    }
 }
 
 #[async_trait]
@@ -84,7 +103,7 @@ impl EmbeddingProvider for OpenAIProvider {
         &self,
         request: &'a GenericEmbeddingRequest,
     ) -> Result<GenericEmbeddingResponse, VectorizeError> {
-        let client = Client::new();
+        let client = provider_client()?;
         let req = OpenAIEmbeddingBody::from(request.clone());
         let num_inputs = request.input.len();
         let todo_requests: Vec<OpenAIEmbeddingBody> = if num_inputs > 2048 {
@@ -104,12 +123,14 @@ impl EmbeddingProvider for OpenAIProvider {
         for request_payload in todo_requests.iter() {
             let payload_val = serde_json::to_value(request_payload)?;
             let embeddings_url = format!("{}/embeddings", self.url);
-            let response = client
+            let request = client
                 .post(&embeddings_url)
-                .timeout(std::time::Duration::from_secs(120_u64))
+                .timeout(self.timeout)
                 .header("Accept", "application/json")
                 .header("Content-Type", "application/json")
-                .header("Authorization", format!("Bearer {}", self.api_key))
+                .header("Authorization", format!("Bearer {}", self.api_key));
+            let response = self
+                .account_headers(request)
                 .json(&payload_val)
                 .send()
                 .await?;
@@ -128,13 +149,17 @@ impl EmbeddingProvider for OpenAIProvider {
     }
 }
 
+pub const MODEL_DIMENSIONS: [(&str, i32); 3] = [
+    ("text-embedding-3-large", 3072),
+    ("text-embedding-3-small", 1536),
+    ("text-embedding-ada-002", 1536),
+];
+
 pub fn openai_embedding_dim(model_name: &str) -> i32 {
-    match model_name {
-        "text-embedding-3-large" => 3072,
-        "text-embedding-3-small" => 1536,
-        "text-embedding-ada-002" => 1536,
-        _ => 1536,
-    }
+    MODEL_DIMENSIONS
+        .iter()
+        .find(|(name, _)| *name == model_name)
+        .map_or(1536, |(_, dim)| *dim)
 }
 
 impl OpenAIProvider {
@@ -143,21 +168,19 @@ impl OpenAIProvider {
         model_name: String,
         messages: &[ChatMessageRequest],
     ) -> Result<String, VectorizeError> {
-        let client = Client::new();
+        let client = provider_client()?;
         let chat_url = format!("{}/chat/completions", self.url);
         let message = serde_json::json!({
             "model": model_name,
             "messages": messages,
         });
-        let response = client
+        let request = client
             .post(&chat_url)
             .timeout(std::time::Duration::from_secs(120_u64))
             .header("Accept", "application/json")
             .header("Content-Type", "application/json")
-            .header("Authorization", &format!("Bearer {}", self.api_key))
-            .json(&message)
-            .send()
-            .await?;
+            .header("Authorization", &format!("Bearer {}", self.api_key));
+        let response = self.account_headers(request).json(&message).send().await?;
         let chat_response = handle_response::<ChatResponse>(response, "embeddings").await?;
         Ok(chat_response.choices[0].message.content.clone())
     }
@@ -188,6 +211,7 @@ pub fn trim_inputs(inputs: &[Inputs]) -> Vec<String> {
 #[cfg(test)]
 mod integration_tests {
     use super::*;
+    use crate::transformers::providers::InputType;
     use tokio::test as async_test;
 
     #[ignore]
@@ -197,6 +221,7 @@ mod integration_tests {
         let request = GenericEmbeddingRequest {
             model: "text-embedding-ada-002".to_string(),
             input: vec!["hello world".to_string()],
+            input_type: InputType::Document,
         };
 
         let embeddings = provider.generate_embedding(&request).await.unwrap();
@@ -218,6 +243,97 @@ mod integration_tests {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::transformers::providers::InputType;
+    use std::time::Instant;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::oneshot;
+
+    // serves a single embedding, returning the server's url and the request it received
+    async fn mock_server() -> (String, oneshot::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = oneshot::channel();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0; 8192];
+            let n = socket.read(&mut buf).await.unwrap();
+            let _ = tx.send(String::from_utf8_lossy(&buf[..n]).to_lowercase());
+            let body = r#"{"model": "text-embedding-3-small", "data": [{"index": 0, "embedding": [0.5]}]}"#;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+        (format!("http://{addr}"), rx)
+    }
+
+    #[tokio::test]
+    async fn test_generate_embedding_account_headers() {
+        let request = GenericEmbeddingRequest {
+            model: "text-embedding-3-small".to_string(),
+            input: vec!["hello world".to_string()],
+            input_type: InputType::Document,
+        };
+
+        let (url, received) = mock_server().await;
+        let mut provider = OpenAIProvider::new(Some(url), Some("key".to_string())).unwrap();
+        provider.organization = Some("org-abc".to_string());
+        provider.project = Some("proj_xyz".to_string());
+        let response = provider.generate_embedding(&request).await.unwrap();
+        assert_eq!(response.embeddings, vec![vec![0.5]]);
+        let headers = received.await.unwrap();
+        assert!(
+            headers.contains("\r\nopenai-organization: org-abc\r\n"),
+            "{headers}"
+        );
+        assert!(
+            headers.contains("\r\nopenai-project: proj_xyz\r\n"),
+            "{headers}"
+        );
+
+        // neither header is sent when they are not configured
+        let (url, received) = mock_server().await;
+        let mut provider = OpenAIProvider::new(Some(url), Some("key".to_string())).unwrap();
+        provider.organization = None;
+        provider.project = None;
+        provider.generate_embedding(&request).await.unwrap();
+        let headers = received.await.unwrap();
+        assert!(
+            headers.contains("\r\nauthorization: bearer key\r\n"),
+            "{headers}"
+        );
+        assert!(!headers.contains("openai-organization"), "{headers}");
+        assert!(!headers.contains("openai-project"), "{headers}");
+    }
+
+    #[tokio::test]
+    async fn test_generate_embedding_timeout() {
+        // accepts the request but never responds
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0; 4096];
+            let _ = socket.read(&mut buf).await;
+            tokio::time::sleep(Duration::from_secs(30)).await;
+        });
+        let mut provider =
+            OpenAIProvider::new(Some(format!("http://{addr}")), Some("key".to_string())).unwrap();
+        provider.timeout = Duration::from_millis(200);
+        let request = GenericEmbeddingRequest {
+            model: "text-embedding-3-small".to_string(),
+            input: vec!["hello world".to_string()],
+            input_type: InputType::Document,
+        };
+
+        let started = Instant::now();
+        let result = provider.generate_embedding(&request).await;
+        assert!(matches!(result, Err(VectorizeError::ProviderTimeout(_))));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
 
     #[test]
     fn test_trim_inputs_no_trimming_required() {
@@ -0,0 +1,159 @@
+use super::openai::{OpenAIEmbeddingBody, OpenAIEmbeddingResponse};
+use super::{EmbeddingProvider, GenericEmbeddingRequest, GenericEmbeddingResponse, InputType};
+use crate::config::provider_request_timeout;
+use crate::errors::VectorizeError;
+use crate::transformers::http_handler::{handle_response, provider_client};
+use async_trait::async_trait;
+use std::env;
+use std::time::Duration;
+
+// any server that speaks the OpenAI embeddings API, e.g. vLLM, LocalAI, Together or Groq
+pub struct OpenAICompatibleProvider {
+    // base url of the server, requests go to {url}/v1/embeddings
+    pub url: String,
+    // many self-hosted servers do not require a key
+    pub api_key: Option<String>,
+    pub timeout: Duration,
+}
+
+impl OpenAICompatibleProvider {
+    pub fn new(url: Option<String>, api_key: Option<String>) -> Result<Self, VectorizeError> {
+        let final_url = match url {
+            Some(url) => url,
+            None => match env::var("OPENAI_COMPAT_BASE_URL") {
+                Ok(url) => url,
+                Err(e) => {
+                    log::error!("OPENAI_COMPAT_BASE_URL environment variable is not set");
+                    Err(e)?
+                }
+            },
+        };
+        Ok(OpenAICompatibleProvider {
+            url: final_url.trim_end_matches('/').to_string(),
+            api_key: api_key.or_else(|| env::var("OPENAI_COMPAT_API_KEY").ok()),
+            timeout: provider_request_timeout(),
+        })
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for OpenAICompatibleProvider {
+    async fn generate_embedding<'a>(
+        &self,
+        request: &'a GenericEmbeddingRequest,
+    ) -> Result<GenericEmbeddingResponse, VectorizeError> {
+        let client = provider_client()?;
+        let payload = OpenAIEmbeddingBody::from(request.clone());
+        let embeddings_url = format!("{}/v1/embeddings", self.url);
+        let mut req = client
+            .post(&embeddings_url)
+            .timeout(self.timeout)
+            .header("Accept", "application/json")
+            .header("Content-Type", "application/json")
+            .json(&payload);
+        if let Some(api_key) = &self.api_key {
+            req = req.header("Authorization", format!("Bearer {api_key}"));
+        }
+        let response = req.send().await?;
+        let mut embeddings =
+            handle_response::<OpenAIEmbeddingResponse>(response, "embeddings").await?;
+        // not every server returns the embeddings in input order
+        embeddings.data.sort_by_key(|e| e.index);
+        Ok(GenericEmbeddingResponse::from(embeddings))
+    }
+
+    async fn model_dim(&self, model_name: &str) -> Result<u32, VectorizeError> {
+        // the server decides which models it serves, determine the dim by generating an embedding
+        let req = GenericEmbeddingRequest {
+            input: vec!["hello world".to_string()],
+            model: model_name.to_string(),
+            input_type: InputType::Document,
+        };
+        let embedding = self.generate_embedding(&req).await?;
+        match embedding.embeddings.first() {
+            Some(e) => Ok(e.len() as u32),
+            None => Err(VectorizeError::EmbeddingGenerationFailed(format!(
+                "no embedding returned for model: {model_name}"
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::oneshot;
+
+    // serves a single canned response, returning the server's url and the request it received
+    async fn mock_server(body: &'static str) -> (String, oneshot::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = oneshot::channel();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0; 8192];
+            let n = socket.read(&mut buf).await.unwrap();
+            let _ = tx.send(String::from_utf8_lossy(&buf[..n]).to_string());
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+        (format!("http://{addr}/"), rx)
+    }
+
+    #[tokio::test]
+    async fn test_generate_embedding() {
+        let (url, received) = mock_server(
+            r#"{"object": "list", "model": "BAAI/bge-small-en-v1.5", "data": [
+                {"object": "embedding", "index": 1, "embedding": [0.4, 0.5, 0.6]},
+                {"object": "embedding", "index": 0, "embedding": [0.1, 0.2, 0.3]}
+            ]}"#,
+        )
+        .await;
+        let provider =
+            OpenAICompatibleProvider::new(Some(url), Some("secret-key".to_string())).unwrap();
+        let request = GenericEmbeddingRequest {
+            model: "BAAI/bge-small-en-v1.5".to_string(),
+            input: vec!["first".to_string(), "second".to_string()],
+            input_type: InputType::Document,
+        };
+        let response = provider.generate_embedding(&request).await.unwrap();
+        assert_eq!(
+            response.embeddings,
+            vec![vec![0.1, 0.2, 0.3], vec![0.4, 0.5, 0.6]]
+        );
+
+        let received = received.await.unwrap();
+        assert!(received.starts_with("POST /v1/embeddings HTTP/1.1"));
+        assert!(
+            received
+                .to_lowercase()
+                .contains("authorization: bearer secret-key")
+        );
+        assert!(received.contains(r#""model":"BAAI/bge-small-en-v1.5""#));
+        assert!(received.contains(r#""input":["first","second"]"#));
+    }
+
+    #[tokio::test]
+    async fn test_model_dim_probes_model() {
+        let (url, received) = mock_server(
+            r#"{"model": "nomic-embed-text", "data": [{"index": 0, "embedding": [0.1, 0.2, 0.3, 0.4]}]}"#,
+        )
+        .await;
+        let provider = OpenAICompatibleProvider::new(Some(url), None).unwrap();
+        assert_eq!(provider.model_dim("nomic-embed-text").await.unwrap(), 4);
+        // no key configured, no authorization header sent
+        assert!(
+            !received
+                .await
+                .unwrap()
+                .to_lowercase()
+                .contains("authorization:")
+        );
+    }
+}
@@ -1,15 +1,15 @@
-use reqwest::Client;
-
 use super::{
     ChatMessageRequest, ChatResponse, EmbeddingProvider, GenericEmbeddingRequest,
-    GenericEmbeddingResponse,
+    GenericEmbeddingResponse, InputType,
 };
+use crate::config::provider_request_timeout;
 use crate::errors::VectorizeError;
-use crate::transformers::http_handler::handle_response;
+use crate::transformers::http_handler::{handle_response, provider_client};
 use crate::transformers::providers;
 use crate::transformers::providers::openai;
 use async_trait::async_trait;
 use std::env;
+use std::time::Duration;
 
 pub const PORTKEY_BASE_URL: &str = "https://api.portkey.ai/v1";
 pub const MAX_TOKEN_LEN: usize = 8192;
@@ -18,6 +18,7 @@ pub struct PortkeyProvider {
     pub url: String,
     pub api_key: String,
     pub virtual_key: String,
+    pub timeout: Duration,
 }
 
 impl PortkeyProvider {
@@ -38,6 +39,7 @@ impl PortkeyProvider {
             url: final_url,
             api_key: final_api_key,
             virtual_key: final_virtual_key,
+            timeout: provider_request_timeout(),
         }
     }
 }
@@ -48,7 +50,7 @@ impl EmbeddingProvider for PortkeyProvider {
         &self,
         request: &'a GenericEmbeddingRequest,
     ) -> Result<GenericEmbeddingResponse, VectorizeError> {
-        let client = Client::new();
+        let client = provider_client()?;
 
         let req = openai::OpenAIEmbeddingBody::from(request.clone());
         let num_inputs = request.input.len();
@@ -70,7 +72,7 @@ impl EmbeddingProvider for PortkeyProvider {
             let payload_val = serde_json::to_value(request_payload)?;
             let response = client
                 .post(&embeddings_url)
-                .timeout(std::time::Duration::from_secs(120_u64))
+                .timeout(self.timeout)
                 .header("Accept", "application/json")
                 .header("Content-Type", "application/json")
                 .header("x-portkey-virtual-key", self.virtual_key.clone())
@@ -93,6 +95,7 @@ impl EmbeddingProvider for PortkeyProvider {
         let req = GenericEmbeddingRequest {
             input: vec!["hello world".to_string()],
             model: model_name.to_string(),
+            input_type: InputType::Document,
         };
         let embedding = self.generate_embedding(&req).await?;
         let dim = embedding.embeddings[0].len();
@@ -106,7 +109,7 @@ impl PortkeyProvider {
         model_name: String,
         messages: &[ChatMessageRequest],
     ) -> Result<String, VectorizeError> {
-        let client = Client::new();
+        let client = provider_client()?;
         let message = serde_json::json!({
             "model": model_name,
             "messages": messages,
@@ -142,6 +145,7 @@ mod portkey_integration_tests {
         let request = GenericEmbeddingRequest {
             model: "text-embedding-ada-002".to_string(),
             input: vec!["hello world".to_string()],
+            input_type: InputType::Document,
         };
 
         let embeddings = provider.generate_embedding(&request).await.unwrap();
@@ -1,18 +1,20 @@
-use reqwest::Client;
 use serde::{Deserialize, Serialize};
 
 use super::{EmbeddingProvider, GenericEmbeddingRequest, GenericEmbeddingResponse};
+use crate::config::provider_request_timeout;
 use crate::errors::VectorizeError;
-use crate::transformers::http_handler::handle_response;
+use crate::transformers::http_handler::{handle_response, provider_client};
 use crate::transformers::providers::openai;
 use async_trait::async_trait;
 use std::env;
+use std::time::Duration;
 
 pub const VECTOR_SERVE_BASE_URL: &str = "http://localhost:3000/v1";
 
 pub struct VectorServeProvider {
     pub url: String,
     pub api_key: Option<String>,
+    pub timeout: Duration,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -38,6 +40,7 @@ impl VectorServeProvider {
         VectorServeProvider {
             url: final_url,
             api_key: final_api_key,
+            timeout: provider_request_timeout(),
         }
     }
 }
@@ -48,7 +51,7 @@ impl EmbeddingProvider for VectorServeProvider {
         &self,
         request: &'a GenericEmbeddingRequest,
     ) -> Result<GenericEmbeddingResponse, VectorizeError> {
-        let client = Client::new();
+        let client = provider_client()?;
         let req = openai::OpenAIEmbeddingBody::from(request.clone());
         let num_inputs = request.input.len();
         let todo_requests: Vec<openai::OpenAIEmbeddingBody> = if num_inputs > 2048 {
@@ -70,7 +73,7 @@ impl EmbeddingProvider for VectorServeProvider {
             let embeddings_url = format!("{}/embeddings", self.url);
             let mut req = client
                 .post(&embeddings_url)
-                .timeout(std::time::Duration::from_secs(120_u64))
+                .timeout(self.timeout)
                 .header("Accept", "application/json")
                 .header("Content-Type", "application/json")
                 .json(&payload_val);
@@ -88,9 +91,10 @@ impl EmbeddingProvider for VectorServeProvider {
     }
 
     async fn model_dim(&self, model_name: &str) -> Result<u32, VectorizeError> {
-        let client = Client::new();
+        let client = provider_client()?;
         let mut req = client
             .get(format!("{}/info/?model_name={}", self.url, model_name))
+            .timeout(self.timeout)
             .header("Accept", "application/json")
             .header("Content-Type", "application/json");
         if let Some(key) = &self.api_key {
@@ -109,6 +113,7 @@ fn split_vector(vec: Vec<String>, chunk_size: usize) -> Vec<Vec<String>> {
 #[cfg(test)]
 mod integration_tests {
     use super::*;
+    use crate::transformers::providers::InputType;
     use tokio::test as async_test;
 
     #[async_test]
@@ -117,6 +122,7 @@ mod integration_tests {
         let request = GenericEmbeddingRequest {
             model: "sentence-transformers/all-MiniLM-L6-v2".to_string(),
             input: vec!["hello world".to_string()],
+            input_type: InputType::Document,
         };
 
         let embeddings = provider.generate_embedding(&request).await.unwrap();
@@ -1,17 +1,44 @@
-use reqwest::Client;
 use serde::{Deserialize, Serialize};
 
-use super::{EmbeddingProvider, GenericEmbeddingRequest, GenericEmbeddingResponse};
+use super::{
+    EmbeddingProvider, GenericEmbeddingRequest, GenericEmbeddingResponse, InputType, RerankResult,
+    Reranker, scores_in_document_order,
+};
+use crate::config::provider_request_timeout;
 use crate::errors::VectorizeError;
-use crate::transformers::http_handler::handle_response;
+use crate::transformers::http_handler::{handle_response, provider_client};
 use async_trait::async_trait;
+use lazy_static::lazy_static;
+use std::collections::HashMap;
 use std::env;
+use std::time::Duration;
 
 pub const VOYAGE_BASE_URL: &str = "https://api.voyageai.com/v1";
 
+lazy_static! {
+    // default output dimensions, from https://docs.voyageai.com/docs/embeddings
+    pub(crate) static ref MODEL_DIMENSIONS: HashMap<&'static str, u32> = {
+        let mut m = HashMap::new();
+        m.insert("voyage-3-large", 1024);
+        m.insert("voyage-3.5", 1024);
+        m.insert("voyage-3.5-lite", 1024);
+        m.insert("voyage-3", 1024);
+        m.insert("voyage-3-lite", 512);
+        m.insert("voyage-code-3", 1024);
+        m.insert("voyage-finance-2", 1024);
+        m.insert("voyage-law-2", 1024);
+        m.insert("voyage-code-2", 1536);
+        m.insert("voyage-multilingual-2", 1024);
+        m.insert("voyage-large-2", 1536);
+        m.insert("voyage-2", 1024);
+        m
+    };
+}
+
 pub struct VoyageProvider {
     pub url: String,
     pub api_key: String,
+    pub timeout: Duration,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -26,7 +53,10 @@ impl From<GenericEmbeddingRequest> for VoyageEmbeddingBody {
         VoyageEmbeddingBody {
             input: request.input,
             model: request.model,
-            input_type: "document".to_string(),
+            input_type: match request.input_type {
+                InputType::Document => "document".to_string(),
+                InputType::Query => "query".to_string(),
+            },
         }
     }
 }
@@ -68,6 +98,7 @@ impl VoyageProvider {
         Ok(VoyageProvider {
             url: final_url,
             api_key: final_api_key,
+            timeout: provider_request_timeout(),
         })
     }
 }
@@ -78,14 +109,14 @@ impl EmbeddingProvider for VoyageProvider {
         &self,
         request: &'a GenericEmbeddingRequest,
     ) -> Result<GenericEmbeddingResponse, VectorizeError> {
-        let client = Client::new();
+        let client = provider_client()?;
 
         let req_body = VoyageEmbeddingBody::from(request.clone());
         let embedding_url = format!("{}/embeddings", self.url);
 
         let response = client
             .post(&embedding_url)
-            .timeout(std::time::Duration::from_secs(120_u64))
+            .timeout(self.timeout)
             .header("Content-Type", "application/json")
             .header("Authorization", format!("Bearer {}", self.api_key))
             .json(&req_body)
@@ -103,10 +134,14 @@ impl EmbeddingProvider for VoyageProvider {
     }
 
     async fn model_dim(&self, model_name: &str) -> Result<u32, VectorizeError> {
-        // determine embedding dim by generating an embedding and getting length of array
+        if let Some(dim) = MODEL_DIMENSIONS.get(model_name) {
+            return Ok(*dim);
+        }
+        // unknown model, determine embedding dim by generating an embedding and getting length of array
         let req = GenericEmbeddingRequest {
             input: vec!["hello world".to_string()],
             model: model_name.to_string(),
+            input_type: InputType::Document,
         };
         let embedding = self.generate_embedding(&req).await?;
         let dim = embedding.embeddings[0].len();
@@ -114,6 +149,74 @@ impl EmbeddingProvider for VoyageProvider {
     }
 }
 
+#[derive(Clone, Debug, Serialize)]
+struct VoyageRerankBody<'a> {
+    model: &'a str,
+    query: &'a str,
+    documents: &'a [String],
+}
+
+#[derive(Deserialize, Debug)]
+struct VoyageRerankResponse {
+    data: Vec<RerankResult>,
+}
+
+#[async_trait]
+impl Reranker for VoyageProvider {
+    async fn rerank(
+        &self,
+        model_name: &str,
+        query: &str,
+        documents: &[String],
+    ) -> Result<Vec<f64>, VectorizeError> {
+        let client = provider_client()?;
+        let body = VoyageRerankBody {
+            model: model_name,
+            query,
+            documents,
+        };
+        let response = client
+            .post(format!("{}/rerank", self.url))
+            .timeout(self.timeout)
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .json(&body)
+            .send()
+            .await?;
+        let reranked = handle_response::<VoyageRerankResponse>(response, "rerank").await?;
+        scores_in_document_order(reranked.data, documents.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_voyage_input_type() {
+        let mut request = GenericEmbeddingRequest {
+            input: vec!["hello world".to_string()],
+            model: "voyage-3".to_string(),
+            input_type: InputType::Document,
+        };
+        let body = VoyageEmbeddingBody::from(request.clone());
+        assert_eq!(body.input_type, "document");
+
+        request.input_type = InputType::Query;
+        let body = serde_json::to_value(VoyageEmbeddingBody::from(request)).unwrap();
+        assert_eq!(body["input_type"], "query");
+        assert_eq!(body["model"], "voyage-3");
+    }
+
+    #[tokio::test]
+    async fn test_voyage_model_dim() {
+        let provider = VoyageProvider::new(None, Some("test-key".to_string())).unwrap();
+        assert_eq!(provider.model_dim("voyage-3").await.unwrap(), 1024);
+        assert_eq!(provider.model_dim("voyage-3-lite").await.unwrap(), 512);
+        assert_eq!(provider.model_dim("voyage-code-2").await.unwrap(), 1536);
+    }
+}
+
 #[cfg(test)]
 mod integration_tests {
     use super::*;
@@ -126,6 +229,7 @@ mod integration_tests {
         let request = GenericEmbeddingRequest {
             input: vec!["hello world".to_string()],
             model: "voyage-3-lite".to_string(),
+            input_type: InputType::Document,
         };
 
         let embeddings = provider.generate_embedding(&request).await.unwrap();
@@ -0,0 +1,138 @@
+use crate::types::{Model, ModelSource};
+use tiktoken_rs::tokenizer::{self, get_tokenizer};
+use tiktoken_rs::{
+    CoreBPE, cl100k_base_singleton, o200k_base_singleton, p50k_base_singleton, p50k_edit_singleton,
+    r50k_base_singleton,
+};
+
+// english text averages about 4 characters per token across common tokenizers
+const CHARS_PER_TOKEN: usize = 4;
+
+/// counts and cuts texts in the tokens of the model they are sent to
+#[derive(Clone, Copy)]
+pub enum Tokenizer {
+    /// the model's tiktoken encoding, exact for OpenAI models
+    Bpe(&'static CoreBPE),
+    /// a character count heuristic, for providers whose tokenizer is not available
+    Chars,
+}
+
+// cl100k_base, the encoding of OpenAI's embedding models
+impl Default for Tokenizer {
+    fn default() -> Self {
+        Tokenizer::Bpe(cl100k_base_singleton())
+    }
+}
+
+impl Tokenizer {
+    pub fn for_model(model: &Model) -> Tokenizer {
+        match model.source {
+            // azure model names are deployment names, which fall back to cl100k_base
+            ModelSource::OpenAI | ModelSource::AzureOpenAI => {
+                let bpe = match get_tokenizer(&model.name) {
+                    Some(tokenizer::Tokenizer::O200kBase) => o200k_base_singleton(),
+                    Some(tokenizer::Tokenizer::P50kBase) => p50k_base_singleton(),
+                    Some(tokenizer::Tokenizer::P50kEdit) => p50k_edit_singleton(),
+                    Some(tokenizer::Tokenizer::R50kBase | tokenizer::Tokenizer::Gpt2) => {
+                        r50k_base_singleton()
+                    }
+                    Some(tokenizer::Tokenizer::Cl100kBase) | None => cl100k_base_singleton(),
+                };
+                Tokenizer::Bpe(bpe)
+            }
+            // these serve models of many families, or ones tiktoken has no encoding for
+            ModelSource::SentenceTransformers
+            | ModelSource::Ollama
+            | ModelSource::Cohere
+            | ModelSource::Portkey
+            | ModelSource::Voyage
+            | ModelSource::Bedrock
+            | ModelSource::Vertex
+            | ModelSource::OpenAICompatible => Tokenizer::Chars,
+        }
+    }
+
+    /// the number of tokens in text
+    pub fn count(&self, text: &str) -> usize {
+        match self {
+            Tokenizer::Bpe(bpe) => bpe.encode_with_special_tokens(text).len(),
+            Tokenizer::Chars => text.chars().count().div_ceil(CHARS_PER_TOKEN),
+        }
+    }
+
+    /// cuts text to its first max_tokens tokens, returns whether anything was cut
+    pub fn truncate(&self, text: &mut String, max_tokens: usize) -> bool {
+        let len = match self {
+            Tokenizer::Bpe(bpe) => {
+                let tokens = bpe.encode_with_special_tokens(text);
+                if tokens.len() <= max_tokens {
+                    return false;
+                }
+                let mut len: usize = bpe
+                    ._decode_native_and_split(tokens[..max_tokens].to_vec())
+                    .map(|bytes| bytes.len())
+                    .sum();
+                // a token can end inside a multi-byte character
+                while !text.is_char_boundary(len) {
+                    len -= 1;
+                }
+                len
+            }
+            Tokenizer::Chars => match text.char_indices().nth(max_tokens * CHARS_PER_TOKEN) {
+                Some((len, _)) => len,
+                None => return false,
+            },
+        };
+        text.truncate(len);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenizer_for_model() {
+        let openai = Tokenizer::for_model(&Model::new("openai/text-embedding-3-small").unwrap());
+        let ollama = Tokenizer::for_model(&Model::new("ollama/nomic-embed-text").unwrap());
+        assert!(matches!(openai, Tokenizer::Bpe(_)));
+        assert!(matches!(ollama, Tokenizer::Chars));
+
+        // the two families estimate the same text differently
+        let text = "The quick brown fox jumps over the lazy dog";
+        assert_eq!(openai.count(text), 9);
+        assert_eq!(ollama.count(text), 11);
+        assert_eq!(Tokenizer::default().count(text), 9);
+
+        // newer chat models use o200k_base, which splits some texts differently than cl100k_base
+        let gpt4o = Tokenizer::for_model(&Model::new("openai/gpt-4o-mini").unwrap());
+        let text = "日本語のテキストをトークンに分割する";
+        assert_ne!(gpt4o.count(text), openai.count(text));
+    }
+
+    #[test]
+    fn test_tokenizer_truncate() {
+        let long = "the quick brown fox jumps over the lazy dog ".repeat(100);
+        for tokenizer in [Tokenizer::default(), Tokenizer::Chars] {
+            let mut text = long.clone();
+            assert!(tokenizer.truncate(&mut text, 50));
+            assert!(long.starts_with(&text));
+            assert_eq!(tokenizer.count(&text), 50);
+
+            let mut short = "a short text".to_string();
+            assert!(!tokenizer.truncate(&mut short, 50));
+            assert_eq!(short, "a short text");
+        }
+        let mut chars = long.clone();
+        Tokenizer::Chars.truncate(&mut chars, 5);
+        assert_eq!(chars, "the quick brown fox ");
+
+        // a cut inside a multi-byte character keeps the text valid
+        let mut emoji = "🦀".repeat(50);
+        assert!(Tokenizer::default().truncate(&mut emoji, 7));
+        assert!(!emoji.is_empty());
+        assert!(emoji.chars().all(|c| c == '🦀'));
+        assert!(Tokenizer::default().count(&emoji) <= 7);
+    }
+}
@@ -1,3 +1,4 @@
+use super::tokenizer::Tokenizer;
 use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize, Debug)]
@@ -32,10 +33,37 @@ pub struct Inputs {
     pub token_estimate: i32, // estimated token count
 }
 
+/// truncates inputs longer than max_tokens tokens so providers do not reject them,
+/// 0 disables truncation, returns the number of inputs that were cut
+pub fn truncate_inputs(inputs: &mut [Inputs], tokenizer: &Tokenizer, max_tokens: usize) -> usize {
+    if max_tokens == 0 {
+        return 0;
+    }
+    let mut truncated = 0;
+    for input in inputs.iter_mut() {
+        if tokenizer.truncate(&mut input.inputs, max_tokens) {
+            input.token_estimate = max_tokens as i32;
+            truncated += 1;
+        }
+    }
+    truncated
+}
+
+/// scales the embedding to unit length, so its inner product with another unit vector is their
+/// cosine similarity. A zero vector has no direction and is left as it is
+pub fn l2_normalize(embedding: &mut [f64]) {
+    let norm = embedding.iter().map(|x| x * x).sum::<f64>().sqrt();
+    if norm > 0.0 {
+        embedding.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct PairedEmbeddings {
     pub primary_key: String,
     pub embeddings: Vec<f64>,
+    // hash of the embedded text, stored when the embeddings table has a content_hash column
+    pub content_hash: Option<String>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -44,3 +72,73 @@ pub struct TransformerMetadata {
     pub max_seq_len: i32,
     pub embedding_dimension: i32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(text: &str) -> Inputs {
+        Inputs {
+            record_id: "1".to_string(),
+            inputs: text.to_string(),
+            token_estimate: 0,
+        }
+    }
+
+    #[test]
+    fn test_truncate_inputs() {
+        let tokenizer = Tokenizer::default();
+        let long = "the quick brown fox jumps over the lazy dog ".repeat(2000);
+        let mut inputs = vec![input("a short text"), input(&long)];
+
+        assert_eq!(truncate_inputs(&mut inputs, &tokenizer, 0), 0);
+        assert_eq!(inputs[1].inputs, long);
+
+        // the oversized input is cut to the budget rather than rejected
+        assert_eq!(truncate_inputs(&mut inputs, &tokenizer, 100), 1);
+        assert_eq!(inputs[0].inputs, "a short text");
+        assert!(long.starts_with(&inputs[1].inputs));
+        assert_eq!(tokenizer.count(&inputs[1].inputs), 100);
+        assert_eq!(inputs[1].token_estimate, 100);
+    }
+
+    #[test]
+    fn test_l2_normalize() {
+        let dot = |a: &[f64], b: &[f64]| a.iter().zip(b).map(|(x, y)| x * y).sum::<f64>();
+        let norm = |a: &[f64]| dot(a, a).sqrt();
+
+        let mut embedding = vec![3.0, 4.0];
+        l2_normalize(&mut embedding);
+        assert_eq!(embedding, vec![0.6, 0.8]);
+        let mut zero = vec![0.0, 0.0];
+        l2_normalize(&mut zero);
+        assert_eq!(zero, vec![0.0, 0.0]);
+
+        // unnormalized, the long vector has the largest inner product although it points away
+        let query = vec![1.0, 0.2, 0.0];
+        let docs = [
+            vec![10.0, 9.0, 8.0],
+            vec![0.9, 0.1, 0.0],
+            vec![0.5, 0.5, 0.1],
+        ];
+        let cosine = |a: &[f64], b: &[f64]| dot(a, b) / (norm(a) * norm(b));
+        let rank = |score: &dyn Fn(&[f64]) -> f64| {
+            let mut order: Vec<usize> = (0..docs.len()).collect();
+            order.sort_by(|&a, &b| score(&docs[b]).total_cmp(&score(&docs[a])));
+            order
+        };
+        let by_cosine = rank(&|doc| cosine(&query, doc));
+        assert_ne!(rank(&|doc| dot(&query, doc)), by_cosine);
+
+        // normalized, inner product ranks as cosine does
+        let mut unit_query = query.clone();
+        l2_normalize(&mut unit_query);
+        let by_inner_product = rank(&|doc| {
+            let mut unit_doc = doc.to_vec();
+            l2_normalize(&mut unit_doc);
+            assert!((norm(&unit_doc) - 1.0).abs() < 1e-12);
+            dot(&unit_query, &unit_doc)
+        });
+        assert_eq!(by_inner_product, by_cosine);
+    }
+}
@@ -1,9 +1,12 @@
+use crate::config;
+use crate::transformers::types::l2_normalize;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use std::fmt;
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 use thiserror::Error;
+use unicode_normalization::UnicodeNormalization;
 use utoipa::ToSchema;
 
 pub const VECTORIZE_SCHEMA: &str = "vectorize";
@@ -14,13 +17,194 @@ pub struct VectorizeJob {
     pub src_table: String,
     pub src_schema: String,
     pub src_columns: Vec<String>,
-    pub primary_key: String,
-    pub update_time_col: String,
+    /// primary key column, or every column of a composite primary key
+    #[serde(deserialize_with = "string_or_list")]
+    pub primary_key: Vec<String>,
+    /// timestamptz column marking updated rows, optional
+    #[serde(default)]
+    pub update_time_col: Option<String>,
+    /// model as source/name, e.g. openai/text-embedding-3-small
     #[serde(
         deserialize_with = "string_to_model",
         serialize_with = "model_to_string"
     )]
+    #[schema(value_type = String)]
     pub model: Model,
+    #[serde(default)]
+    #[sqlx(try_from = "String")]
+    pub index_dist_type: IndexDist,
+    /// schema holding the job's embeddings, search tokens and view
+    #[serde(default = "default_target_schema")]
+    pub target_schema: String,
+    /// what happens when a row that already has an embedding is embedded again
+    #[serde(default)]
+    #[sqlx(try_from = "String")]
+    pub conflict_strategy: ConflictStrategy,
+    /// what the source columns hold, image jobs embed the image a column's URL points to
+    #[serde(default)]
+    #[sqlx(try_from = "String")]
+    pub modality: Modality,
+    /// text search configuration the search tokens are generated with, e.g. english or spanish
+    #[serde(default = "default_fts_language")]
+    pub fts_language: String,
+    /// 'realtime' to embed rows as they change, or a cron expression to rescan the table for changed rows
+    #[serde(default = "default_schedule")]
+    pub schedule: String,
+    /// normalization applied to row and search query text before it is embedded
+    #[serde(default)]
+    #[sqlx(json)]
+    pub normalization: Normalization,
+    /// raw SQL predicate on the source table matching soft-deleted rows, which searches exclude
+    #[serde(default)]
+    pub deleted_filter: Option<String>,
+    /// text columns of another table joined one-to-one to each source row, embedded with src_columns
+    #[serde(default)]
+    #[sqlx(json(nullable))]
+    pub text_join: Option<TextJoin>,
+    /// scale row and query embeddings to unit length, so inner product distance ranks as cosine does
+    #[serde(default)]
+    pub normalize_embeddings: bool,
+    /// source columns whose updates re-embed a row, the embedded columns and update_time_col when not set
+    #[serde(default)]
+    pub watch_columns: Option<Vec<String>>,
+    /// pgvector type of the embeddings column, halfvec stores half precision floats in half the space
+    /// and bit stores one bit per dimension, the sign of each component
+    #[serde(default)]
+    #[sqlx(try_from = "String")]
+    pub vector_type: VectorType,
+    /// prefixes the job's table names were created with, set by the server when the job is created
+    #[serde(default)]
+    #[sqlx(json)]
+    pub table_prefixes: TablePrefixes,
+}
+
+impl VectorizeJob {
+    /// name of the job's embeddings table, without its schema
+    pub fn embeddings_table(&self) -> String {
+        self.table_prefixes.embeddings_table(&self.job_name)
+    }
+
+    /// the source columns the update trigger compares, unless watch_columns is set these are
+    /// src_columns, the text_join's src_column and update_time_col, which marks rows to re-embed
+    pub fn trigger_watch_columns(&self) -> Vec<String> {
+        if let Some(watch_columns) = &self.watch_columns {
+            return watch_columns.clone();
+        }
+        let mut columns = self.src_columns.clone();
+        let extra = self
+            .text_join
+            .iter()
+            .map(|join| &join.src_column)
+            .chain(self.update_time_col.iter());
+        for column in extra {
+            if !columns.contains(column) {
+                columns.push(column.clone());
+            }
+        }
+        columns
+    }
+
+    /// scales a query embedding the way the job's row embeddings were scaled, so the query is
+    /// compared against rows of the same length
+    pub fn prepare_query_embedding(&self, embedding: &mut [f64]) {
+        if self.normalize_embeddings {
+            l2_normalize(embedding);
+        }
+    }
+}
+
+/// prefixes of a job's embeddings and search tokens table names, prepended to the job name
+/// kept with each job, so changing the server's prefixes does not lose the tables of existing jobs
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(default)]
+pub struct TablePrefixes {
+    pub embeddings: String,
+    pub search_tokens: String,
+}
+
+impl Default for TablePrefixes {
+    fn default() -> Self {
+        TablePrefixes {
+            embeddings: config::DEFAULT_EMBEDDINGS_TABLE_PREFIX.to_string(),
+            search_tokens: config::DEFAULT_SEARCH_TOKENS_TABLE_PREFIX.to_string(),
+        }
+    }
+}
+
+impl TablePrefixes {
+    /// the prefixes new jobs are created with, from EMBEDDINGS_TABLE_PREFIX and SEARCH_TOKENS_TABLE_PREFIX
+    pub fn from_config(config: &config::Config) -> Self {
+        TablePrefixes {
+            embeddings: config.embeddings_table_prefix.clone(),
+            search_tokens: config.search_tokens_table_prefix.clone(),
+        }
+    }
+
+    /// name of the job's embeddings table, without its schema
+    pub fn embeddings_table(&self, job_name: &str) -> String {
+        format!("{}{job_name}", self.embeddings)
+    }
+
+    /// name of the job's search tokens table, without its schema
+    pub fn search_tokens_table(&self, job_name: &str) -> String {
+        format!("{}{job_name}", self.search_tokens)
+    }
+}
+
+pub fn default_target_schema() -> String {
+    VECTORIZE_SCHEMA.to_string()
+}
+
+pub fn default_fts_language() -> String {
+    "english".to_string()
+}
+
+/// text normalization applied before embedding, rows and search queries are normalized the same way
+/// every step is off by default, so the text is embedded as it is stored
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(default)]
+pub struct Normalization {
+    /// lowercase the text, so texts differing only in case embed the same
+    pub lowercase: bool,
+    /// replace runs of whitespace with a single space
+    pub collapse_whitespace: bool,
+    /// compose the text to unicode normalization form C
+    pub unicode_nfc: bool,
+}
+
+impl Normalization {
+    pub fn is_enabled(&self) -> bool {
+        self.lowercase || self.collapse_whitespace || self.unicode_nfc
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        let mut text = match self.unicode_nfc {
+            true => text.nfc().collect::<String>(),
+            false => text.to_string(),
+        };
+        if self.lowercase {
+            text = text.to_lowercase();
+        }
+        if self.collapse_whitespace {
+            text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+        }
+        text
+    }
+}
+
+/// a table holding more text of the source rows, e.g. a child table with one row per source row
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+pub struct TextJoin {
+    /// schema of the joined table, the job's src_schema when not set
+    #[serde(default)]
+    pub schema: Option<String>,
+    pub table: String,
+    /// column of the source table the join matches on, e.g. its primary key
+    pub src_column: String,
+    /// column of the joined table matching src_column
+    pub join_column: String,
+    /// text columns of the joined table, embedded after the job's src_columns
+    pub columns: Vec<String>,
 }
 
 #[allow(non_camel_case_types)]
@@ -59,12 +243,38 @@ impl From<String> for SimilarityAlg {
 }
 
 #[allow(non_camel_case_types)]
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
 pub enum IndexDist {
     pgv_hnsw_l2,
     pgv_hnsw_ip,
+    #[default]
     pgv_hnsw_cosine,
     vsc_diskann_cosine,
+    // hamming distance between binary quantized embeddings, requires vector_type bit
+    pgv_hnsw_hamming,
+}
+
+impl IndexDist {
+    /// pgvector distance operator for the index's distance type
+    pub fn distance_operator(&self) -> &'static str {
+        match self {
+            IndexDist::pgv_hnsw_l2 => "<->",
+            IndexDist::pgv_hnsw_ip => "<#>",
+            IndexDist::pgv_hnsw_cosine | IndexDist::vsc_diskann_cosine => "<=>",
+            IndexDist::pgv_hnsw_hamming => "<~>",
+        }
+    }
+
+    /// SQL expression converting a `distance` column into a similarity score, higher is better
+    pub fn similarity_score(&self) -> &'static str {
+        match self {
+            // L2 and hamming distances are unbounded, map them into (0, 1]
+            IndexDist::pgv_hnsw_l2 | IndexDist::pgv_hnsw_hamming => "1 / (1 + distance)",
+            // <#> returns the negative inner product
+            IndexDist::pgv_hnsw_ip => "distance * -1",
+            IndexDist::pgv_hnsw_cosine | IndexDist::vsc_diskann_cosine => "1 - distance",
+        }
+    }
 }
 
 impl Display for IndexDist {
@@ -74,6 +284,7 @@ impl Display for IndexDist {
             IndexDist::pgv_hnsw_ip => write!(f, "pgv_hnsw_ip"),
             IndexDist::pgv_hnsw_cosine => write!(f, "pgv_hnsw_cosine"),
             IndexDist::vsc_diskann_cosine => write!(f, "vsc_diskann_cosine"),
+            IndexDist::pgv_hnsw_hamming => write!(f, "pgv_hnsw_hamming"),
         }
     }
 }
@@ -87,6 +298,7 @@ impl FromStr for IndexDist {
             "pgv_hnsw_ip" => Ok(IndexDist::pgv_hnsw_ip),
             "pgv_hnsw_cosine" => Ok(IndexDist::pgv_hnsw_cosine),
             "vsc_diskann_cosine" => Ok(IndexDist::vsc_diskann_cosine),
+            "pgv_hnsw_hamming" => Ok(IndexDist::pgv_hnsw_hamming),
             _ => Err(format!("Invalid value for IndexDist: {s}")),
         }
     }
@@ -99,11 +311,163 @@ impl From<String> for IndexDist {
             "pgv_hnsw_ip" => IndexDist::pgv_hnsw_ip,
             "pgv_hnsw_cosine" => IndexDist::pgv_hnsw_cosine,
             "vsc_diskann_cosine" => IndexDist::vsc_diskann_cosine,
+            "pgv_hnsw_hamming" => IndexDist::pgv_hnsw_hamming,
             _ => panic!("Invalid value for IndexDist: {s}"),
         }
     }
 }
 
+#[allow(non_camel_case_types)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+pub enum ConflictStrategy {
+    // replace the existing embedding
+    #[default]
+    overwrite,
+    // keep the existing embedding, rows that have one are not sent to the provider again
+    skip,
+}
+
+impl Display for ConflictStrategy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            ConflictStrategy::overwrite => write!(f, "overwrite"),
+            ConflictStrategy::skip => write!(f, "skip"),
+        }
+    }
+}
+
+impl FromStr for ConflictStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "overwrite" => Ok(ConflictStrategy::overwrite),
+            "skip" => Ok(ConflictStrategy::skip),
+            _ => Err(format!("Invalid value for ConflictStrategy: {s}")),
+        }
+    }
+}
+
+impl From<String> for ConflictStrategy {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "overwrite" => ConflictStrategy::overwrite,
+            "skip" => ConflictStrategy::skip,
+            _ => panic!("Invalid value for ConflictStrategy: {s}"),
+        }
+    }
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+pub enum Modality {
+    // the column's text is embedded
+    #[default]
+    text,
+    // the column holds an image URL, the image is embedded with a multimodal model
+    image,
+}
+
+impl Display for Modality {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            Modality::text => write!(f, "text"),
+            Modality::image => write!(f, "image"),
+        }
+    }
+}
+
+impl FromStr for Modality {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Modality::text),
+            "image" => Ok(Modality::image),
+            _ => Err(format!("Invalid value for Modality: {s}")),
+        }
+    }
+}
+
+impl From<String> for Modality {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "text" => Modality::text,
+            "image" => Modality::image,
+            _ => panic!("Invalid value for Modality: {s}"),
+        }
+    }
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+pub enum VectorType {
+    // 4-byte floats
+    #[default]
+    vector,
+    // 2-byte floats, requires pgvector 0.7.0
+    halfvec,
+    // binary quantized, 1 where a component is positive, requires pgvector 0.7.0
+    bit,
+}
+
+impl VectorType {
+    /// column type of embeddings with `dim` dimensions, e.g. halfvec(384)
+    pub fn column_type(&self, dim: u32) -> String {
+        format!("{self}({dim})")
+    }
+
+    /// SQL converting the float embedding bound at `param` into this type, quantizing it for bit
+    pub fn cast_param(&self, param: &str) -> String {
+        match self {
+            VectorType::vector | VectorType::halfvec => format!("{param}::{self}"),
+            VectorType::bit => format!("binary_quantize({param}::vector)"),
+        }
+    }
+
+    /// SQL returning the embeddings column `col` as a float array, bits become 0 and 1
+    pub fn to_real_array(&self, col: &str) -> String {
+        match self {
+            VectorType::vector | VectorType::halfvec => format!("{col}::real[]"),
+            VectorType::bit => format!("regexp_split_to_array({col}::text, '')::real[]"),
+        }
+    }
+}
+
+impl Display for VectorType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            VectorType::vector => write!(f, "vector"),
+            VectorType::halfvec => write!(f, "halfvec"),
+            VectorType::bit => write!(f, "bit"),
+        }
+    }
+}
+
+impl FromStr for VectorType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "vector" => Ok(VectorType::vector),
+            "halfvec" => Ok(VectorType::halfvec),
+            "bit" => Ok(VectorType::bit),
+            _ => Err(format!("Invalid value for VectorType: {s}")),
+        }
+    }
+}
+
+impl From<String> for VectorType {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "vector" => VectorType::vector,
+            "halfvec" => VectorType::halfvec,
+            "bit" => VectorType::bit,
+            _ => panic!("Invalid value for VectorType: {s}"),
+        }
+    }
+}
+
 #[allow(non_camel_case_types)]
 #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub enum TableMethod {
@@ -128,10 +492,13 @@ pub struct JobParams {
     #[serde(default = "default_schedule")]
     pub schedule: String,
     pub args: Option<serde_json::Value>,
+    /// schema holding the job's embeddings table and view, for the join table method
+    #[serde(default = "default_target_schema")]
+    pub target_schema: String,
 }
 
-fn default_schedule() -> String {
-    "realtime".to_string()
+pub fn default_schedule() -> String {
+    crate::schedule::REALTIME.to_string()
 }
 
 // schema for all messages that hit pgmq
@@ -141,6 +508,20 @@ pub struct JobMessage {
     pub record_ids: Vec<String>,
 }
 
+// messages that exceeded max retries, kept on the dead letter queue for inspection and replay
+#[derive(Clone, Deserialize, Debug, Serialize)]
+pub struct DeadLetterMessage {
+    #[serde(flatten)]
+    pub message: JobMessage,
+    pub error: String,
+    pub read_ct: i32,
+}
+
+// name of the dead letter queue paired with a job queue
+pub fn dead_letter_queue(queue_name: &str) -> String {
+    format!("{queue_name}_dlq")
+}
+
 // schema for every job
 // also schema for the vectorize.vectorize_meta table
 #[derive(Clone, Debug, Deserialize, FromRow, Serialize)]
@@ -175,6 +556,23 @@ where
     Ok(Model::from(s))
 }
 
+// accepts a single column name or a list of them
+pub fn string_or_list<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrList {
+        String(String),
+        List(Vec<String>),
+    }
+    Ok(match StringOrList::deserialize(deserializer)? {
+        StringOrList::String(s) => vec![s],
+        StringOrList::List(l) => l,
+    })
+}
+
 // New serialization function
 pub fn model_to_string<S>(model: &Model, serializer: S) -> Result<S::Ok, S::Error>
 where
@@ -211,6 +609,14 @@ impl Model {
             ModelSource::Cohere => self.name.clone(),
             ModelSource::Portkey => self.name.clone(),
             ModelSource::Voyage => self.name.clone(),
+            ModelSource::AzureOpenAI => self.name.clone(),
+            ModelSource::Bedrock => self.name.clone(),
+            ModelSource::Vertex => self.name.clone(),
+            // model names served by these servers often contain a slash, e.g. BAAI/bge-small-en-v1.5
+            ModelSource::OpenAICompatible => self
+                .fullname
+                .split_once('/')
+                .map_or(self.name.clone(), |(_, name)| name.to_string()),
         }
     }
 }
@@ -281,6 +687,26 @@ pub enum ModelSource {
     Cohere,
     Portkey,
     Voyage,
+    AzureOpenAI,
+    Bedrock,
+    Vertex,
+    OpenAICompatible,
+}
+
+impl ModelSource {
+    /// every model source, in the order they are listed to users
+    pub const ALL: [ModelSource; 10] = [
+        ModelSource::OpenAI,
+        ModelSource::SentenceTransformers,
+        ModelSource::Ollama,
+        ModelSource::Cohere,
+        ModelSource::Portkey,
+        ModelSource::Voyage,
+        ModelSource::AzureOpenAI,
+        ModelSource::Bedrock,
+        ModelSource::Vertex,
+        ModelSource::OpenAICompatible,
+    ];
 }
 
 impl FromStr for ModelSource {
@@ -294,6 +720,10 @@ impl FromStr for ModelSource {
             "cohere" => Ok(ModelSource::Cohere),
             "portkey" => Ok(ModelSource::Portkey),
             "voyage" => Ok(ModelSource::Voyage),
+            "azure" => Ok(ModelSource::AzureOpenAI),
+            "bedrock" => Ok(ModelSource::Bedrock),
+            "vertex" => Ok(ModelSource::Vertex),
+            "openai_compat" => Ok(ModelSource::OpenAICompatible),
             _ => Ok(ModelSource::SentenceTransformers),
         }
     }
@@ -308,6 +738,10 @@ impl Display for ModelSource {
             ModelSource::Cohere => write!(f, "cohere"),
             ModelSource::Portkey => write!(f, "portkey"),
             ModelSource::Voyage => write!(f, "voyage"),
+            ModelSource::AzureOpenAI => write!(f, "azure"),
+            ModelSource::Bedrock => write!(f, "bedrock"),
+            ModelSource::Vertex => write!(f, "vertex"),
+            ModelSource::OpenAICompatible => write!(f, "openai_compat"),
         }
     }
 }
@@ -321,6 +755,10 @@ impl From<String> for ModelSource {
             "cohere" => ModelSource::Cohere,
             "portkey" => ModelSource::Portkey,
             "voyage" => ModelSource::Voyage,
+            "azure" => ModelSource::AzureOpenAI,
+            "bedrock" => ModelSource::Bedrock,
+            "vertex" => ModelSource::Vertex,
+            "openai_compat" => ModelSource::OpenAICompatible,
             // other cases are assumed to be private sentence-transformer compatible model
             // and can be hot-loaded
             _ => ModelSource::SentenceTransformers,
@@ -333,6 +771,107 @@ impl From<String> for ModelSource {
 mod model_tests {
     use super::*;
 
+    #[test]
+    fn test_normalization() {
+        // composed and decomposed forms of "café"
+        let composed = "Caf\u{e9}";
+        let decomposed = "Cafe\u{301}";
+        let text = format!("  {decomposed}\tAU   Lait ");
+
+        // off by default, the text is embedded as it is
+        let none = Normalization::default();
+        assert!(!none.is_enabled());
+        assert_eq!(none.apply(&text), text);
+
+        let lowercase = Normalization {
+            lowercase: true,
+            ..Default::default()
+        };
+        assert_eq!(lowercase.apply(&text), format!("  cafe\u{301}\tau   lait "));
+
+        let whitespace = Normalization {
+            collapse_whitespace: true,
+            ..Default::default()
+        };
+        assert_eq!(whitespace.apply(&text), format!("{decomposed} AU Lait"));
+
+        let nfc = Normalization {
+            unicode_nfc: true,
+            ..Default::default()
+        };
+        assert_eq!(nfc.apply(decomposed), composed);
+        assert_ne!(none.apply(decomposed), composed);
+
+        let all = Normalization {
+            lowercase: true,
+            collapse_whitespace: true,
+            unicode_nfc: true,
+        };
+        assert_eq!(all.apply(&text), "caf\u{e9} au lait");
+        assert_eq!(all.apply(&text), all.apply("CAF\u{c9} au lait"));
+
+        // missing steps are off
+        let job: VectorizeJob = serde_json::from_value(serde_json::json!({
+            "job_name": "my_job",
+            "src_table": "products",
+            "src_schema": "public",
+            "src_columns": ["description"],
+            "primary_key": "product_id",
+            "update_time_col": "updated_at",
+            "model": "sentence-transformers/all-MiniLM-L6-v2",
+            "normalization": {"lowercase": true}
+        }))
+        .unwrap();
+        assert_eq!(job.normalization, lowercase);
+    }
+
+    #[test]
+    fn test_primary_key_string_or_list() {
+        let job = |pkey: serde_json::Value| {
+            serde_json::from_value::<VectorizeJob>(serde_json::json!({
+                "job_name": "my_job",
+                "src_table": "orders",
+                "src_schema": "public",
+                "src_columns": ["description"],
+                "primary_key": pkey,
+                "update_time_col": "updated_at",
+                "model": "sentence-transformers/all-MiniLM-L6-v2"
+            }))
+        };
+        assert_eq!(
+            job(serde_json::json!("order_id")).unwrap().primary_key,
+            vec!["order_id"]
+        );
+        assert_eq!(
+            job(serde_json::json!(["order_id", "line_no"]))
+                .unwrap()
+                .primary_key,
+            vec!["order_id", "line_no"]
+        );
+        assert!(job(serde_json::json!(1)).is_err());
+    }
+
+    #[test]
+    fn test_prepare_query_embedding() {
+        let mut job: VectorizeJob = serde_json::from_value(serde_json::json!({
+            "job_name": "my_job",
+            "src_table": "products",
+            "src_schema": "public",
+            "src_columns": ["description"],
+            "primary_key": "product_id",
+            "update_time_col": "updated_at",
+            "model": "sentence-transformers/all-MiniLM-L6-v2"
+        }))
+        .unwrap();
+        let mut embedding = vec![3.0, 4.0];
+        job.prepare_query_embedding(&mut embedding);
+        assert_eq!(embedding, vec![3.0, 4.0]);
+
+        job.normalize_embeddings = true;
+        job.prepare_query_embedding(&mut embedding);
+        assert_eq!(embedding, vec![0.6, 0.8]);
+    }
+
     #[test]
     fn test_portkey_parsing() {
         let model = Model::new("portkey/openai/text-embedding-ada-002").unwrap();
@@ -351,6 +890,45 @@ mod model_tests {
         assert_eq!(model.api_name(), "voyage-3-lite");
     }
 
+    #[test]
+    fn test_azure_parsing() {
+        let model = Model::new("azure/my-embedding-deployment").unwrap();
+        assert_eq!(model.source, ModelSource::AzureOpenAI);
+        assert_eq!(model.fullname, "azure/my-embedding-deployment");
+        assert_eq!(model.api_name(), "my-embedding-deployment");
+        assert_eq!(model.source.to_string(), "azure");
+    }
+
+    #[test]
+    fn test_openai_compat_parsing() {
+        let model = Model::new("openai_compat/BAAI/bge-small-en-v1.5").unwrap();
+        assert_eq!(model.source, ModelSource::OpenAICompatible);
+        assert_eq!(model.fullname, "openai_compat/BAAI/bge-small-en-v1.5");
+        assert_eq!(model.api_name(), "BAAI/bge-small-en-v1.5");
+        assert_eq!(model.source.to_string(), "openai_compat");
+
+        let model = Model::new("openai_compat/nomic-embed-text").unwrap();
+        assert_eq!(model.api_name(), "nomic-embed-text");
+    }
+
+    #[test]
+    fn test_bedrock_parsing() {
+        let model = Model::new("bedrock/amazon.titan-embed-text-v2:0").unwrap();
+        assert_eq!(model.source, ModelSource::Bedrock);
+        assert_eq!(model.fullname, "bedrock/amazon.titan-embed-text-v2:0");
+        assert_eq!(model.api_name(), "amazon.titan-embed-text-v2:0");
+        assert_eq!(model.source.to_string(), "bedrock");
+    }
+
+    #[test]
+    fn test_vertex_parsing() {
+        let model = Model::new("vertex/text-embedding-004").unwrap();
+        assert_eq!(model.source, ModelSource::Vertex);
+        assert_eq!(model.fullname, "vertex/text-embedding-004");
+        assert_eq!(model.api_name(), "text-embedding-004");
+        assert_eq!(model.source.to_string(), "vertex");
+    }
+
     #[test]
     fn test_ollama_parsing() {
         let model = Model::new("ollama/wizardlm2:7b").unwrap();
@@ -421,4 +999,24 @@ mod model_tests {
         let model_string = model.to_string();
         assert_eq!(model_string, "chuckhend/private-model");
     }
+
+    #[test]
+    fn test_dead_letter_message_is_job_message_compatible() {
+        let dead_letter = DeadLetterMessage {
+            message: JobMessage {
+                job_name: "my_job".to_string(),
+                record_ids: vec!["1".to_string(), "2".to_string()],
+            },
+            error: "provider unavailable".to_string(),
+            read_ct: 3,
+        };
+        let value = serde_json::to_value(&dead_letter).unwrap();
+        assert_eq!(value["job_name"], "my_job");
+        assert_eq!(value["error"], "provider unavailable");
+
+        // the dead letter payload can still be read as a regular job message
+        let job_message: JobMessage = serde_json::from_value(value).unwrap();
+        assert_eq!(job_message.record_ids, vec!["1", "2"]);
+        assert_eq!(dead_letter_queue("vectorize_jobs"), "vectorize_jobs_dlq");
+    }
 }
@@ -0,0 +1,86 @@
+// table name prefixes are read from the environment, so this runs in its own test binary
+// where setting them cannot race the unit tests reading the default config
+use std::collections::BTreeMap;
+use vectorize_core::query::{
+    SearchMode, create_embedding_table, create_search_tokens_table, hybrid_search_query,
+};
+use vectorize_core::types::{IndexDist, TablePrefixes, VectorType};
+
+#[test]
+fn test_custom_table_prefix() {
+    unsafe {
+        std::env::set_var("EMBEDDINGS_TABLE_PREFIX", "vec_emb_");
+        std::env::set_var("SEARCH_TOKENS_TABLE_PREFIX", "vec_fts_");
+    }
+    let config = vectorize_core::config::Config::from_env();
+    assert_eq!(config.embeddings_table_prefix, "vec_emb_");
+    assert_eq!(config.search_tokens_table_prefix, "vec_fts_");
+    let prefixes = TablePrefixes::from_config(&config);
+    assert_eq!(prefixes.embeddings_table("my_job"), "vec_emb_my_job");
+    assert_eq!(prefixes.search_tokens_table("my_job"), "vec_fts_my_job");
+
+    let key = ["id".to_string()];
+    let key_type = ["integer".to_string()];
+    let embeddings = create_embedding_table(
+        "my_job",
+        &prefixes,
+        &key,
+        &key_type,
+        "vector(384)",
+        "public",
+        "docs",
+        "vectorize",
+    );
+    assert!(embeddings.contains("CREATE TABLE IF NOT EXISTS vectorize.vec_emb_my_job ("));
+    let tokens = create_search_tokens_table(
+        "my_job",
+        &prefixes,
+        &key,
+        &key_type,
+        "public",
+        "docs",
+        "vectorize",
+    );
+    assert!(tokens.contains("CREATE TABLE IF NOT EXISTS vectorize.vec_fts_my_job ("));
+
+    // search reads the tables creation wrote
+    let search = hybrid_search_query(
+        "my_job",
+        &prefixes,
+        "public",
+        "docs",
+        &key,
+        &["content".to_string()],
+        20,
+        5,
+        60.0,
+        1.0,
+        1.0,
+        &BTreeMap::new(),
+        &IndexDist::pgv_hnsw_cosine,
+        &VectorType::vector,
+        "vectorize",
+        false,
+        None,
+        None,
+        "english",
+        SearchMode::Hybrid,
+    );
+    assert!(search.contains("vectorize.vec_emb_my_job"));
+    assert!(search.contains("vectorize.vec_fts_my_job"));
+    assert!(!search.contains("_embeddings_"));
+    assert!(!search.contains("_search_tokens_"));
+
+    // a job stores its prefixes, jobs stored before they were kept use the defaults
+    let job: vectorize_core::types::VectorizeJob = serde_json::from_value(serde_json::json!({
+        "job_name": "my_job",
+        "src_table": "docs",
+        "src_schema": "public",
+        "src_columns": ["content"],
+        "primary_key": "id",
+        "model": "openai/text-embedding-3-small"
+    }))
+    .unwrap();
+    assert_eq!(job.table_prefixes, TablePrefixes::default());
+    assert_eq!(job.embeddings_table(), "_embeddings_my_job");
+}
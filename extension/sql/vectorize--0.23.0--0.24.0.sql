@@ -0,0 +1,58 @@
+DROP FUNCTION IF EXISTS "hybrid_search"(TEXT, TEXT, TEXT, TEXT[], INT, TEXT);
+
+CREATE  FUNCTION "hybrid_search"(
+	"job_name" TEXT, /* alloc::string::String */
+	"query" TEXT, /* alloc::string::String */
+	"api_key" TEXT DEFAULT NULL, /* core::option::Option<alloc::string::String> */
+	"return_columns" TEXT[] DEFAULT ARRAY['*']::text[], /* alloc::vec::Vec<alloc::string::String> */
+	"num_results" INT DEFAULT 10, /* i32 */
+	"where_sql" TEXT DEFAULT NULL, /* core::option::Option<alloc::string::String> */
+	"window_size" INT DEFAULT NULL, /* core::option::Option<i32> */
+	"rrf_k" real DEFAULT 60.0, /* f32 */
+	"semantic_wt" real DEFAULT 1.0, /* f32 */
+	"fts_wt" real DEFAULT 1.0 /* f32 */
+) RETURNS TABLE (
+	"search_results" jsonb  /* pgrx::datum::json::JsonB */
+)
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'hybrid_search_wrapper';
+
+CREATE  FUNCTION "create_prompt"(
+	"prompt_type" TEXT, /* &str */
+	"sys_prompt" TEXT, /* &str */
+	"user_prompt" TEXT /* &str */
+) RETURNS TEXT /* core::result::Result<alloc::string::String, anyhow::Error> */
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'create_prompt_wrapper';
+
+DROP FUNCTION IF EXISTS vectorize."rag"(TEXT, TEXT, TEXT, TEXT, TEXT, INT, bool);
+
+CREATE  FUNCTION vectorize."rag"(
+	"job_name" TEXT, /* &str */
+	"query" TEXT, /* &str */
+	"chat_model" TEXT DEFAULT 'openai/gpt-4o-mini', /* alloc::string::String */
+	"task" TEXT DEFAULT 'question_answer', /* alloc::string::String */
+	"api_key" TEXT DEFAULT NULL, /* core::option::Option<alloc::string::String> */
+	"num_context" INT DEFAULT 2, /* i32 */
+	"force_trim" bool DEFAULT false, /* bool */
+	"chat_history" jsonb DEFAULT NULL /* core::option::Option<pgrx::datum::json::JsonB> */
+) RETURNS TABLE (
+	"chat_results" jsonb  /* pgrx::datum::json::JsonB */
+)
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'rag_wrapper';
+
+CREATE  FUNCTION vectorize."rag_stream"(
+	"job_name" TEXT, /* &str */
+	"query" TEXT, /* &str */
+	"chat_model" TEXT DEFAULT 'ollama/llama3.2', /* alloc::string::String */
+	"task" TEXT DEFAULT 'question_answer', /* alloc::string::String */
+	"api_key" TEXT DEFAULT NULL, /* core::option::Option<alloc::string::String> */
+	"num_context" INT DEFAULT 2, /* i32 */
+	"force_trim" bool DEFAULT false /* bool */
+) RETURNS TABLE (
+	"chunk" TEXT  /* alloc::string::String */
+)
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'rag_stream_wrapper';
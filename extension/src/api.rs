@@ -1,4 +1,6 @@
-use crate::chat::ops::{call_chat, call_chat_completions};
+use crate::chat::ops::{
+    call_chat, call_chat_completions, call_chat_stream, parse_chat_history, upsert_prompt_template,
+};
 use crate::chat::types::RenderedPrompt;
 use crate::guc::get_guc_configs;
 use crate::init::{init_cron, VECTORIZE_QUEUE};
@@ -9,7 +11,8 @@ use crate::types;
 use crate::util::get_vectorize_meta_spi;
 use text_splitter::TextSplitter;
 use vectorize_core::query::{create_event_trigger, create_trigger_handler};
-use vectorize_core::types::{JobParams, Model};
+use vectorize_core::transformers::providers::InputType;
+use vectorize_core::types::{JobParams, Model, TablePrefixes};
 
 use anyhow::Result;
 use pgrx::prelude::*;
@@ -133,9 +136,9 @@ fn search(
     Ok(TableIterator::new(search_results.into_iter().map(|r| (r,))))
 }
 
-/// EXPERIMENTAL: Hybrid search
-///
-/// This function is experimental and may change in future versions.
+/// search ranked by reciprocal rank fusion of semantic similarity and full text search
+/// requires a job created with the join table method
+#[allow(clippy::too_many_arguments)]
 #[pg_extern]
 fn hybrid_search(
     job_name: String,
@@ -144,7 +147,13 @@ fn hybrid_search(
     return_columns: default!(Vec<String>, "ARRAY['*']::text[]"),
     num_results: default!(i32, 10),
     where_sql: default!(Option<String>, "NULL"),
+    window_size: default!(Option<i32>, "NULL"),
+    rrf_k: default!(f32, 60.0),
+    semantic_wt: default!(f32, 1.0),
+    fts_wt: default!(f32, 1.0),
 ) -> Result<TableIterator<'static, (name!(search_results, pgrx::JsonB),)>> {
+    // candidates taken from each ranking before they are combined, 5 * num_results like the server
+    let window_size = window_size.unwrap_or(5 * num_results);
     let search_results = search::hybrid_search(
         &job_name,
         &query,
@@ -152,6 +161,10 @@ fn hybrid_search(
         return_columns,
         num_results,
         where_sql,
+        window_size,
+        rrf_k,
+        semantic_wt,
+        fts_wt,
     )?;
     Ok(TableIterator::new(search_results.into_iter().map(|r| (r,))))
 }
@@ -163,7 +176,7 @@ fn transform_embeddings(
     api_key: default!(Option<String>, "NULL"),
 ) -> Result<Vec<f64>> {
     let model = Model::new(&model_name)?;
-    Ok(transform(input, &model, api_key).remove(0))
+    Ok(transform(input, &model, api_key, InputType::Document).remove(0))
 }
 
 #[pg_extern]
@@ -173,7 +186,7 @@ fn encode(
     api_key: default!(Option<String>, "NULL"),
 ) -> Result<Vec<f64>> {
     let model = Model::new(&model)?;
-    Ok(transform(input, &model, api_key).remove(0))
+    Ok(transform(input, &model, api_key, InputType::Document).remove(0))
 }
 
 /// creates a table indexed with embeddings for chat completion workloads
@@ -189,8 +202,14 @@ fn rag(
     num_context: default!(i32, 2),
     // truncates context to fit the model's context window
     force_trim: default!(bool, false),
+    // prior turns as a json array of {"role": "user" | "assistant", "content": ...}, oldest first
+    chat_history: default!(Option<pgrx::JsonB>, "NULL"),
 ) -> Result<TableIterator<'static, (name!(chat_results, pgrx::JsonB),)>> {
     let model = Model::new(&chat_model)?;
+    let chat_history = match chat_history {
+        Some(history) => parse_chat_history(history.0)?,
+        None => Vec::new(),
+    };
     let resp = call_chat(
         job_name,
         query,
@@ -199,11 +218,49 @@ fn rag(
         api_key,
         num_context,
         force_trim,
+        chat_history,
     )?;
     let iter = vec![(pgrx::JsonB(serde_json::to_value(resp)?),)];
     Ok(TableIterator::new(iter))
 }
 
+/// same as rag(), but returns the chat completion as a row per chunk as the model generates it
+/// rows are returned in generation order, concatenating them yields the full response
+/// only ollama models are supported
+#[pg_extern]
+fn rag_stream(
+    job_name: &str,
+    query: &str,
+    chat_model: default!(String, "'ollama/llama3.2'"),
+    // points to the type of prompt template to use
+    task: default!(String, "'question_answer'"),
+    api_key: default!(Option<String>, "NULL"),
+    // number of records to include in the context
+    num_context: default!(i32, 2),
+    // truncates context to fit the model's context window
+    force_trim: default!(bool, false),
+) -> Result<TableIterator<'static, (name!(chunk, String),)>> {
+    let model = Model::new(&chat_model)?;
+    let stream = call_chat_stream(
+        job_name,
+        query,
+        &model,
+        &task,
+        api_key,
+        num_context,
+        force_trim,
+    )?;
+    Ok(TableIterator::new(stream.map(|chunk| (chunk,))))
+}
+
+/// registers a prompt template for the `task` argument of rag() and rag_stream()
+/// user_prompt must contain the {{context_str}} and {{query_str}} placeholders
+#[pg_extern]
+fn create_prompt(prompt_type: &str, sys_prompt: &str, user_prompt: &str) -> Result<String> {
+    upsert_prompt_template(prompt_type, sys_prompt, user_prompt)?;
+    Ok(prompt_type.to_string())
+}
+
 #[pg_extern]
 fn generate(
     input: &str,
@@ -213,6 +270,7 @@ fn generate(
     let model = Model::new(&model)?;
     let prompt = RenderedPrompt {
         sys_rendered: "".to_string(),
+        history: Vec::new(),
         user_rendered: input.to_string(),
     };
     let mut guc_configs = get_guc_configs(&model.source);
@@ -260,20 +318,23 @@ fn import_embeddings(
 
     // Process rows based on table method
     let count = if job_params.table_method == vectorize_core::types::TableMethod::join {
+        let embeddings_table = TablePrefixes::default().embeddings_table(job_name);
         let insert_q = format!(
-            "INSERT INTO vectorize._embeddings_{} ({}, embeddings, updated_at)
+            "INSERT INTO {}.{} ({}, embeddings, updated_at)
              SELECT src.{}, src.{}, NOW()
              FROM {} src
-             LEFT JOIN vectorize._embeddings_{} tgt ON src.{} = tgt.{}
+             LEFT JOIN {}.{} tgt ON src.{} = tgt.{}
              WHERE tgt.{} IS NULL
              ON CONFLICT ({}) DO UPDATE 
              SET embeddings = EXCLUDED.embeddings, updated_at = NOW()",
-            job_name,
+            job_params.target_schema,
+            embeddings_table,
             job_params.primary_key,
             src_primary_key,
             src_embeddings_col,
             src_table,
-            job_name,
+            job_params.target_schema,
+            embeddings_table,
             src_primary_key,
             job_params.primary_key,
             job_params.primary_key,
@@ -282,7 +343,10 @@ fn import_embeddings(
 
         Spi::run(&insert_q)?;
 
-        let count_query = format!("SELECT count(*) FROM vectorize._embeddings_{}", job_name);
+        let count_query = format!(
+            "SELECT count(*) FROM {}.{}",
+            job_params.target_schema, embeddings_table
+        );
         Spi::get_one::<i64>(&count_query)?.unwrap_or(0) as i32
     } else {
         // For append method, update the source table's embeddings column
@@ -370,7 +434,8 @@ fn table_from(
     // Now set up the triggers or cron job based on the desired schedule
     if schedule == "realtime" {
         // Create triggers for realtime updates
-        let trigger_handler = create_trigger_handler(job_name, primary_key);
+        let trigger_handler =
+            create_trigger_handler(job_name, &[primary_key.to_string()], None, None);
         Spi::run(&trigger_handler)?;
 
         let insert_trigger = create_event_trigger(job_name, schema, relation, "INSERT");
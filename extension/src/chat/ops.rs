@@ -6,7 +6,7 @@ use anyhow::{anyhow, Result};
 use handlebars::Handlebars;
 use pgrx::prelude::*;
 use vectorize_core::guc::ModelGucConfig;
-use vectorize_core::transformers::providers::ollama::OllamaProvider;
+use vectorize_core::transformers::providers::ollama::{OllamaProvider, ResponseStream};
 use vectorize_core::transformers::providers::openai::OpenAIProvider;
 use vectorize_core::transformers::providers::portkey::PortkeyProvider;
 use vectorize_core::transformers::providers::ChatMessageRequest;
@@ -17,6 +17,8 @@ use crate::chat::types::{ChatResponse, ContextualSearch, PromptTemplate, Rendere
 use tiktoken_rs::{get_bpe_from_model, model::get_context_size, CoreBPE};
 use vectorize_core::types::{JobParams, VectorizeMeta};
 
+/// chat_history holds the prior user and assistant turns, oldest first
+#[allow(clippy::too_many_arguments)]
 pub fn call_chat(
     job_name: &str,
     query: &str,
@@ -25,7 +27,131 @@ pub fn call_chat(
     api_key: Option<String>,
     num_context: i32,
     force_trim: bool,
+    chat_history: Vec<ChatMessageRequest>,
 ) -> Result<ChatResponse> {
+    let (search_results, rendered_prompt) = prepare_chat(
+        job_name,
+        query,
+        chat_model,
+        task,
+        api_key,
+        num_context,
+        force_trim,
+        &chat_history,
+    )?;
+
+    // http request to chat completions
+    let guc_configs = guc::get_guc_configs(&chat_model.source);
+    let chat_response = call_chat_completions(rendered_prompt, chat_model, &guc_configs)?;
+
+    // the query is kept without its context, the next call retrieves context for its own query
+    let mut chat_history = chat_history;
+    chat_history.push(ChatMessageRequest {
+        role: "user".to_owned(),
+        content: query.to_owned(),
+    });
+    chat_history.push(ChatMessageRequest {
+        role: "assistant".to_owned(),
+        content: chat_response.clone(),
+    });
+
+    Ok(ChatResponse {
+        context: search_results,
+        chat_response,
+        chat_history,
+    })
+}
+
+/// parses a chat history given as a json array of {"role", "content"} messages
+pub fn parse_chat_history(history: serde_json::Value) -> Result<Vec<ChatMessageRequest>> {
+    let messages: Vec<ChatMessageRequest> = serde_json::from_value(history)
+        .map_err(|e| anyhow!("chat_history must be an array of role/content messages: {e}"))?;
+    if let Some(message) = messages
+        .iter()
+        .find(|m| m.role != "user" && m.role != "assistant")
+    {
+        return Err(anyhow!(
+            "chat_history roles must be `user` or `assistant`, got `{}`",
+            message.role
+        ));
+    }
+    Ok(messages)
+}
+
+/// same as call_chat, but returns the completion as an iterator of chunks as they are generated
+/// only ollama models support streaming
+pub fn call_chat_stream(
+    job_name: &str,
+    query: &str,
+    chat_model: &Model,
+    task: &str,
+    api_key: Option<String>,
+    num_context: i32,
+    force_trim: bool,
+) -> Result<ChatStream> {
+    if chat_model.source != ModelSource::Ollama {
+        error!(
+            "streaming chat completions are only supported for ollama models, got: {}",
+            chat_model.source
+        );
+    }
+    let (_, rendered_prompt) = prepare_chat(
+        job_name,
+        query,
+        chat_model,
+        task,
+        api_key,
+        num_context,
+        force_trim,
+        &[],
+    )?;
+    let guc_configs = guc::get_guc_configs(&chat_model.source);
+    let messages = chat_messages(&rendered_prompt);
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_io()
+        .enable_time()
+        .build()
+        .unwrap_or_else(|e| error!("failed to initialize tokio runtime: {}", e));
+    let stream = runtime.block_on(async {
+        let provider = OllamaProvider::new(guc_configs.service_url.clone())?;
+        provider
+            .generate_response_stream(chat_model.api_name(), &messages)
+            .await
+    })?;
+    Ok(ChatStream { runtime, stream })
+}
+
+/// chunks of a streamed chat completion, in the order they were generated
+pub struct ChatStream {
+    runtime: tokio::runtime::Runtime,
+    stream: ResponseStream,
+}
+
+impl Iterator for ChatStream {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        match self.runtime.block_on(self.stream.next_chunk()) {
+            Some(Ok(chunk)) => Some(chunk),
+            Some(Err(e)) => error!("failed to stream chat response: {}", e),
+            None => None,
+        }
+    }
+}
+
+// retrieves the context for a query and renders it into the task's prompt template
+#[allow(clippy::too_many_arguments)]
+fn prepare_chat(
+    job_name: &str,
+    query: &str,
+    chat_model: &Model,
+    task: &str,
+    api_key: Option<String>,
+    num_context: i32,
+    force_trim: bool,
+    chat_history: &[ChatMessageRequest],
+) -> Result<(Vec<ContextualSearch>, RenderedPrompt)> {
     // get job metadata
     let project_meta: VectorizeMeta = get_vectorize_meta_spi(job_name)?;
 
@@ -50,6 +176,18 @@ pub fn call_chat(
         ModelSource::Voyage => {
             get_bpe_from_model(&chat_model.name).expect("failed to get BPE from model")
         }
+        ModelSource::AzureOpenAI => {
+            error!("Azure OpenAI not yet supported for chat completions")
+        }
+        ModelSource::Bedrock => {
+            error!("Bedrock not yet supported for chat completions")
+        }
+        ModelSource::Vertex => {
+            error!("Vertex AI not yet supported for chat completions")
+        }
+        ModelSource::OpenAICompatible => {
+            error!("OpenAI-compatible servers not yet supported for chat completions")
+        }
     };
 
     // can only be 1 column in a chat job, for now, so safe to grab first element
@@ -79,26 +217,7 @@ pub fn call_chat(
         });
     }
 
-    // read prompt template
-    let res_prompts: Result<PromptTemplate, spi::Error> = Spi::connect(|c| {
-        let q = format!("select * from vectorize.prompts where prompt_type = '{task}'");
-        let tup_table = c.select(&q, None, &[])?;
-        let mut sys_prompt = String::new();
-        let mut user_prompt = String::new();
-        for row in tup_table {
-            sys_prompt = row["sys_prompt"]
-                .value::<String>()?
-                .expect("sys_prompt is null");
-            user_prompt = row["user_prompt"]
-                .value::<String>()?
-                .expect("user_prompt is null");
-        }
-        Ok(PromptTemplate {
-            sys_prompt,
-            user_prompt,
-        })
-    });
-    let p_ok = res_prompts?;
+    let p_ok = get_prompt_template(task)?;
 
     let sys_prompt_template = p_ok.sys_prompt;
     let user_prompt_template = p_ok.user_prompt;
@@ -110,19 +229,73 @@ pub fn call_chat(
         &sys_prompt_template,
         &user_prompt_template,
         query,
+        chat_history,
         force_trim,
         &bpe,
         max_context_length,
     )?;
 
-    // http request to chat completions
-    let guc_configs = guc::get_guc_configs(&chat_model.source);
-    let chat_response = call_chat_completions(rendered_prompt, chat_model, &guc_configs)?;
+    Ok((search_results, rendered_prompt))
+}
 
-    Ok(ChatResponse {
-        context: search_results,
-        chat_response,
-    })
+/// the prompt template registered in vectorize.prompts under the task's name
+pub fn get_prompt_template(task: &str) -> Result<PromptTemplate> {
+    let template: Option<PromptTemplate> = Spi::connect(|c| {
+        let tup_table = c.select(
+            "SELECT sys_prompt, user_prompt FROM vectorize.prompts WHERE prompt_type = $1",
+            Some(1),
+            &[task.into()],
+        )?;
+        let mut template = None;
+        for row in tup_table {
+            template = Some(PromptTemplate {
+                sys_prompt: row["sys_prompt"]
+                    .value::<String>()?
+                    .expect("sys_prompt is null"),
+                user_prompt: row["user_prompt"]
+                    .value::<String>()?
+                    .expect("user_prompt is null"),
+            });
+        }
+        Ok::<_, spi::Error>(template)
+    })?;
+    template.ok_or_else(|| anyhow!("prompt template `{task}` not found in vectorize.prompts"))
+}
+
+/// registers a prompt template under prompt_type, replacing any template of that name
+pub fn upsert_prompt_template(
+    prompt_type: &str,
+    sys_prompt: &str,
+    user_prompt: &str,
+) -> Result<()> {
+    validate_prompt_template(user_prompt)?;
+    Spi::run_with_args(
+        "INSERT INTO vectorize.prompts (prompt_type, sys_prompt, user_prompt)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (prompt_type)
+        DO UPDATE SET sys_prompt = EXCLUDED.sys_prompt, user_prompt = EXCLUDED.user_prompt",
+        &[prompt_type.into(), sys_prompt.into(), user_prompt.into()],
+    )?;
+    Ok(())
+}
+
+// the user prompt must render both the retrieved context and the query
+fn validate_prompt_template(user_prompt: &str) -> Result<()> {
+    let context_marker = "\u{1}context\u{1}";
+    let query_marker = "\u{1}query\u{1}";
+    let rendered = render_user_message(user_prompt, context_marker, query_marker)
+        .map_err(|e| anyhow!("invalid prompt template: {e}"))?;
+    for (marker, placeholder) in [
+        (context_marker, "{{context_str}}"),
+        (query_marker, "{{query_str}}"),
+    ] {
+        if !rendered.contains(marker) {
+            return Err(anyhow!(
+                "user_prompt must contain the {placeholder} placeholder"
+            ));
+        }
+    }
+    Ok(())
 }
 
 fn render_user_message(user_prompt_template: &str, context: &str, query: &str) -> Result<String> {
@@ -135,21 +308,25 @@ fn render_user_message(user_prompt_template: &str, context: &str, query: &str) -
     Ok(user_rendered)
 }
 
+fn chat_messages(prompts: &RenderedPrompt) -> Vec<ChatMessageRequest> {
+    let mut messages = vec![ChatMessageRequest {
+        role: "system".to_owned(),
+        content: prompts.sys_rendered.clone(),
+    }];
+    messages.extend(prompts.history.iter().cloned());
+    messages.push(ChatMessageRequest {
+        role: "user".to_owned(),
+        content: prompts.user_rendered.clone(),
+    });
+    messages
+}
+
 pub fn call_chat_completions(
     prompts: RenderedPrompt,
     model: &Model,
     guc_configs: &ModelGucConfig,
 ) -> Result<String> {
-    let messages = vec![
-        ChatMessageRequest {
-            role: "system".to_owned(),
-            content: prompts.sys_rendered.clone(),
-        },
-        ChatMessageRequest {
-            role: "user".to_owned(),
-            content: prompts.user_rendered.clone(),
-        },
-    ];
+    let messages = chat_messages(&prompts);
     let runtime = tokio::runtime::Builder::new_current_thread()
         .enable_io()
         .enable_time()
@@ -178,7 +355,7 @@ pub fn call_chat_completions(
                     .await
             }
             ModelSource::Ollama => {
-                let provider = OllamaProvider::new(guc_configs.service_url.clone());
+                let provider = OllamaProvider::new(guc_configs.service_url.clone())?;
                 provider
                     .generate_response(model.api_name(), &messages)
                     .await
@@ -186,6 +363,18 @@ pub fn call_chat_completions(
             ModelSource::SentenceTransformers | ModelSource::Cohere | ModelSource::Voyage => {
                 error!("SentenceTransformers and Cohere not yet supported for chat completions")
             }
+            ModelSource::AzureOpenAI => {
+                error!("Azure OpenAI not yet supported for chat completions")
+            }
+            ModelSource::Bedrock => {
+                error!("Bedrock not yet supported for chat completions")
+            }
+            ModelSource::Vertex => {
+                error!("Vertex AI not yet supported for chat completions")
+            }
+            ModelSource::OpenAICompatible => {
+                error!("OpenAI-compatible servers not yet supported for chat completions")
+            }
         }
     })?;
     Ok(chat_response)
@@ -221,11 +410,14 @@ fn trim_context(context: &str, overage: i32, bpe: &CoreBPE) -> Result<String> {
 
 // handles all preparation of prompt with context
 // optionally rims the context to fit within the token limit
+// when trimming, the oldest history turns are dropped before the context is trimmed
+#[allow(clippy::too_many_arguments)]
 fn prepared_prompt(
     searches: &[ContextualSearch],
     sys_prompt_template: &str,
     user_prompt_template: &str,
     query: &str,
+    history: &[ChatMessageRequest],
     force_trim: bool,
     bpe: &CoreBPE,
     max_context_length: i32,
@@ -244,7 +436,16 @@ fn prepared_prompt(
     let sys_prompt_token_ct = bpe.encode_ordinary(sys_prompt_template).len() as i32;
     let user_prompt_token_ct = bpe.encode_ordinary(user_prompt_template).len() as i32;
 
-    let remaining_tokens = max_context_length - sys_prompt_token_ct - user_prompt_token_ct;
+    let mut remaining_tokens = max_context_length - sys_prompt_token_ct - user_prompt_token_ct;
+
+    let turn_token_ct = |m: &ChatMessageRequest| bpe.encode_ordinary(&m.content).len() as i32;
+    let mut history = history.to_vec();
+    remaining_tokens -= history.iter().map(turn_token_ct).sum::<i32>();
+    if force_trim {
+        while user_message_ct >= remaining_tokens && !history.is_empty() {
+            remaining_tokens += turn_token_ct(&history.remove(0));
+        }
+    }
 
     // overage
     let overage = user_message_ct >= remaining_tokens;
@@ -258,6 +459,7 @@ fn prepared_prompt(
     if !overage {
         return Ok(RenderedPrompt {
             sys_rendered: sys_prompt_template.to_string(),
+            history,
             user_rendered: user_message,
         });
     }
@@ -271,11 +473,13 @@ fn prepared_prompt(
 
     Ok(RenderedPrompt {
         sys_rendered: sys_prompt_template.to_string(),
+        history,
         user_rendered: user_message,
     })
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "pg_test"))]
+#[pg_schema]
 mod tests {
     use super::*;
 
@@ -295,6 +499,7 @@ mod tests {
             sys_prompt_template,
             user_prompt_template,
             query,
+            &[],
             true,
             &bpe,
             36,
@@ -312,6 +517,7 @@ mod tests {
             sys_prompt_template,
             user_prompt_template,
             query,
+            &[],
             false,
             &bpe,
             36,
@@ -323,6 +529,7 @@ mod tests {
             sys_prompt_template,
             user_prompt_template,
             query,
+            &[],
             false,
             &bpe,
             1000,
@@ -339,6 +546,7 @@ mod tests {
             sys_prompt_template,
             user_prompt_template,
             query,
+            &[],
             true,
             &bpe,
             1000,
@@ -377,4 +585,157 @@ mod tests {
         let rendered = render_user_message(prompt_template, context, query).unwrap();
         assert_eq!("You are a sky expert, and here is context: The sky is the color blue. Question: What color is the sky?", rendered);
     }
+
+    #[test]
+    fn test_prepared_prompt_history() {
+        let bpe = get_bpe_from_model("gpt-3.5-turbo").unwrap();
+        let sys_prompt_template = "You are a sky expert";
+        let user_prompt_template = "Here is context: {{context_str}} \nQuestion: {{query_str}}";
+        let query = "Why is that?";
+        let searches = vec![ContextualSearch {
+            record_id: "1".to_string(),
+            content: "The sky is the color blue.".to_string(),
+            token_ct: 7,
+        }];
+        let history = vec![
+            ChatMessageRequest {
+                role: "user".to_string(),
+                content: "What color is the sky?".to_string(),
+            },
+            ChatMessageRequest {
+                role: "assistant".to_string(),
+                content: "The sky is blue.".to_string(),
+            },
+        ];
+
+        // prior turns are sent between the system prompt and the new question
+        let rendered = prepared_prompt(
+            &searches,
+            sys_prompt_template,
+            user_prompt_template,
+            query,
+            &history,
+            false,
+            &bpe,
+            1000,
+        )
+        .unwrap();
+        let messages = chat_messages(&rendered);
+        let roles: Vec<&str> = messages.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, ["system", "user", "assistant", "user"]);
+        assert_eq!(messages[1].content, "What color is the sky?");
+        assert_eq!(messages[2].content, "The sky is blue.");
+        assert_eq!(
+            messages[3].content,
+            "Here is context: The sky is the color blue. \nQuestion: Why is that?"
+        );
+
+        // room for everything but the oldest turn
+        let count = |s: &str| bpe.encode_ordinary(s).len() as i32;
+        let max_context_length = count(sys_prompt_template)
+            + count(user_prompt_template)
+            + count(&rendered.user_rendered)
+            + count(&history[1].content)
+            + 1;
+        let trimmed = prepared_prompt(
+            &searches,
+            sys_prompt_template,
+            user_prompt_template,
+            query,
+            &history,
+            true,
+            &bpe,
+            max_context_length,
+        )
+        .unwrap();
+        assert_eq!(trimmed.history.len(), 1);
+        assert_eq!(trimmed.history[0].content, "The sky is blue.");
+        // the context is only trimmed once the history is gone
+        assert_eq!(trimmed.user_rendered, rendered.user_rendered);
+
+        assert!(prepared_prompt(
+            &searches,
+            sys_prompt_template,
+            user_prompt_template,
+            query,
+            &history,
+            false,
+            &bpe,
+            max_context_length,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_chat_history() {
+        let history = parse_chat_history(serde_json::json!([
+            {"role": "user", "content": "hi"},
+            {"role": "assistant", "content": "hello"}
+        ]))
+        .unwrap();
+        assert_eq!(history.len(), 2);
+        assert!(
+            parse_chat_history(serde_json::json!([{"role": "system", "content": "x"}])).is_err()
+        );
+        assert!(parse_chat_history(serde_json::json!({"role": "user"})).is_err());
+    }
+
+    #[test]
+    fn test_validate_prompt_template() {
+        assert!(validate_prompt_template("{{ context_str }} {{ query_str }}").is_ok());
+        assert!(validate_prompt_template("Question: {{query_str}}").is_err());
+        assert!(validate_prompt_template("Context: {{context_str}}").is_err());
+        // placeholders in an unclosed block do not render
+        assert!(validate_prompt_template("{{#if x}}{{context_str}} {{query_str}}").is_err());
+    }
+
+    #[pg_test]
+    fn test_custom_prompt_template() {
+        Spi::run(
+            "SELECT vectorize.create_prompt(
+                'summarize',
+                'You summarize product reviews.',
+                'Reviews: {{ context_str }} Summarize them for: {{ query_str }}'
+            )",
+        )
+        .unwrap();
+
+        // rag() renders the template registered under its task
+        let template = get_prompt_template("summarize").unwrap();
+        assert_eq!(template.sys_prompt, "You summarize product reviews.");
+        let bpe = get_bpe_from_model("gpt-3.5-turbo").unwrap();
+        let searches = vec![ContextualSearch {
+            record_id: "1".to_string(),
+            content: "Great battery life.".to_string(),
+            token_ct: 4,
+        }];
+        let rendered = prepared_prompt(
+            &searches,
+            &template.sys_prompt,
+            &template.user_prompt,
+            "a buyer",
+            &[],
+            false,
+            &bpe,
+            1000,
+        )
+        .unwrap();
+        assert_eq!(rendered.sys_rendered, "You summarize product reviews.");
+        assert_eq!(
+            rendered.user_rendered,
+            "Reviews: Great battery life. Summarize them for: a buyer"
+        );
+
+        // registering the name again replaces the template
+        upsert_prompt_template("summarize", "Be brief.", "{{context_str}} / {{query_str}}")
+            .unwrap();
+        assert_eq!(
+            get_prompt_template("summarize").unwrap().sys_prompt,
+            "Be brief."
+        );
+
+        assert!(upsert_prompt_template("no_query", "", "{{context_str}}").is_err());
+        assert!(get_prompt_template("no_query").is_err());
+        assert!(get_prompt_template("question_answer").is_ok());
+    }
 }
@@ -1,4 +1,5 @@
 use serde::Serialize;
+use vectorize_core::transformers::providers::ChatMessageRequest;
 
 pub struct PromptTemplate {
     pub sys_prompt: String,
@@ -7,6 +8,8 @@ pub struct PromptTemplate {
 
 pub struct RenderedPrompt {
     pub sys_rendered: String,
+    // prior turns sent between the system and user message, oldest first
+    pub history: Vec<ChatMessageRequest>,
     pub user_rendered: String,
 }
 
@@ -21,4 +24,6 @@ pub struct ContextualSearch {
 pub struct ChatResponse {
     pub context: Vec<ContextualSearch>,
     pub chat_response: String,
+    // the given history followed by this query and its response, to pass to the next call
+    pub chat_history: Vec<ChatMessageRequest>,
 }
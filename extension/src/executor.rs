@@ -7,11 +7,11 @@ use crate::workers::get_vectorize_meta;
 use sqlx::error::Error;
 use sqlx::postgres::PgRow;
 use sqlx::{Pool, Postgres, Row};
-use tiktoken_rs::cl100k_base;
 use vectorize_core::errors::DatabaseError;
 use vectorize_core::query::{check_input, create_batches, new_rows_query_join};
+use vectorize_core::transformers::tokenizer::Tokenizer;
 use vectorize_core::transformers::types::Inputs;
-use vectorize_core::types::{JobMessage, JobParams, TableMethod};
+use vectorize_core::types::{JobMessage, JobParams, TableMethod, TablePrefixes};
 
 #[pg_extern]
 pub fn batch_texts(
@@ -62,7 +62,8 @@ fn job_execute(job_name: String) {
         let job_params = serde_json::from_value::<JobParams>(meta.params.clone())
             .unwrap_or_else(|e| error!("failed to deserialize job params: {}", e));
 
-        let new_or_updated_rows = get_new_updates(&conn, &job_name, job_params)
+        let tokenizer = Tokenizer::for_model(&meta.transformer);
+        let new_or_updated_rows = get_new_updates(&conn, &job_name, job_params, &tokenizer)
             .await
             .unwrap_or_else(|e| error!("failed to get new updates: {}", e));
 
@@ -138,27 +139,31 @@ pub async fn get_new_updates(
     pool: &Pool<Postgres>,
     job_name: &str,
     job_params: JobParams,
+    tokenizer: &Tokenizer,
 ) -> Result<Option<Vec<Inputs>>, DatabaseError> {
     let query = match job_params.table_method {
         TableMethod::append => new_rows_query(job_name, &job_params),
         TableMethod::join => new_rows_query_join(
             job_name,
+            &TablePrefixes::default(),
             &job_params.columns,
+            None,
             &job_params.schema,
             &job_params.relation,
-            &job_params.primary_key,
+            std::slice::from_ref(&job_params.primary_key),
             job_params.update_time_col.clone(),
+            false,
+            &job_params.target_schema,
         ),
     };
     let rows: Result<Vec<PgRow>, Error> = sqlx::query(&query).fetch_all(pool).await;
     match rows {
         Ok(rows) => {
             if !rows.is_empty() {
-                let bpe = cl100k_base().unwrap();
                 let mut new_inputs: Vec<Inputs> = Vec::new();
                 for r in rows {
                     let ipt: String = r.get("input_text");
-                    let token_estimate = bpe.encode_with_special_tokens(&ipt).len() as i32;
+                    let token_estimate = tokenizer.count(&ipt) as i32;
                     new_inputs.push(Inputs {
                         record_id: r.get("record_id"),
                         inputs: ipt.trim().to_owned(),
@@ -176,12 +181,13 @@ pub async fn get_new_updates(
     }
 }
 
+// NULL columns are coalesced to empty strings so they do not null out the whole input
 pub fn collapse_to_csv(strings: &[String]) -> String {
     strings
         .iter()
         .map(|s| {
             check_input(s).expect("Failed to validate input");
-            s.as_str()
+            format!("COALESCE({s}, '')")
         })
         .collect::<Vec<_>>()
         .join("|| ', ' ||")
@@ -248,4 +254,19 @@ mod tests {
         assert_eq!(batches[1].len(), 1);
         assert_eq!(batches[1][0].token_estimate, 100);
     }
+
+    #[test]
+    fn test_collapse_to_csv_coalesces_nulls() {
+        let cols = vec!["title".to_string(), "body".to_string()];
+        assert_eq!(
+            collapse_to_csv(&cols),
+            "COALESCE(title, '')|| ', ' ||COALESCE(body, '')"
+        );
+    }
+
+    #[test]
+    fn test_collapse_to_csv_single_column() {
+        let cols = vec!["content".to_string()];
+        assert_eq!(collapse_to_csv(&cols), "COALESCE(content, '')");
+    }
 }
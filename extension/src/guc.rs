@@ -30,6 +30,9 @@ pub static PORTKEY_SERVICE_URL: GucSetting<Option<CString>> =
 pub static VOYAGE_API_KEY: GucSetting<Option<CString>> = GucSetting::<Option<CString>>::new(None);
 pub static VOYAGE_SERVICE_URL: GucSetting<Option<CString>> =
     GucSetting::<Option<CString>>::new(None);
+pub static AZURE_OPENAI_KEY: GucSetting<Option<CString>> = GucSetting::<Option<CString>>::new(None);
+pub static AZURE_OPENAI_SERVICE_URL: GucSetting<Option<CString>> =
+    GucSetting::<Option<CString>>::new(None);
 pub static SEMANTIC_WEIGHT: GucSetting<i32> = GucSetting::<i32>::new(50);
 // EXPERIMENTAL
 pub static FTS_INDEX_TYPE: GucSetting<Option<CString>> = GucSetting::<Option<CString>>::new(None);
@@ -181,10 +184,28 @@ pub fn init_guc() {
         GucFlags::default(),
     );
 
+    GucRegistry::define_string_guc(
+        c"vectorize.azure_openai_service_url",
+        c"Endpoint for the Azure OpenAI resource",
+        c"Endpoint for the Azure OpenAI resource, e.g. https://my-resource.openai.azure.com",
+        &AZURE_OPENAI_SERVICE_URL,
+        GucContext::Suset,
+        GucFlags::default(),
+    );
+
+    GucRegistry::define_string_guc(
+        c"vectorize.azure_openai_key",
+        c"API Key for the Azure OpenAI resource",
+        c"API Key for the Azure OpenAI resource",
+        &AZURE_OPENAI_KEY,
+        GucContext::Suset,
+        GucFlags::default(),
+    );
+
     GucRegistry::define_int_guc(
         c"vectorize.semantic_weight",
         c"weight for semantic search",
-        c"weight for semantic search. default is 50",
+        c"weight for semantic search. default is 50. scales the semantic_wt and fts_wt arguments of vectorize.hybrid_search",
         &SEMANTIC_WEIGHT,
         0,
         100,
@@ -218,6 +239,8 @@ pub fn get_guc(guc: VectorizeGuc) -> Option<String> {
         VectorizeGuc::PortkeyServiceUrl => PORTKEY_SERVICE_URL.get(),
         VectorizeGuc::VoyageApiKey => VOYAGE_API_KEY.get(),
         VectorizeGuc::VoyageServiceUrl => VOYAGE_SERVICE_URL.get(),
+        VectorizeGuc::AzureOpenAIKey => AZURE_OPENAI_KEY.get(),
+        VectorizeGuc::AzureOpenAIServiceUrl => AZURE_OPENAI_SERVICE_URL.get(),
         VectorizeGuc::TextIndexType => FTS_INDEX_TYPE.get(),
     };
     if let Some(cstring) = val {
@@ -262,5 +285,28 @@ pub fn get_guc_configs(model_source: &ModelSource) -> ModelGucConfig {
             service_url: get_guc(VectorizeGuc::VoyageServiceUrl),
             virtual_key: None,
         },
+        ModelSource::AzureOpenAI => ModelGucConfig {
+            api_key: get_guc(VectorizeGuc::AzureOpenAIKey),
+            service_url: get_guc(VectorizeGuc::AzureOpenAIServiceUrl),
+            virtual_key: None,
+        },
+        // signed with AWS credentials from the environment
+        ModelSource::Bedrock => ModelGucConfig {
+            api_key: None,
+            service_url: None,
+            virtual_key: None,
+        },
+        // authenticated with a Google Cloud service account or the metadata server
+        ModelSource::Vertex => ModelGucConfig {
+            api_key: None,
+            service_url: None,
+            virtual_key: None,
+        },
+        // base url and api key come from OPENAI_COMPAT_BASE_URL and OPENAI_COMPAT_API_KEY
+        ModelSource::OpenAICompatible => ModelGucConfig {
+            api_key: None,
+            service_url: None,
+            virtual_key: None,
+        },
     }
 }
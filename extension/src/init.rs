@@ -4,9 +4,11 @@ use pgrx::prelude::*;
 use anyhow::{anyhow, Context, Result};
 use vectorize_core::guc::VectorizeGuc;
 use vectorize_core::query::{self, check_input};
-use vectorize_core::types::IndexDist;
-use vectorize_core::types::{JobParams, TableMethod, VECTORIZE_SCHEMA};
+use vectorize_core::types::{IndexDist, VectorType};
+use vectorize_core::types::{JobParams, TableMethod, TablePrefixes};
 pub static VECTORIZE_QUEUE: &str = "vectorize_jobs";
+// text search configuration of the search tokens, the server makes this configurable per job
+pub static FTS_LANGUAGE: &str = "english";
 
 pub fn init_pgmq() -> Result<()> {
     // check if queue already created:
@@ -70,30 +72,25 @@ pub fn init_embedding_table_query(
             (src_schema.clone(), src_table.clone(), embeddings_col)
         }
         TableMethod::join => {
-            let table_name = format!("_embeddings_{}", job_name);
+            // the table name prefixes are only configurable for the server, the extension keeps the defaults
+            let table_name = TablePrefixes::default().embeddings_table(job_name);
 
             (
-                VECTORIZE_SCHEMA.to_string(),
+                job_params.target_schema.clone(),
                 table_name.to_string(),
                 "embeddings".to_string(),
             )
         }
     };
 
-    let index_stmt = match index_type {
-        IndexDist::pgv_hnsw_cosine => {
-            query::create_hnsw_cosine_index(job_name, &index_schema, &table_name, &embeddings_col)
-        }
-        IndexDist::vsc_diskann_cosine => {
-            create_diskann_index(job_name, &index_schema, &table_name, &embeddings_col)
-        }
-        IndexDist::pgv_hnsw_ip => {
-            query::create_hnsw_ip_index(job_name, &index_schema, &table_name, &embeddings_col)
-        }
-        IndexDist::pgv_hnsw_l2 => {
-            query::create_hnsw_l2_index(job_name, &index_schema, &table_name, &embeddings_col)
-        }
-    };
+    let index_stmt = query::create_embedding_index(
+        job_name,
+        &index_schema,
+        &table_name,
+        &embeddings_col,
+        index_type,
+        &VectorType::vector,
+    );
 
     match job_params.table_method {
         TableMethod::append => {
@@ -106,20 +103,24 @@ pub fn init_embedding_table_query(
             let mut stmts = vec![
                 query::create_embedding_table(
                     job_name,
-                    &job_params.primary_key,
-                    &job_params.pkey_type,
+                    &TablePrefixes::default(),
+                    std::slice::from_ref(&job_params.primary_key),
+                    std::slice::from_ref(&job_params.pkey_type),
                     &col_type,
                     &src_schema,
                     &src_table,
+                    &job_params.target_schema,
                 ),
                 index_stmt,
                 // also create a view over the source table and the embedding table, for this project
-                query::drop_project_view(job_name),
+                query::drop_project_view(job_name, &job_params.target_schema),
                 query::create_project_view(
                     job_name,
+                    &TablePrefixes::default(),
                     &job_params.schema,
                     &job_params.relation,
-                    &job_params.primary_key,
+                    std::slice::from_ref(&job_params.primary_key),
+                    &job_params.target_schema,
                 ),
             ];
 
@@ -137,12 +138,51 @@ pub fn init_embedding_table_query(
     }
 }
 
-fn create_diskann_index(job_name: &str, schema: &str, table: &str, embedding_col: &str) -> String {
-    format!(
-        "CREATE INDEX IF NOT EXISTS {job_name}_diskann_idx ON {schema}.{table}
-        USING diskann ({embedding_col});
-        ",
-    )
+/// the search tokens table, its index and trigger, used by hybrid_search
+/// matches what the server creates for a job, only the join table method is supported
+pub fn init_search_tokens_queries(job_name: &str, job_params: &JobParams) -> Vec<String> {
+    query::check_input(job_name).expect("invalid job name");
+    let join_key = std::slice::from_ref(&job_params.primary_key);
+    let mut stmts = vec![
+        query::create_search_tokens_table(
+            job_name,
+            &TablePrefixes::default(),
+            join_key,
+            std::slice::from_ref(&job_params.pkey_type),
+            &job_params.schema,
+            &job_params.relation,
+            &job_params.target_schema,
+        ),
+        query::create_fts_index_query(
+            job_name,
+            &TablePrefixes::default(),
+            "GIN",
+            &job_params.target_schema,
+        ),
+    ];
+    stmts.extend(query::update_search_tokens_trigger_queries(
+        job_name,
+        &TablePrefixes::default(),
+        join_key,
+        &job_params.schema,
+        &job_params.relation,
+        &job_params.columns,
+        None,
+        &job_params.target_schema,
+        FTS_LANGUAGE,
+    ));
+    stmts.push(query::populate_search_tokens_query(
+        job_name,
+        &TablePrefixes::default(),
+        join_key,
+        &job_params.schema,
+        &job_params.relation,
+        &job_params.columns,
+        None,
+        &job_params.target_schema,
+        FTS_LANGUAGE,
+    ));
+    stmts
 }
 
 fn append_embedding_column(job_name: &str, schema: &str, table: &str, col_type: &str) -> String {
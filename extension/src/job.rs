@@ -4,35 +4,39 @@ use crate::executor::new_rows_query;
 use crate::guc::BATCH_SIZE;
 use crate::init::VECTORIZE_QUEUE;
 use pgrx::prelude::*;
-use tiktoken_rs::cl100k_base;
 use vectorize_core::query::{create_batches, new_rows_query_join};
+use vectorize_core::transformers::tokenizer::Tokenizer;
 use vectorize_core::transformers::types::Inputs;
-use vectorize_core::types::{JobMessage, JobParams, TableMethod};
+use vectorize_core::types::{JobMessage, JobParams, Model, TableMethod, TablePrefixes};
 
 // creates batches of embedding jobs
 // typically used on table init
-pub fn initalize_table_job(job_name: &str, job_params: &JobParams) -> Result<()> {
+pub fn initalize_table_job(job_name: &str, job_params: &JobParams, model: &Model) -> Result<()> {
     // start with initial batch load
     let rows_need_update_query: String = match job_params.table_method {
         TableMethod::append => new_rows_query(job_name, job_params),
         TableMethod::join => new_rows_query_join(
             job_name,
+            &TablePrefixes::default(),
             &job_params.columns,
+            None,
             &job_params.schema,
             &job_params.relation,
-            &job_params.primary_key,
+            std::slice::from_ref(&job_params.primary_key),
             job_params.update_time_col.clone(),
+            false,
+            &job_params.target_schema,
         ),
     };
     let mut inputs: Vec<Inputs> = Vec::new();
-    let bpe = cl100k_base().unwrap();
+    let tokenizer = Tokenizer::for_model(model);
     let _: Result<_, spi::Error> = Spi::connect(|c| {
         let rows = c.select(&rows_need_update_query, None, &[])?;
         for row in rows {
             let ipt = row["input_text"]
                 .value::<String>()?
                 .expect("input_text is null");
-            let token_estimate = bpe.encode_with_special_tokens(&ipt).len() as i32;
+            let token_estimate = tokenizer.count(&ipt) as i32;
             inputs.push(Inputs {
                 record_id: row["record_id"]
                     .value::<String>()?
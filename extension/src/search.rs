@@ -10,13 +10,13 @@ use anyhow::{Context, Result};
 use pgrx::prelude::*;
 use pgrx::JsonB;
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use vectorize_core::guc::VectorizeGuc;
-use vectorize_core::query;
+use vectorize_core::query::{self, SearchMode};
 use vectorize_core::query::{create_event_trigger, create_trigger_handler};
-use vectorize_core::transformers::providers::get_provider;
 use vectorize_core::transformers::providers::ollama::check_model_host;
-use vectorize_core::types::{self, Model, ModelSource, TableMethod, VectorizeMeta};
+use vectorize_core::transformers::providers::{get_provider, resolve_model_dim, InputType};
+use vectorize_core::types::{self, Model, ModelSource, TableMethod, TablePrefixes, VectorizeMeta};
 
 #[allow(clippy::too_many_arguments)]
 pub fn init_table(
@@ -103,13 +103,14 @@ pub fn init_table(
         .enable_time()
         .build()
         .unwrap_or_else(|e| error!("failed to initialize tokio runtime: {}", e));
-    let model_dim =
-        match runtime.block_on(async { provider.model_dim(&transformer.api_name()).await }) {
-            Ok(e) => e,
-            Err(e) => {
-                error!("error getting model dim: {}", e);
-            }
-        };
+    let model_dim = match runtime
+        .block_on(async { resolve_model_dim(provider.as_ref(), &transformer).await })
+    {
+        Ok(e) => e,
+        Err(e) => {
+            error!("error validating model: {}", e);
+        }
+    };
 
     let valid_params = types::JobParams {
         schema: schema.to_string(),
@@ -122,6 +123,7 @@ pub fn init_table(
         api_key: guc_configs.api_key.clone(),
         schedule: schedule.to_string(),
         args: optional_args,
+        target_schema: types::default_target_schema(),
     };
     let params =
         JsonB(serde_json::to_value(valid_params.clone()).expect("error serializing params"));
@@ -162,11 +164,25 @@ pub fn init_table(
         error!("error creating embedding table: {}", e);
     }
 
+    if table_method == TableMethod::join {
+        let init_fts_q = init::init_search_tokens_queries(job_name, &valid_params);
+        let ran_fts: Result<_, spi::Error> = Spi::connect_mut(|c| {
+            for q in init_fts_q {
+                let _r = c.update(&q, None, &[])?;
+            }
+            Ok(())
+        });
+        if let Err(e) = ran_fts {
+            error!("error creating search tokens table: {}", e);
+        }
+    }
+
     match schedule {
         "realtime" => {
             // setup triggers
             // create the trigger if not exists
-            let trigger_handler = create_trigger_handler(job_name, primary_key);
+            let trigger_handler =
+                create_trigger_handler(job_name, &[primary_key.to_string()], None, None);
             let insert_trigger = create_event_trigger(job_name, schema, table, "INSERT");
             let update_trigger = create_event_trigger(job_name, schema, table, "UPDATE");
             let _: Result<_, spi::Error> = Spi::connect_mut(|c| {
@@ -183,10 +199,148 @@ pub fn init_table(
         }
     }
     // start with initial batch load
-    initalize_table_job(job_name, &valid_params)?;
+    initalize_table_job(job_name, &valid_params, transformer)?;
     Ok(format!("Successfully created job: {job_name}"))
 }
 
+/// semantic and full text rankings combined with reciprocal rank fusion, like the server's search
+#[allow(clippy::too_many_arguments)]
+pub fn hybrid_search(
+    job_name: &str,
+    query: &str,
+    api_key: Option<String>,
+    return_columns: Vec<String>,
+    num_results: i32,
+    where_clause: Option<String>,
+    window_size: i32,
+    rrf_k: f32,
+    semantic_wt: f32,
+    fts_wt: f32,
+) -> Result<Vec<JsonB>> {
+    let project_meta: VectorizeMeta = util::get_vectorize_meta_spi(job_name)?;
+    let proj_params: types::JobParams = serde_json::from_value(
+        serde_json::to_value(project_meta.params).unwrap_or_else(|e| {
+            error!("failed to serialize metadata: {}", e);
+        }),
+    )
+    .unwrap_or_else(|e| error!("failed to deserialize metadata: {}", e));
+
+    // append jobs and join jobs created before 0.24 have no search tokens table
+    let has_search_tokens = if proj_params.table_method == TableMethod::join {
+        let search_tokens_table = format!(
+            "{}.{}",
+            proj_params.target_schema,
+            TablePrefixes::default().search_tokens_table(job_name)
+        );
+        Spi::get_one_with_args(
+            "SELECT to_regclass($1) IS NOT NULL",
+            &[search_tokens_table.into()],
+        )?
+        .unwrap_or(false)
+    } else {
+        false
+    };
+    if !has_search_tokens {
+        return legacy_hybrid_search(
+            job_name,
+            query,
+            api_key,
+            return_columns,
+            num_results,
+            where_clause,
+        );
+    }
+    // vectorize.semantic_weight still shifts the balance, at its default of 50 both weights are kept
+    let semantic_weight = guc::SEMANTIC_WEIGHT.get() as f32;
+    let semantic_wt = semantic_wt * semantic_weight / 50.0;
+    let fts_wt = fts_wt * (100.0 - semantic_weight) / 50.0;
+
+    let proj_api_key = match api_key {
+        Some(k) => Some(k),
+        None => proj_params.api_key.clone(),
+    };
+    let embeddings = transform(
+        query,
+        &project_meta.transformer,
+        proj_api_key,
+        InputType::Query,
+    );
+
+    rrf_search(
+        job_name,
+        &proj_params,
+        &project_meta.index_dist_type,
+        query,
+        &embeddings[0],
+        &return_columns,
+        num_results,
+        where_clause.as_deref(),
+        window_size,
+        rrf_k,
+        semantic_wt,
+        fts_wt,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn rrf_search(
+    job_name: &str,
+    job_params: &types::JobParams,
+    index_dist_type: &types::IndexDist,
+    query: &str,
+    embeddings: &[f64],
+    return_columns: &[String],
+    num_results: i32,
+    where_clause: Option<&str>,
+    window_size: i32,
+    rrf_k: f32,
+    semantic_wt: f32,
+    fts_wt: f32,
+) -> Result<Vec<JsonB>> {
+    let q = query::hybrid_search_query(
+        job_name,
+        &TablePrefixes::default(),
+        &job_params.schema,
+        &job_params.relation,
+        std::slice::from_ref(&job_params.primary_key),
+        return_columns,
+        window_size,
+        num_results,
+        rrf_k,
+        semantic_wt,
+        fts_wt,
+        &BTreeMap::new(),
+        index_dist_type,
+        &types::VectorType::vector,
+        &job_params.target_schema,
+        false,
+        None,
+        where_clause,
+        init::FTS_LANGUAGE,
+        SearchMode::Hybrid,
+    );
+    Spi::connect(|client| {
+        let mut results: Vec<JsonB> = Vec::new();
+        let tup_table = client.select(&q, None, &[embeddings.into(), query.into()])?;
+        for row in tup_table {
+            match row["results"].value::<JsonB>()? {
+                Some(mut r) => {
+                    // results of vectorize.hybrid_search have always named the text rank full_text_rank
+                    if let Some(obj) = r.0.as_object_mut() {
+                        if let Some(rank) = obj.remove("fts_rank") {
+                            obj.insert("full_text_rank".to_string(), rank);
+                        }
+                    }
+                    results.push(r)
+                }
+                None => error!("failed to get results"),
+            }
+        }
+        Ok(results)
+    })
+}
+
+// hybrid search of jobs without a search tokens table, the source table's text is matched directly
 pub fn full_text_search(
     job_name: &str,
     query: &str,
@@ -276,7 +430,8 @@ pub struct AllResults {
     rrf_score: f32,
 }
 
-pub fn hybrid_search(
+// combines full text and semantic results in memory, weighted by vectorize.semantic_weight
+fn legacy_hybrid_search(
     job_name: &str,
     query: &str,
     api_key: Option<String>,
@@ -390,11 +545,17 @@ pub fn search(
         // if not, use the one from the project metadata
         None => proj_params.api_key.clone(),
     };
-    let embeddings = transform(query, &project_meta.transformer, proj_api_key);
+    let embeddings = transform(
+        query,
+        &project_meta.transformer,
+        proj_api_key,
+        InputType::Query,
+    );
 
     match project_meta.index_dist_type {
         types::IndexDist::pgv_hnsw_l2 => error!("Not implemented."),
         types::IndexDist::pgv_hnsw_ip => error!("Not implemented."),
+        types::IndexDist::pgv_hnsw_hamming => error!("Not implemented."),
         types::IndexDist::pgv_hnsw_cosine | types::IndexDist::vsc_diskann_cosine => {
             cosine_similarity_search(
                 job_name,
@@ -437,6 +598,7 @@ pub fn cosine_similarity_search(
             return_columns,
             num_results,
             where_clause,
+            &job_params.target_schema,
         ),
     };
     Spi::connect(|client| {
@@ -452,6 +614,7 @@ pub fn cosine_similarity_search(
     })
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn join_table_cosine_similarity(
     project: &str,
     schema: &str,
@@ -460,6 +623,7 @@ pub fn join_table_cosine_similarity(
     return_columns: &[String],
     num_results: i32,
     where_clause: Option<String>,
+    target_schema: &str,
 ) -> String {
     let cols = &return_columns
         .iter()
@@ -471,12 +635,13 @@ pub fn join_table_cosine_similarity(
     } else {
         "".to_string()
     };
+    let embeddings_table = TablePrefixes::default().embeddings_table(project);
     let inner_query = format!(
         "
     SELECT
         {join_key},
         1 - (embeddings <=> $1::vector) AS similarity_score
-    FROM vectorize._embeddings_{project}
+    FROM {target_schema}.{embeddings_table}
     ORDER BY similarity_score DESC
     "
     );
@@ -534,3 +699,95 @@ fn prepare_filter(filter: &str, pkey: &str) -> String {
     let wc = filter.replace(pkey, &format!("t0.{pkey}"));
     format!("AND {wc}")
 }
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pg_schema]
+mod tests {
+    use super::*;
+
+    #[pg_test]
+    fn test_rrf_search() {
+        Spi::run(
+            "CREATE TABLE public.rrf_products (product_id INT PRIMARY KEY, product_name TEXT NOT NULL)",
+        )
+        .unwrap();
+        Spi::run(
+            "INSERT INTO public.rrf_products VALUES
+                (1, 'wireless headphones'), (2, 'camping tent'), (3, 'hiking boots')",
+        )
+        .unwrap();
+        let params = types::JobParams {
+            schema: "public".to_string(),
+            relation: "rrf_products".to_string(),
+            columns: vec!["product_name".to_string()],
+            update_time_col: None,
+            table_method: TableMethod::join,
+            primary_key: "product_id".to_string(),
+            pkey_type: "integer".to_string(),
+            api_key: None,
+            schedule: "realtime".to_string(),
+            args: None,
+            target_schema: types::default_target_schema(),
+        };
+        Spi::run(&query::create_embedding_table(
+            "rrf_job",
+            &TablePrefixes::default(),
+            std::slice::from_ref(&params.primary_key),
+            std::slice::from_ref(&params.pkey_type),
+            "vector(3)",
+            &params.schema,
+            &params.relation,
+            &params.target_schema,
+        ))
+        .unwrap();
+        Spi::run(
+            "INSERT INTO vectorize._embeddings_rrf_job (product_id, embeddings)
+                VALUES (1, '[1,0,0]'), (2, '[0,1,0]'), (3, '[0,0,1]')",
+        )
+        .unwrap();
+        for q in init::init_search_tokens_queries("rrf_job", &params) {
+            Spi::run(&q).unwrap();
+        }
+
+        let results = rrf_search(
+            "rrf_job",
+            &params,
+            &types::IndexDist::pgv_hnsw_cosine,
+            "tent",
+            &[0.0, 0.0, 1.0],
+            &["product_id".to_string(), "product_name".to_string()],
+            3,
+            None,
+            15,
+            60.0,
+            1.0,
+            1.0,
+        )
+        .unwrap();
+        assert_eq!(results.len(), 3);
+
+        // the tent is the only text match and also ranks semantically, the boots only rank semantically
+        let first = &results[0].0;
+        assert_eq!(first["product_id"], 2);
+        assert_eq!(first["full_text_rank"], 1);
+        assert!(first["semantic_rank"].is_number());
+        let second = &results[1].0;
+        assert_eq!(second["product_id"], 3);
+        assert_eq!(second["semantic_rank"], 1);
+        assert!(second["full_text_rank"].is_null());
+        let scores: Vec<f64> = results
+            .iter()
+            .map(|r| r.0["rrf_score"].as_f64().unwrap())
+            .collect();
+        assert!(scores.windows(2).all(|w| w[0] >= w[1]));
+
+        // rows added later get their tokens from the trigger
+        Spi::run("INSERT INTO public.rrf_products VALUES (4, 'tent stakes')").unwrap();
+        let tokens: Option<i64> = Spi::get_one(
+            "SELECT count(*) FROM vectorize._search_tokens_rrf_job
+                WHERE search_tokens @@ websearch_to_tsquery('english', 'tent')",
+        )
+        .unwrap();
+        assert_eq!(tokens, Some(2));
+    }
+}
@@ -1,11 +1,11 @@
 use anyhow::Result;
 use pgrx::prelude::*;
-use vectorize_core::transformers::http_handler::handle_response;
+use vectorize_core::transformers::http_handler::{handle_response, provider_client};
 
 use crate::guc::EMBEDDING_REQ_TIMEOUT_SEC;
 
 pub fn validate_api_key(key: &str) -> Result<()> {
-    let client = reqwest::Client::new();
+    let client = provider_client().unwrap_or_else(|e| error!("failed to build http client: {}", e));
     let timeout = EMBEDDING_REQ_TIMEOUT_SEC.get();
     let runtime = tokio::runtime::Builder::new_current_thread()
         .enable_io()
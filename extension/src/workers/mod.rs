@@ -5,14 +5,14 @@ use anyhow::Result;
 use pgmq::{Message, PGMQueueExt};
 use pgrx::*;
 use sqlx::{Pool, Postgres};
-use tiktoken_rs::cl100k_base;
 use vectorize_core::errors::DatabaseError;
 use vectorize_core::guc;
 use vectorize_core::transformers::http_handler;
 use vectorize_core::transformers::providers;
+use vectorize_core::transformers::tokenizer::Tokenizer;
 use vectorize_core::transformers::types::Inputs;
 use vectorize_core::types;
-use vectorize_core::types::{JobMessage, JobParams, VectorizeMeta};
+use vectorize_core::types::{JobMessage, JobParams, TablePrefixes, VectorizeMeta};
 use vectorize_worker::ops;
 
 pub async fn run_worker(
@@ -103,7 +103,7 @@ pub async fn execute_job(dbclient: &Pool<Postgres>, msg: Message<JobMessage>) ->
         Err(e) => return Err(anyhow::anyhow!("Failed to get job meta: {}", e)),
     };
     let mut job_params: JobParams = serde_json::from_value(job_meta.params.clone())?;
-    let bpe = cl100k_base().unwrap();
+    let tokenizer = Tokenizer::for_model(&job_meta.transformer);
 
     let guc_configs = guc::get_guc_configs(&job_meta.transformer.source, dbclient).await;
     // if api_key found in GUC, then use that and re-assign
@@ -148,7 +148,7 @@ pub async fn execute_job(dbclient: &Pool<Postgres>, msg: Message<JobMessage>) ->
     let inputs: Vec<Inputs> = job_records
         .iter()
         .map(|row| {
-            let token_estimate = bpe.encode_with_special_tokens(&row.input_text).len() as i32;
+            let token_estimate = tokenizer.count(&row.input_text) as i32;
             Inputs {
                 record_id: row.record_id.clone(),
                 inputs: row.input_text.trim().to_owned(),
@@ -157,8 +157,11 @@ pub async fn execute_job(dbclient: &Pool<Postgres>, msg: Message<JobMessage>) ->
         })
         .collect();
 
-    let embedding_request =
-        providers::prepare_generic_embedding_request(&job_meta.transformer, &inputs);
+    let embedding_request = providers::prepare_generic_embedding_request(
+        &job_meta.transformer,
+        &inputs,
+        providers::InputType::Document,
+    );
 
     let embeddings = provider.generate_embedding(&embedding_request).await?;
 
@@ -179,11 +182,13 @@ pub async fn execute_job(dbclient: &Pool<Postgres>, msg: Message<JobMessage>) ->
         vectorize_core::types::TableMethod::join => {
             ops::upsert_embedding_table(
                 dbclient,
-                &job_meta.name,
+                &TablePrefixes::default().embeddings_table(&job_meta.name),
                 paired_embeddings,
-                "vectorize", // embeddings always live in vectorize schema in JOIN method
-                &job_params.primary_key,
-                &job_params.pkey_type,
+                &job_params.target_schema,
+                std::slice::from_ref(&job_params.primary_key),
+                std::slice::from_ref(&job_params.pkey_type),
+                &vectorize_core::types::ConflictStrategy::overwrite,
+                &vectorize_core::types::VectorType::vector,
             )
             .await?
         }
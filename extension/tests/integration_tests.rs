@@ -83,6 +83,71 @@ async fn test_hybrid_search() {
     assert_eq!(hybrid_search_results.len(), 3);
 }
 
+#[tokio::test]
+async fn test_search_tokens_created() {
+    let conn = common::init_database().await;
+    let mut rng = rand::thread_rng();
+    let test_num = rng.gen_range(1..100000);
+    let test_table_name = format!("products_test_{}", test_num);
+    common::init_test_table(&test_table_name, &conn).await;
+    let job_name = format!("job_{}", test_num);
+
+    common::init_embedding_svc_url(&conn).await;
+
+    let _ = sqlx::query(&format!(
+        "SELECT vectorize.table(
+        job_name => '{job_name}',
+        relation => '{test_table_name}',
+        primary_key => 'product_id',
+        columns => ARRAY['product_name', 'description'],
+        transformer => 'sentence-transformers/all-MiniLM-L6-v2',
+        schedule => 'realtime'
+    );"
+    ))
+    .execute(&conn)
+    .await
+    .expect("failed to init job");
+
+    // the same tables, index and trigger the server creates for a job
+    let tokens_table = format!("vectorize._search_tokens_{job_name}");
+    let source_rows = common::row_count(&test_table_name, &conn).await;
+    assert_eq!(common::row_count(&tokens_table, &conn).await, source_rows);
+
+    let index_exists: bool = sqlx::query_scalar(
+        "SELECT EXISTS (
+            SELECT 1 FROM pg_indexes
+            WHERE schemaname = 'vectorize' AND tablename = $1 AND indexname = $2
+        )",
+    )
+    .bind(format!("_search_tokens_{job_name}"))
+    .bind(format!("{job_name}_gin_idx"))
+    .fetch_one(&conn)
+    .await
+    .unwrap();
+    assert!(index_exists, "missing GIN index on {tokens_table}");
+
+    let trigger_exists: bool =
+        sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM pg_trigger WHERE tgname = $1)")
+            .bind(format!("{job_name}_search_tokens_trigger"))
+            .fetch_one(&conn)
+            .await
+            .unwrap();
+    assert!(trigger_exists, "missing search tokens trigger");
+
+    // new rows are tokenized by the trigger
+    sqlx::query(&format!(
+        "INSERT INTO {test_table_name} (product_id, product_name, description, product_category, price)
+        VALUES (999999, 'Camping Stove', 'a compact stove for backpacking', 'outdoors', 35.0)"
+    ))
+    .execute(&conn)
+    .await
+    .expect("failed to insert row");
+    assert_eq!(
+        common::row_count(&tokens_table, &conn).await,
+        source_rows + 1
+    );
+}
+
 #[tokio::test]
 async fn test_chunk_text() {
     let conn = common::init_database().await;
@@ -2,6 +2,7 @@ use log::{error, info};
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
+use vectorize_core::db;
 use vectorize_core::types::VectorizeJob;
 
 use super::protocol::{ProxyConfig, WireProxyError};
@@ -119,19 +120,7 @@ async fn try_listen_for_changes(
 pub async fn refresh_job_cache(
     config: &ProxyConfig,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let all_jobs: Vec<VectorizeJob> = sqlx::query_as(
-        "SELECT job_name, src_table, src_schema, src_columns, primary_key, update_time_col, model FROM vectorize.job",
-    )
-    .fetch_all(&config.db_pool)
-    .await?;
-
-    let jobmap: HashMap<String, VectorizeJob> = all_jobs
-        .into_iter()
-        .map(|mut item| {
-            let key = std::mem::take(&mut item.job_name);
-            (key, item)
-        })
-        .collect();
+    let jobmap = jobs_by_name(db::get_all_jobs(&config.db_pool).await?);
 
     {
         let mut jobmap_write = config.jobmap.write().await;
@@ -145,20 +134,18 @@ pub async fn refresh_job_cache(
 pub async fn load_initial_job_cache(
     pool: &sqlx::PgPool,
 ) -> Result<HashMap<String, VectorizeJob>, WireProxyError> {
-    let all_jobs: Vec<VectorizeJob> = sqlx::query_as(
-        "SELECT job_name, src_table, src_schema, src_columns, primary_key, update_time_col, model FROM vectorize.job",
-    )
-    .fetch_all(pool)
-    .await
-    .map_err(WireProxyError::Database)?;
-
-    let jobmap: HashMap<String, VectorizeJob> = all_jobs
-        .into_iter()
-        .map(|mut item| {
-            let key = std::mem::take(&mut item.job_name);
-            (key, item)
-        })
-        .collect();
-
-    Ok(jobmap)
+    let all_jobs: Vec<VectorizeJob> =
+        sqlx::query_as(&format!("SELECT {} FROM vectorize.job", db::JOB_COLUMNS))
+            .fetch_all(pool)
+            .await
+            .map_err(WireProxyError::Database)?;
+
+    Ok(jobs_by_name(all_jobs))
+}
+
+// cached jobs keep their job_name, as in the server's job cache
+fn jobs_by_name(jobs: Vec<VectorizeJob>) -> HashMap<String, VectorizeJob> {
+    jobs.into_iter()
+        .map(|job| (job.job_name.clone(), job))
+        .collect()
 }
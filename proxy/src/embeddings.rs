@@ -4,7 +4,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 
 use vectorize_core::errors::VectorizeError;
-use vectorize_core::transformers::providers::{self, prepare_generic_embedding_request};
+use vectorize_core::transformers::providers::{self, InputType, prepare_generic_embedding_request};
 use vectorize_core::transformers::types::Inputs;
 use vectorize_core::types::VectorizeJob;
 
@@ -48,18 +48,24 @@ impl JobMapEmbeddingProvider {
         // Get the provider based on the model source using the proper get_provider function
         let provider = providers::get_provider(&vectorize_job.model.source, None, None, None)?;
 
-        // Create input for embedding generation
-        let input = Inputs {
-            record_id: String::new(),
-            inputs: query.to_string(),
-            token_estimate: 0,
-        };
-
-        let embedding_request = prepare_generic_embedding_request(&vectorize_job.model, &[input]);
+        let input = query_input(vectorize_job, query);
+        let embedding_request =
+            prepare_generic_embedding_request(&vectorize_job.model, &[input], InputType::Query);
         let response = provider.generate_embedding(&embedding_request).await?;
-        response.embeddings.into_iter().next().ok_or_else(|| {
+        let mut embedding = response.embeddings.into_iter().next().ok_or_else(|| {
             VectorizeError::EmbeddingGenerationFailed("No embeddings returned".to_string())
-        })
+        })?;
+        vectorize_job.prepare_query_embedding(&mut embedding);
+        Ok(embedding)
+    }
+}
+
+// rows were embedded normalized, so the query is normalized the same way
+fn query_input(vectorize_job: &VectorizeJob, query: &str) -> Inputs {
+    Inputs {
+        record_id: String::new(),
+        inputs: vectorize_job.normalization.apply(query),
+        token_estimate: 0,
     }
 }
 
@@ -235,6 +241,29 @@ mod tests {
         assert_eq!(calls[0].project_name, "my_project");
     }
 
+    #[test]
+    fn test_query_input_normalized() {
+        let job: VectorizeJob = serde_json::from_value(serde_json::json!({
+            "job_name": "my_project",
+            "src_table": "products",
+            "src_schema": "public",
+            "src_columns": ["description"],
+            "primary_key": "product_id",
+            "update_time_col": "updated_at",
+            "model": "sentence-transformers/all-MiniLM-L6-v2",
+            "normalization": {"lowercase": true, "collapse_whitespace": true}
+        }))
+        .unwrap();
+        assert_eq!(
+            query_input(&job, "  Camping   GEAR ").inputs,
+            job.normalization.apply("  Camping   GEAR ")
+        );
+        assert_eq!(
+            query_input(&job, "  Camping   GEAR ").inputs,
+            "camping gear"
+        );
+    }
+
     #[test]
     fn test_no_embed_calls() {
         let sql = "SELECT * FROM documents WHERE id = 1";
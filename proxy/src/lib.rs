@@ -3,7 +3,9 @@ pub mod embeddings;
 pub mod message_parser;
 pub mod protocol;
 pub mod proxy;
+pub mod tls;
 
 pub use cache::*;
 pub use protocol::*;
 pub use proxy::*;
+pub use tls::TlsMode;
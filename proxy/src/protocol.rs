@@ -1,8 +1,10 @@
 use crate::embeddings::EmbedCall;
+use crate::tls::TlsMode;
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::RwLock;
+use tokio_rustls::TlsConnector;
 use vectorize_core::types::VectorizeJob;
 
 #[derive(Debug, thiserror::Error)]
@@ -13,6 +15,8 @@ pub enum WireProxyError {
     Database(#[from] sqlx::Error),
     #[error("Connection timeout")]
     Timeout,
+    #[error("TLS error: {0}")]
+    Tls(String),
 }
 
 #[derive(Debug, Clone)]
@@ -22,10 +26,29 @@ pub struct PreparedStatement {
     pub embed_calls: Vec<EmbedCall>,
 }
 
+/// bounds on the connections the proxy serves
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectionLimits {
+    /// time allowed for the client to start its session, and then to connect to the upstream server,
+    /// including the TLS handshake
+    pub connect_timeout: Duration,
+    /// close a connection after this long without traffic in either direction, None keeps it open
+    pub idle_timeout: Option<Duration>,
+    /// client connections served at once, further clients wait until one closes
+    pub max_connections: usize,
+}
+
 #[derive(Clone)]
 pub struct ProxyConfig {
     pub postgres_addr: std::net::SocketAddr,
-    pub timeout: Duration,
+    /// host name of the upstream server, used to verify its certificate
+    pub postgres_host: String,
+    pub tls_mode: TlsMode,
+    /// CA certificate (PEM) used to verify the upstream server in verify-full mode
+    pub ca_cert_path: Option<String>,
+    /// built from tls_mode and ca_cert_path, None when tls is disabled
+    pub tls_connector: Option<TlsConnector>,
+    pub limits: ConnectionLimits,
     pub jobmap: Arc<RwLock<HashMap<String, VectorizeJob>>>,
     pub db_pool: sqlx::PgPool,
     pub prepared_statements: Arc<RwLock<HashMap<String, PreparedStatement>>>,
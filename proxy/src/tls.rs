@@ -0,0 +1,263 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::WebPkiSupportedAlgorithms;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+use tokio_rustls::client::TlsStream;
+use tracing::info;
+
+use super::protocol::{
+    GSSENC_REQUEST_CODE, MIN_STARTUP_MESSAGE_SIZE, SSL_REQUEST_CODE, WireProxyError,
+};
+
+/// how the proxy secures its connection to the upstream Postgres server
+/// mirrors the libpq sslmode values of the same name
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TlsMode {
+    /// plaintext to the upstream, client SSL requests are passed through untouched
+    #[default]
+    Disable,
+    /// encrypt the upstream connection without verifying the server certificate
+    Require,
+    /// encrypt the upstream connection, verify the certificate chain and the host name
+    VerifyFull,
+}
+
+impl FromStr for TlsMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "disable" => Ok(TlsMode::Disable),
+            "require" => Ok(TlsMode::Require),
+            "verify-full" => Ok(TlsMode::VerifyFull),
+            _ => Err(format!(
+                "invalid tls mode: {s}, expected one of disable, require, verify-full"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for TlsMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TlsMode::Disable => write!(f, "disable"),
+            TlsMode::Require => write!(f, "require"),
+            TlsMode::VerifyFull => write!(f, "verify-full"),
+        }
+    }
+}
+
+/// the 8 byte SSLRequest startup packet
+pub fn ssl_request() -> [u8; 8] {
+    let mut packet = [0u8; 8];
+    packet[..4].copy_from_slice(&8u32.to_be_bytes());
+    packet[4..].copy_from_slice(&SSL_REQUEST_CODE.to_be_bytes());
+    packet
+}
+
+/// builds the connector used for upstream TLS, None when tls is disabled
+pub fn build_tls_connector(
+    mode: TlsMode,
+    ca_cert_path: Option<&str>,
+) -> Result<Option<TlsConnector>, WireProxyError> {
+    let provider = Arc::new(rustls::crypto::aws_lc_rs::default_provider());
+    let builder = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| WireProxyError::Tls(e.to_string()))?;
+
+    let config = match mode {
+        TlsMode::Disable => return Ok(None),
+        TlsMode::Require => builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(NoCertificateVerification(
+                provider.signature_verification_algorithms,
+            )))
+            .with_no_client_auth(),
+        TlsMode::VerifyFull => {
+            let mut roots = RootCertStore::empty();
+            match ca_cert_path {
+                Some(path) => {
+                    for cert in CertificateDer::pem_file_iter(path).map_err(|e| {
+                        WireProxyError::Tls(format!("failed to read CA cert {path}: {e}"))
+                    })? {
+                        let cert = cert.map_err(|e| {
+                            WireProxyError::Tls(format!("failed to parse CA cert {path}: {e}"))
+                        })?;
+                        roots
+                            .add(cert)
+                            .map_err(|e| WireProxyError::Tls(e.to_string()))?;
+                    }
+                }
+                None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+            }
+            builder.with_root_certificates(roots).with_no_client_auth()
+        }
+    };
+    Ok(Some(TlsConnector::from(Arc::new(config))))
+}
+
+/// asks the upstream server to switch to TLS and performs the handshake
+pub async fn upgrade_to_tls(
+    mut stream: TcpStream,
+    connector: &TlsConnector,
+    host: &str,
+) -> Result<TlsStream<TcpStream>, WireProxyError> {
+    stream.write_all(&ssl_request()).await?;
+    let mut response = [0u8; 1];
+    stream.read_exact(&mut response).await?;
+    if response[0] != b'S' {
+        return Err(WireProxyError::Tls(
+            "upstream PostgreSQL server does not support SSL".to_string(),
+        ));
+    }
+
+    let server_name = ServerName::try_from(host.to_string())
+        .map_err(|e| WireProxyError::Tls(format!("invalid server name {host}: {e}")))?;
+    Ok(connector.connect(server_name, stream).await?)
+}
+
+/// answers 'N' to any SSLRequest or GSSENCRequest from the client
+/// returns the bytes of the client's first other message, which still need to be forwarded
+pub async fn decline_client_encryption<S>(client: &mut S) -> Result<Vec<u8>, WireProxyError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    loop {
+        let mut header = vec![0u8; MIN_STARTUP_MESSAGE_SIZE];
+        client.read_exact(&mut header).await?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let code = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+        if length == MIN_STARTUP_MESSAGE_SIZE
+            && (code == SSL_REQUEST_CODE || code == GSSENC_REQUEST_CODE)
+        {
+            info!("Declining client encryption request, upstream connection uses TLS");
+            client.write_all(b"N").await?;
+            client.flush().await?;
+            continue;
+        }
+        return Ok(header);
+    }
+}
+
+// used by TlsMode::Require, encrypts without trusting the certificate, like libpq's sslmode=require
+#[derive(Debug)]
+struct NoCertificateVerification(WebPkiSupportedAlgorithms);
+
+impl ServerCertVerifier for NoCertificateVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.0)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.0)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_tls_mode_from_str() {
+        assert_eq!("disable".parse::<TlsMode>().unwrap(), TlsMode::Disable);
+        assert_eq!("REQUIRE".parse::<TlsMode>().unwrap(), TlsMode::Require);
+        assert_eq!(
+            "verify-full".parse::<TlsMode>().unwrap(),
+            TlsMode::VerifyFull
+        );
+        assert!("prefer".parse::<TlsMode>().is_err());
+        assert_eq!(TlsMode::VerifyFull.to_string(), "verify-full");
+    }
+
+    #[test]
+    fn test_build_tls_connector() {
+        assert!(
+            build_tls_connector(TlsMode::Disable, None)
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            build_tls_connector(TlsMode::Require, None)
+                .unwrap()
+                .is_some()
+        );
+        assert!(
+            build_tls_connector(TlsMode::VerifyFull, None)
+                .unwrap()
+                .is_some()
+        );
+        assert!(build_tls_connector(TlsMode::VerifyFull, Some("/does/not/exist.pem")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_to_tls_rejected_by_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 8];
+            socket.read_exact(&mut request).await.unwrap();
+            socket.write_all(b"N").await.unwrap();
+            request
+        });
+
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let connector = build_tls_connector(TlsMode::Require, None)
+            .unwrap()
+            .unwrap();
+        let result = upgrade_to_tls(stream, &connector, "localhost").await;
+        assert!(matches!(result, Err(WireProxyError::Tls(_))));
+        assert_eq!(server.await.unwrap(), ssl_request());
+    }
+
+    #[tokio::test]
+    async fn test_decline_client_encryption() {
+        let (mut client, mut proxy) = tokio::io::duplex(64);
+        // protocol 3.0 startup message header
+        let startup = [0, 0, 0, 9, 0, 3, 0, 0];
+
+        client.write_all(&ssl_request()).await.unwrap();
+        client.write_all(&startup).await.unwrap();
+
+        let prefix = decline_client_encryption(&mut proxy).await.unwrap();
+        assert_eq!(prefix, startup);
+
+        let mut response = [0u8; 1];
+        client.read_exact(&mut response).await.unwrap();
+        assert_eq!(&response, b"N");
+    }
+}
@@ -1,12 +1,15 @@
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 use tracing::error;
 use vectorize_core::config::Config;
+use vectorize_core::transformers::providers::cache::EmbeddingCache;
 use vectorize_core::types::VectorizeJob;
-use vectorize_worker::WorkerHealth;
+use vectorize_worker::{WorkerHealth, WorkerMetrics};
 
 use crate::cache;
+use crate::rate_limit::RateLimits;
 
 #[derive(Debug, thiserror::Error)]
 pub enum AppStateError {
@@ -27,21 +30,58 @@ pub struct AppState {
     pub job_cache: Arc<RwLock<HashMap<String, VectorizeJob>>>,
     /// worker health monitoring data
     pub worker_health: Arc<RwLock<WorkerHealth>>,
+    /// prometheus metrics registry, exposed on /metrics
+    pub metrics: WorkerMetrics,
+    /// embeddings of recently seen texts, shared by the search routes and the worker
+    pub embedding_cache: EmbeddingCache,
+    /// per client request counters of the search and table routes
+    pub rate_limits: RateLimits,
+}
+
+/// connection counts of a pool, reported by /health and /metrics
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+pub struct PoolStats {
+    pub size: u32,
+    pub idle: u32,
+    pub in_use: u32,
+}
+
+impl PoolStats {
+    pub fn from_pool(pool: &sqlx::PgPool) -> Self {
+        let size = pool.size();
+        let idle = pool.num_idle() as u32;
+        PoolStats {
+            size,
+            idle,
+            in_use: size.saturating_sub(idle),
+        }
+    }
+}
+
+/// pool options shared by the db and cache pools, only the max size differs
+pub fn pool_options(config: &Config, max_connections: u32) -> sqlx::postgres::PgPoolOptions {
+    let idle_timeout = match config.database_pool_idle_timeout {
+        0 => None,
+        secs => Some(Duration::from_secs(secs)),
+    };
+    sqlx::postgres::PgPoolOptions::new()
+        .max_connections(max_connections)
+        .min_connections(config.database_pool_min.min(max_connections))
+        .acquire_timeout(Duration::from_secs(config.database_pool_acquire_timeout))
+        .idle_timeout(idle_timeout)
 }
 
 impl AppState {
     pub async fn new(config: Config) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
-        let db_pool = sqlx::postgres::PgPoolOptions::new()
-            .max_connections(config.database_pool_max)
+        let db_pool = pool_options(&config, config.database_pool_max)
             .connect(&config.database_url)
             .await?;
 
-        let cache_pool = sqlx::postgres::PgPoolOptions::new()
-            .max_connections(config.database_cache_pool_max)
+        let cache_pool = pool_options(&config, config.database_cache_pool_max)
             .connect(&config.database_url)
             .await?;
 
-        vectorize_core::init::init_project(&db_pool)
+        vectorize_core::init::init_project(&db_pool, &config.queue_name)
             .await
             .map_err(|e| format!("Failed to initialize project: {e}"))?;
 
@@ -67,14 +107,25 @@ impl AppState {
         }));
 
         Ok(AppState {
-            config,
             db_pool,
             cache_pool,
             job_cache,
             worker_health,
+            metrics: WorkerMetrics::new(),
+            embedding_cache: EmbeddingCache::from_config(&config),
+            rate_limits: RateLimits::from_config(&config),
+            config,
         })
     }
 
+    /// current connection counts of the db and cache pools, keyed by pool name
+    pub fn pool_stats(&self) -> [(&'static str, PoolStats); 2] {
+        [
+            ("db", PoolStats::from_pool(&self.db_pool)),
+            ("cache", PoolStats::from_pool(&self.cache_pool)),
+        ]
+    }
+
     async fn start_cache_sync_listener_task(
         cache_pool: &sqlx::PgPool,
         job_cache: &Arc<RwLock<HashMap<String, VectorizeJob>>>,
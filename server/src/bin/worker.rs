@@ -1,47 +1,66 @@
 use tracing::{debug, error, info};
 use vectorize_core::config::Config;
 use vectorize_core::init;
-use vectorize_worker::executor::poll_job;
+use vectorize_core::transformers::providers::cache::EmbeddingCache;
+use vectorize_worker::WorkerMetrics;
+use vectorize_worker::backoff::PollBackoff;
+use vectorize_worker::executor::poll_jobs;
+use vectorize_worker::shutdown::{shutdown_channel, sleep_or_shutdown};
 
 #[tokio::main]
 async fn main() {
-    tracing_subscriber::fmt().with_target(false).init();
+    let cfg = Config::from_env();
+    vectorize_server::logging::init(&cfg.log_format);
 
     info!("starting pg-vectorize worker");
 
-    let cfg = Config::from_env();
-
     let pool = sqlx::postgres::PgPoolOptions::new()
-        .max_connections(5)
+        // each concurrently processed message holds a connection
+        .max_connections(cfg.worker_concurrency as u32 + 4)
         .connect(&cfg.database_url)
         .await
         .expect("unable to connect to postgres");
 
-    init::init_project(&pool)
+    init::init_project(&pool, &cfg.queue_name)
         .await
         .expect("Failed to initialize project");
 
     let queue = pgmq::PGMQueueExt::new_with_pool(pool.clone()).await;
+    let mut shutdown = shutdown_channel();
+    // the standalone worker has no http server, metrics are only exposed by the main server
+    let metrics = WorkerMetrics::new();
+    let embedding_cache = EmbeddingCache::from_config(&cfg);
+    let mut backoff = PollBackoff::new(
+        tokio::time::Duration::from_millis(cfg.poll_interval_min_ms),
+        tokio::time::Duration::from_millis(cfg.poll_interval_max_ms),
+    );
 
-    loop {
-        match poll_job(&pool, &queue, &cfg).await {
-            Ok(Some(_)) => {
-                info!("processed job!");
-                // continue processing
+    // stop reading new messages once shutdown is requested, in-flight messages finish first
+    while !*shutdown.borrow() {
+        match poll_jobs(&pool, &queue, &cfg, &metrics, &embedding_cache).await {
+            Ok(Some(processed)) => {
+                debug!("processed {processed} jobs");
+                // continue processing, and poll quickly once the queue drains
+                backoff.reset();
             }
+            // the read already waited for messages, poll again right away
+            Ok(None) if cfg.read_poll_timeout > 0 => {}
             Ok(None) => {
-                // no messages, small wait
-                debug!(
-                    "No messages in queue, waiting for {} seconds",
-                    cfg.poll_interval
-                );
-                tokio::time::sleep(tokio::time::Duration::from_secs(cfg.poll_interval)).await;
+                // no messages, wait longer each time the queue is still empty
+                let wait = backoff.next_empty();
+                debug!("No messages in queue, waiting for {wait:?}");
+                sleep_or_shutdown(wait, &mut shutdown).await;
             }
             Err(e) => {
                 // error, long wait
                 error!("Error processing job: {e:?}");
-                tokio::time::sleep(tokio::time::Duration::from_secs(cfg.poll_interval)).await;
+                sleep_or_shutdown(
+                    tokio::time::Duration::from_secs(cfg.poll_interval),
+                    &mut shutdown,
+                )
+                .await;
             }
         }
     }
+    info!("pg-vectorize worker shut down");
 }
@@ -1,7 +1,8 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use tracing::{error, info};
+use tracing::{error, info, warn};
+use vectorize_core::db;
 use vectorize_core::types::VectorizeJob;
 
 /// Cache sync functions for job change notifications
@@ -81,34 +82,32 @@ async fn try_listen_for_changes(
     let mut listener = sqlx::postgres::PgListener::connect_with(db_pool).await?;
     listener.listen("vectorize_job_changes").await?;
 
+    // jobs may have changed while no listener was connected, and those changes were not notified
+    refresh_job_cache(db_pool, job_cache).await?;
     info!("Connected and listening for vectorize job changes");
 
     loop {
-        match listener.recv().await {
-            Ok(notification) => {
+        match listener.try_recv().await {
+            Ok(Some(notification)) => {
                 info!(
                     "Received job change notification: {}",
                     notification.payload()
                 );
 
-                if let Ok(payload) =
-                    serde_json::from_str::<serde_json::Value>(notification.payload())
-                {
-                    let operation = payload.get("operation").and_then(|v| v.as_str());
-                    let job_name = payload.get("job_name").and_then(|v| v.as_str());
-                    info!(
-                        "Job change detected - Operation: {}, Job: {}",
-                        operation.unwrap_or("unknown"),
-                        job_name.unwrap_or("unknown")
-                    );
-                }
-
-                if let Err(e) = refresh_job_cache(db_pool, job_cache).await {
+                let result = match serde_json::from_str::<JobChange>(notification.payload()) {
+                    Ok(change) => apply_job_change(db_pool, job_cache, &change).await,
+                    // without a job name the whole cache is reloaded
+                    Err(_) => refresh_job_cache(db_pool, job_cache).await,
+                };
+                if let Err(e) = result {
                     error!("Failed to refresh job cache: {e}");
-                } else {
-                    info!("Job cache refreshed successfully");
                 }
             }
+            // the connection was lost and the listener reconnected, changes in between were missed
+            Ok(None) => {
+                warn!("Reconnected to vectorize job change notifications, reloading job cache");
+                refresh_job_cache(db_pool, job_cache).await?;
+            }
             Err(e) => {
                 error!("Error receiving notification: {e}");
                 return Err(e.into());
@@ -117,23 +116,43 @@ async fn try_listen_for_changes(
     }
 }
 
+/// payload of a vectorize_job_changes notification
+#[derive(Debug, serde::Deserialize)]
+pub struct JobChange {
+    /// INSERT, UPDATE or DELETE
+    pub operation: String,
+    pub job_name: String,
+}
+
+/// reloads the changed job's full row, so changes to any of its columns reach the cache
+pub async fn apply_job_change(
+    db_pool: &sqlx::PgPool,
+    job_cache: &Arc<RwLock<HashMap<String, VectorizeJob>>>,
+    change: &JobChange,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let job = match change.operation.as_str() {
+        "DELETE" => None,
+        _ => db::find_vectorize_job(db_pool, &change.job_name).await?,
+    };
+    let mut jobmap = job_cache.write().await;
+    match job {
+        Some(job) => {
+            info!("Job cache updated for job: {}", change.job_name);
+            jobmap.insert(change.job_name.clone(), job);
+        }
+        None => {
+            info!("Job removed from cache: {}", change.job_name);
+            jobmap.remove(&change.job_name);
+        }
+    }
+    Ok(())
+}
+
 pub async fn refresh_job_cache(
     db_pool: &sqlx::PgPool,
     job_cache: &Arc<RwLock<HashMap<String, VectorizeJob>>>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let all_jobs: Vec<VectorizeJob> = sqlx::query_as(
-        "SELECT job_name, src_table, src_schema, src_columns, primary_key, update_time_col, model FROM vectorize.job",
-    )
-    .fetch_all(db_pool)
-    .await?;
-
-    let jobmap: HashMap<String, VectorizeJob> = all_jobs
-        .into_iter()
-        .map(|mut item| {
-            let key = std::mem::take(&mut item.job_name);
-            (key, item)
-        })
-        .collect();
+    let jobmap = jobs_by_name(db::get_all_jobs(db_pool).await?);
 
     {
         let mut jobmap_write = job_cache.write().await;
@@ -147,20 +166,18 @@ pub async fn refresh_job_cache(
 pub async fn load_initial_job_cache(
     pool: &sqlx::PgPool,
 ) -> Result<HashMap<String, VectorizeJob>, crate::app_state::AppStateError> {
-    let all_jobs: Vec<VectorizeJob> = sqlx::query_as(
-        "SELECT job_name, src_table, src_schema, src_columns, primary_key, update_time_col, model FROM vectorize.job",
-    )
-    .fetch_all(pool)
-    .await
-    .map_err(crate::app_state::AppStateError::Database)?;
-
-    let jobmap: HashMap<String, VectorizeJob> = all_jobs
-        .into_iter()
-        .map(|mut item| {
-            let key = std::mem::take(&mut item.job_name);
-            (key, item)
-        })
-        .collect();
-
-    Ok(jobmap)
+    let all_jobs: Vec<VectorizeJob> =
+        sqlx::query_as(&format!("SELECT {} FROM vectorize.job", db::JOB_COLUMNS))
+            .fetch_all(pool)
+            .await
+            .map_err(crate::app_state::AppStateError::Database)?;
+
+    Ok(jobs_by_name(all_jobs))
+}
+
+// cached jobs keep their job_name, as when a route inserts them
+fn jobs_by_name(jobs: Vec<VectorizeJob>) -> HashMap<String, VectorizeJob> {
+    jobs.into_iter()
+        .map(|job| (job.job_name.clone(), job))
+        .collect()
 }
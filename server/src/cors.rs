@@ -0,0 +1,115 @@
+use actix_cors::Cors;
+use actix_web::http::Method;
+use actix_web::http::header::HeaderName;
+use vectorize_core::config::Config;
+
+/// the CORS policy of the API
+/// only the configured origins may call it from a browser, unless permissive mode is enabled for development
+/// requests from other origins are still served, without the CORS headers a browser needs to read the response
+pub fn cors(config: &Config) -> Cors {
+    if config.cors_permissive {
+        return Cors::permissive();
+    }
+
+    let methods: Vec<Method> = config
+        .cors_allowed_methods
+        .iter()
+        .filter_map(|m| match Method::from_bytes(m.to_uppercase().as_bytes()) {
+            Ok(method) => Some(method),
+            Err(_) => {
+                tracing::warn!("ignoring invalid CORS method: {m}");
+                None
+            }
+        })
+        .collect();
+    let headers: Vec<HeaderName> = config
+        .cors_allowed_headers
+        .iter()
+        .filter_map(|h| match HeaderName::from_bytes(h.as_bytes()) {
+            Ok(header) => Some(header),
+            Err(_) => {
+                tracing::warn!("ignoring invalid CORS header: {h}");
+                None
+            }
+        })
+        .collect();
+
+    let mut cors = Cors::default()
+        .allowed_methods(methods)
+        .allowed_headers(headers)
+        .max_age(3600);
+    // lets browser clients read the request id of a response, e.g. to report a failed request
+    if let Ok(request_id) = HeaderName::from_bytes(config.request_id_header.as_bytes()) {
+        cors = cors.expose_headers([request_id]);
+    }
+    if config.cors_allowed_origins.iter().any(|o| o == "*") {
+        return cors.allow_any_origin();
+    }
+    let origins = config.cors_allowed_origins.clone();
+    if !origins.is_empty() {
+        cors = cors.allowed_origin_fn(move |origin, _| {
+            origin
+                .to_str()
+                .is_ok_and(|origin| origins.iter().any(|p| origin_matches(p, origin)))
+        });
+    }
+    cors
+}
+
+/// whether an Origin header matches an allowed origin
+/// a `*` in the pattern matches one or more subdomains, e.g. https://*.example.com
+/// matches https://app.example.com but not https://example.com
+pub fn origin_matches(pattern: &str, origin: &str) -> bool {
+    let pattern = pattern.trim_end_matches('/');
+    let Some((prefix, suffix)) = pattern.split_once('*') else {
+        return pattern.eq_ignore_ascii_case(origin);
+    };
+    let origin = origin.to_ascii_lowercase();
+    let (prefix, suffix) = (prefix.to_ascii_lowercase(), suffix.to_ascii_lowercase());
+    if origin.len() <= prefix.len() + suffix.len()
+        || !origin.starts_with(&prefix)
+        || !origin.ends_with(&suffix)
+    {
+        return false;
+    }
+    // only host name characters, so the wildcard can not swallow a scheme, port or path
+    let subdomains = &origin[prefix.len()..origin.len() - suffix.len()];
+    subdomains
+        .bytes()
+        .all(|c| c.is_ascii_alphanumeric() || c == b'-' || c == b'.')
+        && !subdomains.starts_with('.')
+        && !subdomains.ends_with('.')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_origin_matches() {
+        assert!(origin_matches(
+            "https://app.example.com",
+            "https://APP.example.com"
+        ));
+        assert!(origin_matches(
+            "https://*.example.com",
+            "https://a.b.example.com"
+        ));
+        assert!(!origin_matches(
+            "https://*.example.com",
+            "https://example.com"
+        ));
+        assert!(!origin_matches(
+            "https://*.example.com",
+            "http://app.example.com"
+        ));
+        assert!(!origin_matches(
+            "https://*.example.com",
+            "https://evil.com/.example.com"
+        ));
+        assert!(!origin_matches(
+            "https://*.example.com",
+            "https://app.example.com.evil.com"
+        ));
+    }
+}
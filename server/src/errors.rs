@@ -1,10 +1,15 @@
 use vectorize_core::errors;
 
-use actix_web::{HttpResponse, ResponseError, http::StatusCode, web::JsonConfig};
+use crate::request_id;
+
+use actix_web::{
+    HttpResponse, ResponseError,
+    http::StatusCode,
+    web::{JsonConfig, QueryConfig},
+};
 use anyhow::Error as AnyhowError;
 use pgmq::errors::PgmqError;
 use serde::Serialize;
-use serde::ser::SerializeMap;
 use serde_json;
 use sqlx;
 use thiserror::Error;
@@ -20,6 +25,8 @@ pub enum ServerError {
     DatabaseError(#[from] sqlx::Error),
     #[error("InvalidRequest: {0}")]
     InvalidRequest(String),
+    #[error("Conflict: {0}")]
+    Conflict(String),
     #[error("HTTP error: {0}")]
     Reqwest(#[from] reqwest::Error),
     // serde error
@@ -31,60 +38,123 @@ pub enum ServerError {
     PgmqError(#[from] PgmqError),
 }
 
-// public facing http errors
-#[derive(Error, Debug)]
-pub enum ErrorResponse {
-    #[error("{0}")]
-    NotFound(String),
-    #[error("{0}")]
-    Conflict(String),
-    #[error("{0}")]
-    NotAuthorized(String),
-    #[error("{0}")]
-    BadRequest(String),
-    #[error("{0}")]
-    InternalServerError(String),
+/// stable machine-readable error codes, clients should branch on these rather than the message
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    NotFound,
+    InvalidRequest,
+    Conflict,
+    /// the client exceeded its request rate limit
+    RateLimited,
+    /// the embedding or reranker provider failed
+    Provider,
+    Database,
+    Internal,
+}
+
+impl ErrorCode {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            ErrorCode::NotFound => StatusCode::NOT_FOUND,
+            ErrorCode::InvalidRequest => StatusCode::BAD_REQUEST,
+            ErrorCode::Conflict => StatusCode::CONFLICT,
+            ErrorCode::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            ErrorCode::Provider | ErrorCode::Database | ErrorCode::Internal => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+}
+
+// public facing http errors, rendered as {"error": {"code": "...", "message": "..."}}
+#[derive(Serialize, Debug, ToSchema)]
+pub struct ErrorResponse {
+    pub error: ErrorDetail,
 }
 
-// FOR DOC ONLY
-#[allow(dead_code)]
-#[derive(ToSchema)]
-pub struct ErrorResponseSchema {
-    pub error: String,
+#[derive(Serialize, Debug, ToSchema)]
+pub struct ErrorDetail {
+    pub code: ErrorCode,
+    pub message: String,
+    /// id of the failed request, to find its lines in the server logs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+impl ErrorResponse {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        ErrorResponse {
+            error: ErrorDetail {
+                code,
+                message: message.into(),
+                request_id: request_id::current(),
+            },
+        }
+    }
 }
 
-impl Serialize for ErrorResponse {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
-    {
-        let variant_str = format!("{self}");
-        let mut map = serializer.serialize_map(Some(1))?;
-        map.serialize_entry("error", &variant_str)?;
-        map.end()
+impl ServerError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ServerError::NotFoundError(_)
+            | ServerError::VectorizeError(errors::VectorizeError::NotFound(_))
+            | ServerError::VectorizeError(errors::VectorizeError::JobNotFound(_)) => {
+                ErrorCode::NotFound
+            }
+            ServerError::InvalidRequest(_)
+            | ServerError::VectorizeError(errors::VectorizeError::InvalidInput(_)) => {
+                ErrorCode::InvalidRequest
+            }
+            ServerError::Conflict(_) => ErrorCode::Conflict,
+            ServerError::Reqwest(_)
+            | ServerError::VectorizeError(
+                errors::VectorizeError::Reqwest(_)
+                | errors::VectorizeError::ProviderTimeout(_)
+                | errors::VectorizeError::OllamaError(_)
+                | errors::VectorizeError::ModelNotFound(_)
+                | errors::VectorizeError::EmbeddingGenerationFailed(_),
+            ) => ErrorCode::Provider,
+            ServerError::DatabaseError(_)
+            | ServerError::PgmqError(_)
+            | ServerError::VectorizeError(
+                errors::VectorizeError::DatabaseError(_)
+                | errors::VectorizeError::SqlError(_)
+                | errors::VectorizeError::PgmqError(_),
+            ) => ErrorCode::Database,
+            _ => ErrorCode::Internal,
+        }
+    }
+
+    // client errors carry their own message, server errors are only detailed in the logs
+    pub(crate) fn message(&self) -> String {
+        match self {
+            ServerError::NotFoundError(msg)
+            | ServerError::InvalidRequest(msg)
+            | ServerError::Conflict(msg) => msg.clone(),
+            ServerError::VectorizeError(e) if !self.code().status_code().is_server_error() => {
+                e.to_string()
+            }
+            _ => match self.code() {
+                ErrorCode::Provider => "Model provider error. Check server logs".to_string(),
+                ErrorCode::Database => "Database error. Check server logs".to_string(),
+                _ => "Internal Server Error. Check server logs".to_string(),
+            },
+        }
     }
 }
 
 impl ResponseError for ServerError {
     fn error_response(&self) -> HttpResponse {
-        let resp = match self {
-            ServerError::InvalidRequest(_) => ErrorResponse::BadRequest(self.to_string()),
-            ServerError::NotFoundError(_) => ErrorResponse::NotFound(self.to_string()),
-            _ => ErrorResponse::InternalServerError(
-                "Internal Server Error. Check server logs".to_string(),
-            ),
-        };
-        HttpResponse::build(self.status_code()).json(resp)
+        HttpResponse::build(self.status_code())
+            .json(ErrorResponse::new(self.code(), self.message()))
     }
     fn status_code(&self) -> StatusCode {
-        match *self {
-            ServerError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
-            ServerError::NotFoundError(_) => StatusCode::NOT_FOUND,
-            _ => {
-                tracing::error!("Internal Server Error: {self:?}");
-                StatusCode::INTERNAL_SERVER_ERROR
-            }
+        let status = self.code().status_code();
+        if status.is_server_error() {
+            tracing::error!("Internal Server Error: {self:?}");
         }
+        status
     }
 }
 
@@ -92,14 +162,24 @@ pub fn make_json_config() -> JsonConfig {
     use actix_web::error::InternalError;
 
     JsonConfig::default().error_handler(|error, _request| {
-        #[derive(Serialize)]
-        struct ErrorBody<T: Serialize> {
-            error: T,
-        }
+        let response = HttpResponse::BadRequest().json(ErrorResponse::new(
+            ErrorCode::InvalidRequest,
+            error.to_string(),
+        ));
+
+        InternalError::from_response(error, response).into()
+    })
+}
+
+// malformed query strings get the same error body as every other bad request
+pub fn make_query_config() -> QueryConfig {
+    use actix_web::error::InternalError;
 
-        let error_msg = error.to_string();
-        let error_body = ErrorBody { error: error_msg };
-        let response = HttpResponse::BadRequest().json(error_body);
+    QueryConfig::default().error_handler(|error, _request| {
+        let response = HttpResponse::BadRequest().json(ErrorResponse::new(
+            ErrorCode::InvalidRequest,
+            error.to_string(),
+        ));
 
         InternalError::from_response(error, response).into()
     })
@@ -1,5 +1,11 @@
 pub mod app_state;
 pub mod cache;
+pub mod cors;
 pub mod errors;
+pub mod logging;
+pub mod openapi;
+pub mod provider_key;
+pub mod rate_limit;
+pub mod request_id;
 pub mod routes;
 pub mod server;
@@ -0,0 +1,82 @@
+use tracing::Subscriber;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::util::SubscriberInitExt;
+
+/// installs the global subscriber, LOG_FORMAT is pretty (default) or json
+pub fn init(log_format: &str) {
+    match log_format {
+        "pretty" => tracing_subscriber::fmt().with_target(false).init(),
+        "json" => json_subscriber(std::io::stdout).init(),
+        other => panic!("invalid LOG_FORMAT: {other}, expected pretty or json"),
+    }
+}
+
+/// writes each event as one JSON object per line, with timestamp, level, target and message keys,
+/// the event's fields, e.g. job_name, msg_id, provider and latency_ms, and the fields of its
+/// spans, e.g. the request_id of an http request
+pub fn json_subscriber<W>(writer: W) -> impl Subscriber + Send + Sync
+where
+    W: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    tracing_subscriber::fmt()
+        .json()
+        .flatten_event(true)
+        .with_writer(writer)
+        .finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_log_format() {
+        // collects the formatted lines instead of writing them to stdout
+        #[derive(Clone, Default)]
+        struct Buffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+        impl std::io::Write for Buffer {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        tracing::subscriber::with_default(json_subscriber(move || writer.clone()), || {
+            tracing::info!(
+                job_name = "my_job",
+                msg_id = 7_i64,
+                provider = "openai",
+                latency_ms = 12_u64,
+                "embedded records"
+            );
+            let span = tracing::info_span!("request", request_id = "abc-123");
+            span.in_scope(|| {
+                tracing::error!(job_name = "my_job", error = %"timed out", "embedding request failed");
+            });
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("every line is a JSON object"))
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["level"], "INFO");
+        assert_eq!(lines[0]["message"], "embedded records");
+        assert_eq!(lines[0]["job_name"], "my_job");
+        assert_eq!(lines[0]["msg_id"], 7);
+        assert_eq!(lines[0]["provider"], "openai");
+        assert_eq!(lines[0]["latency_ms"], 12);
+        assert!(lines[0]["timestamp"].is_string());
+        assert_eq!(lines[1]["level"], "ERROR");
+        assert_eq!(lines[1]["error"], "timed out");
+        // span fields are kept
+        assert_eq!(lines[1]["span"]["request_id"], "abc-123");
+        assert_eq!(lines[1]["spans"][0]["name"], "request");
+    }
+}
@@ -1,18 +1,18 @@
-use actix_cors::Cors;
 use actix_web::{App, HttpServer, middleware, web};
 use std::time::Duration;
-use tracing::error;
+use tokio::sync::watch;
+use tracing::{error, info, warn};
 
 use vectorize_core::config::Config;
-use vectorize_proxy::start_postgres_proxy;
+use vectorize_proxy::{ConnectionLimits, TlsMode, start_postgres_proxy};
 use vectorize_server::app_state::AppState;
+use vectorize_worker::scheduler::start_scheduler;
 use vectorize_worker::{WorkerHealthMonitor, start_vectorize_worker_with_monitoring};
 
 #[actix_web::main]
 async fn main() {
-    tracing_subscriber::fmt().with_target(false).init();
-
     let cfg = Config::from_env();
+    vectorize_server::logging::init(&cfg.log_format);
 
     let app_state = AppState::new(cfg)
         .await
@@ -24,9 +24,32 @@ async fn main() {
         let database_url = app_state.config.database_url.clone();
         let job_cache = app_state.job_cache.clone();
         let db_pool = app_state.db_pool.clone();
+        let tls_mode: TlsMode = app_state
+            .config
+            .proxy_tls_mode
+            .parse()
+            .expect("invalid VECTORIZE_PROXY_TLS_MODE");
+        let ca_cert_path = app_state.config.proxy_tls_ca_cert.clone();
+        let limits = ConnectionLimits {
+            connect_timeout: Duration::from_secs(app_state.config.proxy_connect_timeout),
+            idle_timeout: match app_state.config.proxy_idle_timeout {
+                0 => None,
+                secs => Some(Duration::from_secs(secs)),
+            },
+            max_connections: app_state.config.proxy_max_connections,
+        };
 
         tokio::spawn(async move {
-            if let Err(e) = start_postgres_proxy(proxy_port, database_url, job_cache, db_pool).await
+            if let Err(e) = start_postgres_proxy(
+                proxy_port,
+                database_url,
+                job_cache,
+                db_pool,
+                tls_mode,
+                ca_cert_path,
+                limits,
+            )
+            .await
             {
                 error!("Failed to start PostgreSQL proxy: {e}");
             }
@@ -35,13 +58,18 @@ async fn main() {
 
     // start the vectorize worker with health monitoring
     let worker_state = app_state.clone();
-    let worker_health_monitor = WorkerHealthMonitor::new();
+    let worker_health_monitor = WorkerHealthMonitor::with_health(app_state.worker_health.clone());
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let worker_shutdown = shutdown_rx.clone();
 
-    tokio::spawn(async move {
+    let worker_handle = tokio::spawn(async move {
         if let Err(e) = start_vectorize_worker_with_monitoring(
             worker_state.config.clone(),
             worker_state.db_pool.clone(),
             worker_health_monitor,
+            worker_state.metrics.clone(),
+            worker_state.embedding_cache.clone(),
+            worker_shutdown,
         )
         .await
         {
@@ -49,24 +77,59 @@ async fn main() {
         }
     });
 
+    // rescan the tables of jobs with a cron schedule, the worker embeds the rows it enqueues
+    let scheduler_handle = tokio::spawn(start_scheduler(
+        app_state.config.clone(),
+        app_state.db_pool.clone(),
+        shutdown_rx,
+    ));
+
     // store values before moving app_state
     let server_workers = app_state.config.num_server_workers;
     let server_port = app_state.config.webserver_port;
+    let shutdown_timeout = app_state.config.shutdown_timeout;
+    if app_state.config.cors_permissive {
+        warn!("CORS_PERMISSIVE is enabled, any origin may call the API");
+    }
 
     let _ = HttpServer::new(move || {
-        let cors = Cors::permissive();
+        let cors = vectorize_server::cors::cors(&app_state.config);
 
         App::new()
             .wrap(cors)
             .wrap(middleware::Logger::default())
+            // outermost, so the access log line and CORS responses carry the request id too
+            .wrap(middleware::from_fn(
+                vectorize_server::request_id::request_id,
+            ))
             .app_data(web::Data::new(app_state.clone()))
+            .app_data(vectorize_server::errors::make_json_config())
+            .app_data(vectorize_server::errors::make_query_config())
             .configure(vectorize_server::server::route_config)
             .configure(vectorize_server::routes::health::configure_health_routes)
+            .configure(vectorize_server::openapi::configure_openapi_routes)
+            .service(vectorize_server::routes::metrics::metrics)
     })
     .workers(server_workers)
     .keep_alive(Duration::from_secs(75))
+    // actix stops accepting connections on SIGINT/SIGTERM and drains in-flight requests
+    .shutdown_timeout(shutdown_timeout)
     .bind(("0.0.0.0", server_port))
     .expect("Failed to bind server")
     .run()
     .await;
+
+    // then let the worker finish the messages it is processing
+    info!("HTTP server stopped, waiting for worker to finish in-flight jobs");
+    let _ = shutdown_tx.send(true);
+    let stopped = async {
+        let _ = scheduler_handle.await;
+        let _ = worker_handle.await;
+    };
+    if tokio::time::timeout(Duration::from_secs(shutdown_timeout), stopped)
+        .await
+        .is_err()
+    {
+        warn!("worker did not finish within {shutdown_timeout} seconds, exiting");
+    }
 }
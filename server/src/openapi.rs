@@ -0,0 +1,81 @@
+use actix_web::web;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::errors;
+use crate::routes::{embed, health, jobs, models, search, table};
+use vectorize_core::transformers::providers;
+use vectorize_core::types;
+
+/// the OpenAPI document of every annotated route, served at /openapi.json
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "pg_vectorize server"),
+    paths(
+        search::search,
+        search::search_json,
+        search::search_batch,
+        search::search_federated,
+        search::search_explain,
+        table::table,
+        table::delete_table,
+        jobs::list_jobs,
+        jobs::job_status,
+        jobs::job_progress_stream,
+        jobs::update_job,
+        jobs::reindex,
+        jobs::pause_job,
+        jobs::resume_job,
+        jobs::repair_job,
+        jobs::delete_record,
+        jobs::requeue_dead_letters,
+        models::list_models,
+        embed::embed,
+        health::health_check,
+        health::liveness_check,
+        health::readiness_check,
+        health::worker_health_check,
+    ),
+    components(schemas(
+        search::SearchRequest,
+        search::SearchRequestPOST,
+        search::FederatedSearchRequest,
+        search::EmptySearchResponse,
+        search::ExplainParam,
+        search::ExplainResponse,
+        table::CreateTableResponse,
+        table::DeleteJobResponse,
+        jobs::ReindexResponse,
+        jobs::PauseJobResponse,
+        jobs::RepairJobResponse,
+        jobs::DeleteRecordResponse,
+        jobs::UpdateJobRequest,
+        jobs::UpdateJobResponse,
+        jobs::JobStatusResponse,
+        jobs::ProgressStreamParams,
+        jobs::JobSummary,
+        jobs::RequeueDeadLettersResponse,
+        embed::EmbedRequest,
+        embed::EmbedResponse,
+        types::VectorizeJob,
+        types::Normalization,
+        types::IndexDist,
+        types::ConflictStrategy,
+        types::Modality,
+        types::VectorType,
+        types::Model,
+        types::ModelSource,
+        providers::ModelSourceInfo,
+        providers::KnownModel,
+        providers::DimensionLookup,
+        errors::ErrorResponse,
+        errors::ErrorDetail,
+        errors::ErrorCode,
+    ))
+)]
+pub struct ApiDoc;
+
+/// serves the OpenAPI document at /openapi.json and a Swagger UI for it at /docs
+pub fn configure_openapi_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(SwaggerUi::new("/docs/{_:.*}").url("/openapi.json", ApiDoc::openapi()));
+}
@@ -0,0 +1,42 @@
+use actix_web::{FromRequest, HttpRequest, dev::Payload};
+use std::fmt;
+use std::future::{Ready, ready};
+
+/// header carrying the caller's own key for the model provider, e.g. in multi-tenant deployments
+pub const PROVIDER_API_KEY_HEADER: &str = "X-Provider-Api-Key";
+
+/// a per-request model provider api key, None falls back to the server's configured key
+#[derive(Clone, Default)]
+pub struct ProviderApiKey(pub Option<String>);
+
+impl ProviderApiKey {
+    pub fn into_inner(self) -> Option<String> {
+        self.0
+    }
+}
+
+// the key must never end up in logs
+impl fmt::Debug for ProviderApiKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(_) => f.write_str("ProviderApiKey(<redacted>)"),
+            None => f.write_str("ProviderApiKey(None)"),
+        }
+    }
+}
+
+impl FromRequest for ProviderApiKey {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let key = req
+            .headers()
+            .get(PROVIDER_API_KEY_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string);
+        ready(Ok(ProviderApiKey(key)))
+    }
+}
@@ -0,0 +1,172 @@
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::{Method, header};
+use actix_web::middleware::Next;
+use actix_web::{HttpResponse, web};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use vectorize_core::config::Config;
+
+use crate::app_state::AppState;
+use crate::errors::{ErrorCode, ErrorResponse};
+
+// clients tracked at once, the ones whose window started first are forgotten beyond it
+const MAX_CLIENTS: usize = 10_000;
+
+/// fixed window request counter per client, a limit of 0 allows every request
+#[derive(Clone)]
+pub struct RateLimiter {
+    limit: u32,
+    window: Duration,
+    max_clients: usize,
+    hits: Arc<Mutex<Hits>>,
+}
+
+#[derive(Default)]
+struct Hits {
+    // client key -> (start of its current window, requests in it)
+    windows: HashMap<String, (Instant, u32)>,
+    // (window start, client key) in the order the windows started
+    starts: VecDeque<(Instant, String)>,
+}
+
+impl Hits {
+    // forgets the oldest window, unless its client has started a newer one since
+    fn pop_oldest(&mut self) {
+        if let Some((start, key)) = self.starts.pop_front()
+            && self.windows.get(&key).is_some_and(|(s, _)| *s == start)
+        {
+            self.windows.remove(&key);
+        }
+    }
+}
+
+impl RateLimiter {
+    pub fn new(limit: u32, window: Duration) -> Self {
+        Self::with_max_clients(limit, window, MAX_CLIENTS)
+    }
+
+    pub fn with_max_clients(limit: u32, window: Duration, max_clients: usize) -> Self {
+        RateLimiter {
+            limit,
+            window,
+            max_clients,
+            hits: Arc::new(Mutex::new(Hits::default())),
+        }
+    }
+
+    /// counts a request of the client, Err holds the time until its window resets
+    pub fn check(&self, key: &str) -> Result<(), Duration> {
+        if self.limit == 0 {
+            return Ok(());
+        }
+        let now = Instant::now();
+        let mut hits = self.hits.lock().unwrap_or_else(|e| e.into_inner());
+        // windows are dropped in the order they started, so this stays O(1) per request on average
+        while hits
+            .starts
+            .front()
+            .is_some_and(|(start, _)| now.duration_since(*start) >= self.window)
+        {
+            hits.pop_oldest();
+        }
+        let active = hits
+            .windows
+            .get(key)
+            .is_some_and(|(start, _)| now.duration_since(*start) < self.window);
+        if !active {
+            while hits.windows.len() >= self.max_clients && !hits.starts.is_empty() {
+                hits.pop_oldest();
+            }
+            hits.windows.insert(key.to_string(), (now, 0));
+            hits.starts.push_back((now, key.to_string()));
+        }
+        let entry = hits.windows.get_mut(key).expect("window was just inserted");
+        if entry.1 >= self.limit {
+            return Err(self.window - now.duration_since(entry.0));
+        }
+        entry.1 += 1;
+        Ok(())
+    }
+
+    /// clients with a window in memory
+    pub fn tracked_clients(&self) -> usize {
+        self.hits
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .windows
+            .len()
+    }
+}
+
+/// the limiters of the rate limited routes, job creation writes and is limited separately from search
+#[derive(Clone)]
+pub struct RateLimits {
+    pub search: RateLimiter,
+    pub table: RateLimiter,
+}
+
+impl RateLimits {
+    pub fn from_config(config: &Config) -> Self {
+        let window = Duration::from_secs(config.rate_limit_window);
+        RateLimits {
+            search: RateLimiter::new(config.search_rate_limit, window),
+            table: RateLimiter::new(config.table_rate_limit, window),
+        }
+    }
+}
+
+// no limits
+impl Default for RateLimits {
+    fn default() -> Self {
+        RateLimits {
+            search: RateLimiter::new(0, Duration::ZERO),
+            table: RateLimiter::new(0, Duration::ZERO),
+        }
+    }
+}
+
+// requests are counted per client ip, headers are not trusted since a client can vary them freely
+fn client_key(req: &ServiceRequest) -> String {
+    match req.peer_addr() {
+        Some(addr) => format!("ip:{}", addr.ip()),
+        None => "ip:unknown".to_string(),
+    }
+}
+
+/// middleware rejecting search, embed and job creation requests over the client's limit with 429
+pub async fn rate_limit(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let limiter = req.app_data::<web::Data<AppState>>().and_then(|state| {
+        let path = req.path();
+        // embedding texts calls the model provider just like a search does
+        if path.starts_with("/api/v1/search") || path == "/api/v1/embed" {
+            Some(state.rate_limits.search.clone())
+        } else if path == "/api/v1/table" && req.method() == Method::POST {
+            Some(state.rate_limits.table.clone())
+        } else {
+            None
+        }
+    });
+
+    if let Some(limiter) = limiter
+        && let Err(retry_after) = limiter.check(&client_key(&req))
+    {
+        // round up so clients never retry before the window has reset
+        let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+        let response = HttpResponse::TooManyRequests()
+            .insert_header((header::RETRY_AFTER, secs.to_string()))
+            .json(ErrorResponse::new(
+                ErrorCode::RateLimited,
+                format!("Rate limit exceeded, retry after {secs} seconds"),
+            ));
+        return Ok(req.into_response(response));
+    }
+
+    next.call(req)
+        .await
+        .map(ServiceResponse::map_into_boxed_body)
+}
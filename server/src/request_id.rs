@@ -0,0 +1,64 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::web;
+use tracing::Instrument;
+
+use crate::app_state::AppState;
+
+pub const DEFAULT_REQUEST_ID_HEADER: &str = "x-request-id";
+
+// longer or otherwise malformed ids are replaced, they end up in logs and response headers
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// id of the request being handled on this task, none outside of a request
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// whether a client supplied id is kept, letters, digits and `-_.:` up to 128 characters
+pub fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+}
+
+/// middleware reading the request id header, or generating a uuid when it is missing or invalid
+/// the request is handled within a tracing span carrying the id, and the response echoes it
+pub async fn request_id(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let header_name = req
+        .app_data::<web::Data<AppState>>()
+        .and_then(|state| HeaderName::from_bytes(state.config.request_id_header.as_bytes()).ok())
+        .unwrap_or(HeaderName::from_static(DEFAULT_REQUEST_ID_HEADER));
+    let id = req
+        .headers()
+        .get(&header_name)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| is_valid_request_id(id))
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    let span = tracing::info_span!(
+        "request",
+        request_id = %id,
+        method = %req.method(),
+        path = %req.path()
+    );
+    let mut res = REQUEST_ID
+        .scope(id.clone(), next.call(req).instrument(span))
+        .await?;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        res.headers_mut().insert(header_name, value);
+    }
+    Ok(res)
+}
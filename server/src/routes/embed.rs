@@ -0,0 +1,104 @@
+use crate::app_state::AppState;
+use crate::errors::ServerError;
+use crate::provider_key::ProviderApiKey;
+use actix_web::{HttpResponse, post, web};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use vectorize_core::transformers::providers::cache::generate_embeddings_cached;
+use vectorize_core::transformers::providers::{InputType, get_provider};
+use vectorize_core::transformers::tokenizer::Tokenizer;
+use vectorize_core::transformers::types::{Inputs, truncate_inputs};
+use vectorize_core::types::Model;
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct EmbedRequest {
+    /// embedding model, e.g. sentence-transformers/all-MiniLM-L6-v2
+    pub model: String,
+    /// texts to embed, at most MAX_EMBED_INPUTS
+    pub inputs: Vec<String>,
+    /// document (default) or query, asymmetric models embed the two differently
+    #[serde(default)]
+    #[schema(value_type = String)]
+    pub input_type: InputType,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct EmbedResponse {
+    pub model: String,
+    /// one embedding per input, in input order
+    pub embeddings: Vec<Vec<f64>>,
+}
+
+/// POST /embed: Embeds texts with a model without creating a job
+#[utoipa::path(
+    context_path = "/api/v1",
+    request_body = EmbedRequest,
+    params(
+        ("X-Provider-Api-Key" = Option<String>, Header, description = "Optional model provider api key for this request, overrides the server's configured key"),
+    ),
+    responses(
+        (
+            status = 200, description = "The embedding of each input, in input order",
+            body = EmbedResponse,
+        ),
+        (
+            status = 400, description = "Invalid model, no inputs or more than MAX_EMBED_INPUTS inputs",
+        ),
+    ),
+)]
+#[post("/embed")]
+pub async fn embed(
+    app_state: web::Data<AppState>,
+    payload: web::Json<EmbedRequest>,
+    api_key: ProviderApiKey,
+) -> Result<HttpResponse, ServerError> {
+    let payload = payload.into_inner();
+    let max_inputs = app_state.config.max_embed_inputs;
+    if payload.inputs.is_empty() {
+        return Err(ServerError::InvalidRequest(
+            "inputs must contain at least one text".to_string(),
+        ));
+    }
+    if payload.inputs.len() > max_inputs {
+        return Err(ServerError::InvalidRequest(format!(
+            "inputs contains {} texts, at most {max_inputs} are allowed per request",
+            payload.inputs.len()
+        )));
+    }
+    let model =
+        Model::new(&payload.model).map_err(|e| ServerError::InvalidRequest(e.to_string()))?;
+    let provider = get_provider(&model.source, api_key.into_inner(), None, None)?;
+
+    // token estimates split the inputs into requests the provider accepts
+    let tokenizer = Tokenizer::for_model(&model);
+    let mut inputs: Vec<Inputs> = payload
+        .inputs
+        .into_iter()
+        .enumerate()
+        .map(|(idx, text)| Inputs {
+            record_id: idx.to_string(),
+            token_estimate: tokenizer.count(&text) as i32,
+            inputs: text,
+        })
+        .collect();
+    let max_tokens = app_state.config.max_input_tokens;
+    let truncated = truncate_inputs(&mut inputs, &tokenizer, max_tokens);
+    if truncated > 0 {
+        tracing::warn!("truncated {truncated} inputs to {max_tokens} tokens of model {model}");
+    }
+
+    let embeddings = generate_embeddings_cached(
+        &app_state.embedding_cache,
+        provider.as_ref(),
+        &model,
+        &inputs,
+        payload.input_type,
+        app_state.config.embedding_batch_max_tokens,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(EmbedResponse {
+        model: model.to_string(),
+        embeddings,
+    }))
+}
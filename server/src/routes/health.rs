@@ -1,8 +1,18 @@
 use crate::app_state::AppState;
 use actix_web::{HttpResponse, Result, web};
 use serde_json::json;
-use std::time::SystemTime;
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime};
+use vectorize_worker::WorkerHealth;
 
+#[utoipa::path(
+    get,
+    path = "/health",
+    responses(
+        (status = 200, description = "Worker and database pool status", body = serde_json::Value),
+        (status = 503, description = "The worker is not running or has not started within two minutes", body = serde_json::Value),
+    ),
+)]
 pub async fn health_check(app_state: web::Data<AppState>) -> Result<HttpResponse> {
     let health = app_state.worker_health.read().await;
     let is_healthy = match &health.status {
@@ -20,17 +30,42 @@ pub async fn health_check(app_state: web::Data<AppState>) -> Result<HttpResponse
 
     let response = json!({
         "status": if is_healthy { "healthy" } else { "unhealthy" },
-        "worker": {
-            "status": format!("{:?}", health.status),
-            "last_heartbeat": health.last_heartbeat
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs(),
-            "jobs_processed": health.jobs_processed,
-            "uptime_seconds": health.uptime.as_secs(),
-            "restart_count": health.restart_count,
-            "last_error": health.last_error
-        },
+        "worker": worker_json(&health),
+        "database_pools": app_state.pool_stats().into_iter().collect::<BTreeMap<_, _>>(),
+        "timestamp": SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+    });
+
+    if is_healthy {
+        Ok(HttpResponse::Ok().json(response))
+    } else {
+        Ok(HttpResponse::ServiceUnavailable().json(response))
+    }
+}
+
+/// reports unhealthy unless the worker is running and has sent a heartbeat within the timeout
+#[utoipa::path(
+    get,
+    path = "/health/worker",
+    responses(
+        (status = 200, description = "The worker is running and sent a heartbeat within the heartbeat timeout", body = serde_json::Value),
+        (status = 503, description = "The worker is not running or its last heartbeat is too old", body = serde_json::Value),
+    ),
+)]
+pub async fn worker_health_check(app_state: web::Data<AppState>) -> Result<HttpResponse> {
+    let health = app_state.worker_health.read().await;
+    let heartbeat_age = health.last_heartbeat.elapsed().unwrap_or_default();
+    let heartbeat_timeout = Duration::from_secs(app_state.config.worker_heartbeat_timeout);
+    let is_healthy = matches!(health.status, vectorize_worker::WorkerStatus::Healthy)
+        && heartbeat_age < heartbeat_timeout;
+
+    let response = json!({
+        "status": if is_healthy { "healthy" } else { "unhealthy" },
+        "worker": worker_json(&health),
+        "heartbeat_age_seconds": heartbeat_age.as_secs(),
+        "heartbeat_timeout_seconds": heartbeat_timeout.as_secs(),
         "timestamp": SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
             .unwrap_or_default()
@@ -44,6 +79,27 @@ pub async fn health_check(app_state: web::Data<AppState>) -> Result<HttpResponse
     }
 }
 
+fn worker_json(health: &WorkerHealth) -> serde_json::Value {
+    json!({
+        "status": format!("{:?}", health.status),
+        "last_heartbeat": health.last_heartbeat
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+        "jobs_processed": health.jobs_processed,
+        "uptime_seconds": health.uptime.as_secs(),
+        "restart_count": health.restart_count,
+        "last_error": health.last_error
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/health/live",
+    responses(
+        (status = 200, description = "The server is accepting requests", body = serde_json::Value),
+    ),
+)]
 pub async fn liveness_check() -> Result<HttpResponse> {
     Ok(HttpResponse::Ok().json(json!({
         "status": "alive",
@@ -54,6 +110,14 @@ pub async fn liveness_check() -> Result<HttpResponse> {
     })))
 }
 
+#[utoipa::path(
+    get,
+    path = "/health/ready",
+    responses(
+        (status = 200, description = "The worker is running", body = serde_json::Value),
+        (status = 503, description = "The worker is not running yet", body = serde_json::Value),
+    ),
+)]
 pub async fn readiness_check(app_state: web::Data<AppState>) -> Result<HttpResponse> {
     let health = app_state.worker_health.read().await;
     let is_ready = matches!(health.status, vectorize_worker::WorkerStatus::Healthy);
@@ -79,6 +143,7 @@ pub fn configure_health_routes(cfg: &mut web::ServiceConfig) {
         web::scope("/health")
             .route("", web::get().to(health_check))
             .route("/live", web::get().to(liveness_check))
-            .route("/ready", web::get().to(readiness_check)),
+            .route("/ready", web::get().to(readiness_check))
+            .route("/worker", web::get().to(worker_health_check)),
     );
 }
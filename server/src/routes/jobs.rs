@@ -0,0 +1,643 @@
+use crate::app_state::AppState;
+use crate::errors::{ErrorResponse, ServerError};
+use crate::provider_key::ProviderApiKey;
+use crate::routes::search::get_cached_job;
+use actix_web::http::header;
+use actix_web::{HttpResponse, delete, get, patch, post, web};
+use futures::stream;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use utoipa::{IntoParams, ToSchema};
+use vectorize_core::errors::VectorizeError;
+use vectorize_core::types::{Model, VectorizeJob};
+use vectorize_core::{db, init, query};
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema, IntoParams)]
+pub struct ReindexParams {
+    /// delete all existing embeddings before re-enqueueing the table
+    #[serde(default)]
+    pub truncate: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ReindexResponse {
+    pub job_name: String,
+    pub batches_enqueued: usize,
+}
+
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(ReindexParams),
+    responses(
+        (
+            status = 200, description = "Re-enqueued every row in the job's source table",
+            body = ReindexResponse,
+        ),
+        (
+            status = 404, description = "Job not found",
+        ),
+    ),
+)]
+#[post("/jobs/{job_name}/reindex")]
+pub async fn reindex(
+    app_state: web::Data<AppState>,
+    job_name: web::Path<String>,
+    params: web::Query<ReindexParams>,
+) -> Result<HttpResponse, ServerError> {
+    let job_name = job_name.into_inner();
+    query::check_input(&job_name)?;
+
+    let job = db::get_vectorize_job(&app_state.db_pool, &job_name)
+        .await
+        .map_err(|e| match e {
+            VectorizeError::SqlError(sqlx::Error::RowNotFound) => {
+                ServerError::NotFoundError(format!("Job '{}' not found", job_name))
+            }
+            _ => ServerError::from(e),
+        })?;
+
+    if params.truncate {
+        sqlx::query(&query::truncate_embeddings_table(
+            &job_name,
+            &job.table_prefixes,
+            &job.target_schema,
+        ))
+        .execute(&app_state.db_pool)
+        .await?;
+    }
+
+    let batches_enqueued = init::scan_job(
+        &app_state.db_pool,
+        &job,
+        true,
+        app_state.config.scan_batch_size,
+        &init::JobQueue::from_config(&app_state.config),
+    )
+    .await?;
+    tracing::info!("reindexing job: {job_name}, enqueued {batches_enqueued} batches");
+
+    Ok(HttpResponse::Ok().json(ReindexResponse {
+        job_name,
+        batches_enqueued,
+    }))
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct PauseJobResponse {
+    pub job_name: String,
+    pub paused: bool,
+}
+
+#[utoipa::path(
+    context_path = "/api/v1",
+    responses(
+        (
+            status = 200, description = "Paused the job, its changes stay queued until it is resumed",
+            body = PauseJobResponse,
+        ),
+        (
+            status = 404, description = "Job not found",
+        ),
+    ),
+)]
+#[post("/jobs/{job_name}/pause")]
+pub async fn pause_job(
+    app_state: web::Data<AppState>,
+    job_name: web::Path<String>,
+) -> Result<HttpResponse, ServerError> {
+    set_paused(app_state, job_name.into_inner(), true).await
+}
+
+#[utoipa::path(
+    context_path = "/api/v1",
+    responses(
+        (
+            status = 200, description = "Resumed the job, the changes queued while it was paused are embedded",
+            body = PauseJobResponse,
+        ),
+        (
+            status = 404, description = "Job not found",
+        ),
+    ),
+)]
+#[post("/jobs/{job_name}/resume")]
+pub async fn resume_job(
+    app_state: web::Data<AppState>,
+    job_name: web::Path<String>,
+) -> Result<HttpResponse, ServerError> {
+    set_paused(app_state, job_name.into_inner(), false).await
+}
+
+async fn set_paused(
+    app_state: web::Data<AppState>,
+    job_name: String,
+    paused: bool,
+) -> Result<HttpResponse, ServerError> {
+    query::check_input(&job_name)?;
+    init::set_job_paused(
+        &app_state.db_pool,
+        &job_name,
+        paused,
+        &app_state.config.queue_name,
+    )
+    .await?;
+    tracing::info!("set paused = {paused} for job: {job_name}");
+
+    Ok(HttpResponse::Ok().json(PauseJobResponse { job_name, paused }))
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct RepairJobResponse {
+    pub job_name: String,
+    /// the missing objects that were recreated, e.g. view or triggers, empty when none were missing
+    pub recreated: Vec<String>,
+    /// batches enqueued to embed the rows again when the embeddings table was recreated
+    pub batches_enqueued: usize,
+}
+
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(
+        ("X-Provider-Api-Key" = Option<String>, Header, description = "Optional model provider api key for this request, overrides the server's configured key"),
+    ),
+    responses(
+        (
+            status = 200, description = "Recreated the job's missing tables, view, indexes and triggers",
+            body = RepairJobResponse,
+        ),
+        (
+            status = 404, description = "Job not found",
+        ),
+    ),
+)]
+#[post("/jobs/{job_name}/repair")]
+pub async fn repair_job(
+    app_state: web::Data<AppState>,
+    job_name: web::Path<String>,
+    api_key: ProviderApiKey,
+) -> Result<HttpResponse, ServerError> {
+    let job_name = job_name.into_inner();
+    query::check_input(&job_name)?;
+
+    let job = db::get_vectorize_job(&app_state.db_pool, &job_name)
+        .await
+        .map_err(|e| match e {
+            VectorizeError::SqlError(sqlx::Error::RowNotFound) => {
+                ServerError::NotFoundError(format!("Job '{}' not found", job_name))
+            }
+            _ => ServerError::from(e),
+        })?;
+
+    let repaired = init::repair_job(
+        &app_state.db_pool,
+        &job,
+        app_state.config.scan_batch_size,
+        api_key.into_inner(),
+        &init::JobQueue::from_config(&app_state.config),
+    )
+    .await?;
+    tracing::info!(
+        "repaired job: {job_name}, recreated {:?}, enqueued {} batches",
+        repaired.recreated,
+        repaired.batches_enqueued
+    );
+
+    Ok(HttpResponse::Ok().json(RepairJobResponse {
+        job_name,
+        recreated: repaired.recreated,
+        batches_enqueued: repaired.batches_enqueued,
+    }))
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct DeleteRecordResponse {
+    pub job_name: String,
+    pub record_id: String,
+    /// false when the record had no embedding
+    pub deleted: bool,
+}
+
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(
+        ("job_name" = String, Path, description = "Name of the vectorize job"),
+        ("record_id" = String, Path, description = "Primary key of the record, a JSON array of the key values for a composite primary key"),
+    ),
+    responses(
+        (
+            status = 200, description = "Deleted the record's embedding and search tokens",
+            body = DeleteRecordResponse,
+        ),
+        (
+            status = 400, description = "The record id does not match the type of the primary key",
+        ),
+        (
+            status = 404, description = "Job not found",
+        ),
+    ),
+)]
+#[delete("/jobs/{job_name}/records/{record_id}")]
+pub async fn delete_record(
+    app_state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, ServerError> {
+    let (job_name, record_id) = path.into_inner();
+    query::check_input(&job_name)?;
+    let job = get_cached_job(&app_state, &job_name).await?;
+
+    let deleted = init::delete_record(&app_state.db_pool, &job, &record_id).await?;
+    tracing::info!("deleted record {record_id} of job: {job_name}, had an embedding: {deleted}");
+
+    Ok(HttpResponse::Ok().json(DeleteRecordResponse {
+        job_name,
+        record_id,
+        deleted,
+    }))
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct UpdateJobRequest {
+    /// new embedding model, e.g. sentence-transformers/all-mpnet-base-v2
+    pub model: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct UpdateJobResponse {
+    pub job_name: String,
+    pub model: String,
+    /// dimension of the embeddings produced by the new model
+    pub model_dim: u32,
+    pub batches_enqueued: usize,
+}
+
+#[utoipa::path(
+    context_path = "/api/v1",
+    request_body = UpdateJobRequest,
+    params(
+        ("X-Provider-Api-Key" = Option<String>, Header, description = "Optional model provider api key for this request, overrides the server's configured key"),
+    ),
+    responses(
+        (
+            status = 200, description = "Switched the job to the new model and re-enqueued every row",
+            body = UpdateJobResponse,
+        ),
+        (
+            status = 400, description = "Invalid model",
+        ),
+        (
+            status = 404, description = "Job not found",
+        ),
+    ),
+)]
+#[patch("/jobs/{job_name}")]
+pub async fn update_job(
+    app_state: web::Data<AppState>,
+    job_name: web::Path<String>,
+    payload: web::Json<UpdateJobRequest>,
+    api_key: ProviderApiKey,
+) -> Result<HttpResponse, ServerError> {
+    let job_name = job_name.into_inner();
+    query::check_input(&job_name)?;
+    let model =
+        Model::new(&payload.model).map_err(|e| ServerError::InvalidRequest(e.to_string()))?;
+
+    let job = db::get_vectorize_job(&app_state.db_pool, &job_name)
+        .await
+        .map_err(|e| match e {
+            VectorizeError::SqlError(sqlx::Error::RowNotFound) => {
+                ServerError::NotFoundError(format!("Job '{}' not found", job_name))
+            }
+            _ => ServerError::from(e),
+        })?;
+
+    let updated = init::update_job_model(
+        &app_state.db_pool,
+        &job,
+        &model,
+        app_state.config.scan_batch_size,
+        api_key.into_inner(),
+        &init::JobQueue::from_config(&app_state.config),
+    )
+    .await?;
+    tracing::info!(
+        "updated model of job: {job_name} to {model}, enqueued {} batches",
+        updated.batches_enqueued
+    );
+
+    {
+        let mut job_cache = app_state.job_cache.write().await;
+        job_cache.insert(
+            job_name.clone(),
+            VectorizeJob {
+                model: model.clone(),
+                ..job
+            },
+        );
+    }
+
+    Ok(HttpResponse::Ok().json(UpdateJobResponse {
+        job_name,
+        model: model.to_string(),
+        model_dim: updated.model_dim,
+        batches_enqueued: updated.batches_enqueued,
+    }))
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct JobStatusResponse {
+    pub job_name: String,
+    /// rows in the job's source table
+    pub total_rows: i64,
+    /// rows that have an embedding
+    pub embedded_rows: i64,
+    /// queued messages for the job that the worker has not processed yet
+    pub pending_messages: i64,
+    /// embedded_rows as a percentage of total_rows
+    pub percent_complete: f64,
+    /// when an embedding was last written, RFC 3339
+    pub last_updated_at: Option<String>,
+    /// when the worker last finished a batch of the job, RFC 3339
+    pub last_completion: Option<String>,
+    /// true while the job's processing is paused
+    pub paused: bool,
+}
+
+#[utoipa::path(
+    context_path = "/api/v1",
+    responses(
+        (
+            status = 200, description = "Embedding progress of the job",
+            body = JobStatusResponse,
+        ),
+        (
+            status = 404, description = "Job not found",
+        ),
+    ),
+)]
+#[get("/jobs/{job_name}/status")]
+pub async fn job_status(
+    app_state: web::Data<AppState>,
+    job_name: web::Path<String>,
+) -> Result<HttpResponse, ServerError> {
+    let job_name = job_name.into_inner();
+    query::check_input(&job_name)?;
+    let status = fetch_job_status(&app_state, &job_name).await?;
+    Ok(HttpResponse::Ok().json(status))
+}
+
+// the embedding progress of the job, 404 when it does not exist
+async fn fetch_job_status(
+    app_state: &web::Data<AppState>,
+    job_name: &str,
+) -> Result<JobStatusResponse, ServerError> {
+    let job = get_cached_job(app_state, job_name).await?;
+
+    #[derive(sqlx::FromRow)]
+    struct Status {
+        total_rows: i64,
+        embedded_rows: i64,
+        last_updated_at: Option<String>,
+        pending_messages: i64,
+        last_completion: Option<String>,
+        paused: bool,
+    }
+
+    let status: Status = sqlx::query_as(&query::job_status_query(
+        job_name,
+        &job.table_prefixes,
+        &job.src_schema,
+        &job.src_table,
+        &app_state.config.queue_name,
+        &job.target_schema,
+    ))
+    .bind(job_name)
+    .fetch_one(&app_state.db_pool)
+    .await?;
+
+    let percent_complete = if status.total_rows == 0 {
+        100.0
+    } else {
+        status.embedded_rows as f64 / status.total_rows as f64 * 100.0
+    };
+
+    Ok(JobStatusResponse {
+        job_name: job_name.to_string(),
+        total_rows: status.total_rows,
+        embedded_rows: status.embedded_rows,
+        pending_messages: status.pending_messages,
+        percent_complete,
+        last_updated_at: status.last_updated_at,
+        last_completion: status.last_completion,
+        paused: status.paused,
+    })
+}
+
+// messages still queued for the job, without counting its rows
+async fn fetch_pending_messages(
+    app_state: &web::Data<AppState>,
+    job_name: &str,
+) -> Result<i64, ServerError> {
+    let pending: i64 = sqlx::query_scalar(&query::job_pending_messages_query(
+        &app_state.config.queue_name,
+    ))
+    .bind(job_name)
+    .fetch_one(&app_state.db_pool)
+    .await?;
+    Ok(pending)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema, IntoParams)]
+pub struct ProgressStreamParams {
+    /// milliseconds between progress events, at least 100
+    #[serde(default = "default_progress_interval_ms")]
+    pub interval_ms: u64,
+    /// seconds before the stream closes with a `timeout` event, at least 1
+    #[serde(default = "default_progress_max_duration_secs")]
+    pub max_duration_secs: u64,
+}
+
+fn default_progress_interval_ms() -> u64 {
+    1000
+}
+
+fn default_progress_max_duration_secs() -> u64 {
+    3600
+}
+
+// row counts are re-read every this many events, the events between only re-read the queue depth
+const PROGRESS_REFRESH_EVENTS: u64 = 10;
+
+enum Progress {
+    Poll {
+        status: JobStatusResponse,
+        sent: u64,
+    },
+    Done,
+}
+
+fn sse_event(event: &str, data: &impl Serialize) -> web::Bytes {
+    let data = serde_json::to_string(data).unwrap_or_default();
+    web::Bytes::from(format!("event: {event}\ndata: {data}\n\n"))
+}
+
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(ProgressStreamParams),
+    responses(
+        (
+            status = 200,
+            description = "Server-sent events: `progress` with a JobStatusResponse every interval, \
+                then `complete` with the final status once no messages for the job are queued, \
+                `timeout` after max_duration_secs, or `error`; the stream closes after any of them",
+            content_type = "text/event-stream",
+            body = JobStatusResponse,
+        ),
+        (
+            status = 404, description = "Job not found",
+        ),
+    ),
+)]
+#[get("/jobs/{job_name}/progress/stream")]
+pub async fn job_progress_stream(
+    app_state: web::Data<AppState>,
+    job_name: web::Path<String>,
+    params: web::Query<ProgressStreamParams>,
+) -> Result<HttpResponse, ServerError> {
+    let job_name = job_name.into_inner();
+    query::check_input(&job_name)?;
+    // an unknown job is rejected before the stream starts
+    let first = fetch_job_status(&app_state, &job_name).await?;
+    let interval = Duration::from_millis(params.interval_ms.max(100));
+    let deadline =
+        tokio::time::Instant::now() + Duration::from_secs(params.max_duration_secs.max(1));
+
+    let initial = Progress::Poll {
+        status: first,
+        sent: 0,
+    };
+    let events = stream::unfold(initial, move |progress| {
+        let app_state = app_state.clone();
+        let job_name = job_name.clone();
+        async move {
+            let Progress::Poll { mut status, sent } = progress else {
+                return None;
+            };
+            if sent > 0 {
+                if tokio::time::Instant::now() >= deadline {
+                    return Some((Ok(sse_event("timeout", &status)), Progress::Done));
+                }
+                tokio::time::sleep(interval).await;
+                let refreshed = match fetch_pending_messages(&app_state, &job_name).await {
+                    Ok(pending) if pending == 0 || sent % PROGRESS_REFRESH_EVENTS == 0 => {
+                        fetch_job_status(&app_state, &job_name).await
+                    }
+                    Ok(pending) => {
+                        status.pending_messages = pending;
+                        Ok(status)
+                    }
+                    Err(e) => Err(e),
+                };
+                status = match refreshed {
+                    Ok(status) => status,
+                    Err(e) => {
+                        tracing::error!("progress stream of job {job_name} failed: {e:?}");
+                        let body = ErrorResponse::new(e.code(), e.message());
+                        return Some((Ok(sse_event("error", &body)), Progress::Done));
+                    }
+                };
+            }
+            // rows that fail to embed never get an embedding, so the job is done once its queue drains
+            let (event, next) = if status.pending_messages == 0 {
+                (sse_event("complete", &status), Progress::Done)
+            } else {
+                let sent = sent + 1;
+                (
+                    sse_event("progress", &status),
+                    Progress::Poll { status, sent },
+                )
+            };
+            Some((Ok::<_, actix_web::Error>(event), next))
+        }
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .streaming(events))
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema, sqlx::FromRow)]
+pub struct JobSummary {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub job: VectorizeJob,
+    /// when the worker last finished a batch of the job, RFC 3339
+    pub last_completion: Option<String>,
+    /// true while the job's processing is paused
+    pub paused: bool,
+}
+
+#[utoipa::path(
+    context_path = "/api/v1",
+    responses(
+        (
+            status = 200, description = "Every vectorize job",
+            body = Vec<JobSummary>,
+        ),
+    ),
+)]
+#[get("/jobs")]
+pub async fn list_jobs(app_state: web::Data<AppState>) -> Result<HttpResponse, ServerError> {
+    let jobs: Vec<JobSummary> = sqlx::query_as(&format!(
+        "SELECT {},
+            to_json(last_completion) #>> '{{}}' AS last_completion,
+            paused
+         FROM vectorize.job
+         ORDER BY job_name",
+        db::JOB_COLUMNS
+    ))
+    .fetch_all(&app_state.db_pool)
+    .await?;
+    Ok(HttpResponse::Ok().json(jobs))
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema, IntoParams)]
+pub struct RequeueDeadLettersParams {
+    /// the most messages to move back onto the job queue
+    #[serde(default = "default_max_messages")]
+    pub max_messages: usize,
+}
+
+fn default_max_messages() -> usize {
+    1000
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct RequeueDeadLettersResponse {
+    pub requeued: usize,
+}
+
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(RequeueDeadLettersParams),
+    responses(
+        (
+            status = 200, description = "Moved messages from the dead letter queue back onto the job queue",
+            body = RequeueDeadLettersResponse,
+        ),
+    ),
+)]
+#[post("/dead_letters/requeue")]
+pub async fn requeue_dead_letters(
+    app_state: web::Data<AppState>,
+    params: web::Query<RequeueDeadLettersParams>,
+) -> Result<HttpResponse, ServerError> {
+    let queue = pgmq::PGMQueueExt::new_with_pool(app_state.db_pool.clone()).await;
+    let requeued = vectorize_worker::executor::requeue_dead_letters(
+        &queue,
+        &app_state.config.queue_name,
+        params.max_messages,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(RequeueDeadLettersResponse { requeued }))
+}
@@ -0,0 +1,40 @@
+use crate::app_state::AppState;
+use crate::errors::ServerError;
+use actix_web::{HttpResponse, get, web};
+
+/// prometheus metrics for the worker and job queue
+#[get("/metrics")]
+pub async fn metrics(app_state: web::Data<AppState>) -> Result<HttpResponse, ServerError> {
+    let queue_name = &app_state.config.queue_name;
+    // queue depth is read at scrape time rather than tracked by the worker
+    let queue_length: Option<i64> = sqlx::query_scalar("SELECT queue_length FROM pgmq.metrics($1)")
+        .bind(queue_name)
+        .fetch_optional(&app_state.db_pool)
+        .await?;
+    app_state
+        .metrics
+        .queue_depth
+        .with_label_values(&[queue_name])
+        .set(queue_length.unwrap_or_default());
+    for (pool, stats) in app_state.pool_stats() {
+        for (state, count) in [
+            ("size", stats.size),
+            ("idle", stats.idle),
+            ("in_use", stats.in_use),
+        ] {
+            app_state
+                .metrics
+                .db_pool_connections
+                .with_label_values(&[pool, state])
+                .set(count as i64);
+        }
+    }
+
+    let body = app_state
+        .metrics
+        .render()
+        .map_err(|e| ServerError::InternalError(anyhow::anyhow!(e)))?;
+    Ok(HttpResponse::Ok()
+        .content_type(prometheus::TEXT_FORMAT)
+        .body(body))
+}
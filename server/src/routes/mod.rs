@@ -1,3 +1,7 @@
+pub mod embed;
 pub mod health;
+pub mod jobs;
+pub mod metrics;
+pub mod models;
 pub mod search;
 pub mod table;
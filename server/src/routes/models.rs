@@ -0,0 +1,16 @@
+use actix_web::{HttpResponse, get};
+use vectorize_core::transformers::providers;
+
+#[utoipa::path(
+    context_path = "/api/v1",
+    responses(
+        (
+            status = 200, description = "Model sources, their known models and embedding dimensions",
+            body = Vec<providers::ModelSourceInfo>,
+        ),
+    ),
+)]
+#[get("/models")]
+pub async fn list_models() -> HttpResponse {
+    HttpResponse::Ok().json(providers::model_catalog())
+}
@@ -1,16 +1,23 @@
 use crate::app_state::AppState;
 use crate::errors::ServerError;
-use actix_web::{HttpResponse, get, web};
+use crate::provider_key::ProviderApiKey;
+use actix_web::{HttpResponse, get, post, web};
 use serde::{Deserialize, Serialize};
-use sqlx::{Row, prelude::FromRow};
-use std::collections::BTreeMap;
+use sqlx::postgres::PgArguments;
+use sqlx::query::Query;
+use sqlx::{Postgres, Row, prelude::FromRow};
+use std::collections::{BTreeMap, HashMap};
+use std::time::Instant;
 
 use utoipa::ToSchema;
 use uuid::Uuid;
-use vectorize_core::query::{self, FilterValue};
-use vectorize_core::transformers::providers::prepare_generic_embedding_request;
-use vectorize_core::transformers::types::Inputs;
-use vectorize_core::types::VectorizeJob;
+use vectorize_core::query::{self, FilterColumn, FilterValue, SearchMode};
+use vectorize_core::rerank;
+use vectorize_core::transformers::providers::cache::generate_embeddings_cached;
+use vectorize_core::transformers::providers::{self, InputType};
+use vectorize_core::transformers::tokenizer::Tokenizer;
+use vectorize_core::transformers::types::{Inputs, truncate_inputs};
+use vectorize_core::types::{Model, VectorizeJob};
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema, FromRow)]
 pub struct SearchRequest {
@@ -26,7 +33,34 @@ pub struct SearchRequest {
     pub semantic_wt: f32,
     #[serde(default = "default_fts_wt")]
     pub fts_wt: f32,
+    /// re-rank the window_size candidates with maximal marginal relevance, 0.0 to 1.0
+    #[serde(default)]
+    pub mmr_lambda: Option<f32>,
+    /// re-rank the window_size candidates with a reranker model, e.g. cohere/rerank-english-v3.0
+    #[serde(default)]
+    pub rerank_model: Option<String>,
+    /// only return results whose similarity score is at least this threshold
+    #[serde(default)]
+    pub min_score: Option<f32>,
+    /// hnsw.ef_search for this query, higher values trade speed for recall
+    #[serde(default)]
+    pub ef_search: Option<i32>,
+    /// raw SQL predicate on the source table, only accepted when VECTORIZE_ALLOW_RAW_WHERE is enabled
+    #[serde(default)]
+    pub where_sql: Option<String>,
+    /// hybrid (default), semantic or fts, single-ranking modes skip the other ranking
+    #[serde(default)]
+    #[schema(value_type = String)]
+    pub search_mode: SearchMode,
+    /// source table columns included in each result, comma separated in a query string
+    #[serde(
+        default = "default_return_columns",
+        deserialize_with = "deserialize_return_columns"
+    )]
+    pub return_columns: Vec<String>,
+    /// filters on source table columns, sent as additional query parameters, e.g. price=gte.10
     #[serde(flatten, default)]
+    #[schema(value_type = HashMap<String, String>)]
     pub filters: BTreeMap<String, FilterValue>,
 }
 
@@ -45,6 +79,34 @@ pub struct SearchRequestPOST {
     pub semantic_wt: f32,
     #[serde(default = "default_fts_wt")]
     pub fts_wt: f32,
+    /// re-rank the window_size candidates with maximal marginal relevance, 0.0 to 1.0
+    #[serde(default)]
+    pub mmr_lambda: Option<f32>,
+    /// re-rank the window_size candidates with a reranker model, e.g. cohere/rerank-english-v3.0
+    #[serde(default)]
+    pub rerank_model: Option<String>,
+    /// only return results whose similarity score is at least this threshold
+    #[serde(default)]
+    pub min_score: Option<f32>,
+    /// hnsw.ef_search for this query, higher values trade speed for recall
+    #[serde(default)]
+    pub ef_search: Option<i32>,
+    /// raw SQL predicate on the source table, only accepted when VECTORIZE_ALLOW_RAW_WHERE is enabled
+    #[serde(default)]
+    pub where_sql: Option<String>,
+    /// hybrid (default), semantic or fts, single-ranking modes skip the other ranking
+    #[serde(default)]
+    #[schema(value_type = String)]
+    pub search_mode: SearchMode,
+    /// source table columns included in each result, all of them by default
+    #[serde(
+        default = "default_return_columns",
+        deserialize_with = "deserialize_return_columns"
+    )]
+    pub return_columns: Vec<String>,
+    /// filters on source table columns keyed by column, e.g. {"price": "gte.10", "in_stock": true}
+    #[serde(default)]
+    #[schema(value_type = HashMap<String, Value>)]
     pub filters: BTreeMap<String, FilterValue>,
 }
 
@@ -58,11 +120,40 @@ impl From<SearchRequestPOST> for SearchRequest {
             rrf_k: request.rrf_k,
             semantic_wt: request.semantic_wt,
             fts_wt: request.fts_wt,
+            mmr_lambda: request.mmr_lambda,
+            rerank_model: request.rerank_model,
+            min_score: request.min_score,
+            ef_search: request.ef_search,
+            where_sql: request.where_sql,
+            search_mode: request.search_mode,
+            return_columns: request.return_columns,
             filters: request.filters,
         }
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct FederatedSearchRequest {
+    /// jobs searched, each result names the job it came from
+    pub job_names: Vec<String>,
+    pub query: String,
+    /// candidates taken from each job before the results are merged
+    #[serde(default = "default_window_size")]
+    pub window_size: i32,
+    #[serde(default = "default_limit")]
+    pub limit: i32,
+    #[serde(default = "default_rrf_k")]
+    pub rrf_k: f32,
+    #[serde(default = "default_semantic_wt")]
+    pub semantic_wt: f32,
+    #[serde(default = "default_fts_wt")]
+    pub fts_wt: f32,
+    /// hybrid (default), semantic or fts, single-ranking modes skip the other ranking
+    #[serde(default)]
+    #[schema(value_type = String)]
+    pub search_mode: SearchMode,
+}
+
 fn default_semantic_wt() -> f32 {
     1.0
 }
@@ -83,11 +174,43 @@ fn default_rrf_k() -> f32 {
     60.0
 }
 
+fn default_return_columns() -> Vec<String> {
+    vec!["*".to_string()]
+}
+
+// a JSON array, or a comma separated list as sent in a query string
+fn deserialize_return_columns<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Columns {
+        List(Vec<String>),
+        Csv(String),
+    }
+    Ok(match Columns::deserialize(deserializer)? {
+        Columns::List(columns) => columns,
+        Columns::Csv(columns) => columns.split(',').map(|c| c.trim().to_string()).collect(),
+    })
+}
+
+fn all_columns(columns: &[String]) -> bool {
+    columns.iter().any(|c| c == "*")
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct SearchResponse {
     pub id: Uuid,
 }
 
+/// returned instead of results while a job has no embeddings, e.g. right after it is created
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct EmptySearchResponse {
+    pub results: Vec<serde_json::Value>,
+    pub warning: String,
+}
+
 #[utoipa::path(
     context_path = "/api/v1",
     params(
@@ -98,11 +221,19 @@ pub struct SearchResponse {
         ("rrf_k" = Option<i64>, Query, description = "Optional RRF k parameter for hybrid search"),
         ("semantic_wt" = Option<f32>, Query, description = "Optional weight for semantic search (default: 1.0)"),
         ("fts_wt" = Option<f32>, Query, description = "Optional weight for full-text search (default: 1.0)"),
-        ("filters" = Option<HashMap<String, String>>, Query, description = "Optional filters for the search"),
+        ("mmr_lambda" = Option<f32>, Query, description = "Optional MMR re-ranking of the window_size candidates, from 0.0 (most diverse) to 1.0 (most relevant)"),
+        ("rerank_model" = Option<String>, Query, description = "Optional reranker model applied to the window_size candidates, e.g. cohere/rerank-english-v3.0"),
+        ("min_score" = Option<f32>, Query, description = "Optional minimum similarity score, results below it are excluded"),
+        ("ef_search" = Option<i32>, Query, description = "Optional hnsw.ef_search for this query, from 1 to 1000. Higher values improve recall at the cost of speed"),
+        ("where_sql" = Option<String>, Query, description = "Optional raw SQL predicate on the source table. Rejected unless the server runs with VECTORIZE_ALLOW_RAW_WHERE enabled"),
+        ("search_mode" = Option<String>, Query, description = "Optional search mode: hybrid (default), semantic for vector similarity only, or fts for full-text search only, which does not embed the query"),
+        ("return_columns" = Option<String>, Query, description = "Optional comma separated source table columns to include in each result (default: all columns)"),
+        ("filters" = Option<HashMap<String, String>>, Query, style = Form, explode, description = "Optional filters on source table columns, each one a separate query parameter named after the column, e.g. product_category=outdoor or price=gte.10. The operator prefix is one of eq (default), gt, gte, lt, lte, like and ilike. A jsonb key is filtered with column->>key, e.g. metadata->>category=news"),
+        ("X-Provider-Api-Key" = Option<String>, Header, description = "Optional model provider api key for this request, overrides the server's configured key"),
     ),
     responses(
         (
-            status = 200, description = "Search results",
+            status = 200, description = "Search results, or an EmptySearchResponse while the job has no embeddings",
             body = Vec<serde_json::Value>,
         ),
     ),
@@ -111,99 +242,697 @@ pub struct SearchResponse {
 pub async fn search(
     app_state: web::Data<AppState>,
     payload: web::Query<SearchRequest>,
+    api_key: ProviderApiKey,
 ) -> Result<HttpResponse, ServerError> {
-    search_internal(app_state, payload.into_inner()).await
+    search_internal(app_state, payload.into_inner(), api_key).await
 }
 
 /// POST /search_json: Accepts a JSON body instead of URL query params for search
 #[utoipa::path(
-    post,
-    path = "/api/v1",
+    context_path = "/api/v1",
     request_body = SearchRequestPOST,
+    params(
+        ("X-Provider-Api-Key" = Option<String>, Header, description = "Optional model provider api key for this request, overrides the server's configured key"),
+    ),
     responses(
         (
-            status = 200, description = "Search results",
+            status = 200, description = "Search results, or an EmptySearchResponse while the job has no embeddings",
             body = Vec<serde_json::Value>,
         ),
     ),
 )]
-#[actix_web::post("/search")]
+#[post("/search")]
 pub async fn search_json(
     app_state: web::Data<AppState>,
     payload: web::Json<SearchRequestPOST>,
+    api_key: ProviderApiKey,
+) -> Result<HttpResponse, ServerError> {
+    search_internal(app_state, payload.into_inner().into(), api_key).await
+}
+
+/// POST /search/batch: Runs multiple searches in one request, returning one result array per search
+#[utoipa::path(
+    context_path = "/api/v1",
+    request_body = Vec<SearchRequestPOST>,
+    params(
+        ("X-Provider-Api-Key" = Option<String>, Header, description = "Optional model provider api key for this request, overrides the server's configured key"),
+    ),
+    responses(
+        (
+            status = 200, description = "Search results for each request, in request order",
+            body = Vec<Vec<serde_json::Value>>,
+        ),
+    ),
+)]
+#[post("/search/batch")]
+pub async fn search_batch(
+    app_state: web::Data<AppState>,
+    payload: web::Json<Vec<SearchRequestPOST>>,
+    api_key: ProviderApiKey,
+) -> Result<HttpResponse, ServerError> {
+    let mut requests: Vec<SearchRequest> =
+        payload.into_inner().into_iter().map(Into::into).collect();
+
+    let mut jobs = Vec::with_capacity(requests.len());
+    for request in &mut requests {
+        validate_search_request(request, app_state.config.allow_raw_where)?;
+        let job = get_cached_job(&app_state, &request.job_name).await?;
+        validate_columns(&app_state.db_pool, request, &job).await?;
+        jobs.push(job);
+    }
+
+    let query_embeddings = embed_queries(&app_state, &requests, &jobs, api_key.clone()).await?;
+
+    let searches = requests
+        .iter()
+        .zip(jobs.iter())
+        .zip(query_embeddings.iter())
+        .map(|((request, job), embedding)| {
+            run_search(&app_state.db_pool, request, job, embedding, &api_key)
+        });
+    let results = futures::future::try_join_all(searches).await?;
+
+    Ok(HttpResponse::Ok().json(results))
+}
+
+/// POST /search/federated: Searches several jobs with one query, returning a single ranked result set
+#[utoipa::path(
+    context_path = "/api/v1",
+    request_body = FederatedSearchRequest,
+    params(
+        ("X-Provider-Api-Key" = Option<String>, Header, description = "Optional model provider api key for this request, overrides the server's configured key"),
+    ),
+    responses(
+        (
+            status = 200, description = "Results of every job ranked together, each with the job_name it came from",
+            body = Vec<serde_json::Value>,
+        ),
+    ),
+)]
+#[post("/search/federated")]
+pub async fn search_federated(
+    app_state: web::Data<AppState>,
+    payload: web::Json<FederatedSearchRequest>,
+    api_key: ProviderApiKey,
 ) -> Result<HttpResponse, ServerError> {
-    search_internal(app_state, payload.into_inner().into()).await
+    let payload = payload.into_inner();
+    if payload.job_names.is_empty() {
+        return Err(ServerError::InvalidRequest(
+            "job_names must name at least one job".to_string(),
+        ));
+    }
+    let mut seen = std::collections::HashSet::new();
+    if let Some(duplicate) = payload.job_names.iter().find(|name| !seen.insert(*name)) {
+        return Err(ServerError::InvalidRequest(format!(
+            "job_names contains {duplicate} more than once"
+        )));
+    }
+
+    // each job contributes its window_size best candidates, which are ranked together below
+    let requests: Vec<SearchRequest> = payload
+        .job_names
+        .iter()
+        .map(|job_name| SearchRequest {
+            job_name: job_name.clone(),
+            query: payload.query.clone(),
+            window_size: payload.window_size,
+            limit: payload.window_size,
+            rrf_k: payload.rrf_k,
+            semantic_wt: payload.semantic_wt,
+            fts_wt: payload.fts_wt,
+            mmr_lambda: None,
+            rerank_model: None,
+            min_score: None,
+            ef_search: None,
+            where_sql: None,
+            search_mode: payload.search_mode,
+            return_columns: default_return_columns(),
+            filters: BTreeMap::new(),
+        })
+        .collect();
+    let mut jobs = Vec::with_capacity(requests.len());
+    for request in &requests {
+        validate_search_request(request, app_state.config.allow_raw_where)?;
+        jobs.push(get_cached_job(&app_state, &request.job_name).await?);
+    }
+
+    // jobs sharing a model share a single embedding of the query
+    let query_embeddings = embed_queries(&app_state, &requests, &jobs, api_key.clone()).await?;
+    let searches = requests
+        .iter()
+        .zip(jobs.iter())
+        .zip(query_embeddings.iter())
+        .map(|((request, job), embedding)| {
+            run_search(&app_state.db_pool, request, job, embedding, &api_key)
+        });
+    let results = futures::future::try_join_all(searches).await?;
+
+    Ok(HttpResponse::Ok().json(federated_rrf(
+        payload.job_names.iter().cloned().zip(results).collect(),
+        &payload,
+    )))
+}
+
+// merges the results of several jobs with reciprocal rank fusion over each job's own rankings:
+// similarity scores and full-text scores are not comparable between jobs, which may use different
+// models and distance types, so a result's semantic_rank and fts_rank stay the ranks within its job.
+// Each result gets its job_name, and its rrf_score is recomputed with the request's weights
+fn federated_rrf(
+    results: Vec<(String, Vec<serde_json::Value>)>,
+    payload: &FederatedSearchRequest,
+) -> Vec<serde_json::Value> {
+    let k = payload.rrf_k as f64;
+    let mut scored: Vec<(f64, serde_json::Value)> = results
+        .into_iter()
+        .flat_map(|(job_name, rows)| {
+            rows.into_iter().map(move |mut row| {
+                let mut score = 0.0;
+                if let Some(rank) = row["semantic_rank"].as_f64() {
+                    score += payload.semantic_wt as f64 / (k + rank);
+                }
+                if let Some(rank) = row["fts_rank"].as_f64() {
+                    score += payload.fts_wt as f64 / (k + rank);
+                }
+                if let Some(obj) = row.as_object_mut() {
+                    obj.insert("job_name".to_string(), serde_json::json!(job_name));
+                    obj.insert("rrf_score".to_string(), serde_json::json!(score));
+                }
+                (score, row)
+            })
+        })
+        .collect();
+    // a stable sort keeps job order, then each job's own order, between equal scores
+    scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+    scored
+        .into_iter()
+        .take(payload.limit.max(0) as usize)
+        .map(|(_, row)| row)
+        .collect()
+}
+
+// embeds each request's query with its job's model, one embedding call per distinct model
+async fn embed_queries(
+    app_state: &web::Data<AppState>,
+    requests: &[SearchRequest],
+    jobs: &[VectorizeJob],
+    api_key: ProviderApiKey,
+) -> Result<Vec<Vec<f64>>, ServerError> {
+    let mut by_model: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (idx, job) in jobs.iter().enumerate() {
+        // full-text searches are not embedded, their embedding stays empty
+        if requests[idx].search_mode != SearchMode::Fts {
+            by_model.entry(job.model.to_string()).or_default().push(idx);
+        }
+    }
+    let mut query_embeddings: Vec<Vec<f64>> = vec![Vec::new(); requests.len()];
+    for indices in by_model.values() {
+        let model = &jobs[indices[0]].model;
+        let provider = vectorize_core::transformers::providers::get_provider(
+            &model.source,
+            api_key.0.clone(),
+            None,
+            None,
+        )?;
+        // rows were embedded normalized, so each query is normalized the same way
+        let mut inputs: Vec<Inputs> = indices
+            .iter()
+            .map(|&idx| Inputs {
+                record_id: "".to_string(),
+                inputs: jobs[idx].normalization.apply(&requests[idx].query),
+                token_estimate: 0,
+            })
+            .collect();
+        for (input, &idx) in inputs.iter_mut().zip(indices) {
+            truncate_query(input, &jobs[idx].model, app_state.config.max_input_tokens);
+        }
+        let started = Instant::now();
+        let embeddings = generate_embeddings_cached(
+            &app_state.embedding_cache,
+            provider.as_ref(),
+            model,
+            &inputs,
+            InputType::Query,
+            app_state.config.embedding_batch_max_tokens,
+        )
+        .await?;
+        tracing::info!(
+            provider = model.source.to_string(),
+            model = %model,
+            latency_ms = started.elapsed().as_millis() as u64,
+            queries = inputs.len(),
+            "embedded search queries"
+        );
+        for (&idx, mut embedding) in indices.iter().zip(embeddings) {
+            jobs[idx].prepare_query_embedding(&mut embedding);
+            query_embeddings[idx] = embedding;
+        }
+    }
+    Ok(query_embeddings)
+}
+
+/// a bound parameter of the search SQL, the query embedding is summarized by its dimensions
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ExplainParam {
+    /// the $n placeholder in the SQL
+    pub position: usize,
+    pub name: String,
+    pub value: serde_json::Value,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ExplainResponse {
+    /// the generated search query
+    pub sql: String,
+    pub params: Vec<ExplainParam>,
+    /// hnsw.ef_search set for the query, if any
+    pub ef_search: Option<i32>,
+    /// the output of EXPLAIN (FORMAT JSON) for the query
+    pub plan: serde_json::Value,
+}
+
+/// GET /search/explain: Returns the SQL and query plan of a search instead of its results
+#[utoipa::path(
+    context_path = "/api/v1",
+    params(
+        ("job_name" = String, Query, description = "Name of the vectorize job"),
+        ("query" = String, Query, description = "Search query string"),
+        ("filters" = Option<HashMap<String, String>>, Query, style = Form, explode, description = "Any other parameter or filter accepted by GET /search"),
+        ("X-Provider-Api-Key" = Option<String>, Header, description = "Optional model provider api key for this request, overrides the server's configured key"),
+    ),
+    responses(
+        (
+            status = 200, description = "The generated SQL, its parameters and plan. Only available when the server runs with VECTORIZE_ALLOW_SEARCH_EXPLAIN enabled",
+            body = ExplainResponse,
+        ),
+    ),
+)]
+#[get("/search/explain")]
+pub async fn search_explain(
+    app_state: web::Data<AppState>,
+    payload: web::Query<SearchRequest>,
+    api_key: ProviderApiKey,
+) -> Result<HttpResponse, ServerError> {
+    if !app_state.config.allow_search_explain {
+        return Err(ServerError::InvalidRequest(
+            "search explain is disabled, set VECTORIZE_ALLOW_SEARCH_EXPLAIN=true to enable it"
+                .to_string(),
+        ));
+    }
+    let mut payload = payload.into_inner();
+    validate_search_request(&payload, app_state.config.allow_raw_where)?;
+    let vectorizejob = get_cached_job(&app_state, &payload.job_name).await?;
+    validate_columns(&app_state.db_pool, &mut payload, &vectorizejob).await?;
+
+    // full-text search binds an empty embedding, as it does when searching
+    let embedding = match payload.search_mode {
+        SearchMode::Fts => Vec::new(),
+        _ => embed_query(&app_state, &vectorizejob, &payload.query, api_key).await?,
+    };
+
+    let (sql, _) = build_search_query(&payload, &vectorizejob);
+    let explain = format!("EXPLAIN (FORMAT JSON) {sql}");
+    let mut tx = app_state.db_pool.begin().await?;
+    if let Some(ef_search) = payload.ef_search {
+        sqlx::query(&format!("SET LOCAL hnsw.ef_search = {ef_search}"))
+            .execute(&mut *tx)
+            .await?;
+    }
+    let plan: serde_json::Value = bind_search_params(sqlx::query(&explain), &payload, &embedding)
+        .fetch_one(&mut *tx)
+        .await?
+        .get(0);
+    tx.commit().await?;
+
+    Ok(HttpResponse::Ok().json(ExplainResponse {
+        params: explain_params(&payload, &embedding),
+        sql,
+        ef_search: payload.ef_search,
+        plan,
+    }))
+}
+
+// the parameters bind_search_params binds, in placeholder order
+fn explain_params(payload: &SearchRequest, embedding: &[f64]) -> Vec<ExplainParam> {
+    let mut params = vec![
+        (
+            "query_embedding".to_string(),
+            serde_json::json!(format!("vector({})", embedding.len())),
+        ),
+        ("query".to_string(), serde_json::json!(payload.query)),
+    ];
+    for (key, value) in &payload.filters {
+        let value = match &value.value {
+            query::FilterValueType::String(s) => serde_json::json!(s),
+            query::FilterValueType::Integer(i) => serde_json::json!(i),
+            query::FilterValueType::Float(f) => serde_json::json!(f),
+            query::FilterValueType::Boolean(b) => serde_json::json!(b),
+        };
+        params.push((key.clone(), value));
+    }
+    params
+        .into_iter()
+        .enumerate()
+        .map(|(idx, (name, value))| ExplainParam {
+            position: idx + 1,
+            name,
+            value,
+        })
+        .collect()
 }
 
 // Internal function for search logic, used by both GET and POST
 async fn search_internal(
     app_state: web::Data<AppState>,
-    payload: SearchRequest,
+    mut payload: SearchRequest,
+    api_key: ProviderApiKey,
 ) -> Result<HttpResponse, ServerError> {
-    // check inputs and filters are valid if they exist and create a SQL string for them
-    query::check_input(&payload.job_name)?;
-    if !payload.filters.is_empty() {
-        for key in payload.filters.keys() {
-            // validate key only (column names should be alphanumeric + underscore)
-            query::check_input(key)?;
-            // Note: filter values are validated during deserialization in FilterValue
-        }
+    validate_search_request(&payload, app_state.config.allow_raw_where)?;
+
+    let vectorizejob = get_cached_job(&app_state, &payload.job_name).await?;
+    validate_columns(&app_state.db_pool, &mut payload, &vectorizejob).await?;
+
+    // full-text search needs no query embedding, and its search tokens exist before any embeddings
+    if payload.search_mode == SearchMode::Fts {
+        let json_results = run_search(
+            &app_state.db_pool,
+            &payload,
+            &vectorizejob,
+            &Vec::new(),
+            &api_key,
+        )
+        .await?;
+        return Ok(HttpResponse::Ok().json(json_results));
     }
 
-    // Try to get job info from cache first, fallback to database with write-through on miss
-    let vectorizejob = {
-        if let Some(job_info) = {
-            let job_cache = app_state.job_cache.read().await;
-            job_cache.get(&payload.job_name).cloned()
-        } {
-            job_info
-        } else {
-            tracing::warn!(
-                "Job not found in cache, querying database for job: {}",
+    // nothing to search yet, skip embedding the query
+    let has_embeddings: bool = sqlx::query_scalar(&query::has_embeddings(
+        &payload.job_name,
+        &vectorizejob.table_prefixes,
+        &vectorizejob.target_schema,
+    ))
+    .fetch_one(&app_state.db_pool)
+    .await?;
+    if !has_embeddings {
+        return Ok(HttpResponse::Ok().json(EmptySearchResponse {
+            results: Vec::new(),
+            warning: format!(
+                "job {} has no embeddings yet, results will be returned once the source rows are embedded",
                 payload.job_name
-            );
-            let job = get_vectorize_job(&app_state.db_pool, &payload.job_name).await?;
-            let mut job_cache = app_state.job_cache.write().await;
-            job_cache.insert(payload.job_name.clone(), job.clone());
-            job
-        }
-    };
+            ),
+        }));
+    }
 
+    let embedding = embed_query(&app_state, &vectorizejob, &payload.query, api_key.clone()).await?;
+    let json_results = run_search(
+        &app_state.db_pool,
+        &payload,
+        &vectorizejob,
+        &embedding,
+        &api_key,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(json_results))
+}
+
+// embeds a search query with the job's model
+async fn embed_query(
+    app_state: &web::Data<AppState>,
+    vectorizejob: &VectorizeJob,
+    query: &str,
+    api_key: ProviderApiKey,
+) -> Result<Vec<f64>, ServerError> {
     let provider = vectorize_core::transformers::providers::get_provider(
         &vectorizejob.model.source,
-        None,
+        api_key.into_inner(),
         None,
         None,
     )?;
 
-    let input = Inputs {
+    // rows were embedded normalized, so the query is normalized the same way
+    let mut input = Inputs {
         record_id: "".to_string(),
-        inputs: payload.query.clone(),
+        inputs: vectorizejob.normalization.apply(query),
         token_estimate: 0,
     };
+    truncate_query(
+        &mut input,
+        &vectorizejob.model,
+        app_state.config.max_input_tokens,
+    );
+
+    let started = Instant::now();
+    let mut embeddings = generate_embeddings_cached(
+        &app_state.embedding_cache,
+        provider.as_ref(),
+        &vectorizejob.model,
+        &[input],
+        InputType::Query,
+        app_state.config.embedding_batch_max_tokens,
+    )
+    .await?;
+    tracing::info!(
+        job_name = vectorizejob.job_name,
+        provider = vectorizejob.model.source.to_string(),
+        model = %vectorizejob.model,
+        latency_ms = started.elapsed().as_millis() as u64,
+        "embedded search query"
+    );
+    let mut embedding = embeddings.swap_remove(0);
+    vectorizejob.prepare_query_embedding(&mut embedding);
+    Ok(embedding)
+}
+
+// long queries are embedded from their start instead of being rejected by the provider
+fn truncate_query(input: &mut Inputs, model: &Model, max_tokens: usize) {
+    let tokenizer = Tokenizer::for_model(model);
+    if truncate_inputs(std::slice::from_mut(input), &tokenizer, max_tokens) > 0 {
+        tracing::warn!("truncated a search query to {max_tokens} tokens of model {model}");
+    }
+}
+
+// check inputs and filters are valid if they exist
+fn validate_search_request(
+    payload: &SearchRequest,
+    allow_raw_where: bool,
+) -> Result<(), ServerError> {
+    query::check_input(&payload.job_name)?;
+    if payload.where_sql.is_some() && !allow_raw_where {
+        return Err(ServerError::InvalidRequest(
+            "where_sql is disabled, set VECTORIZE_ALLOW_RAW_WHERE=true to enable it".to_string(),
+        ));
+    }
+    if let Some(lambda) = payload.mmr_lambda
+        && !(0.0..=1.0).contains(&lambda)
+    {
+        return Err(ServerError::InvalidRequest(format!(
+            "mmr_lambda must be between 0.0 and 1.0, got {lambda}"
+        )));
+    }
+    if let Some(min_score) = payload.min_score
+        && !min_score.is_finite()
+    {
+        return Err(ServerError::InvalidRequest(format!(
+            "min_score must be a finite number, got {min_score}"
+        )));
+    }
+    if let Some(ef_search) = payload.ef_search
+        && !(1..=1000).contains(&ef_search)
+    {
+        return Err(ServerError::InvalidRequest(format!(
+            "ef_search must be between 1 and 1000, got {ef_search}"
+        )));
+    }
+    if payload.search_mode == SearchMode::Fts {
+        // both rank by the query embedding, which full-text search does not compute
+        if payload.mmr_lambda.is_some() {
+            return Err(ServerError::InvalidRequest(
+                "mmr_lambda can not be used with search_mode fts".to_string(),
+            ));
+        }
+        if payload.min_score.is_some() {
+            return Err(ServerError::InvalidRequest(
+                "min_score can not be used with search_mode fts".to_string(),
+            ));
+        }
+    }
+    if let Some(rerank_model) = &payload.rerank_model {
+        if payload.mmr_lambda.is_some() {
+            return Err(ServerError::InvalidRequest(
+                "mmr_lambda and rerank_model cannot be combined".to_string(),
+            ));
+        }
+        Model::new(rerank_model).map_err(|e| ServerError::InvalidRequest(e.to_string()))?;
+    }
+    if payload.return_columns.is_empty() {
+        return Err(ServerError::InvalidRequest(
+            "return_columns must name at least one column".to_string(),
+        ));
+    }
+    for column in &payload.return_columns {
+        if column != "*" {
+            query::check_input(column).map_err(|e| ServerError::InvalidRequest(e.to_string()))?;
+        }
+    }
+    if !payload.filters.is_empty() {
+        for key in payload.filters.keys() {
+            // validate key only, a column or a jsonb path of alphanumeric + underscore names
+            FilterColumn::parse(key).map_err(|e| ServerError::InvalidRequest(e.to_string()))?;
+            // Note: filter values are validated during deserialization in FilterValue
+        }
+    }
+    Ok(())
+}
+
+// Try to get job info from cache first, fallback to database with write-through on miss
+pub(crate) async fn get_cached_job(
+    app_state: &web::Data<AppState>,
+    job_name: &str,
+) -> Result<VectorizeJob, ServerError> {
+    if let Some(job_info) = {
+        let job_cache = app_state.job_cache.read().await;
+        job_cache.get(job_name).cloned()
+    } {
+        Ok(job_info)
+    } else {
+        tracing::warn!(
+            "Job not found in cache, querying database for job: {}",
+            job_name
+        );
+        let job = get_vectorize_job(&app_state.db_pool, job_name).await?;
+        let mut job_cache = app_state.job_cache.write().await;
+        job_cache.insert(job_name.to_string(), job.clone());
+        Ok(job)
+    }
+}
+
+// requested columns and filter columns must exist on the source table
+// otherwise the search query fails with a SQL error
+// values of filters on plain columns are converted to the column's type, so they bind as it
+async fn validate_columns(
+    pool: &sqlx::PgPool,
+    payload: &mut SearchRequest,
+    vectorizejob: &VectorizeJob,
+) -> Result<(), ServerError> {
+    if all_columns(&payload.return_columns) && payload.filters.is_empty() {
+        return Ok(());
+    }
+    let column_types: HashMap<String, String> = sqlx::query_as::<_, (String, String)>(
+        "SELECT column_name::text, data_type::text FROM information_schema.columns
+         WHERE table_schema = $1 AND table_name = $2",
+    )
+    .bind(&vectorizejob.src_schema)
+    .bind(&vectorizejob.src_table)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .collect();
+    let table = format!("{}.{}", vectorizejob.src_schema, vectorizejob.src_table);
 
-    let embedding_request = prepare_generic_embedding_request(&vectorizejob.model, &[input]);
-    let embeddings = provider.generate_embedding(&embedding_request).await?;
+    if !all_columns(&payload.return_columns) {
+        let missing: Vec<&str> = payload
+            .return_columns
+            .iter()
+            .filter(|c| !column_types.contains_key(*c))
+            .map(String::as_str)
+            .collect();
+        if !missing.is_empty() {
+            return Err(ServerError::InvalidRequest(format!(
+                "return_columns not found in {table}: {}",
+                missing.join(", ")
+            )));
+        }
+    }
+    for (key, value) in payload.filters.iter_mut() {
+        // invalid keys are rejected by validate_search_request
+        let Ok(filter) = FilterColumn::parse(key) else {
+            continue;
+        };
+        match column_types.get(&filter.column).map(String::as_str) {
+            // jsonb paths compare jsonb values, see FilterColumn::condition
+            Some("jsonb") if !filter.path.is_empty() => {}
+            Some(data_type) if !filter.path.is_empty() => {
+                return Err(ServerError::InvalidRequest(format!(
+                    "filter {key} requires a jsonb column, {} is {data_type}",
+                    filter.column
+                )));
+            }
+            Some(data_type) => {
+                value
+                    .coerce_to_column(data_type)
+                    .map_err(|e| ServerError::InvalidRequest(format!("filter {key}: {e}")))?;
+            }
+            None => {
+                return Err(ServerError::InvalidRequest(format!(
+                    "filter {key}: column {} not found in {table}",
+                    filter.column
+                )));
+            }
+        }
+    }
+    Ok(())
+}
 
+// the hybrid search SQL for a request, and the source columns it fetches only for the reranker
+fn build_search_query(
+    payload: &SearchRequest,
+    vectorizejob: &VectorizeJob,
+) -> (String, Vec<String>) {
+    // with mmr or a reranker, every candidate in the window is fetched and re-ranked here
+    let limit = if payload.mmr_lambda.is_some() || payload.rerank_model.is_some() {
+        payload.window_size.max(payload.limit)
+    } else {
+        payload.limit
+    };
+    // the reranker scores the job's source columns, so they are fetched even when not requested
+    let rerank_columns: Vec<String> =
+        if payload.rerank_model.is_some() && !all_columns(&payload.return_columns) {
+            vectorizejob
+                .src_columns
+                .iter()
+                .filter(|c| !payload.return_columns.contains(c))
+                .cloned()
+                .collect()
+        } else {
+            Vec::new()
+        };
+    let columns = [payload.return_columns.as_slice(), &rerank_columns].concat();
+    let predicate = query::search_predicate(
+        payload.where_sql.as_deref(),
+        vectorizejob.deleted_filter.as_deref(),
+    );
     let q = query::hybrid_search_query(
         &payload.job_name,
+        &vectorizejob.table_prefixes,
         &vectorizejob.src_schema,
         &vectorizejob.src_table,
         &vectorizejob.primary_key,
-        &["*".to_string()],
+        &columns,
         payload.window_size,
-        payload.limit,
+        limit,
         payload.rrf_k,
         payload.semantic_wt,
         payload.fts_wt,
         &payload.filters,
+        &vectorizejob.index_dist_type,
+        &vectorizejob.vector_type,
+        &vectorizejob.target_schema,
+        payload.mmr_lambda.is_some(),
+        payload.min_score,
+        predicate.as_deref(),
+        &vectorizejob.fts_language,
+        payload.search_mode,
     );
+    (q, rerank_columns)
+}
 
-    let mut prepared_query = sqlx::query(&q)
-        .bind(&embeddings.embeddings[0])
-        .bind(&payload.query);
-
-    // Bind filter values
+// binds the query embedding, the query text and the filter values, in the order the SQL expects them
+fn bind_search_params<'q>(
+    mut prepared_query: Query<'q, Postgres, PgArguments>,
+    payload: &'q SearchRequest,
+    embedding: &'q Vec<f64>,
+) -> Query<'q, Postgres, PgArguments> {
+    prepared_query = prepared_query.bind(embedding).bind(&payload.query);
     for value in payload.filters.values() {
         prepared_query = match &value.value {
             query::FilterValueType::String(s) => prepared_query.bind(s),
@@ -212,34 +941,132 @@ async fn search_internal(
             query::FilterValueType::Boolean(b) => prepared_query.bind(b),
         };
     }
+    prepared_query
+}
 
-    let results = prepared_query.fetch_all(&app_state.db_pool).await?;
+// runs the hybrid search query for an already embedded search request
+// a rerank_model is called with the request's provider api key, like the query's embedding
+async fn run_search(
+    pool: &sqlx::PgPool,
+    payload: &SearchRequest,
+    vectorizejob: &VectorizeJob,
+    embedding: &Vec<f64>,
+    api_key: &ProviderApiKey,
+) -> Result<Vec<serde_json::Value>, ServerError> {
+    let (q, rerank_columns) = build_search_query(payload, vectorizejob);
+    let prepared_query = bind_search_params(sqlx::query(&q), payload, embedding);
 
-    let json_results: Vec<serde_json::Value> = results
+    // SET LOCAL only lasts until the end of the transaction, scoping it to this query
+    let mut tx = pool.begin().await?;
+    if let Some(ef_search) = payload.ef_search {
+        sqlx::query(&format!("SET LOCAL hnsw.ef_search = {ef_search}"))
+            .execute(&mut *tx)
+            .await?;
+    }
+    let started = Instant::now();
+    let results = prepared_query.fetch_all(&mut *tx).await?;
+    tx.commit().await?;
+    tracing::info!(
+        job_name = vectorizejob.job_name,
+        latency_ms = started.elapsed().as_millis() as u64,
+        rows = results.len(),
+        "ran search query"
+    );
+    let results: Vec<serde_json::Value> = results
         .iter()
         .map(|row| row.get::<serde_json::Value, _>("results"))
         .collect();
 
-    Ok(HttpResponse::Ok().json(json_results))
+    if let Some(lambda) = payload.mmr_lambda {
+        return Ok(mmr_rerank(results, embedding, lambda, payload.limit));
+    }
+    if let Some(rerank_model) = &payload.rerank_model {
+        let mut results =
+            model_rerank(results, payload, vectorizejob, rerank_model, api_key).await?;
+        for result in results.iter_mut() {
+            if let Some(obj) = result.as_object_mut() {
+                for column in &rerank_columns {
+                    obj.remove(column);
+                }
+            }
+        }
+        return Ok(results);
+    }
+    Ok(results)
+}
+
+// re-ranks search results with a reranker model, scoring the job's source columns
+async fn model_rerank(
+    mut results: Vec<serde_json::Value>,
+    payload: &SearchRequest,
+    vectorizejob: &VectorizeJob,
+    rerank_model: &str,
+    api_key: &ProviderApiKey,
+) -> Result<Vec<serde_json::Value>, ServerError> {
+    let model = Model::new(rerank_model).map_err(|e| ServerError::InvalidRequest(e.to_string()))?;
+    let reranker = providers::get_reranker(&model.source, api_key.0.clone(), None)?;
+    let documents: Vec<String> = results
+        .iter()
+        .map(|r| {
+            vectorizejob
+                .src_columns
+                .iter()
+                .filter_map(|col| match r.get(col) {
+                    Some(serde_json::Value::String(s)) => Some(s.clone()),
+                    Some(serde_json::Value::Null) | None => None,
+                    Some(v) => Some(v.to_string()),
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect();
+    let order = rerank::rerank(
+        reranker.as_ref(),
+        &model.api_name(),
+        &payload.query,
+        &documents,
+        payload.limit.max(0) as usize,
+    )
+    .await?;
+    Ok(order
+        .into_iter()
+        .map(|idx| std::mem::take(&mut results[idx]))
+        .collect())
+}
+
+// re-ranks search results by maximal marginal relevance, removing their embedding field
+fn mmr_rerank(
+    mut results: Vec<serde_json::Value>,
+    query_embedding: &[f64],
+    lambda: f32,
+    limit: i32,
+) -> Vec<serde_json::Value> {
+    let candidates: Vec<Vec<f64>> = results
+        .iter_mut()
+        .map(|r| {
+            r.as_object_mut()
+                .and_then(|obj| obj.remove("embedding"))
+                .and_then(|e| serde_json::from_value(e).ok())
+                .unwrap_or_default()
+        })
+        .collect();
+    let selected = rerank::mmr(
+        query_embedding,
+        &candidates,
+        lambda as f64,
+        limit.max(0) as usize,
+    );
+    selected
+        .into_iter()
+        .map(|idx| std::mem::take(&mut results[idx]))
+        .collect()
 }
 
 async fn get_vectorize_job(
     pool: &sqlx::PgPool,
     job_name: &str,
 ) -> Result<VectorizeJob, ServerError> {
-    match sqlx::query(
-        "SELECT job_name, src_table, src_schema, src_columns, primary_key, update_time_col, model 
-         FROM vectorize.job 
-         WHERE job_name = $1",
-    )
-    .bind(job_name)
-    .fetch_optional(pool)
-    .await?
-    {
-        Some(row) => Ok(VectorizeJob::from_row(&row)?),
-        None => Err(ServerError::NotFoundError(format!(
-            "Job not found: {}",
-            job_name
-        ))),
-    }
+    vectorize_core::db::find_vectorize_job(pool, job_name)
+        .await?
+        .ok_or_else(|| ServerError::NotFoundError(format!("Job not found: {}", job_name)))
 }
@@ -1,24 +1,41 @@
 use crate::app_state::AppState;
 use crate::errors::ServerError;
+use crate::provider_key::ProviderApiKey;
 use actix_web::{HttpResponse, delete, post, web};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use uuid::Uuid;
+use vectorize_core::db;
+use vectorize_core::errors::VectorizeError;
 use vectorize_core::init::{self, get_column_datatype};
 
-use vectorize_core::types::VectorizeJob;
+use vectorize_core::types::{TablePrefixes, VectorizeJob};
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
-pub struct JobResponse {
+pub struct CreateTableResponse {
     pub id: Uuid,
+    /// dimension of the embeddings generated by the job's model
+    pub model_dim: u32,
+    /// table the embeddings are written to, e.g. vectorize._embeddings_my_job
+    pub embeddings_table: String,
 }
 
 #[utoipa::path(
     context_path = "/api/v1",
+    request_body = VectorizeJob,
+    params(
+        ("X-Provider-Api-Key" = Option<String>, Header, description = "Optional model provider api key for this request, overrides the server's configured key"),
+    ),
     responses(
         (
-            status = 200, description = "Initialize a vectorize job",
-            body = JobResponse,
+            status = 200, description = "Initialize a vectorize job, or update an existing one",
+            body = CreateTableResponse,
+        ),
+        (
+            status = 400, description = "Invalid request, e.g. the model is unknown to its provider",
+        ),
+        (
+            status = 409, description = "Job exists with a different model, source table, primary key, target schema, index distance type, modality, normalize_embeddings, vector_type, src_columns, text_join or normalization",
         ),
     ),
 )]
@@ -26,40 +43,135 @@ pub struct JobResponse {
 pub async fn table(
     app_state: web::Data<AppState>,
     payload: web::Json<VectorizeJob>,
+    api_key: ProviderApiKey,
 ) -> Result<HttpResponse, ServerError> {
-    let payload = payload.into_inner();
+    // new jobs name their tables with the server's prefixes, existing jobs keep theirs
+    let payload = VectorizeJob {
+        table_prefixes: TablePrefixes::from_config(&app_state.config),
+        ..payload.into_inner()
+    };
+    init::validate_job(&payload)?;
+    if let Some(deleted_filter) = &payload.deleted_filter {
+        if !app_state.config.allow_raw_where {
+            return Err(ServerError::InvalidRequest(
+                "deleted_filter is disabled, set VECTORIZE_ALLOW_RAW_WHERE=true to enable it"
+                    .to_string(),
+            ));
+        }
+        // fail now rather than on every search
+        sqlx::query(&format!(
+            "SELECT 1 FROM {}.{} WHERE {deleted_filter} LIMIT 0",
+            payload.src_schema, payload.src_table
+        ))
+        .execute(&app_state.db_pool)
+        .await
+        .map_err(|e| ServerError::InvalidRequest(format!("invalid deleted_filter: {e}")))?;
+    }
 
     // validate update_time_col is timestamptz
-    let datatype = get_column_datatype(
-        &app_state.db_pool,
-        &payload.src_schema,
-        &payload.src_table,
-        &payload.update_time_col,
-    )
-    .await
-    .map_err(|e| match e {
-        vectorize_core::errors::VectorizeError::NotFound(msg) => ServerError::NotFoundError(msg),
-        _ => ServerError::from(e),
-    })?;
-    if datatype != "timestamp with time zone" {
-        return Err(ServerError::InvalidRequest(format!(
-            "Column {} in table {}.{} must be of type 'timestamp with time zone'",
-            payload.update_time_col, payload.src_schema, payload.src_table
-        )));
+    if let Some(update_time_col) = &payload.update_time_col {
+        let datatype = get_column_datatype(
+            &app_state.db_pool,
+            &payload.src_schema,
+            &payload.src_table,
+            update_time_col,
+        )
+        .await
+        .map_err(|e| match e {
+            vectorize_core::errors::VectorizeError::NotFound(msg) => {
+                ServerError::NotFoundError(msg)
+            }
+            _ => ServerError::from(e),
+        })?;
+        if datatype != "timestamp with time zone" {
+            return Err(ServerError::InvalidRequest(format!(
+                "Column {} in table {}.{} must be of type 'timestamp with time zone'",
+                update_time_col, payload.src_schema, payload.src_table
+            )));
+        }
     }
 
-    let job_id = init::initialize_job(&app_state.db_pool, &payload).await?;
+    // re-posting a job updates it, unless the change needs a new embeddings table
+    match db::get_vectorize_job(&app_state.db_pool, &payload.job_name).await {
+        Ok(existing) => {
+            if let Some(field) = conflicting_field(&existing, &payload) {
+                return Err(ServerError::Conflict(format!(
+                    "job {} already exists with a different {field}, delete it first",
+                    payload.job_name
+                )));
+            }
+            tracing::info!("updating existing job: {}", payload.job_name);
+        }
+        Err(VectorizeError::SqlError(sqlx::Error::RowNotFound)) => {}
+        Err(e) => return Err(e.into()),
+    }
+
+    let job = init::initialize_job(
+        &app_state.db_pool,
+        &payload,
+        app_state.config.scan_batch_size,
+        api_key.into_inner(),
+        &init::JobQueue::from_config(&app_state.config),
+    )
+    .await?;
 
     // Update the job cache with the new job information
     {
         let mut job_cache = app_state.job_cache.write().await;
-        job_cache.insert(payload.job_name.clone(), payload.clone());
+        let cached = VectorizeJob {
+            table_prefixes: job.table_prefixes.clone(),
+            ..payload.clone()
+        };
+        job_cache.insert(payload.job_name.clone(), cached);
     }
 
-    let resp = JobResponse { id: job_id };
+    let resp = CreateTableResponse {
+        id: job.id,
+        model_dim: job.model_dim,
+        embeddings_table: format!(
+            "{}.{}",
+            payload.target_schema,
+            job.table_prefixes.embeddings_table(&payload.job_name)
+        ),
+    };
     Ok(HttpResponse::Ok().json(resp))
 }
 
+// fields that determine the embeddings table and its source, these can not change in place
+fn conflicting_field(existing: &VectorizeJob, requested: &VectorizeJob) -> Option<&'static str> {
+    if existing.model.to_string() != requested.model.to_string() {
+        Some("model")
+    } else if existing.src_schema != requested.src_schema
+        || existing.src_table != requested.src_table
+    {
+        Some("source table")
+    } else if existing.primary_key != requested.primary_key {
+        Some("primary key")
+    } else if existing.target_schema != requested.target_schema {
+        Some("target schema")
+    } else if existing.index_dist_type != requested.index_dist_type {
+        // the vector index is built for one distance type
+        Some("index distance type")
+    } else if existing.modality != requested.modality {
+        // text and image embeddings can not share a table
+        Some("modality")
+    } else if existing.normalize_embeddings != requested.normalize_embeddings {
+        // stored embeddings would mix unit and raw lengths
+        Some("normalize_embeddings")
+    } else if existing.vector_type != requested.vector_type {
+        // the embeddings column and its index are created for one vector type
+        Some("vector_type")
+    } else if existing.src_columns != requested.src_columns
+        || existing.text_join != requested.text_join
+        || existing.normalization != requested.normalization
+    {
+        // stored embeddings were made from the old text, and only changed rows are rescanned
+        Some("embedded text (src_columns, text_join or normalization)")
+    } else {
+        None
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct DeleteJobResponse {
     pub job_name: String,
@@ -86,7 +198,7 @@ pub async fn delete_table(
     let job_name = job_name.into_inner();
 
     // Cleanup the job resources
-    init::cleanup_job(&app_state.db_pool, &job_name)
+    init::cleanup_job(&app_state.db_pool, &job_name, &app_state.config.queue_name)
         .await
         .map_err(|e| match e {
             vectorize_core::errors::VectorizeError::NotFound(msg) => {
@@ -1,13 +1,30 @@
-use actix_web::web;
+use actix_web::{middleware, web};
 
+use crate::rate_limit::rate_limit;
 use crate::routes;
 
 pub fn route_config(configuration: &mut web::ServiceConfig) {
     configuration.service(
         web::scope("/api/v1")
+            .wrap(middleware::from_fn(rate_limit))
             .service(routes::table::table)
             .service(routes::table::delete_table)
+            .service(routes::jobs::reindex)
+            .service(routes::jobs::pause_job)
+            .service(routes::jobs::resume_job)
+            .service(routes::jobs::repair_job)
+            .service(routes::jobs::delete_record)
+            .service(routes::jobs::update_job)
+            .service(routes::jobs::job_status)
+            .service(routes::jobs::job_progress_stream)
+            .service(routes::jobs::list_jobs)
+            .service(routes::jobs::requeue_dead_letters)
+            .service(routes::models::list_models)
+            .service(routes::embed::embed)
+            .service(routes::search::search_explain)
             .service(routes::search::search)
-            .service(routes::search::search_json),
+            .service(routes::search::search_json)
+            .service(routes::search::search_batch)
+            .service(routes::search::search_federated),
     );
 }
@@ -4,7 +4,6 @@ pub mod common {
     use actix_web::test;
     use actix_web::{App, Error, dev::ServiceResponse, web};
     use rand::prelude::*;
-    use std::process::Command;
     use vectorize_core::init;
 
     #[cfg(test)]
@@ -17,7 +16,7 @@ pub mod common {
             .connect(&cfg.database_url)
             .await
             .expect("unable to connect to postgres");
-        init::init_project(&pool)
+        init::init_project(&pool, &cfg.queue_name)
             .await
             .expect("Failed to initialize project");
         test::init_service(
@@ -38,7 +37,7 @@ pub mod common {
             .connect(&cfg.database_url)
             .await
             .expect("unable to connect to postgres");
-        init::init_project(&pool)
+        init::init_project(&pool, &cfg.queue_name)
             .await
             .expect("Failed to initialize project");
     }
@@ -61,7 +60,11 @@ pub mod common {
             let resp = client.get(&search_url).send().await?;
 
             if resp.status().is_success() {
-                let search_results: Vec<serde_json::Value> = resp.json().await?;
+                // an object with a warning until the job has embeddings
+                let search_results: Vec<serde_json::Value> = match resp.json().await? {
+                    serde_json::Value::Array(results) => results,
+                    _ => Vec::new(),
+                };
                 // Check if we have the expected number of results
                 if search_results.len() == num_expected {
                     return Ok(search_results);
@@ -149,24 +152,13 @@ pub mod common {
         .expect("unable to update test data");
     }
 
-    pub fn exec_psql(conn_string: &str, sql_content: &str) -> Result<(), String> {
-        let output = Command::new("psql")
-            .arg(conn_string)
-            .arg("-c")
-            .arg(sql_content)
-            .output()
-            .unwrap();
-        if !output.status.success() {
-            tracing::error!(
-                "failed to execute SQL: {}",
-                String::from_utf8_lossy(&output.stderr)
-            );
-            Err(format!(
-                "failed to execute SQL: {}",
-                String::from_utf8_lossy(&output.stderr)
-            ))
-        } else {
-            Ok(())
-        }
+    // runs a SQL script through sqlx, so the tests do not need psql installed
+    pub async fn exec_sql(pool: &sqlx::PgPool, sql_content: &str) -> Result<(), String> {
+        init::execute_sql_script(pool, sql_content)
+            .await
+            .map_err(|e| {
+                tracing::error!("failed to execute SQL: {e}");
+                format!("failed to execute SQL: {e}")
+            })
     }
 }
@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+/// how long the worker waits before polling an empty queue again
+/// starts at `min`, doubles toward `max` while the queue stays empty, and resets once work arrives
+#[derive(Debug, Clone)]
+pub struct PollBackoff {
+    min: Duration,
+    max: Duration,
+    current: Duration,
+}
+
+impl PollBackoff {
+    pub fn new(min: Duration, max: Duration) -> Self {
+        let max = std::cmp::max(min, max);
+        Self {
+            min,
+            max,
+            current: min,
+        }
+    }
+
+    /// the interval the next empty poll will wait
+    pub fn current(&self) -> Duration {
+        self.current
+    }
+
+    /// call after messages were processed, the next empty poll waits the minimum again
+    pub fn reset(&mut self) {
+        self.current = self.min;
+    }
+
+    /// call when the queue was empty, returns how long to wait and grows the next interval
+    pub fn next_empty(&mut self) -> Duration {
+        let wait = self.current;
+        self.current = std::cmp::min(self.current * 2, self.max);
+        wait
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_poll_backoff() {
+        let mut backoff = PollBackoff::new(Duration::from_millis(100), Duration::from_millis(1000));
+
+        // repeatedly empty queue, the wait grows toward the max
+        let waits: Vec<u128> = (0..6).map(|_| backoff.next_empty().as_millis()).collect();
+        assert_eq!(waits, vec![100, 200, 400, 800, 1000, 1000]);
+
+        // processing a message resets to the short interval
+        backoff.reset();
+        assert_eq!(backoff.current(), Duration::from_millis(100));
+        assert_eq!(backoff.next_empty(), Duration::from_millis(100));
+        assert_eq!(backoff.next_empty(), Duration::from_millis(200));
+
+        // a max below the min is raised to the min
+        let mut backoff = PollBackoff::new(Duration::from_millis(500), Duration::from_millis(100));
+        assert_eq!(backoff.next_empty(), Duration::from_millis(500));
+        assert_eq!(backoff.next_empty(), Duration::from_millis(500));
+    }
+}
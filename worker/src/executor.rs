@@ -1,23 +1,39 @@
 use pgmq::Message;
 use sqlx::PgPool;
 use vectorize_core::errors::VectorizeError;
-use vectorize_core::types::JobMessage;
+use vectorize_core::types::{
+    ConflictStrategy, DeadLetterMessage, JobMessage, Modality, dead_letter_queue,
+};
 
+use crate::metrics::WorkerMetrics;
 use crate::ops;
 use anyhow::Result;
+use futures::stream::{self, StreamExt};
 use pgmq::PGMQueueExt;
-use tiktoken_rs::cl100k_base;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::Duration;
 use vectorize_core::config::Config;
 use vectorize_core::db;
 use vectorize_core::init;
-use vectorize_core::transformers::{http_handler, providers, types::Inputs};
+use vectorize_core::query;
+use vectorize_core::transformers::providers::cache::{EmbeddingCache, generate_embeddings_cached};
+use vectorize_core::transformers::{
+    http_handler, providers,
+    tokenizer::Tokenizer,
+    types::{Inputs, l2_normalize, truncate_inputs},
+};
 
 pub async fn poll_job(
     conn: &PgPool,
     queue: &PGMQueueExt,
     config: &Config,
+    metrics: &WorkerMetrics,
+    cache: &EmbeddingCache,
 ) -> Result<Option<()>, VectorizeError> {
-    let msg: Message<JobMessage> = match queue.read::<JobMessage>(&config.queue_name, 300_i32).await
+    let msg: Message<JobMessage> = match queue
+        .read::<JobMessage>(&config.queue_name, config.queue_visibility_timeout)
+        .await
     {
         Ok(Some(msg)) => msg,
         Ok(None) => {
@@ -29,37 +45,369 @@ pub async fn poll_job(
         }
     };
 
+    process_message(conn, queue, config, metrics, cache, msg, &[]).await?;
+
+    Ok(Some(()))
+}
+
+/// reads up to `config.read_batch_size` messages and processes up to `config.worker_concurrency`
+/// of them concurrently. an empty queue is waited on for up to `config.read_poll_timeout` seconds,
+/// so a message enqueued meanwhile is read as soon as it arrives
+/// returns the number of messages that were processed successfully, or None if the queue was empty
+pub async fn poll_jobs(
+    conn: &PgPool,
+    queue: &PGMQueueExt,
+    config: &Config,
+    metrics: &WorkerMetrics,
+    cache: &EmbeddingCache,
+) -> Result<Option<usize>, VectorizeError> {
+    let messages: Vec<Message<JobMessage>> = match queue
+        .read_batch_with_poll::<JobMessage>(
+            &config.queue_name,
+            config.queue_visibility_timeout,
+            config.read_batch_size.max(1) as i32,
+            Some(Duration::from_secs(config.read_poll_timeout)),
+            Some(Duration::from_millis(config.read_poll_interval_ms.max(1))),
+        )
+        .await
+    {
+        Ok(Some(messages)) if !messages.is_empty() => messages,
+        Ok(_) => {
+            log::debug!("No message found in queue: {}", config.queue_name);
+            return Ok(None);
+        }
+        Err(e) => {
+            return Err(anyhow::anyhow!("failed reading messages: {}", e).into());
+        }
+    };
+
+    let num_messages = messages.len();
+    let coalesced = coalesce_messages(messages);
+    // messages waiting for a free slot, their visibility timeout is extended until they are processed,
+    // otherwise those read beyond worker_concurrency could expire and be read again meanwhile
+    let waiting: Mutex<HashSet<i64>> = Mutex::new(
+        coalesced
+            .iter()
+            .flat_map(|(msg, merged)| std::iter::once(msg.msg_id).chain(merged.iter().copied()))
+            .collect(),
+    );
+    let waiting = &waiting;
+    let mut in_flight = stream::iter(coalesced)
+        .map(|(msg, merged)| async move {
+            {
+                let mut waiting = waiting.lock().unwrap();
+                waiting.remove(&msg.msg_id);
+                merged.iter().for_each(|id| {
+                    waiting.remove(id);
+                });
+            }
+            let result = process_message(conn, queue, config, metrics, cache, msg, &merged).await;
+            (result, 1 + merged.len())
+        })
+        .buffer_unordered(config.worker_concurrency.max(1));
+
+    let drain = async {
+        let mut processed = 0;
+        let mut last_error: Option<VectorizeError> = None;
+        while let Some((result, count)) = in_flight.next().await {
+            match result {
+                Ok(()) => processed += count,
+                Err(e) => last_error = Some(e),
+            }
+        }
+        (processed, last_error)
+    };
+    let vt = config.queue_visibility_timeout;
+    let extend = || async move {
+        let ids: Vec<i64> = waiting.lock().unwrap().iter().copied().collect();
+        for id in ids {
+            queue
+                .set_vt::<JobMessage>(&config.queue_name, id, vt)
+                .await?;
+        }
+        Ok::<(), pgmq::PgmqError>(())
+    };
+    let (processed, last_error) =
+        keep_invisible(drain, visibility_extension_interval(vt), extend).await;
+
+    match last_error {
+        // only surface the error when nothing in the batch succeeded, so the caller backs off
+        Some(e) if processed == 0 => Err(e),
+        _ => {
+            log::debug!("processed {processed} of {num_messages} messages");
+            Ok(Some(processed))
+        }
+    }
+}
+
+// a message, the ids of the messages folded into it, and the record ids of all of them
+type MessageGroup = (Message<JobMessage>, Vec<i64>, HashSet<String>);
+
+/// folds messages of the same job whose record ids overlap into the first of them, so rows updated
+/// several times in quick succession are embedded once per poll instead of once per message
+/// messages with disjoint ids are kept apart and still processed concurrently
+/// returns each message with the ids of the messages folded into it
+fn coalesce_messages(messages: Vec<Message<JobMessage>>) -> Vec<(Message<JobMessage>, Vec<i64>)> {
+    // a folded group is None once it has been folded into an earlier one
+    let mut groups: Vec<Option<MessageGroup>> = Vec::new();
+    for msg in messages {
+        let ids: HashSet<String> = msg.message.record_ids.iter().cloned().collect();
+        let overlapping: Vec<usize> = groups
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, group)| {
+                let (first, _, group_ids) = group.as_ref()?;
+                (first.message.job_name == msg.message.job_name && !group_ids.is_disjoint(&ids))
+                    .then_some(idx)
+            })
+            .collect();
+        let Some((&target, rest)) = overlapping.split_first() else {
+            groups.push(Some((msg, Vec::new(), ids)));
+            continue;
+        };
+        // a message overlapping several groups joins them into the earliest
+        let mut folded = vec![(msg, Vec::new(), ids)];
+        folded.extend(rest.iter().filter_map(|&idx| groups[idx].take()));
+        let (first, merged, group_ids) = groups[target].as_mut().expect("group is not folded");
+        for (msg, inner, ids) in folded {
+            first.message.record_ids.extend(msg.message.record_ids);
+            // the most retried message decides when the batch moves to the dead letter queue
+            first.read_ct = first.read_ct.max(msg.read_ct);
+            merged.push(msg.msg_id);
+            merged.extend(inner);
+            group_ids.extend(ids);
+        }
+    }
+    let mut coalesced: Vec<(Message<JobMessage>, Vec<i64>)> = groups
+        .into_iter()
+        .flatten()
+        .map(|(msg, merged, _)| (msg, merged))
+        .collect();
+    for (msg, merged) in coalesced.iter_mut() {
+        merged.sort_unstable();
+        if !merged.is_empty() {
+            log::debug!(
+                "coalesced {} messages of job: {}",
+                merged.len() + 1,
+                msg.message.job_name
+            );
+        }
+        dedup_record_ids(&mut msg.message.record_ids);
+    }
+    coalesced
+}
+
+// drops repeated ids, keeping the first of each in order
+// each row's current content is read when it is embedded, so one id per row is enough
+fn dedup_record_ids(record_ids: &mut Vec<String>) {
+    let mut seen = HashSet::new();
+    record_ids.retain(|id| seen.insert(id.clone()));
+}
+
+/// executes a single message, retrying it via its visibility timeout on failure
+/// and moving it to the dead letter queue once it exceeds max retries
+/// `merged` are the ids of messages folded into it, they are deleted along with it
+async fn process_message(
+    conn: &PgPool,
+    queue: &PGMQueueExt,
+    config: &Config,
+    metrics: &WorkerMetrics,
+    cache: &EmbeddingCache,
+    msg: Message<JobMessage>,
+    merged: &[i64],
+) -> Result<(), VectorizeError> {
     let job_name = msg.message.job_name.clone();
 
     let read_ct: i32 = msg.read_ct;
     let msg_id: i64 = msg.msg_id;
+    let job_message = msg.message.clone();
+    if db::is_job_paused(conn, &job_name).await? {
+        set_aside(queue, config, job_message, msg_id).await?;
+        delete_merged(queue, &config.queue_name, merged).await?;
+        tracing::info!(job_name, msg_id, "job is paused, set aside job message");
+        return Ok(());
+    }
     if read_ct <= config.max_retries {
-        match execute_job(conn, msg).await {
+        let vt = config.queue_visibility_timeout;
+        let extend = || async move {
+            for id in std::iter::once(msg_id).chain(merged.iter().copied()) {
+                queue
+                    .set_vt::<JobMessage>(&config.queue_name, id, vt)
+                    .await?;
+            }
+            Ok::<(), pgmq::PgmqError>(())
+        };
+        let started = std::time::Instant::now();
+        let job = execute_job(conn, config, metrics, cache, msg);
+        let result = keep_invisible(job, visibility_extension_interval(vt), extend).await;
+        let latency_ms = started.elapsed().as_millis() as u64;
+        match result {
             Ok(_) => {
-                log::info!("Successfully processed job: {job_name}");
+                metrics.jobs_processed.inc();
+                tracing::info!(job_name, msg_id, latency_ms, "processed job message");
             }
             Err(e) => {
-                log::error!("Error processing job: {job_name}, msg_id: {msg_id}, error: {e}");
+                metrics.jobs_failed.inc();
+                tracing::error!(
+                    job_name,
+                    msg_id,
+                    latency_ms,
+                    read_ct,
+                    error = %e,
+                    "failed processing job message"
+                );
+                if read_ct == config.max_retries {
+                    // last attempt, so keep the failure around instead of retrying
+                    send_to_dead_letter_queue(
+                        queue,
+                        &config.queue_name,
+                        job_message,
+                        read_ct,
+                        &e.to_string(),
+                    )
+                    .await?;
+                    queue.delete(&config.queue_name, msg_id).await?;
+                    delete_merged(queue, &config.queue_name, merged).await?;
+                }
                 Err(e)?;
             }
         }
     } else {
-        log::error!(
-            "message exceeds max retry of {}, archiving msg_id: {}",
-            config.max_retries,
-            msg_id
+        tracing::error!(
+            job_name,
+            msg_id,
+            read_ct,
+            "message exceeds max retry of {}, moving it to the dead letter queue",
+            config.max_retries
         );
+        let error = format!("exceeded max retries of {}", config.max_retries);
+        send_to_dead_letter_queue(queue, &config.queue_name, job_message, read_ct, &error).await?;
     }
 
     queue.delete(&config.queue_name, msg_id).await?;
+    delete_merged(queue, &config.queue_name, merged).await?;
 
-    Ok(Some(()))
+    Ok(())
 }
 
-/// processes a single job from the queue
-async fn execute_job(pool: &PgPool, msg: Message<JobMessage>) -> Result<(), VectorizeError> {
-    let bpe = cl100k_base().unwrap();
+async fn delete_merged(
+    queue: &PGMQueueExt,
+    queue_name: &str,
+    merged: &[i64],
+) -> Result<(), VectorizeError> {
+    if !merged.is_empty() {
+        queue.delete_batch(queue_name, merged).await?;
+    }
+    Ok(())
+}
+
+// how often a message being processed has its visibility timeout pushed forward,
+// halfway through the timeout so a slow extension still lands before it expires
+fn visibility_extension_interval(vt: i32) -> Duration {
+    Duration::from_secs((vt / 2).max(1) as u64)
+}
+
+/// runs `work`, calling `extend` every `interval` until it finishes
+/// keeps a message invisible to other workers however long its batch takes to embed
+async fn keep_invisible<T, E, Fut>(
+    work: impl Future<Output = T>,
+    interval: Duration,
+    mut extend: impl FnMut() -> Fut,
+) -> T
+where
+    E: std::fmt::Display,
+    Fut: Future<Output = Result<(), E>>,
+{
+    tokio::pin!(work);
+    let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+    loop {
+        tokio::select! {
+            out = &mut work => return out,
+            _ = ticker.tick() => {
+                // the message may be read again once the timeout expires, but processing goes on
+                if let Err(e) = extend().await {
+                    log::warn!("failed to extend message visibility timeout: {e}");
+                }
+            }
+        }
+    }
+}
 
+// re-sends a paused job's message with a delay so it is checked again later
+// a fresh message starts with no reads, so a long pause never moves it to the dead letter queue
+async fn set_aside(
+    queue: &PGMQueueExt,
+    config: &Config,
+    message: JobMessage,
+    msg_id: i64,
+) -> Result<(), VectorizeError> {
+    let delay = config.queue_visibility_timeout.max(1) as u32;
+    let mut tx = queue.connection.begin().await?;
+    queue
+        .send_delay_with_cxn(&config.queue_name, &message, delay, &mut *tx)
+        .await?;
+    queue
+        .delete_with_cxn(&config.queue_name, msg_id, &mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+async fn send_to_dead_letter_queue(
+    queue: &PGMQueueExt,
+    queue_name: &str,
+    message: JobMessage,
+    read_ct: i32,
+    error: &str,
+) -> Result<(), VectorizeError> {
+    let dlq = dead_letter_queue(queue_name);
+    let dead_letter = DeadLetterMessage {
+        message,
+        error: error.to_string(),
+        read_ct,
+    };
+    let dlq_msg_id = queue.send(&dlq, &dead_letter).await?;
+    log::warn!(
+        "sent job: {} to dead letter queue: {dlq}, msg_id: {dlq_msg_id}",
+        dead_letter.message.job_name
+    );
+    Ok(())
+}
+
+/// moves up to `max_messages` messages from the dead letter queue back onto the job queue
+/// returns the number of messages that were re-enqueued
+pub async fn requeue_dead_letters(
+    queue: &PGMQueueExt,
+    queue_name: &str,
+    max_messages: usize,
+) -> Result<usize, VectorizeError> {
+    let dlq = dead_letter_queue(queue_name);
+    let mut requeued = 0;
+    while requeued < max_messages {
+        let Some(msg) = queue.read::<DeadLetterMessage>(&dlq, 30_i32).await? else {
+            break;
+        };
+        // send and delete together so a message is never lost or duplicated
+        let mut tx = queue.connection.begin().await?;
+        queue
+            .send_with_cxn(queue_name, &msg.message.message, &mut *tx)
+            .await?;
+        queue.delete_with_cxn(&dlq, msg.msg_id, &mut *tx).await?;
+        tx.commit().await?;
+        requeued += 1;
+    }
+    log::info!("re-enqueued {requeued} messages from {dlq} onto {queue_name}");
+    Ok(requeued)
+}
+
+/// processes a single job from the queue
+async fn execute_job(
+    pool: &PgPool,
+    config: &Config,
+    metrics: &WorkerMetrics,
+    cache: &EmbeddingCache,
+    msg: Message<JobMessage>,
+) -> Result<(), VectorizeError> {
     let job_name = msg.message.job_name.clone();
 
     // Check if the job still exists - it may have been deleted
@@ -87,9 +435,19 @@ async fn execute_job(pool: &PgPool, msg: Message<JobMessage>) -> Result<(), Vect
     log::debug!("Retrieved vectorize job: {vectorizejob:?}");
     let provider = providers::get_provider(&vectorizejob.model.source, None, None, None)?;
 
-    log::info!("processing job: {vectorizejob:?}");
+    let msg_id = msg.msg_id;
+    let mut record_ids = msg.message.record_ids;
+    dedup_record_ids(&mut record_ids);
+    let provider_name = vectorizejob.model.source.to_string();
+    tracing::info!(
+        job_name,
+        msg_id,
+        provider = provider_name,
+        records = record_ids.len(),
+        "processing job message"
+    );
 
-    let pkey_type = init::get_column_datatype(
+    let pkey_type = init::get_column_datatypes(
         pool,
         &vectorizejob.src_schema,
         &vectorizejob.src_table,
@@ -97,23 +455,60 @@ async fn execute_job(pool: &PgPool, msg: Message<JobMessage>) -> Result<(), Vect
     )
     .await?;
 
-    let select_cols = vectorizejob
-        .src_columns
-        .iter()
-        .map(|col| format!("'{col}: ' || COALESCE({col}, '') || ' '"))
-        .collect::<Vec<String>>()
-        .join(" || ' ' || ");
+    // image jobs have a single column holding the image URL, rows without one have nothing to embed
+    let (select_cols, image_filter) = match vectorizejob.modality {
+        Modality::text => {
+            // text of a joined table is labelled by its column like the source columns
+            let joined = vectorizejob
+                .text_join
+                .as_ref()
+                .map(|join| query::joined_text_columns(join, &vectorizejob.src_schema, "t0"))
+                .unwrap_or_default();
+            (
+                vectorizejob
+                    .src_columns
+                    .iter()
+                    .map(|col| (col.clone(), col.clone()))
+                    .chain(joined)
+                    .map(|(col, expr)| format!("'{col}: ' || COALESCE({expr}, '') || ' '"))
+                    .collect::<Vec<String>>()
+                    .join(" || ' ' || "),
+                String::new(),
+            )
+        }
+        Modality::image => {
+            let col = &vectorizejob.src_columns[0];
+            (
+                format!("COALESCE({col}, '')"),
+                format!(" AND COALESCE(t0.{col}, '') <> ''"),
+            )
+        }
+    };
+    // rows that already have an embedding are kept as they are, so they are not embedded again
+    let skip_filter = match vectorizejob.conflict_strategy {
+        ConflictStrategy::overwrite => String::new(),
+        ConflictStrategy::skip => format!(
+            " AND {}",
+            query::missing_embedding_filter(
+                &vectorizejob.job_name,
+                &vectorizejob.table_prefixes,
+                &vectorizejob.target_schema,
+                &vectorizejob.primary_key,
+                "t0",
+            )
+        ),
+    };
     let job_records_query = format!(
         "
     SELECT
-        {primary_key}::text as record_id,
+        {record_id} as record_id,
         {select_cols} as input_text
-    FROM {schema}.{relation}
-    WHERE {primary_key} = ANY ($1::{pk_type}[])",
-        primary_key = vectorizejob.primary_key,
+    FROM {schema}.{relation} t0
+    WHERE {record_ids_filter}{skip_filter}{image_filter}",
+        record_id = query::record_id_expr(&vectorizejob.primary_key, "t0"),
+        record_ids_filter = query::record_ids_filter(&vectorizejob.primary_key, &pkey_type, 1),
         schema = vectorizejob.src_schema,
         relation = vectorizejob.src_table,
-        pk_type = pkey_type
     );
 
     #[derive(sqlx::FromRow)]
@@ -123,38 +518,510 @@ async fn execute_job(pool: &PgPool, msg: Message<JobMessage>) -> Result<(), Vect
     }
 
     let job_records: Vec<Res> = sqlx::query_as(&job_records_query)
-        .bind(&msg.message.record_ids)
+        .bind(&record_ids)
         .fetch_all(pool)
         .await?;
+    if job_records.is_empty() {
+        // the rows were deleted, or all of them already have an embedding when skipping
+        log::debug!("no records to embed for job: {job_name}");
+        return Ok(());
+    }
 
-    let inputs: Vec<Inputs> = job_records
-        .iter()
-        .map(|row| {
-            let token_estimate = bpe.encode_with_special_tokens(&row.input_text).len() as i32;
-            Inputs {
-                record_id: row.record_id.clone(),
-                inputs: row.input_text.trim().to_owned(),
-                token_estimate,
+    let mut inputs: Vec<Inputs> = match vectorizejob.modality {
+        Modality::text => {
+            let tokenizer = Tokenizer::for_model(&vectorizejob.model);
+            let mut inputs: Vec<Inputs> = job_records
+                .iter()
+                .map(|row| {
+                    let input_text = vectorizejob.normalization.apply(row.input_text.trim());
+                    let token_estimate = tokenizer.count(&input_text) as i32;
+                    Inputs {
+                        record_id: row.record_id.clone(),
+                        inputs: input_text,
+                        token_estimate,
+                    }
+                })
+                .collect();
+            let truncated = truncate_inputs(&mut inputs, &tokenizer, config.max_input_tokens);
+            if truncated > 0 {
+                log::warn!(
+                    "truncated {truncated} inputs of job {job_name} to {} tokens",
+                    config.max_input_tokens
+                );
             }
+            inputs
+        }
+        // an image URL or data URI is sent as it is, normalizing or truncating it would break it
+        Modality::image => job_records
+            .iter()
+            .map(|row| Inputs {
+                record_id: row.record_id.clone(),
+                inputs: row.input_text.trim().to_string(),
+                token_estimate: 0,
+            })
+            .collect(),
+    };
+
+    // rows whose text is unchanged keep their embedding, only its update time moves forward
+    let mut hashes: HashMap<String, String> = inputs
+        .iter()
+        .map(|input| {
+            (
+                input.record_id.clone(),
+                http_handler::content_hash(&input.inputs),
+            )
         })
         .collect();
+    let record_ids: Vec<String> = hashes.keys().cloned().collect();
+    let stored_hashes: Vec<(String, Option<String>)> =
+        sqlx::query_as(&query::stored_content_hashes(
+            &vectorizejob.job_name,
+            &vectorizejob.table_prefixes,
+            &vectorizejob.target_schema,
+            &vectorizejob.primary_key,
+            &pkey_type,
+        ))
+        .bind(&record_ids)
+        .fetch_all(pool)
+        .await?;
+    let unchanged: HashSet<String> = stored_hashes
+        .into_iter()
+        .filter(|(record_id, hash)| hash.is_some() && hashes.get(record_id) == hash.as_ref())
+        .map(|(record_id, _)| record_id)
+        .collect();
+    if !unchanged.is_empty() {
+        log::debug!(
+            "{} records of job {job_name} are unchanged, keeping their embeddings",
+            unchanged.len()
+        );
+        sqlx::query(&query::touch_embeddings(
+            &vectorizejob.job_name,
+            &vectorizejob.table_prefixes,
+            &vectorizejob.target_schema,
+            &vectorizejob.primary_key,
+            &pkey_type,
+        ))
+        .bind(unchanged.iter().cloned().collect::<Vec<String>>())
+        .execute(pool)
+        .await?;
+        inputs.retain(|input| !unchanged.contains(&input.record_id));
+        if inputs.is_empty() {
+            return record_completion(pool, &job_name).await;
+        }
+    }
 
-    let embedding_request =
-        providers::prepare_generic_embedding_request(&vectorizejob.model, &inputs);
-
-    let embeddings = provider.generate_embedding(&embedding_request).await?;
+    let timer = metrics.embedding_latency.start_timer();
+    let embeddings = match vectorizejob.modality {
+        Modality::text => {
+            generate_embeddings_cached(
+                cache,
+                provider.as_ref(),
+                &vectorizejob.model,
+                &inputs,
+                providers::InputType::Document,
+                config.embedding_batch_max_tokens,
+            )
+            .await
+        }
+        Modality::image => {
+            providers::generate_image_embeddings(provider.as_ref(), &vectorizejob.model, &inputs)
+                .await
+        }
+    };
+    let mut embeddings = match embeddings {
+        Ok(embeddings) => embeddings,
+        Err(e) => {
+            metrics
+                .provider_errors
+                .with_label_values(&[&provider_name])
+                .inc();
+            tracing::warn!(
+                job_name,
+                msg_id,
+                provider = provider_name,
+                latency_ms = (timer.stop_and_record() * 1000.0) as u64,
+                error = %e,
+                "embedding request failed"
+            );
+            return Err(e);
+        }
+    };
+    let latency_ms = (timer.stop_and_record() * 1000.0) as u64;
+    tracing::info!(
+        job_name,
+        msg_id,
+        provider = provider_name,
+        latency_ms,
+        records = embeddings.len(),
+        "embedded records"
+    );
+    if vectorizejob.normalize_embeddings {
+        embeddings.iter_mut().for_each(|e| l2_normalize(e));
+    }
 
-    let paired_embeddings = http_handler::merge_input_output(inputs, embeddings.embeddings);
+    let mut paired_embeddings = http_handler::merge_input_output(inputs, embeddings);
+    for pair in paired_embeddings.iter_mut() {
+        pair.content_hash = hashes.remove(&pair.primary_key);
+    }
 
     ops::upsert_embedding_table(
         pool,
-        &vectorizejob.job_name,
+        &vectorizejob.embeddings_table(),
         paired_embeddings,
-        "vectorize", // embeddings always live in vectorize schema
+        &vectorizejob.target_schema,
         &vectorizejob.primary_key,
         &pkey_type,
+        &vectorizejob.conflict_strategy,
+        &vectorizejob.vector_type,
     )
     .await?;
 
+    record_completion(pool, &job_name).await
+}
+
+async fn record_completion(pool: &PgPool, job_name: &str) -> Result<(), VectorizeError> {
+    sqlx::query(&query::update_last_completion())
+        .bind(job_name)
+        .execute(pool)
+        .await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use std::sync::{Arc, Mutex};
+    use tokio::time::Instant;
+    use vectorize_core::transformers::providers::{
+        EmbeddingProvider, GenericEmbeddingRequest, GenericEmbeddingResponse,
+    };
+    use vectorize_core::types::Model;
+
+    // takes longer to embed a batch than the visibility timeout lasts
+    struct SlowProvider {
+        delay: Duration,
+    }
+
+    #[async_trait]
+    impl EmbeddingProvider for SlowProvider {
+        async fn generate_embedding<'a>(
+            &self,
+            request: &'a GenericEmbeddingRequest,
+        ) -> Result<GenericEmbeddingResponse, VectorizeError> {
+            tokio::time::sleep(self.delay).await;
+            Ok(GenericEmbeddingResponse {
+                embeddings: request.input.iter().map(|_| vec![1.0]).collect(),
+            })
+        }
+
+        async fn model_dim(&self, _model_name: &str) -> Result<u32, VectorizeError> {
+            Ok(1)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_batch_not_read_twice() {
+        let vt = Duration::from_millis(200);
+        let provider = SlowProvider {
+            delay: Duration::from_millis(1000),
+        };
+        let model = Model::new("openai/text-embedding-3-small").unwrap();
+        let inputs = vec![Inputs {
+            record_id: "1".to_string(),
+            inputs: "pizza".to_string(),
+            token_estimate: 1,
+        }];
+
+        // stands in for the queue: the message can be read again once this instant passes
+        let visible_at = Arc::new(Mutex::new(Instant::now() + vt));
+        let extend = || {
+            let visible_at = visible_at.clone();
+            async move {
+                *visible_at.lock().unwrap() = Instant::now() + vt;
+                Ok::<(), VectorizeError>(())
+            }
+        };
+        // a second worker polling the queue while the first one embeds
+        let reads = Arc::new(Mutex::new(0));
+        let poller = {
+            let visible_at = visible_at.clone();
+            let reads = reads.clone();
+            tokio::spawn(async move {
+                loop {
+                    if Instant::now() >= *visible_at.lock().unwrap() {
+                        *reads.lock().unwrap() += 1;
+                    }
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            })
+        };
+
+        let work = providers::generate_embeddings_batched(
+            &provider,
+            &model,
+            &inputs,
+            providers::InputType::Document,
+            10000,
+        );
+        let embeddings = keep_invisible(work, vt / 4, extend).await.unwrap();
+        poller.abort();
+        assert_eq!(embeddings, vec![vec![1.0]]);
+        assert_eq!(*reads.lock().unwrap(), 0);
+    }
+
+    fn job_message(
+        msg_id: i64,
+        read_ct: i32,
+        job_name: &str,
+        record_ids: &[&str],
+    ) -> Message<JobMessage> {
+        Message {
+            msg_id,
+            vt: chrono::Utc::now(),
+            enqueued_at: chrono::Utc::now(),
+            read_ct,
+            message: JobMessage {
+                job_name: job_name.to_string(),
+                record_ids: record_ids.iter().map(|id| id.to_string()).collect(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_coalesce_messages() {
+        // a row updated several times in quick succession, and another job's message between them
+        let messages = vec![
+            job_message(1, 1, "products", &["1", "2", "1"]),
+            job_message(2, 1, "articles", &["1"]),
+            job_message(3, 2, "products", &["2", "3"]),
+            job_message(4, 1, "products", &["1"]),
+            job_message(5, 1, "products", &["7"]),
+        ];
+        let coalesced = coalesce_messages(messages);
+        assert_eq!(coalesced.len(), 3);
+
+        let (products, merged) = &coalesced[0];
+        assert_eq!(products.msg_id, 1);
+        assert_eq!(products.read_ct, 2);
+        assert_eq!(products.message.record_ids, vec!["1", "2", "3"]);
+        assert_eq!(merged, &vec![3, 4]);
+
+        let (articles, merged) = &coalesced[1];
+        assert_eq!(articles.msg_id, 2);
+        assert_eq!(articles.message.record_ids, vec!["1"]);
+        assert!(merged.is_empty());
+
+        // rows no other message touches are embedded on their own, concurrently with the rest
+        let (disjoint, merged) = &coalesced[2];
+        assert_eq!(disjoint.msg_id, 5);
+        assert_eq!(disjoint.message.record_ids, vec!["7"]);
+        assert!(merged.is_empty());
+
+        // a message overlapping two groups joins them
+        let messages = vec![
+            job_message(1, 1, "products", &["1"]),
+            job_message(2, 1, "products", &["2"]),
+            job_message(3, 1, "products", &["2", "1"]),
+        ];
+        let coalesced = coalesce_messages(messages);
+        assert_eq!(coalesced.len(), 1);
+        let (products, merged) = &coalesced[0];
+        assert_eq!(products.msg_id, 1);
+        assert_eq!(products.message.record_ids, vec!["1", "2"]);
+        assert_eq!(merged, &vec![2, 3]);
+    }
+
+    // an openai compatible embeddings server recording the inputs of each request it receives
+    // it responds after the delay, which is 0 until changed
+    async fn recording_server() -> (String, Arc<Mutex<Vec<Vec<String>>>>, Arc<Mutex<Duration>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        let delay = Arc::new(Mutex::new(Duration::ZERO));
+        let response_delay = delay.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut received = Vec::new();
+                let mut buf = vec![0; 8192];
+                // reads the headers, then the body up to its content-length
+                let body = loop {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    received.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&received).to_string();
+                    let Some((headers, body)) = text.split_once("\r\n\r\n") else {
+                        continue;
+                    };
+                    let len = headers
+                        .lines()
+                        .find_map(|line| {
+                            line.to_lowercase()
+                                .strip_prefix("content-length: ")?
+                                .parse()
+                                .ok()
+                        })
+                        .unwrap_or(0);
+                    if body.len() >= len {
+                        break body.to_string();
+                    }
+                };
+                let request: serde_json::Value = serde_json::from_str(&body).unwrap();
+                let inputs: Vec<String> = serde_json::from_value(request["input"].clone()).unwrap();
+                let data: Vec<serde_json::Value> = (0..inputs.len())
+                    .map(|index| serde_json::json!({"index": index, "embedding": [0.1, 0.2, 0.3]}))
+                    .collect();
+                recorded.lock().unwrap().push(inputs);
+                let delay = *response_delay.lock().unwrap();
+                tokio::time::sleep(delay).await;
+                let body = serde_json::json!({ "data": data }).to_string();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (format!("http://{addr}"), requests, delay)
+    }
+
+    #[ignore]
+    #[tokio::test]
+    async fn test_duplicate_record_ids_embedded_once() {
+        let (url, requests, delay) = recording_server().await;
+        // only this test uses the openai_compat provider
+        unsafe {
+            std::env::set_var("OPENAI_COMPAT_BASE_URL", url);
+        }
+        let mut cfg = Config::from_env();
+        cfg.queue_name = "coalesce_test".to_string();
+        cfg.read_batch_size = 10;
+        cfg.worker_concurrency = 4;
+        let pool = PgPool::connect(&cfg.database_url).await.unwrap();
+        init::init_pgmq(&pool).await.unwrap();
+        for statement in [
+            "DROP TABLE IF EXISTS public.coalesce_test",
+            "CREATE TABLE public.coalesce_test (id INTEGER PRIMARY KEY, content TEXT, updated_at TIMESTAMPTZ DEFAULT now())",
+            "INSERT INTO public.coalesce_test (id, content) VALUES (1, 'pizza'), (2, 'pencil'), (3, 'airplane'), (4, 'bicycle')",
+        ] {
+            sqlx::query(statement).execute(&pool).await.unwrap();
+        }
+        let _ = sqlx::query("SELECT pgmq.drop_queue('coalesce_test')")
+            .execute(&pool)
+            .await;
+        sqlx::query("SELECT pgmq.create('coalesce_test')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let job: vectorize_core::types::VectorizeJob = serde_json::from_value(serde_json::json!({
+            "job_name": "coalesce_test",
+            "src_table": "coalesce_test",
+            "src_schema": "public",
+            "src_columns": ["content"],
+            "primary_key": "id",
+            "update_time_col": "updated_at",
+            "model": "openai_compat/test-model"
+        }))
+        .unwrap();
+        init::initialize_job(
+            &pool,
+            &job,
+            100,
+            None,
+            &init::JobQueue::new("coalesce_test"),
+        )
+        .await
+        .unwrap();
+        // drops the initial scan, and the probe of the model's dimension
+        sqlx::query("SELECT pgmq.purge_queue('coalesce_test')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        requests.lock().unwrap().clear();
+
+        // rows 1 to 3 updated several times in quick succession, and row 4 on its own
+        let queue = PGMQueueExt::new_with_pool(pool.clone()).await;
+        for record_ids in [vec!["1", "2", "1"], vec!["2", "3"], vec!["1"], vec!["4"]] {
+            let msg = JobMessage {
+                job_name: "coalesce_test".to_string(),
+                record_ids: record_ids.into_iter().map(String::from).collect(),
+            };
+            queue.send("coalesce_test", &msg).await.unwrap();
+        }
+        let processed = poll_jobs(
+            &pool,
+            &queue,
+            &cfg,
+            &WorkerMetrics::new(),
+            &EmbeddingCache::new(0, Duration::ZERO),
+        )
+        .await
+        .unwrap();
+        assert_eq!(processed, Some(4));
+
+        // one provider call for the overlapping messages and one for row 4, each row embedded once
+        let recorded = requests.lock().unwrap().clone();
+        assert_eq!(recorded.len(), 2);
+        let mut embedded: Vec<String> = recorded.into_iter().flatten().collect();
+        embedded.sort();
+        assert_eq!(
+            embedded,
+            vec![
+                "content: airplane",
+                "content: bicycle",
+                "content: pencil",
+                "content: pizza"
+            ]
+        );
+
+        // a message read beyond worker_concurrency stays invisible while it waits for a slot,
+        // here row 1 takes longer to embed than the visibility timeout lasts
+        sqlx::query("DELETE FROM vectorize._embeddings_coalesce_test")
+            .execute(&pool)
+            .await
+            .unwrap();
+        *delay.lock().unwrap() = Duration::from_secs(3);
+        cfg.worker_concurrency = 1;
+        cfg.queue_visibility_timeout = 2;
+        for record_id in ["1", "4"] {
+            let msg = JobMessage {
+                job_name: "coalesce_test".to_string(),
+                record_ids: vec![record_id.to_string()],
+            };
+            queue.send("coalesce_test", &msg).await.unwrap();
+        }
+        let metrics = WorkerMetrics::new();
+        let cache = EmbeddingCache::new(0, Duration::ZERO);
+        let visible = async {
+            tokio::time::sleep(Duration::from_millis(2500)).await;
+            sqlx::query_scalar::<_, i64>(
+                "SELECT COUNT(*) FROM pgmq.q_coalesce_test WHERE vt <= now()",
+            )
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+        };
+        let (processed, visible) =
+            tokio::join!(poll_jobs(&pool, &queue, &cfg, &metrics, &cache), visible);
+        assert_eq!(processed.unwrap(), Some(2));
+        assert_eq!(visible, 0);
+    }
+
+    #[test]
+    fn test_dedup_record_ids() {
+        let mut ids: Vec<String> = ["3", "1", "3", "2", "1"].map(String::from).to_vec();
+        dedup_record_ids(&mut ids);
+        assert_eq!(ids, vec!["3", "1", "2"]);
+    }
+
+    #[test]
+    fn test_visibility_extension_interval() {
+        assert_eq!(visibility_extension_interval(300), Duration::from_secs(150));
+        assert_eq!(visibility_extension_interval(1), Duration::from_secs(1));
+        assert_eq!(visibility_extension_interval(0), Duration::from_secs(1));
+    }
+}
@@ -54,6 +54,14 @@ impl WorkerHealthMonitor {
         }
     }
 
+    /// monitor that reports into an existing shared health state
+    pub fn with_health(health: Arc<RwLock<WorkerHealth>>) -> Self {
+        Self {
+            health,
+            start_time: SystemTime::now(),
+        }
+    }
+
     pub async fn heartbeat(&self) {
         let mut health = self.health.write().await;
         health.last_heartbeat = SystemTime::now();
@@ -1,19 +1,33 @@
+pub mod backoff;
 pub mod executor;
 pub mod health;
+pub mod metrics;
 pub mod ops;
+pub mod scheduler;
+pub mod shutdown;
 
 pub use health::*;
+pub use metrics::WorkerMetrics;
 
-use crate::executor::poll_job;
+use crate::backoff::PollBackoff;
+use crate::executor::poll_jobs;
+use crate::shutdown::{shutdown_channel, sleep_or_shutdown};
 use log::{debug, error, info, warn};
 use sqlx::PgPool;
 use std::time::Duration;
+use tokio::sync::watch;
 use vectorize_core::config::Config;
+use vectorize_core::transformers::providers::cache::EmbeddingCache;
 
+/// runs the worker until `shutdown` flips to true
+/// messages already being processed are finished before returning
 pub async fn start_vectorize_worker_with_monitoring(
     cfg: Config,
     pool: PgPool,
     health_monitor: WorkerHealthMonitor,
+    metrics: WorkerMetrics,
+    embedding_cache: EmbeddingCache,
+    mut shutdown: watch::Receiver<bool>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let mut restart_count = 0;
     let max_restarts = 5;
@@ -23,7 +37,15 @@ pub async fn start_vectorize_worker_with_monitoring(
     loop {
         health_monitor.set_status(WorkerStatus::Starting).await;
 
-        match start_vectorize_worker_inner(cfg.clone(), pool.clone(), health_monitor.clone()).await
+        match start_vectorize_worker_inner(
+            cfg.clone(),
+            pool.clone(),
+            health_monitor.clone(),
+            metrics.clone(),
+            embedding_cache.clone(),
+            shutdown.clone(),
+        )
+        .await
         {
             Ok(_) => {
                 info!("Vectorize worker completed normally");
@@ -47,7 +69,10 @@ pub async fn start_vectorize_worker_with_monitoring(
                 warn!(
                     "Restarting worker in {restart_delay:?} (attempt {restart_count}/{max_restarts})"
                 );
-                tokio::time::sleep(restart_delay).await;
+                sleep_or_shutdown(restart_delay, &mut shutdown).await;
+                if *shutdown.borrow() {
+                    break;
+                }
                 restart_delay = std::cmp::min(restart_delay * 2, max_restart_delay);
             }
         }
@@ -60,35 +85,48 @@ async fn start_vectorize_worker_inner(
     cfg: Config,
     pool: PgPool,
     health_monitor: WorkerHealthMonitor,
+    metrics: WorkerMetrics,
+    embedding_cache: EmbeddingCache,
+    mut shutdown: watch::Receiver<bool>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     info!("Starting vectorize worker");
     health_monitor.set_status(WorkerStatus::Healthy).await;
 
     let queue = pgmq::PGMQueueExt::new_with_pool(pool.clone()).await;
+    let mut backoff = PollBackoff::new(
+        Duration::from_millis(cfg.poll_interval_min_ms),
+        Duration::from_millis(cfg.poll_interval_max_ms),
+    );
 
-    loop {
+    // stop reading new messages once shutdown is requested, in-flight messages finish first
+    while !*shutdown.borrow() {
         health_monitor.heartbeat().await;
 
-        match poll_job(&pool, &queue, &cfg).await {
-            Ok(Some(_)) => {
-                info!("processed job!");
-                health_monitor.job_processed().await;
+        match poll_jobs(&pool, &queue, &cfg, &metrics, &embedding_cache).await {
+            Ok(Some(processed)) => {
+                debug!("processed {processed} jobs");
+                for _ in 0..processed {
+                    health_monitor.job_processed().await;
+                }
+                backoff.reset();
             }
+            // the read already waited for messages, poll again right away
+            Ok(None) if cfg.read_poll_timeout > 0 => {}
             Ok(None) => {
-                debug!(
-                    "No messages in queue, waiting for {} seconds",
-                    cfg.poll_interval
-                );
-                tokio::time::sleep(tokio::time::Duration::from_secs(cfg.poll_interval)).await;
+                let wait = backoff.next_empty();
+                debug!("No messages in queue, waiting for {wait:?}");
+                sleep_or_shutdown(wait, &mut shutdown).await;
             }
             Err(e) => {
                 let error_msg = format!("Error processing job: {e:?}");
                 error!("{error_msg}");
                 health_monitor.set_error(error_msg).await;
-                tokio::time::sleep(tokio::time::Duration::from_secs(cfg.poll_interval)).await;
+                sleep_or_shutdown(Duration::from_secs(cfg.poll_interval), &mut shutdown).await;
             }
         }
     }
+    info!("Vectorize worker shut down");
+    Ok(())
 }
 
 // Legacy function for backward compatibility
@@ -97,5 +135,14 @@ pub async fn start_vectorize_worker(
     pool: PgPool,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let health_monitor = WorkerHealthMonitor::new();
-    start_vectorize_worker_with_monitoring(cfg, pool, health_monitor).await
+    let embedding_cache = EmbeddingCache::from_config(&cfg);
+    start_vectorize_worker_with_monitoring(
+        cfg,
+        pool,
+        health_monitor,
+        WorkerMetrics::new(),
+        embedding_cache,
+        shutdown_channel(),
+    )
+    .await
 }
@@ -0,0 +1,110 @@
+use vectorize_core::types::ModelSource;
+
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+
+/// prometheus metrics recorded by the worker, rendered by the server's /metrics endpoint
+#[derive(Clone)]
+pub struct WorkerMetrics {
+    pub registry: Registry,
+    pub queue_depth: IntGaugeVec,
+    pub jobs_processed: IntCounter,
+    pub jobs_failed: IntCounter,
+    pub embedding_latency: Histogram,
+    pub provider_errors: IntCounterVec,
+    pub db_pool_connections: IntGaugeVec,
+}
+
+impl Default for WorkerMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WorkerMetrics {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+
+        let queue_depth = IntGaugeVec::new(
+            Opts::new(
+                "vectorize_queue_depth",
+                "Number of messages in the job queue",
+            ),
+            &["queue"],
+        )
+        .expect("valid queue_depth metric");
+        let jobs_processed = IntCounter::new(
+            "vectorize_jobs_processed_total",
+            "Number of queue messages processed successfully",
+        )
+        .expect("valid jobs_processed metric");
+        let jobs_failed = IntCounter::new(
+            "vectorize_jobs_failed_total",
+            "Number of queue messages that failed processing",
+        )
+        .expect("valid jobs_failed metric");
+        let embedding_latency = Histogram::with_opts(HistogramOpts::new(
+            "vectorize_embedding_request_duration_seconds",
+            "Latency of embedding requests to the model provider",
+        ))
+        .expect("valid embedding_latency metric");
+        let provider_errors = IntCounterVec::new(
+            Opts::new(
+                "vectorize_provider_errors_total",
+                "Number of failed embedding requests by model provider",
+            ),
+            &["provider"],
+        )
+        .expect("valid provider_errors metric");
+        let db_pool_connections = IntGaugeVec::new(
+            Opts::new(
+                "vectorize_db_pool_connections",
+                "Number of database connections by pool and state",
+            ),
+            &["pool", "state"],
+        )
+        .expect("valid db_pool_connections metric");
+        // start every provider at zero so the series exist before the first error
+        for source in ModelSource::ALL {
+            provider_errors.with_label_values(&[source.to_string()]);
+        }
+
+        registry
+            .register(Box::new(queue_depth.clone()))
+            .expect("register queue_depth");
+        registry
+            .register(Box::new(jobs_processed.clone()))
+            .expect("register jobs_processed");
+        registry
+            .register(Box::new(jobs_failed.clone()))
+            .expect("register jobs_failed");
+        registry
+            .register(Box::new(embedding_latency.clone()))
+            .expect("register embedding_latency");
+        registry
+            .register(Box::new(provider_errors.clone()))
+            .expect("register provider_errors");
+        registry
+            .register(Box::new(db_pool_connections.clone()))
+            .expect("register db_pool_connections");
+
+        Self {
+            registry,
+            queue_depth,
+            jobs_processed,
+            jobs_failed,
+            embedding_latency,
+            provider_errors,
+            db_pool_connections,
+        }
+    }
+
+    /// renders all metrics in the prometheus text exposition format
+    pub fn render(&self) -> Result<String, prometheus::Error> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8_lossy(&buffer).into_owned())
+    }
+}
@@ -2,20 +2,44 @@ use anyhow::Result;
 use serde_json::to_string;
 use sqlx::{Pool, Postgres};
 use std::fmt::Write;
+use vectorize_core::query;
+use vectorize_core::types::{ConflictStrategy, VectorType};
 use vectorize_core::{errors::VectorizeError, transformers::types::PairedEmbeddings};
 
+// pkey and pkey_type hold every column of a composite primary key, in key order
+// strategy decides whether an existing embedding is overwritten or kept
+// vector_type is the type of the embeddings column the embeddings are cast to, bit quantizes them
+// embeddings_table is the job's embeddings table in schema, see VectorizeJob::embeddings_table
+#[allow(clippy::too_many_arguments)]
 pub async fn upsert_embedding_table(
     conn: &Pool<Postgres>,
-    project: &str,
+    embeddings_table: &str,
     embeddings: Vec<PairedEmbeddings>,
     schema: &str,
-    pkey: &str,
-    pkey_type: &str,
+    pkey: &[String],
+    pkey_type: &[String],
+    strategy: &ConflictStrategy,
+    vector_type: &VectorType,
 ) -> Result<(), VectorizeError> {
-    let (query, bindings) = build_upsert_query(project, embeddings, pkey, pkey_type, schema);
+    // content hashes are only written when the caller computed them,
+    // embeddings tables created by older versions have no content_hash column
+    let with_hash = embeddings.iter().any(|pair| pair.content_hash.is_some());
+    let (query, bindings) = build_upsert_query(
+        embeddings_table,
+        embeddings,
+        pkey,
+        pkey_type,
+        schema,
+        strategy,
+        vector_type,
+        with_hash,
+    );
     let mut q = sqlx::query(&query);
-    for (record_id, embeddings) in bindings {
+    for (record_id, embeddings, content_hash) in bindings {
         q = q.bind(record_id).bind(embeddings);
+        if with_hash {
+            q = q.bind(content_hash);
+        }
     }
     match q.execute(conn).await {
         Ok(_) => Ok(()),
@@ -25,39 +49,57 @@ pub async fn upsert_embedding_table(
 
 // returns query and bindings
 // only compatible with pg-vector data types
+#[allow(clippy::too_many_arguments)]
 fn build_upsert_query(
-    project: &str,
+    embeddings_table: &str,
     embeddings: Vec<PairedEmbeddings>,
-    pkey: &str,
-    pkey_type: &str,
+    pkey: &[String],
+    pkey_type: &[String],
     schema: &str,
-) -> (String, Vec<(String, String)>) {
+    strategy: &ConflictStrategy,
+    vector_type: &VectorType,
+    with_hash: bool,
+) -> (String, Vec<(String, String, Option<String>)>) {
+    let value_columns: &[&str] = if with_hash {
+        &["embeddings", "content_hash"]
+    } else {
+        &["embeddings"]
+    };
+    // bind parameters per row: the record id, then one per value column
+    let per_row = 1 + value_columns.len();
     let mut query = format!(
         "
-        INSERT INTO {schema}._embeddings_{project} ({pkey}, embeddings) VALUES",
+        INSERT INTO {schema}.{embeddings_table} ({}, {}) VALUES",
+        pkey.join(", "),
+        value_columns.join(", ")
     );
-    let mut bindings: Vec<(String, String)> = Vec::new();
+    let mut bindings: Vec<(String, String, Option<String>)> = Vec::new();
 
     for (index, pair) in embeddings.into_iter().enumerate() {
         if index > 0 {
             query.push(',');
         }
+        let first = per_row * index + 1;
+        let hash_param = if with_hash {
+            format!(", ${}", first + 2)
+        } else {
+            String::new()
+        };
         query.push_str(&format!(
-            " (${}::{}, ${}::vector)",
-            2 * index + 1,
-            pkey_type,
-            2 * index + 2
+            " ({}, {}{hash_param})",
+            query::record_id_params(pkey_type, first),
+            vector_type.cast_param(&format!("${}", first + 1))
         ));
 
         let embedding =
             serde_json::to_string(&pair.embeddings).expect("failed to serialize embedding");
-        bindings.push((pair.primary_key, embedding));
+        bindings.push((pair.primary_key, embedding, pair.content_hash));
     }
-    let upsert = format!(
-        " ON CONFLICT ({pkey})
-        DO UPDATE SET embeddings = EXCLUDED.embeddings, updated_at = NOW();"
-    );
-    query.push_str(&upsert);
+    query.push_str(&query::embeddings_conflict_clause(
+        pkey,
+        value_columns,
+        strategy,
+    ));
     (query, bindings)
 }
 
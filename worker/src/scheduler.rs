@@ -0,0 +1,221 @@
+use chrono::{DateTime, Utc};
+use log::{error, info, warn};
+use sqlx::{Connection, PgConnection, PgPool};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::watch;
+use vectorize_core::config::Config;
+use vectorize_core::types::VectorizeJob;
+use vectorize_core::{db, init, schedule};
+
+use crate::shutdown::sleep_or_shutdown;
+
+// cron schedules can fire every second, so due jobs are checked that often
+const TICK: Duration = Duration::from_secs(1);
+// how often a replica that is not running the scheduler tries to take it over
+const STANDBY_TICK: Duration = Duration::from_secs(10);
+
+/// when each scheduled job runs next, keyed by job name
+#[derive(Default)]
+pub struct JobSchedules {
+    // the schedule the next run was computed from, so a changed schedule is picked up
+    next_runs: HashMap<String, (String, DateTime<Utc>)>,
+}
+
+impl JobSchedules {
+    /// returns the jobs that are due at `now` and moves their next run forward
+    /// a job seen for the first time, or whose schedule changed, waits for its next scheduled time
+    pub fn due<'a>(
+        &mut self,
+        jobs: &'a [VectorizeJob],
+        now: DateTime<Utc>,
+    ) -> Vec<&'a VectorizeJob> {
+        self.next_runs
+            .retain(|name, _| jobs.iter().any(|job| &job.job_name == name));
+
+        let mut due = Vec::new();
+        for job in jobs {
+            match self.next_runs.get(&job.job_name) {
+                Some((expr, next)) if *expr == job.schedule => {
+                    if *next > now {
+                        continue;
+                    }
+                    due.push(job);
+                }
+                _ => {}
+            }
+            let next = match schedule::parse_schedule(&job.schedule) {
+                Ok(cron) => schedule::next_run(&cron, now),
+                Err(e) => {
+                    warn!("not scheduling job {}: {e}", job.job_name);
+                    None
+                }
+            };
+            match next {
+                Some(next) => {
+                    self.next_runs
+                        .insert(job.job_name.clone(), (job.schedule.clone(), next));
+                }
+                None => {
+                    self.next_runs.remove(&job.job_name);
+                }
+            }
+        }
+        due
+    }
+}
+
+// replicas sharing a queue run the scheduler in one of them, the one holding this advisory lock
+fn scheduler_lock_key(queue_name: &str) -> String {
+    format!("vectorize_scheduler_{queue_name}")
+}
+
+// takes the scheduler lock on a connection of its own, the lock belongs to that session
+// so the connection is detached from the pool and the lock is released when it closes
+async fn try_lock_scheduler(pool: &PgPool, key: &str) -> Result<Option<PgConnection>, sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    let locked: bool = sqlx::query_scalar("SELECT pg_try_advisory_lock(hashtext($1))")
+        .bind(key)
+        .fetch_one(&mut *conn)
+        .await?;
+    Ok(locked.then(|| conn.detach()))
+}
+
+/// rescans the source table of each job with a cron schedule when it comes due,
+/// enqueueing the rows updated since they were last embedded
+/// only one replica per queue scans at a time, the others stand by until its lock is released
+/// runs until `shutdown` flips to true
+pub async fn start_scheduler(cfg: Config, pool: PgPool, mut shutdown: watch::Receiver<bool>) {
+    info!("Starting job scheduler");
+    // scans wait in place for room, so a job's next scan never overlaps its previous one
+    let queue = init::JobQueue::from_config(&cfg).with_shutdown(shutdown.clone());
+    let lock_key = scheduler_lock_key(&cfg.queue_name);
+    let mut lock: Option<PgConnection> = None;
+    let mut schedules = JobSchedules::default();
+    while !*shutdown.borrow() {
+        // a lost session takes the lock with it, another replica may have taken over since
+        if let Some(conn) = lock.as_mut()
+            && let Err(e) = conn.ping().await
+        {
+            warn!("lost the job scheduler lock: {e}");
+            lock = None;
+        }
+        if lock.is_none() {
+            match try_lock_scheduler(&pool, &lock_key).await {
+                Ok(Some(conn)) => {
+                    info!("running the job scheduler in this replica");
+                    lock = Some(conn);
+                    schedules = JobSchedules::default();
+                }
+                Ok(None) => {}
+                Err(e) => error!("failed to take the job scheduler lock: {e}"),
+            }
+        }
+        if lock.is_none() {
+            sleep_or_shutdown(STANDBY_TICK, &mut shutdown).await;
+            continue;
+        }
+
+        match db::get_scheduled_jobs(&pool).await {
+            Ok(jobs) => {
+                for job in schedules.due(&jobs, Utc::now()) {
+                    match init::scan_job(&pool, job, false, cfg.scan_batch_size, &queue).await {
+                        Ok(batches) => info!(
+                            "scheduled scan of job {} enqueued {batches} batches",
+                            job.job_name
+                        ),
+                        Err(e) => error!("scheduled scan of job {} failed: {e}", job.job_name),
+                    }
+                }
+            }
+            Err(e) => error!("failed to load scheduled jobs: {e}"),
+        }
+        sleep_or_shutdown(TICK, &mut shutdown).await;
+    }
+    if let Some(conn) = lock {
+        let _ = conn.close().await;
+    }
+    info!("Job scheduler shut down");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn job(name: &str, schedule: &str) -> VectorizeJob {
+        serde_json::from_value(serde_json::json!({
+            "job_name": name,
+            "src_table": "products",
+            "src_schema": "public",
+            "src_columns": ["description"],
+            "primary_key": "product_id",
+            "update_time_col": "updated_at",
+            "model": "sentence-transformers/all-MiniLM-L6-v2",
+            "schedule": schedule,
+        }))
+        .unwrap()
+    }
+
+    fn at(secs: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, secs).unwrap()
+    }
+
+    fn names(jobs: Vec<&VectorizeJob>) -> Vec<&str> {
+        jobs.into_iter().map(|j| j.job_name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_short_interval_schedule() {
+        let mut schedules = JobSchedules::default();
+        let jobs = vec![job("fast", "*/2 * * * * *"), job("hourly", "0 * * * *")];
+
+        // first seen at :01, so the first run is at :02
+        assert!(schedules.due(&jobs, at(1)).is_empty());
+        assert_eq!(names(schedules.due(&jobs, at(2))), vec!["fast"]);
+        assert!(schedules.due(&jobs, at(3)).is_empty());
+        // runs missed while the scheduler was busy collapse into one
+        assert_eq!(names(schedules.due(&jobs, at(7))), vec!["fast"]);
+        assert!(schedules.due(&jobs, at(7)).is_empty());
+
+        // a changed schedule waits for its own next run
+        let jobs = vec![job("fast", "*/10 * * * * *")];
+        assert!(schedules.due(&jobs, at(8)).is_empty());
+        assert_eq!(names(schedules.due(&jobs, at(10))), vec!["fast"]);
+
+        // deleted jobs are forgotten
+        assert!(schedules.due(&[], at(20)).is_empty());
+        assert!(schedules.next_runs.is_empty());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_scheduler_lock() {
+        let cfg = Config::from_env();
+        let pool = PgPool::connect(&cfg.database_url).await.unwrap();
+        let key = scheduler_lock_key("scheduler_lock_test");
+
+        let held = try_lock_scheduler(&pool, &key).await.unwrap();
+        assert!(held.is_some());
+        // a second replica stands by, the failed attempt leaves no lock behind
+        assert!(try_lock_scheduler(&pool, &key).await.unwrap().is_none());
+        assert!(
+            try_lock_scheduler(&pool, &scheduler_lock_key("other_queue"))
+                .await
+                .unwrap()
+                .is_some()
+        );
+
+        held.unwrap().close().await.unwrap();
+        assert!(try_lock_scheduler(&pool, &key).await.unwrap().is_some());
+    }
+
+    #[test]
+    fn test_invalid_schedule_never_runs() {
+        let mut schedules = JobSchedules::default();
+        let jobs = vec![job("broken", "not a schedule")];
+        assert!(schedules.due(&jobs, at(1)).is_empty());
+        assert!(schedules.due(&jobs, at(59)).is_empty());
+        assert!(schedules.next_runs.is_empty());
+    }
+}